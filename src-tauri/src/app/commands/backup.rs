@@ -1,14 +1,26 @@
 // 標準ライブラリ
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // 外部クレート
 use tauri::AppHandle;
 
 // 内部モジュール (自作)
+use crate::app::commands::generation::lock_root_for_backup;
+use crate::app::commands::restore::restore_to;
+use crate::app::commands::vault::password_or_vault;
 use crate::app::hdiff::*;
 use crate::app::state::AppState;
 use crate::core::backup::workflow;
+use crate::core::backup::auto_generation;
+use crate::core::backup::cache_manager;
+use crate::core::backup::crypto;
+use crate::core::backup::dedup;
+use crate::core::backup::identity;
+use crate::core::backup::volume;
+use crate::core::ignore;
+use crate::core::backup::restore::ConflictPolicy;
+use crate::core::backup::rotation::RotationPolicy;
 use crate::core::{backup::archive, utils};
 use tauri::Manager;
 
@@ -28,161 +40,411 @@ pub async fn backup_or_diff(
     } else {
         PathBuf::from(custom_dir.trim_end_matches(|c| c == '/' || c == '\\'))
     };
+    let _guard = lock_root_for_backup(&app, &initial_path, "差分バックアップ")?;
     let target = workflow::resolve_backup_target(initial_path, &work_file)?;
 
+    let (final_path_str, _) =
+        run_diff_workflow(&app, &work_file, &target, &ts, &algo, &compress, &ignore_list).await?;
+    record_identity(&target.project_root, &work_file, &ts);
+    Ok(final_path_str)
+}
+
+/// バックアップ後に識別情報を更新する（作業ファイルの名前・場所が変わっても履歴を追えるようにする）
+/// 失敗してもバックアップ自体は成功しているので、エラーにはしない
+fn record_identity(root: &Path, work_file: &str, ts: &str) {
+    if let Err(e) = identity::record_backup(root, Path::new(work_file), ts) {
+        log::warn!("識別情報を更新できませんでした: {}", e);
+    }
+}
+
+fn now_ts() -> String {
+    chrono::Local::now().format("%Y%m%d_%H%M%S").to_string()
+}
+
+/// 差分バックアップのフェーズ1〜2を実行する
+/// 戻り値: (作成した差分のパス, 世代交代が起きたか)
+/// .base の新規作成のみで終わった場合、パスは空文字
+pub(crate) async fn run_diff_workflow(
+    app: &AppHandle,
+    work_file: &str,
+    target: &workflow::BackupTargetInfo,
+    ts: &str,
+    algo: &str,
+    compress: &str,
+    ignore_list: &[String],
+) -> Result<(String, bool), String> {
     if !target.target_dir.exists() {
         fs::create_dir_all(&target.target_dir).map_err(|e| e.to_string())?;
     }
     let mut final_path_str = String::new();
+    let mut rotated = false;
     // 2. フェーズ1: 最初の作成
-    if let Some((base, work, temp)) = workflow::prepare_initial_plan(&work_file, &target, &ts)? {
-        crate::app::hdiff::create_hdiff(
+    if let Some((base, work, temp)) = workflow::prepare_initial_plan(work_file, target, ts)? {
+        // 除外パターンを hdiffz の -g で表せない場合は、除外したもの抜きのコピーを差分の入力にする
+        // 表せる場合は、作業フォルダの .wbtignore も -g に加える
+        let staged = workflow::stage_filtered_work(work_file, ts, ignore_list)?;
+        let (hdiff_work, hdiff_ignore) = match &staged {
+            Some(stage) => (stage.clone(), Vec::new()),
+            None => {
+                let mut patterns = ignore::to_hdiff_patterns(ignore_list);
+                patterns.extend(ignore::to_hdiff_patterns(&ignore::read_ignore_file(
+                    Path::new(work_file),
+                )));
+                (work, patterns)
+            }
+        };
+        let created = crate::app::hdiff::create_hdiff(
             app.clone(),
             &base.to_string_lossy(),
-            &work.to_string_lossy(),
+            &hdiff_work.to_string_lossy(),
             &temp.to_string_lossy(),
-            &compress,
-            &ignore_list,
+            compress,
+            &hdiff_ignore,
         )
-        .await?;
+        .await;
+        if let Some(stage) = staged {
+            if let Err(e) = fs::remove_dir_all(&stage) {
+                log::warn!("一時フォルダを削除できませんでした {}: {}", stage.display(), e);
+            }
+        }
+        created?;
 
-        // 3. 判定用の閾値取得
-        let threshold = {
+        // 3. 世代交代の条件を取得
+        let policy = {
             let state = app.state::<AppState>();
             let cfg = state.config.lock().unwrap();
-            if cfg.auto_base_generation_threshold <= 0.0 {
-                0.8
-            } else {
-                cfg.auto_base_generation_threshold
-            }
+            RotationPolicy::from_config(&cfg)
         };
         let (path_str, next_plan) =
-            workflow::finalize_or_next_plan(&work_file, temp, &target, threshold, &algo, &ts)?;
+            workflow::finalize_or_next_plan(
+                work_file,
+                temp,
+                target,
+                &policy,
+                algo,
+                ts,
+                ignore_list,
+            )?;
 
         final_path_str = path_str;
         // 4. フェーズ2: 判定と後始末（世代交代が必要なら次を実行）
         if let Some((new_base, new_work, final_dest)) = next_plan {
+            rotated = true;
             crate::app::hdiff::create_hdiff(
-                app,
+                app.clone(),
                 &new_base.to_string_lossy(),
                 &new_work.to_string_lossy(),
                 &final_dest.to_string_lossy(),
-                &compress,
+                compress,
                 &[],
             )
             .await?;
         }
     }
 
-    Ok(final_path_str)
+    Ok((final_path_str, rotated))
 }
 
+/// 複数の差分を順に復元する
+/// output を指定した場合はそこへ出力し、既存のものとぶつかったときは conflict に従う
+/// 戻り値: 出力したパス一覧
 #[tauri::command]
 pub async fn apply_multi_diff(
     app: AppHandle,
     work_file: String,
     diff_paths: Vec<String>,
+    password: Option<String>,
+    output: Option<String>,
+    conflict: Option<String>,
+) -> Result<Vec<String>, String> {
+    let conflict: ConflictPolicy = conflict.unwrap_or_default().parse()?;
+    let mut outputs = Vec::new();
+    for dp in diff_paths {
+        let out = restore_to(
+            &app,
+            &dp,
+            &work_file,
+            output.as_deref(),
+            conflict,
+            password.clone(),
+        )
+        .await
+        .map_err(|e| format!("復元失敗 ({}): {}", dp, e))?;
+        outputs.push(out);
+    }
+    Ok(outputs)
+}
+
+/// 差分を1つ適用して out_path へ出力する
+pub(crate) async fn apply_diff_to(
+    app: &AppHandle,
+    work_file: &str,
+    dp: &str,
+    out_path: &str,
+    password: Option<String>,
 ) -> Result<(), String> {
-    let hdiff_strict_hash_check = {
+    let (hdiff_strict_hash_check, use_same_dir, cache_max_bytes, cache_archives) = {
         let state = app.state::<AppState>();
         let cfg = state.config.lock().unwrap();
-        cfg.hdiff_strict_hash_check
+        (
+            cfg.hdiff_strict_hash_check,
+            cfg.use_same_dir_for_temp,
+            crate::app::commands::cache::cache_max_bytes(cfg.cache_max_size_mb),
+            cfg.cache_archived_generations,
+        )
     };
+    let algo = workflow::detect_diff_algo(dp);
+    let password = password_or_vault(app, Path::new(dp), password);
 
-    for dp in diff_paths {
-        let algo = workflow::detect_diff_algo(&dp);
-
-        let result = match algo {
-            workflow::DiffAlgo::HDiff => {
-                apply_hdiff_wrapper(app.clone(), &work_file, &dp, hdiff_strict_hash_check).await
-            }
-            workflow::DiffAlgo::BsDiff => {
-                return Err("`bsdiff` is not supported currently.".into());
+    // 世代アーカイブ内の差分は、展開済みのキャッシュがあればそこから適用する
+    // キャッシュは速くするためのもので、設定で有効にした場合だけ世代全体を展開する
+    // それ以外は .base と差分のエントリだけを一時フォルダへ取り出す
+    // (一時フォルダはこの関数の終わりで削除される)
+    let mut _staging = None;
+    let mut diff_file = dp.to_string();
+    if let Some((archive_path, _)) = archive::split_archived_entry_path(dp) {
+        let backup_dir = identity::find_root(&archive_path)
+            .or_else(|| archive_path.parent().map(Path::to_path_buf))
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let cache_root = utils::get_cache_root(use_same_dir, &backup_dir, work_file);
+        if cache_archives || cache_manager::has_valid_entry(&cache_root, &archive_path) {
+            if let Err(e) = cache_manager::ensure_cached(
+                &cache_root,
+                &archive_path,
+                password.clone(),
+                cache_max_bytes,
+            ) {
+                log::warn!(
+                    "展開キャッシュを作成できないため、必要なエントリだけを取り出します ({}): {}",
+                    dp,
+                    e
+                );
             }
-            _ => Err("Unknown format".into()),
-        };
+        }
+        let tmp = tempfile::tempdir().map_err(|e| e.to_string())?;
+        let staged =
+            archive::stage_archived_diff(dp, &cache_root, tmp.path(), password.as_deref())?;
+        diff_file = staged.to_string_lossy().into_owned();
+        _staging = Some(tmp);
+    }
 
-        if let Err(e) = result {
-            return Err(format!("復元失敗 ({}): {}", dp, e));
+    // 暗号化した差分・ベースは、一時フォルダへ復号してから適用する
+    if crypto::diff_needs_decryption(Path::new(&diff_file)) {
+        let password = password
+            .as_deref()
+            .filter(|p| !p.is_empty())
+            .ok_or("暗号化されたバックアップです。パスワードを入力してください")?;
+        let tmp = tempfile::tempdir().map_err(|e| e.to_string())?;
+        let staged = crypto::stage_encrypted_diff(Path::new(&diff_file), password, tmp.path())?;
+        diff_file = staged.to_string_lossy().into_owned();
+        _staging = Some(tmp);
+    }
+
+    match algo {
+        workflow::DiffAlgo::HDiff => {
+            apply_hdiff_to(
+                app.clone(),
+                work_file,
+                &diff_file,
+                out_path,
+                hdiff_strict_hash_check,
+            )
+            .await
         }
+        workflow::DiffAlgo::BsDiff => Err("`bsdiff` is not supported currently.".into()),
+        _ => Err("Unknown format".into()),
     }
-    Ok(())
 }
 
 /// ファイルをそのままコピーしてバックアップする (Go版の CopyBackupFile 相当)
 #[tauri::command]
-pub fn copy_backup_file(src: String, backup_dir: String) -> Result<String, String> {
+pub fn copy_backup_file(
+    app: AppHandle,
+    src: String,
+    backup_dir: String,
+    ignore_list: Option<Vec<String>>,
+) -> Result<String, String> {
     // 1. 引数の加工 (app層の仕事)
+    let root = crate::app::commands::generation::resolve_root(&src, &backup_dir);
+    let _guard = lock_root_for_backup(&app, &root, "コピーバックアップ")?;
     let dir_opt = if backup_dir.is_empty() {
         None
     } else {
         Some(PathBuf::from(backup_dir))
     };
+    let keep_symlinks = {
+        let state = app.state::<AppState>();
+        let cfg = state.config.lock().unwrap();
+        cfg.keep_symlinks
+    };
 
     // 2. 実行 (ロジックはすべてcoreへ)
-    workflow::execute_copy_backup(&src, dir_opt)
+    let dst = workflow::execute_copy_backup_with(
+        &src,
+        dir_opt,
+        keep_symlinks,
+        &ignore_list.unwrap_or_default(),
+    )?;
+    record_identity(&root, &src, &now_ts());
+    Ok(dst)
 }
 
 #[tauri::command]
 pub async fn archive_backup_file(
+    app: AppHandle,
     src: String,
     backup_dir: String,
     format: String,
     password: String,
+    ignore_list: Option<Vec<String>>,
 ) -> Result<String, String> {
     // 1. 引数の正規化
+    let root = crate::app::commands::generation::resolve_root(&src, &backup_dir);
+    let _guard = lock_root_for_backup(&app, &root, "アーカイブバックアップ")?;
     let dir_opt = if backup_dir.is_empty() {
         None
     } else {
         Some(PathBuf::from(backup_dir))
     };
 
+    let opts = {
+        let state = app.state::<AppState>();
+        let cfg = state.config.lock().unwrap();
+        archive::CompressionOptions {
+            ignore_patterns: ignore_list.unwrap_or_default(),
+            ..archive::CompressionOptions::from_config(&cfg)
+        }
+    };
+
     // 2. coreのワークフローを呼び出す
-    crate::core::backup::archive::execute_archive_backup(&src, dir_opt, &format, &password)
+    let dst = archive::execute_archive_backup(&src, dir_opt, &format, &password, &opts)?;
+    record_identity(&root, &src, &now_ts());
+    Ok(dst)
 }
 
+/// 重複排除バックアップを作成する（チャンクはバックアップ先の `chunks/` に保存）
+/// 戻り値: 作成したマニフェストのパス
 #[tauri::command]
-pub async fn restore_backup(
-    app: tauri::AppHandle,
-    path: String,
-    work_file: String,
-) -> Result<(), String> {
-    let lower_path = path.to_lowercase();
+pub fn dedup_backup(
+    app: AppHandle,
+    src: String,
+    backup_dir: String,
+    ignore_list: Option<Vec<String>>,
+) -> Result<String, String> {
+    let root = crate::app::commands::generation::resolve_root(&src, &backup_dir);
+    // チャンクを書いてからマニフェストを書くまでの間に、チャンクの整理で消されないようにする
+    let _guard = lock_root_for_backup(&app, &root, "重複排除バックアップ")?;
+    let ts = now_ts();
+    let report =
+        dedup::create_dedup_backup_with(&src, &root, &ts, &ignore_list.unwrap_or_default())?;
+    record_identity(&root, &src, &ts);
+    Ok(report.manifest_path)
+}
 
-    // 1. 差分パッチの場合のみSidecar（async/app層）が必要
-    if lower_path.ends_with(".diff") {
-        return apply_multi_diff(app, work_file, vec![path]).await;
-    }
+/// どのマニフェストからも参照されていないチャンクを削除する
+#[tauri::command]
+pub fn gc_dedup_chunks(
+    app: AppHandle,
+    work_file: String,
+    backup_dir: String,
+) -> Result<crate::core::types::ChunkGcReport, String> {
+    let root = crate::app::commands::generation::resolve_root(&work_file, &backup_dir);
+    // 実行中の重複排除バックアップがまだマニフェストを書いていないチャンクを消さないよう、排他で実行する
+    let _guard = app
+        .state::<AppState>()
+        .root_locks
+        .acquire(&root, "チャンクの整理")?;
+    dedup::gc_chunks(&root)
+}
 
-    // 出力パスの自動生成
-    let restored_path = utils::auto_output_path(&work_file);
+/// 選択したバックアップ（差分・ベース・フルコピー・単体アーカイブ）をパスワードで暗号化する
+/// 最新世代の .base は次の差分の作成に使うため対象外
+/// 戻り値: 暗号化後のパス
+#[tauri::command]
+pub fn encrypt_backup_files(
+    paths: Vec<String>,
+    password: String,
+    work_file: String,
+    backup_dir: String,
+) -> Result<Vec<String>, String> {
+    let root = crate::app::commands::generation::resolve_root(&work_file, &backup_dir);
+    let latest = auto_generation::get_latest_generation(&root)?.map(|g| g.dir_path);
 
-    if lower_path.ends_with(".zip") || lower_path.ends_with(".tar.gz") {
-        // 2 & 3. アーカイブ展開 (core::backup::archive に丸投げ)
-        archive::restore_archive(&path, &restored_path)
-    } else {
-        // 4. フルコピー (core::utils)
-        utils::copy_file(&path, &restored_path)?;
-        Ok(())
+    // 途中で止まって一部だけ暗号化されないよう、先にすべて確認する
+    for path in &paths {
+        let p = Path::new(path);
+        if archive::split_archived_entry_path(path).is_some() {
+            return Err(format!("アーカイブ内のファイルは暗号化できません: {}", path));
+        }
+        if volume::split_volume_name(path).is_some_and(|(stem, _)| archive::is_archive_name(stem)) {
+            return Err(format!("分割アーカイブは暗号化できません: {}", path));
+        }
+        if dedup::is_manifest(path) {
+            return Err(format!("重複排除バックアップは暗号化できません: {}", path));
+        }
+        let is_base = p
+            .file_name()
+            .map(|n| n.to_string_lossy().ends_with(".base"))
+            .unwrap_or(false);
+        if is_base && p.parent() == latest.as_deref() {
+            return Err(format!(
+                "最新世代の .base は差分の作成に使うため暗号化できません: {}",
+                path
+            ));
+        }
     }
+    paths
+        .iter()
+        .map(|path| {
+            crypto::encrypt_in_place(Path::new(path), &password)
+                .map(|p| p.to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+/// バックアップ（差分・アーカイブ・フルコピー）を復元する
+/// output を省略した場合は作業ファイルの隣 (`<stem>_restored_<ts>`) に出力する
+/// 戻り値: 出力したパス（conflict が skip で何も書き出さなかった場合は空文字）
+#[tauri::command]
+pub async fn restore_backup(
+    app: tauri::AppHandle,
+    path: String,
+    work_file: String,
+    password: Option<String>,
+    output: Option<String>,
+    conflict: Option<String>,
+) -> Result<String, String> {
+    let conflict: ConflictPolicy = conflict.unwrap_or_default().parse()?;
+    restore_to(
+        &app,
+        &path,
+        &work_file,
+        output.as_deref(),
+        conflict,
+        password,
+    )
+    .await
 }
 
 #[tauri::command]
 pub async fn archive_generation(
+    app: AppHandle,
     target_n: u32,
     format: String,
     work_file: String,
     backup_dir: String,
     password: Option<String>,
 ) -> Result<(), String> {
+    let root = crate::app::commands::generation::resolve_root(&work_file, &backup_dir);
+    let _guard = lock_root_for_backup(&app, &root, "世代のアーカイブ")?;
     let pwd = password.unwrap_or_default();
+    let opts = {
+        let state = app.state::<AppState>();
+        let cfg = state.config.lock().unwrap();
+        archive::CompressionOptions::from_config(&cfg)
+    };
 
     // core 側のワークフローに丸投げ
-    crate::core::backup::archive::execute_generation_archive(
-        target_n,
-        &format,
-        &work_file,
-        &backup_dir,
-        &pwd,
-    )
+    archive::execute_generation_archive(target_n, &format, &work_file, &backup_dir, &pwd, &opts)
 }
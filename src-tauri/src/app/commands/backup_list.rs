@@ -6,13 +6,14 @@ use tauri::Manager;
 // Tauriプラグイン
 
 // 内部モジュール (自作)
-use crate::core::types::BackupItem;
+use crate::core::types::{BackupItem, BackupStats};
 
 #[tauri::command]
 pub fn get_backup_list(
     app: tauri::AppHandle,
     work_file: String,
     backup_dir: String,
+    mirror_dir: Option<String>,
 ) -> Result<Vec<BackupItem>, String> {
     // 1. AppStateから設定値を抜き出す
     let (strict, use_same_dir) = {
@@ -21,13 +22,28 @@ pub fn get_backup_list(
         (cfg.strict_file_name_match, cfg.use_same_dir_for_temp)
     };
 
-    // 2. core::backup::scanner の「scan_backups」を呼ぶ
-    Ok(crate::core::backup::scanner::scan_backups(
-        &work_file,
-        &backup_dir,
-        strict,
-        use_same_dir,
-    ))
+    // 2. 中断された作業の後始末をしてから、core::backup::scanner の「scan_backups」を呼ぶ
+    let root = crate::app::commands::generation::resolve_root(&work_file, &backup_dir);
+    crate::app::commands::generation::recover_interrupted_work(&app, &root);
+    let mut items =
+        crate::core::backup::scanner::scan_backups(&work_file, &backup_dir, strict, use_same_dir);
+
+    // 3. 生成済みのサムネイルがあれば添える（未生成のものは get_backup_thumbnail で作る）
+    let cache_root = crate::core::utils::get_cache_root(use_same_dir, &backup_dir, &work_file);
+    for item in &mut items {
+        item.thumbnail =
+            crate::core::preview::thumbnail::cached_thumbnail(&cache_root, &item.file_path)
+                .map(|p| p.to_string_lossy().into_owned());
+    }
+
+    // 4. ミラー先を設定している場合は、各項目が写し終えているかを添える
+    if mirror_dir.is_some_and(|d| !d.is_empty()) {
+        let check = crate::core::backup::mirror::MirrorCheck::new(&root, &cache_root);
+        for item in &mut items {
+            item.mirror = Some(check.item_status(&item.file_path).to_string());
+        }
+    }
+    Ok(items)
 }
 
 #[tauri::command]
@@ -37,3 +53,20 @@ pub fn get_generation_folders(
 ) -> Result<Vec<BackupItem>, String> {
     crate::core::backup::scanner::scan_generation_folders(&work_file, &backup_dir)
 }
+
+/// バックアップ先の容量の内訳（世代ごとの内訳、フルコピー・アーカイブ・キャッシュの合計）を返す
+#[tauri::command]
+pub fn get_backup_stats(
+    app: tauri::AppHandle,
+    work_file: String,
+    backup_dir: String,
+) -> Result<BackupStats, String> {
+    let use_same_dir = {
+        let state = app.state::<crate::app::state::AppState>();
+        let cfg = state.config.lock().unwrap();
+        cfg.use_same_dir_for_temp
+    };
+    let root = crate::app::commands::generation::resolve_root(&work_file, &backup_dir);
+    let cache_root = crate::core::utils::get_cache_root(use_same_dir, &backup_dir, &work_file);
+    crate::core::backup::stats::collect_backup_stats(&work_file, &root, &cache_root)
+}
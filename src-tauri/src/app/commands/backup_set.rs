@@ -0,0 +1,88 @@
+// 標準ライブラリ
+use std::path::{Path, PathBuf};
+
+// 外部クレート
+use tauri::{AppHandle, Manager};
+
+// 内部モジュール (自作)
+use crate::app::commands::restore::restore_into;
+use crate::app::state::AppState;
+use crate::core::backup::restore::ConflictPolicy;
+use crate::core::backup::{backup_set, in_place};
+use crate::core::types::{BackupSetDef, BackupSetInfo, BackupSetMember};
+
+/// バックアップセットのメンバーを `<backup_root>/.wbt_set/<name>/` へ揃える
+/// セットの作成時と、毎回のバックアップの直前に呼ぶ（以降はこのフォルダを作業対象として各モードでバックアップする）
+/// backup_dir が空の場合は1つ目のメンバーの隣の `wbt_backup_<name>/` を使う
+#[tauri::command]
+pub fn sync_backup_set(
+    app: AppHandle,
+    set: BackupSetDef,
+    backup_dir: String,
+    ignore_list: Option<Vec<String>>,
+) -> Result<BackupSetInfo, String> {
+    backup_set::validate_set_name(&set.name)?;
+    let root = if backup_dir.is_empty() {
+        backup_set::default_set_root(&set)?
+    } else {
+        PathBuf::from(backup_dir.trim_end_matches(|c| c == '/' || c == '\\'))
+    };
+    let keep_symlinks = {
+        let state = app.state::<AppState>();
+        let cfg = state.config.lock().unwrap();
+        cfg.keep_symlinks
+    };
+
+    let work_dir = backup_set::set_work_dir(&root, &set.name);
+    let ts = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let manifest = backup_set::sync_set(
+        &work_dir,
+        &set,
+        keep_symlinks,
+        &ignore_list.unwrap_or_default(),
+        &ts,
+    )?;
+    Ok(BackupSetInfo {
+        work_file: work_dir.to_string_lossy().into_owned(),
+        backup_dir: root.to_string_lossy().into_owned(),
+        members: manifest.members,
+    })
+}
+
+/// セットのフォルダに記録されている現在のメンバー一覧
+#[tauri::command]
+pub fn get_backup_set_members(work_file: String) -> Result<Vec<BackupSetMember>, String> {
+    Ok(backup_set::load_set_manifest(Path::new(&work_file))?.members)
+}
+
+/// セットのバックアップを復元し、メンバーを元の場所の隣（または output フォルダ）へ書き出す
+/// member を省略した場合はセット全体、指定した場合はそのメンバーだけを書き出す
+/// 戻り値: 出力したパス一覧
+#[tauri::command]
+pub async fn restore_backup_set(
+    app: AppHandle,
+    path: String,
+    work_file: String,
+    member: Option<String>,
+    password: Option<String>,
+    output: Option<String>,
+    conflict: Option<String>,
+) -> Result<Vec<String>, String> {
+    let conflict: ConflictPolicy = conflict.unwrap_or_default().parse()?;
+    let ts = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+
+    // セットのフォルダの隣へ一旦まとめて復元する（メンバーは rename で取り出せる）
+    let staging = in_place::staging_output_path(Path::new(&work_file), &ts)?;
+    let staging_str = staging.to_string_lossy().into_owned();
+    let result = match restore_into(&app, &path, &work_file, &staging_str, password).await {
+        Ok(()) => backup_set::restore_set_members(
+            &staging,
+            member.as_deref(),
+            output.as_deref(),
+            conflict,
+        ),
+        Err(e) => Err(e),
+    };
+    in_place::discard_staging(&staging);
+    result
+}
@@ -8,7 +8,9 @@ use tauri::{AppHandle, Manager};
 // Tauriプラグイン
 
 // 内部モジュール (自作)
+use crate::app::commands::vault::password_or_vault;
 use crate::app::state::AppState;
+use crate::core::backup::{archive, cache_manager, identity};
 use crate::core::utils;
 
 #[tauri::command]
@@ -31,17 +33,29 @@ pub async fn prepare_archive_cache(
     work_file: String,
     password: Option<String>,
 ) -> Result<String, String> {
-    let state = app.state::<AppState>();
-    let cfg = state.config.lock().unwrap();
+    let (use_same_dir, max_bytes) = {
+        let state = app.state::<AppState>();
+        let cfg = state.config.lock().unwrap();
+        (cfg.use_same_dir_for_temp, cache_max_bytes(cfg.cache_max_size_mb))
+    };
 
     let archive_file = Path::new(&archive_path);
-    let backup_dir = archive_file
-        .parent()
+    // ルートは記述ファイルから求める（ない古いルートはアーカイブの親フォルダ）
+    let backup_dir = identity::find_root(archive_file)
+        .or_else(|| archive_file.parent().map(Path::to_path_buf))
         .map(|p| p.to_string_lossy().to_string())
         .unwrap_or_default();
-    let cache_root = utils::get_cache_root(cfg.use_same_dir_for_temp, &backup_dir, &work_file);
+    let cache_root = utils::get_cache_root(use_same_dir, &backup_dir, &work_file);
+    let password = password_or_vault(&app, archive_file, password);
 
-    crate::core::backup::archive::extract_to_cache(&archive_path, &cache_root, password)
+    // 既に同じアーカイブから展開済みなら再展開しない
+    cache_manager::ensure_cached(&cache_root, archive_file, password, max_bytes)?;
+    Ok(cache_root.to_string_lossy().to_string())
+}
+
+/// 設定値 (MB) をバイト数に変換する。0 は無制限
+pub(crate) fn cache_max_bytes(max_size_mb: u64) -> u64 {
+    max_size_mb.saturating_mul(1024 * 1024)
 }
 
 #[tauri::command]
@@ -50,8 +64,14 @@ pub async fn rebuild_archive_caches(
     work_file: String,
     backup_dir: String,
 ) -> Result<(), String> {
-    // 1. キャッシュの一掃
-    clear_all_caches(app.clone(), backup_dir.clone(), work_file.clone())?;
+    // 1. アーカイブが更新・削除された世代のキャッシュだけを取り除く
+    //    (内容が一致しているキャッシュは展開し直さない)
+    let cache_root = {
+        let state = app.state::<AppState>();
+        let cfg = state.config.lock().unwrap();
+        utils::get_cache_root(cfg.use_same_dir_for_temp, &backup_dir, &work_file)
+    };
+    cache_manager::remove_stale(&cache_root)?;
 
     let root = if backup_dir.is_empty() {
         utils::default_backup_dir(&work_file)
@@ -75,10 +95,8 @@ pub async fn rebuild_archive_caches(
                 .and_then(|s| s.to_str())
                 .unwrap_or("")
                 .to_lowercase();
-            let is_archive =
-                name.ends_with(".zip") || name.ends_with(".tar.gz") || name.ends_with(".tgz");
-
-            if name.starts_with("base") && is_archive {
+            // 暗号化したアーカイブは、鍵保管庫が解錠されていればそのパスワードで展開する
+            if name.starts_with("base") && archive::is_archive_name(&name) {
                 let _ = prepare_archive_cache(
                     app.clone(),
                     path.to_string_lossy().to_string(),
@@ -1,8 +1,8 @@
 use crate::core::utils;
 
 #[tauri::command]
-pub fn get_file_size(path: String) -> Result<i64, String> {
-    utils::get_file_size(&path)
+pub fn get_file_size(path: String, ignore_list: Option<Vec<String>>) -> Result<i64, String> {
+    utils::get_work_size(&path, &ignore_list.unwrap_or_default())
 }
 
 #[tauri::command]
@@ -40,9 +40,45 @@ pub async fn update_config_value(
         "hdiffStrictHashCheck" => {
             cfg.hdiff_strict_hash_check = value.as_bool().unwrap_or(false);
         }
+        "keepSymlinks" => {
+            cfg.keep_symlinks = value.as_bool().unwrap_or(false);
+        }
+        "rotateOnTypeChange" => {
+            cfg.rotate_on_type_change = value.as_bool().unwrap_or(false);
+        }
+        "cacheArchivedGenerations" => {
+            cfg.cache_archived_generations = value.as_bool().unwrap_or(false);
+        }
         "strictFileNameMatch" => {
             cfg.strict_file_name_match = value.as_bool().unwrap_or(true);
         }
+        "cacheMaxSizeMb" => {
+            cfg.cache_max_size_mb = value.as_u64().unwrap_or(0);
+        }
+        // 世代交代の条件 (0 で無効)
+        "rotateCumulativeRatio" => {
+            cfg.rotate_cumulative_ratio = value.as_f64().unwrap_or(0.0);
+        }
+        "rotateMaxDiffs" => {
+            cfg.rotate_max_diffs = value.as_u64().unwrap_or(0) as usize;
+        }
+        "rotateMaxAgeDays" => {
+            cfg.rotate_max_age_days = value.as_u64().unwrap_or(0);
+        }
+        "rotateShrinkRatio" => {
+            cfg.rotate_shrink_ratio = value.as_f64().unwrap_or(0.0);
+        }
+        // TAR 系アーカイブの圧縮設定 (0 で既定値)
+        "archiveCompressionLevel" => {
+            cfg.archive_compression_level = value.as_i64().unwrap_or(0) as i32;
+        }
+        "archiveCompressionThreads" => {
+            cfg.archive_compression_threads = value.as_u64().unwrap_or(0) as u32;
+        }
+        // アーカイブの分割サイズ (0 で分割しない)
+        "archiveVolumeSizeMb" => {
+            cfg.archive_volume_size_mb = value.as_u64().unwrap_or(0);
+        }
         _ => return Err(format!("Unknown numeric config key: {}", key)),
     }
 
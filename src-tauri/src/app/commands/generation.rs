@@ -0,0 +1,296 @@
+// 標準ライブラリ
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 外部クレート
+use tauri::{AppHandle, Manager};
+
+// 内部モジュール (自作)
+use crate::app::commands::vault::password_or_vault;
+use crate::app::hdiff::{apply_hdiff, create_hdiff};
+use crate::app::state::AppState;
+use crate::core::backup::archive;
+use crate::core::backup::generation_ops::{self, MergePlan};
+use crate::core::backup::rebase::{self, RebasePlan};
+use crate::core::backup::root_lock::{RootGuard, RootLocks};
+use crate::core::types::RebaseReport;
+use crate::core::utils;
+
+pub(crate) fn resolve_root(work_file: &str, backup_dir: &str) -> PathBuf {
+    if backup_dir.is_empty() {
+        utils::default_backup_dir(work_file)
+    } else {
+        PathBuf::from(backup_dir.trim_end_matches(|c| c == '/' || c == '\\'))
+    }
+}
+
+/// 中断されたリベース・世代統合の後始末をする（起動時、バックアップ・一覧の取得の前に呼ぶ）
+/// 同じルートで作業を実行中の場合は、その作業フォルダを消さないよう何もしない
+pub(crate) fn recover_root(root_locks: &RootLocks, root: &Path) {
+    let Ok(_guard) = root_locks.acquire(root, "中断された作業の後始末") else {
+        return;
+    };
+    if let Err(e) = rebase::recover_interrupted_rebase(root) {
+        log::warn!("中断されたリベースを後始末できませんでした: {}", e);
+    }
+    if let Err(e) = generation_ops::recover_interrupted_merge(root) {
+        log::warn!("中断された世代の統合を後始末できませんでした: {}", e);
+    }
+}
+
+/// バックアップ・一覧の取得の前に、中断された作業の後始末をする
+pub(crate) fn recover_interrupted_work(app: &AppHandle, root: &Path) {
+    recover_root(&app.state::<AppState>().root_locks, root);
+}
+
+/// バックアップ・アーカイブの前に中断された作業を後始末し、終わるまでルートを共有で使用中にする
+/// 同じルートでリベース・世代統合・世代の削除を実行中ならエラー（作業中の世代へ書き込んで失われないように）
+pub(crate) fn lock_root_for_backup(
+    app: &AppHandle,
+    root: &Path,
+    task: &str,
+) -> Result<RootGuard, String> {
+    recover_interrupted_work(app, root);
+    app.state::<AppState>()
+        .root_locks
+        .acquire_shared(root, task)
+}
+
+/// 世代の差分を、より新しいスナップショットを .base として再エンコードする
+/// 作業は一時フォルダで行い、検証が通った場合のみ世代フォルダを入れ替える
+#[tauri::command]
+pub async fn rebase_generation(
+    app: AppHandle,
+    target_n: u32,
+    work_file: String,
+    backup_dir: String,
+    strategy: String,
+    compress: String,
+) -> Result<RebaseReport, String> {
+    let root = resolve_root(&work_file, &backup_dir);
+    let _guard = app
+        .state::<AppState>()
+        .root_locks
+        .acquire(&root, "リベース")?;
+    rebase::recover_interrupted_rebase(&root)?;
+
+    let plan = rebase::plan_rebase(&root, target_n, &work_file, strategy.parse()?)?;
+    let result = run_rebase(&app, &plan, &compress).await;
+    if result.is_err() {
+        rebase::abort_rebase(&plan);
+    }
+    result
+}
+
+async fn run_rebase(
+    app: &AppHandle,
+    plan: &RebasePlan,
+    compress: &str,
+) -> Result<RebaseReport, String> {
+    let strict = {
+        let state = app.state::<AppState>();
+        let cfg = state.config.lock().unwrap();
+        cfg.hdiff_strict_hash_check
+    };
+    let old_base = plan.old_base.to_string_lossy().into_owned();
+    let new_base = plan.staged_base().to_string_lossy().into_owned();
+
+    // 1. 旧 .base から全差分を復元する
+    for diff in &plan.diffs {
+        apply_hdiff(
+            app.clone(),
+            &old_base,
+            &diff.to_string_lossy(),
+            &plan.snapshot_path(diff).to_string_lossy(),
+            strict,
+        )
+        .await?;
+    }
+
+    // 2. 新しい .base を配置し、各スナップショットを新 .base からの差分として作り直す
+    rebase::stage_new_base(plan)?;
+    for diff in &plan.diffs {
+        create_hdiff(
+            app.clone(),
+            &new_base,
+            &plan.snapshot_path(diff).to_string_lossy(),
+            &plan.staged_diff(diff).to_string_lossy(),
+            compress,
+            &[],
+        )
+        .await?;
+    }
+    if let Some(original_base_diff) = plan.original_base_diff() {
+        create_hdiff(
+            app.clone(),
+            &new_base,
+            &old_base,
+            &original_base_diff.to_string_lossy(),
+            compress,
+            &[],
+        )
+        .await?;
+    }
+
+    // 3. 作り直した差分が元と同じ状態に復元できるか検証する
+    let verify = plan.verify_path();
+    for diff in &plan.diffs {
+        apply_hdiff(
+            app.clone(),
+            &new_base,
+            &plan.staged_diff(diff).to_string_lossy(),
+            &verify.to_string_lossy(),
+            strict,
+        )
+        .await?;
+        if !rebase::same_content(&verify, &plan.snapshot_path(diff))? {
+            return Err(format!(
+                "再エンコードした差分の検証に失敗しました: {}",
+                diff.display()
+            ));
+        }
+        if verify.is_dir() {
+            fs::remove_dir_all(&verify).map_err(|e| e.to_string())?;
+        } else {
+            fs::remove_file(&verify).map_err(|e| e.to_string())?;
+        }
+    }
+
+    // 4. 世代フォルダを入れ替える
+    rebase::commit_rebase(plan)
+}
+
+fn cache_root_for(app: &AppHandle, backup_dir: &str, work_file: &str) -> PathBuf {
+    let state = app.state::<AppState>();
+    let cfg = state.config.lock().unwrap();
+    utils::get_cache_root(cfg.use_same_dir_for_temp, backup_dir, work_file)
+}
+
+/// 世代をまるごと削除する（フォルダ/アーカイブと展開キャッシュ）
+/// 最新世代は force が true のときのみ削除する
+#[tauri::command]
+pub fn delete_generation(
+    app: AppHandle,
+    target_n: u32,
+    work_file: String,
+    backup_dir: String,
+    force: bool,
+) -> Result<Vec<String>, String> {
+    let root = resolve_root(&work_file, &backup_dir);
+    let cache_root = cache_root_for(&app, &backup_dir, &work_file);
+    let _guard = app
+        .state::<AppState>()
+        .root_locks
+        .acquire(&root, "世代の削除")?;
+    generation_ops::delete_generation(&root, &cache_root, target_n, force)
+}
+
+/// fold_n の世代を keep_n の世代へ統合する
+/// fold 側の差分は keep 側の .base からの差分として作り直してから移す
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn merge_generations(
+    app: AppHandle,
+    keep_n: u32,
+    fold_n: u32,
+    work_file: String,
+    backup_dir: String,
+    force: bool,
+    password: Option<String>,
+    compress: String,
+) -> Result<String, String> {
+    let root = resolve_root(&work_file, &backup_dir);
+    let cache_root = cache_root_for(&app, &backup_dir, &work_file);
+    let _guard = app
+        .state::<AppState>()
+        .root_locks
+        .acquire(&root, "世代の統合")?;
+    generation_ops::recover_interrupted_merge(&root)?;
+    let password = password_or_vault(&app, &root, password);
+
+    let plan = generation_ops::plan_merge(&root, &work_file, keep_n, fold_n, force, password)?;
+    let result = run_merge(&app, &plan, &compress).await;
+    match result {
+        Ok(()) => {
+            let opts = {
+                let state = app.state::<AppState>();
+                let cfg = state.config.lock().unwrap();
+                archive::CompressionOptions::from_config(&cfg)
+            };
+            generation_ops::commit_merge(&plan, &cache_root, &opts)
+        }
+        Err(e) => {
+            generation_ops::abort_merge(&plan);
+            Err(e)
+        }
+    }
+}
+
+async fn run_merge(app: &AppHandle, plan: &MergePlan, compress: &str) -> Result<(), String> {
+    let strict = {
+        let state = app.state::<AppState>();
+        let cfg = state.config.lock().unwrap();
+        cfg.hdiff_strict_hash_check
+    };
+    let keep_base = plan.keep_base().to_string_lossy().into_owned();
+    let fold_base = plan.fold_base().to_string_lossy().into_owned();
+
+    // 1. fold 側の差分を復元し、keep 側の .base からの差分として作り直す
+    for diff in &plan.fold_diffs {
+        let snapshot = plan.snapshot_path(diff).to_string_lossy().into_owned();
+        apply_hdiff(
+            app.clone(),
+            &fold_base,
+            &diff.to_string_lossy(),
+            &snapshot,
+            strict,
+        )
+        .await?;
+        create_hdiff(
+            app.clone(),
+            &keep_base,
+            &snapshot,
+            &plan.staged_diff(diff).to_string_lossy(),
+            compress,
+            &[],
+        )
+        .await?;
+    }
+    if let Some(fold_base_diff) = plan.fold_base_diff() {
+        create_hdiff(
+            app.clone(),
+            &keep_base,
+            &fold_base,
+            &fold_base_diff.to_string_lossy(),
+            compress,
+            &[],
+        )
+        .await?;
+    }
+    generation_ops::stage_merge_extras(plan)?;
+
+    // 2. 作り直した差分が元と同じ状態に復元できるか検証する
+    let verify = plan.verify_path();
+    for diff in &plan.fold_diffs {
+        apply_hdiff(
+            app.clone(),
+            &keep_base,
+            &plan.staged_diff(diff).to_string_lossy(),
+            &verify.to_string_lossy(),
+            strict,
+        )
+        .await?;
+        if !rebase::same_content(&verify, &plan.snapshot_path(diff))? {
+            return Err(format!(
+                "統合用に作り直した差分の検証に失敗しました: {}",
+                diff.display()
+            ));
+        }
+        if verify.is_dir() {
+            fs::remove_dir_all(&verify).map_err(|e| e.to_string())?;
+        } else {
+            fs::remove_file(&verify).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+// 標準ライブラリ
+use std::path::{Path, PathBuf};
+
+// 外部クレート
+use tauri::{AppHandle, Manager};
+
+// 内部モジュール (自作)
+use crate::app::commands::generation::resolve_root;
+use crate::app::commands::vault::relocate_vault_entry;
+use crate::app::state::AppState;
+use crate::core::backup::identity;
+use crate::core::types::{OpenedRoot, RelinkCandidate, RelinkReport, RootSettings};
+use crate::core::utils;
+
+/// 作業ファイルの名前・場所が変わった場合に、元の履歴の候補を返す
+/// 作業ファイルの既定のバックアップ先が既にある場合は、付け替えの必要がないので空を返す
+/// search_dirs: 作業ファイルのフォルダに加えて探すフォルダ（最近使ったファイルのフォルダなど）
+#[tauri::command]
+pub fn find_relink_candidates(
+    work_file: String,
+    search_dirs: Vec<String>,
+) -> Result<Vec<RelinkCandidate>, String> {
+    let work_path = Path::new(&work_file);
+    if !work_path.exists() || utils::default_backup_dir(&work_file).exists() {
+        return Ok(Vec::new());
+    }
+    let mut dirs: Vec<PathBuf> = work_path
+        .parent()
+        .map(|p| p.to_path_buf())
+        .into_iter()
+        .collect();
+    dirs.extend(
+        search_dirs
+            .iter()
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from),
+    );
+    Ok(identity::find_relink_candidates(work_path, &dirs))
+}
+
+/// backup_dir の履歴を作業ファイルへ付け替える（バックアップ名の変更・既定の場所への移動・キャッシュの移行）
+#[tauri::command]
+pub fn relink_backup_history(
+    app: AppHandle,
+    work_file: String,
+    backup_dir: String,
+) -> Result<RelinkReport, String> {
+    let use_same_dir = {
+        let state = app.state::<AppState>();
+        let cfg = state.config.lock().unwrap();
+        cfg.use_same_dir_for_temp
+    };
+    let ts = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let root = Path::new(backup_dir.trim_end_matches(|c| c == '/' || c == '\\'));
+    let report = identity::relink(root, Path::new(&work_file), use_same_dir, &ts)?;
+    relocate_vault_entry(&app, root, Path::new(&report.backup_dir));
+    Ok(report)
+}
+
+/// タブの設定をバックアップルートの記述ファイルへ記録する（別の PC などでルートを開いたときに使う）
+#[tauri::command]
+pub fn save_root_settings(
+    work_file: String,
+    backup_dir: String,
+    settings: RootSettings,
+) -> Result<(), String> {
+    identity::save_root_settings(&resolve_root(&work_file, &backup_dir), &settings)
+}
+
+/// 既存のバックアップルートを開き、記述ファイルからタブを組み立てるための情報を返す
+#[tauri::command]
+pub fn open_backup_root(app: AppHandle, backup_dir: String) -> Result<OpenedRoot, String> {
+    let use_same_dir = {
+        let state = app.state::<AppState>();
+        let cfg = state.config.lock().unwrap();
+        cfg.use_same_dir_for_temp
+    };
+    let ts = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let root = Path::new(backup_dir.trim_end_matches(|c| c == '/' || c == '\\'));
+    // 前回の場所（鍵保管庫の登録を引き継ぐのに使う）
+    let previous_root = identity::load_identity(root)
+        .map(|id| id.root_path)
+        .filter(|p| !p.is_empty());
+    let opened = identity::open_root(root, use_same_dir, &ts)?;
+    if let Some(previous) = previous_root.filter(|_| opened.relocated) {
+        relocate_vault_entry(&app, Path::new(&previous), root);
+    }
+    Ok(opened)
+}
@@ -0,0 +1,75 @@
+// 標準ライブラリ
+use std::path::{Path, PathBuf};
+
+// 外部クレート
+use tauri::AppHandle;
+
+// 内部モジュール (自作)
+use crate::app::commands::backup::run_diff_workflow;
+use crate::app::commands::generation::lock_root_for_backup;
+use crate::core::backup::{import, workflow};
+use crate::core::utils;
+
+/// 手動で作られていた過去のバックアップ（`xxx_final_v3.clip` など）を世代構造に取り込む
+/// 最も古いものを `baseN_<ts>/<name>.base` とし、残りは通常の差分ワークフローで差分化する
+/// 戻り値: 作成した世代フォルダと差分のパス一覧
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn import_manual_backups(
+    app: AppHandle,
+    work_file: String,
+    backup_dir: String,
+    files: Vec<String>,
+    folder: Option<String>,
+    pattern: Option<String>,
+    algo: String,
+    compress: String,
+    ignore_list: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let sources = import::collect_import_sources(&files, folder.as_deref(), pattern.as_deref())?;
+    let (first, rest) = sources
+        .split_first()
+        .ok_or("インポート対象が見つかりません")?;
+
+    let root = if backup_dir.is_empty() {
+        utils::default_backup_dir(&work_file)
+    } else {
+        PathBuf::from(backup_dir.trim_end_matches(|c| c == '/' || c == '\\'))
+    };
+    let _guard = lock_root_for_backup(&app, &root, "過去のバックアップの取り込み")?;
+
+    // 作業ファイル名でコピーするための一時領域（終了時に自動削除）
+    let staging = tempfile::tempdir().map_err(|e| e.to_string())?;
+    let mut created = Vec::new();
+
+    // 1. 最も古いものを新しい世代の .base にする
+    let staged = import::stage_import_source(first, staging.path(), &work_file)?;
+    let seeded = import::seed_import_generation(&root, &staged, first)?;
+    created.push(seeded.target_dir.to_string_lossy().into_owned());
+
+    // 2. 残りは時系列順に差分化（世代交代の判定も通常どおり行う）
+    for src in rest {
+        let staged = import::stage_import_source(src, staging.path(), &work_file)?;
+        let staged_str = staged.to_string_lossy().into_owned();
+        let target = workflow::resolve_backup_target(root.clone(), &staged_str)?;
+
+        let (path, rotated) = run_diff_workflow(
+            &app,
+            &staged_str,
+            &target,
+            &src.ts,
+            &algo,
+            &compress,
+            &ignore_list,
+        )
+        .await
+        .map_err(|e| format!("インポート失敗 ({}): {}", src.path.display(), e))?;
+
+        if !path.is_empty() {
+            import::backdate_import_entry(Path::new(&path), &staged, src.modified, rotated)?;
+            created.push(path);
+        }
+    }
+
+    Ok(created)
+}
@@ -0,0 +1,34 @@
+// 標準ライブラリ
+use std::path::Path;
+
+// 内部モジュール (自作)
+use crate::app::commands::generation::resolve_root;
+use crate::core::backup::mirror;
+use crate::core::types::MirrorStatus;
+
+/// バックアップ先の新しいもの・変わったものをミラー先へ写す（バックアップの後と、失敗したものの再試行で呼ぶ）
+#[tauri::command]
+pub async fn sync_mirror(
+    work_file: String,
+    backup_dir: String,
+    mirror_dir: String,
+) -> Result<MirrorStatus, String> {
+    if mirror_dir.is_empty() {
+        return Err("ミラー先が設定されていません".to_string());
+    }
+    let ts = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+    mirror::sync_mirror(
+        &resolve_root(&work_file, &backup_dir),
+        Path::new(mirror_dir.trim_end_matches(|c| c == '/' || c == '\\')),
+        &ts,
+    )
+}
+
+/// ミラーの現在の状態（写し終えた数・未了の数・失敗したもの）
+#[tauri::command]
+pub fn get_mirror_status(work_file: String, backup_dir: String) -> Result<MirrorStatus, String> {
+    Ok(mirror::mirror_status(&resolve_root(
+        &work_file,
+        &backup_dir,
+    )))
+}
@@ -1,13 +1,29 @@
 pub mod backup;
 pub mod backup_list;
+pub mod backup_set;
 pub mod cache;
 pub mod common;
 pub mod config;
 pub mod dialog;
+pub mod generation;
+pub mod identity;
+pub mod import;
+pub mod mirror;
+pub mod preview;
+pub mod restore;
+pub mod vault;
 
 pub use backup::*;
 pub use backup_list::*;
+pub use backup_set::*;
 pub use cache::*;
 pub use common::*;
 pub use config::*;
 pub use dialog::*;
+pub use generation::*;
+pub use identity::*;
+pub use import::*;
+pub use mirror::*;
+pub use preview::*;
+pub use restore::*;
+pub use vault::*;
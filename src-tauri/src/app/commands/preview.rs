@@ -0,0 +1,102 @@
+// 標準ライブラリ
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 外部クレート
+use tauri::{AppHandle, Manager};
+
+// 内部モジュール (自作)
+use crate::app::commands::restore::restore_into;
+use crate::app::state::AppState;
+use crate::core::backup::restore::{self, RestoreKind};
+use crate::core::preview::{embedded, thumbnail, visual};
+use crate::core::types::VisualComparison;
+use crate::core::utils;
+
+/// ファイルのバックアップを画像として読める形にする
+/// 作業ファイルと同じ拡張子のフルコピーはそのまま、それ以外は tmp 配下へ作業ファイルと同じ名前で復元する
+pub(crate) async fn materialize_file(
+    app: &AppHandle,
+    path: &str,
+    work_file: &str,
+    tmp: &Path,
+    password: Option<String>,
+) -> Result<PathBuf, String> {
+    let work_path = Path::new(work_file);
+    let is_copy = restore::restore_kind_of(path) == RestoreKind::Copy;
+    if is_copy && Path::new(path).extension() == work_path.extension() {
+        return Ok(PathBuf::from(path));
+    }
+    let name = work_path.file_name().ok_or("Invalid work file path")?;
+    let out = tmp.join(name);
+    if is_copy {
+        // .base など拡張子が異なるものは作業ファイルの名前でコピーして形式を判定させる
+        fs::copy(path, &out).map_err(|e| e.to_string())?;
+    } else {
+        restore_into(app, path, work_file, &out.to_string_lossy(), password).await?;
+    }
+    Ok(out)
+}
+
+/// 2つのバックアップを画像として比較し、横並び画像と差分ヒートマップを作る
+/// PNG/JPEG/WebP と .clip/.kra/.ora/.psd の埋め込みプレビューに対応
+/// 結果はキャッシュルートの visual/ に保存し、同じ組み合わせでは再利用する
+#[tauri::command]
+pub async fn compare_images(
+    app: AppHandle,
+    a: String,
+    b: String,
+    work_file: String,
+    backup_dir: String,
+    password: Option<String>,
+) -> Result<VisualComparison, String> {
+    if !embedded::is_preview_supported(Path::new(&work_file)) {
+        return Err(format!("画像として比較できない形式です: {}", work_file));
+    }
+    let cache_root = {
+        let state = app.state::<AppState>();
+        let cfg = state.config.lock().unwrap();
+        utils::get_cache_root(cfg.use_same_dir_for_temp, &backup_dir, &work_file)
+    };
+    let out_dir = cache_root.join("visual");
+    let key = visual::comparison_key(&a, &b);
+    if let Some(cached) = visual::load_cached(&out_dir, &key) {
+        return Ok(cached);
+    }
+
+    let tmp_a = tempfile::tempdir().map_err(|e| e.to_string())?;
+    let tmp_b = tempfile::tempdir().map_err(|e| e.to_string())?;
+    let file_a = materialize_file(&app, &a, &work_file, tmp_a.path(), password.clone()).await?;
+    let file_b = materialize_file(&app, &b, &work_file, tmp_b.path(), password).await?;
+    visual::render_comparison(&file_a, &file_b, &out_dir, &key)
+}
+
+/// バックアップ1件のサムネイルを返す（未生成なら作ってキャッシュルートの thumbs/ に保存する）
+/// 差分は一時フォルダへ復元してから縮小する
+/// 戻り値: サムネイル (PNG) のパス
+#[tauri::command]
+pub async fn get_backup_thumbnail(
+    app: AppHandle,
+    path: String,
+    work_file: String,
+    backup_dir: String,
+    password: Option<String>,
+) -> Result<String, String> {
+    if !embedded::is_preview_supported(Path::new(&work_file)) {
+        return Err(format!("サムネイルに対応していない形式です: {}", work_file));
+    }
+    let cache_root = {
+        let state = app.state::<AppState>();
+        let cfg = state.config.lock().unwrap();
+        utils::get_cache_root(cfg.use_same_dir_for_temp, &backup_dir, &work_file)
+    };
+    if let Some(cached) = thumbnail::cached_thumbnail(&cache_root, &path) {
+        return Ok(cached.to_string_lossy().into_owned());
+    }
+
+    let tmp = tempfile::tempdir().map_err(|e| e.to_string())?;
+    let src = materialize_file(&app, &path, &work_file, tmp.path(), password).await?;
+    let dest = thumbnail::thumbnail_path(&cache_root, &path);
+    thumbnail::render_thumbnail(&src, &dest, thumbnail::THUMB_SIZE)?;
+    Ok(dest.to_string_lossy().into_owned())
+}
@@ -0,0 +1,336 @@
+// 標準ライブラリ
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 外部クレート
+use tauri::{AppHandle, Manager};
+
+// 内部モジュール (自作)
+use crate::app::commands::backup::apply_diff_to;
+use crate::app::commands::generation::resolve_root;
+use crate::app::commands::vault::password_or_vault;
+use crate::app::state::AppState;
+use crate::core::backup::restore::{self, ConflictPolicy, RestoreKind};
+use crate::core::backup::{archive, compare, crypto, dedup, in_place, selective, volume};
+use crate::core::types::{BackupFileEntry, FolderComparison, UndoRecord};
+use crate::core::utils;
+
+/// 指定した日時の時点のファイルを復元する
+/// datetime 以前で最も新しいバックアップ（差分・アーカイブ・フルコピー・世代アーカイブ内の差分）を選ぶ
+/// 戻り値: 復元に使ったバックアップのパス
+#[tauri::command]
+pub async fn restore_at(
+    app: AppHandle,
+    work_file: String,
+    backup_dir: String,
+    datetime: String,
+    password: Option<String>,
+    output: Option<String>,
+    conflict: Option<String>,
+) -> Result<String, String> {
+    let at = restore::parse_restore_instant(&datetime)?;
+    let conflict: ConflictPolicy = conflict.unwrap_or_default().parse()?;
+    let (strict, use_same_dir) = {
+        let state = app.state::<AppState>();
+        let cfg = state.config.lock().unwrap();
+        (cfg.strict_file_name_match, cfg.use_same_dir_for_temp)
+    };
+
+    let points = restore::collect_restore_points(&work_file, &backup_dir, strict, use_same_dir);
+    let point = restore::find_restore_point(&points, at)
+        .ok_or_else(|| format!("{} 以前のバックアップが見つかりません", datetime))?
+        .clone();
+
+    restore_to(
+        &app,
+        &point.path,
+        &work_file,
+        output.as_deref(),
+        conflict,
+        password,
+    )
+    .await?;
+    Ok(point.path)
+}
+
+/// 暗号化したバックアップ（`.enc`）を tmp へ復号し、復号後のパスを返す
+/// 暗号化されていなければそのまま返す（差分は apply_diff_to 側で .base と一緒に復号する）
+pub(crate) fn decrypt_for_restore(
+    path: &str,
+    password: Option<&str>,
+    tmp: &Path,
+) -> Result<String, String> {
+    // 分割アーカイブは `.enc.001` のように暗号化してから分割している
+    let plain_name = volume::strip_first_volume_ext(path);
+    if !crypto::is_encrypted_name(plain_name) || !Path::new(path).is_file() {
+        return Ok(path.to_string());
+    }
+    let password = password
+        .filter(|p| !p.is_empty())
+        .ok_or("暗号化されたバックアップです。パスワードを入力してください")?;
+    let decrypted = crypto::decrypt_to_dir(Path::new(path), password, tmp)?;
+    Ok(decrypted.to_string_lossy().into_owned())
+}
+
+/// バックアップの種類に応じて out へ復元する（出力先の衝突は考慮しない）
+pub(crate) async fn restore_into(
+    app: &AppHandle,
+    path: &str,
+    work_file: &str,
+    out: &str,
+    password: Option<String>,
+) -> Result<(), String> {
+    let password = password_or_vault(app, Path::new(path), password);
+    let kind = restore::restore_kind_of(path);
+    if kind == RestoreKind::Diff {
+        return apply_diff_to(app, work_file, path, out, password).await;
+    }
+    let tmp = tempfile::tempdir().map_err(|e| e.to_string())?;
+    let path = decrypt_for_restore(path, password.as_deref(), tmp.path())?;
+    match kind {
+        RestoreKind::Archive => archive::restore_archive(&path, out),
+        RestoreKind::Copy => restore::restore_copy(&path, out),
+        RestoreKind::Dedup => dedup::restore_dedup(&path, out),
+        RestoreKind::Diff => unreachable!(),
+    }
+}
+
+/// 出力先を指定して復元する
+/// output が None（または空）のときは作業ファイルの隣へ自動命名で出力する
+/// output を指定した場合は一時出力に復元してから、conflict に従って配置する
+/// 戻り値: 出力したパス（Skip で何も書き出さなかった場合は空文字）
+pub(crate) async fn restore_to(
+    app: &AppHandle,
+    path: &str,
+    work_file: &str,
+    output: Option<&str>,
+    conflict: ConflictPolicy,
+    password: Option<String>,
+) -> Result<String, String> {
+    let Some(output) = output.filter(|o| !o.is_empty()) else {
+        let out = utils::auto_output_path(work_file);
+        restore_into(app, path, work_file, &out, password).await?;
+        return Ok(out);
+    };
+
+    let dest = restore::resolve_output_target(work_file, output);
+    let ts = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let staging = in_place::staging_output_path(&dest, &ts)?;
+    if let Some(parent) = staging.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("ディレクトリ作成失敗: {}", e))?;
+    }
+    if let Err(e) = restore_into(app, path, work_file, &staging.to_string_lossy(), password).await {
+        in_place::discard_staging(&staging);
+        return Err(e);
+    }
+
+    match restore::place_restored(&staging, &dest, conflict) {
+        Ok(placed) => Ok(placed
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default()),
+        Err(e) => {
+            in_place::discard_staging(&staging);
+            Err(e)
+        }
+    }
+}
+
+/// 作業ファイル/フォルダそのものをバックアップの内容で上書きする
+/// 上書き前の状態は `<backup_root>/.wbt_undo/` へ退避し、undo_in_place_restore で戻せる
+#[tauri::command]
+pub async fn restore_in_place(
+    app: AppHandle,
+    path: String,
+    work_file: String,
+    backup_dir: String,
+    password: Option<String>,
+) -> Result<UndoRecord, String> {
+    let ts = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let root = resolve_root(&work_file, &backup_dir);
+    let work_path = Path::new(&work_file);
+
+    // 1. 一時出力へ復元する（作業ファイルにはまだ触れない）
+    let staging = in_place::staging_output_path(work_path, &ts)?;
+    let staging_str = staging.to_string_lossy().into_owned();
+    if let Err(e) = restore_into(&app, &path, &work_file, &staging_str, password).await {
+        in_place::discard_staging(&staging);
+        return Err(e);
+    }
+
+    // 2. 現在の状態を退避してから入れ替える
+    let snapshot = match in_place::take_safety_snapshot(work_path, &root, &ts) {
+        Ok(p) => p,
+        Err(e) => {
+            in_place::discard_staging(&staging);
+            return Err(e);
+        }
+    };
+    if let Err(e) = in_place::replace_work_target(work_path, &staging, &ts) {
+        in_place::discard_staging(&staging);
+        return Err(e);
+    }
+
+    // 3. 取り消し用に記録する
+    let record = UndoRecord {
+        id: ts,
+        work_path: work_file,
+        snapshot_path: snapshot.to_string_lossy().into_owned(),
+        restored_from: path,
+    };
+    in_place::record_undo(&root, record.clone())?;
+    Ok(record)
+}
+
+/// 直近の上書き復元を取り消す
+#[tauri::command]
+pub fn undo_in_place_restore(work_file: String, backup_dir: String) -> Result<UndoRecord, String> {
+    let ts = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let root = resolve_root(&work_file, &backup_dir);
+    in_place::undo_last(&root, Path::new(&work_file), &ts)
+}
+
+/// フォルダのバックアップ（差分・アーカイブ・フルコピー）に含まれるファイルを一覧する
+/// 差分は一時フォルダへ復元してから列挙する
+#[tauri::command]
+pub async fn list_backup_files(
+    app: AppHandle,
+    path: String,
+    work_file: String,
+    password: Option<String>,
+) -> Result<Vec<BackupFileEntry>, String> {
+    let password = password_or_vault(&app, Path::new(&path), password);
+    let tmp = tempfile::tempdir().map_err(|e| e.to_string())?;
+    let path = match restore::restore_kind_of(&path) {
+        RestoreKind::Copy | RestoreKind::Archive => {
+            decrypt_for_restore(&path, password.as_deref(), tmp.path())?
+        }
+        _ => path,
+    };
+    match restore::restore_kind_of(&path) {
+        RestoreKind::Copy => selective::list_folder_files(Path::new(&path)),
+        RestoreKind::Archive => Ok(archive::list_folder_archive_files(Path::new(&path))?
+            .into_iter()
+            .map(|(rel_path, file_size)| BackupFileEntry {
+                rel_path,
+                file_size,
+            })
+            .collect()),
+        RestoreKind::Diff | RestoreKind::Dedup => {
+            let out = tmp.path().join("restored");
+            restore_into(&app, &path, &work_file, &out.to_string_lossy(), password).await?;
+            selective::list_folder_files(&out)
+        }
+    }
+}
+
+/// フォルダのバックアップから選択したファイルだけを復元する
+/// rel_paths は作業フォルダからの相対パス（フォルダを指定した場合は配下すべて）
+/// output を省略した場合は作業フォルダの隣 (`<name>_restored_<ts>/`) に出力する
+/// 戻り値: 書き出したファイルのパス
+#[tauri::command]
+pub async fn restore_selected_files(
+    app: AppHandle,
+    path: String,
+    work_file: String,
+    rel_paths: Vec<String>,
+    output: Option<String>,
+    conflict: Option<String>,
+    password: Option<String>,
+) -> Result<Vec<String>, String> {
+    let conflict: ConflictPolicy = conflict.unwrap_or_default().parse()?;
+    let rels = rel_paths
+        .iter()
+        .map(|r| selective::normalize_rel_path(r))
+        .collect::<Result<Vec<_>, _>>()?;
+    if rels.is_empty() {
+        return Err("復元するファイルが選択されていません".to_string());
+    }
+    let dest_root = match output.filter(|o| !o.is_empty()) {
+        Some(o) => PathBuf::from(o),
+        None => PathBuf::from(utils::auto_output_path(&work_file)),
+    };
+    selective::prepare_dest_root(&dest_root)?;
+    let password = password_or_vault(&app, Path::new(&path), password);
+
+    // 選択したファイルを含む復元結果を一時フォルダに用意してからコピーする
+    let tmp = tempfile::tempdir().map_err(|e| e.to_string())?;
+    let src_root = match restore::restore_kind_of(&path) {
+        RestoreKind::Copy => PathBuf::from(decrypt_for_restore(
+            &path,
+            password.as_deref(),
+            tmp.path(),
+        )?),
+        RestoreKind::Archive => {
+            let path = decrypt_for_restore(&path, password.as_deref(), tmp.path())?;
+            let out = tmp.path().join("restored");
+            archive::extract_folder_entries(Path::new(&path), &rels, &out, password.as_deref())?;
+            out
+        }
+        RestoreKind::Diff | RestoreKind::Dedup => {
+            let out = tmp.path().join("restored");
+            restore_into(&app, &path, &work_file, &out.to_string_lossy(), password).await?;
+            out
+        }
+    };
+    selective::copy_selected(&src_root, &rels, &dest_root, conflict)
+}
+
+/// バックアップの内容をフォルダとして参照できる形にする
+/// フルコピーはそのまま、アーカイブ・差分は tmp 配下へ展開/復元したパスを返す
+pub(crate) async fn materialize_backup(
+    app: &AppHandle,
+    path: &str,
+    work_file: &str,
+    tmp: &Path,
+    password: Option<String>,
+) -> Result<PathBuf, String> {
+    let password = password_or_vault(app, Path::new(path), password);
+    match restore::restore_kind_of(path) {
+        RestoreKind::Copy => Ok(PathBuf::from(decrypt_for_restore(
+            path,
+            password.as_deref(),
+            tmp,
+        )?)),
+        RestoreKind::Archive => {
+            let path = decrypt_for_restore(path, password.as_deref(), tmp)?;
+            let out = tmp.join("restored");
+            archive::extract_folder_entries(
+                Path::new(&path),
+                &[PathBuf::new()],
+                &out,
+                password.as_deref(),
+            )?;
+            Ok(out)
+        }
+        RestoreKind::Diff | RestoreKind::Dedup => {
+            let out = tmp.join("restored");
+            restore_into(app, path, work_file, &out.to_string_lossy(), password).await?;
+            Ok(out)
+        }
+    }
+}
+
+/// フォルダのバックアップ同士、またはバックアップと現在の作業フォルダを比較する
+/// b を省略（または空）した場合は a と現在の作業フォルダを比較する
+/// 作業フォルダ側は ignore_list（タブの除外パターン）と `.wbtignore` に一致するものを除く
+#[tauri::command]
+pub async fn compare_backups(
+    app: AppHandle,
+    a: String,
+    b: Option<String>,
+    work_file: String,
+    password: Option<String>,
+    ignore_list: Vec<String>,
+) -> Result<FolderComparison, String> {
+    let tmp_a = tempfile::tempdir().map_err(|e| e.to_string())?;
+    let tmp_b = tempfile::tempdir().map_err(|e| e.to_string())?;
+
+    let root_a = materialize_backup(&app, &a, &work_file, tmp_a.path(), password.clone()).await?;
+    match b.filter(|b| !b.is_empty()) {
+        Some(b) => {
+            let root_b = materialize_backup(&app, &b, &work_file, tmp_b.path(), password).await?;
+            compare::compare_folders(&root_a, &root_b)
+        }
+        None => compare::compare_with_work(&root_a, Path::new(&work_file), &ignore_list),
+    }
+}
@@ -0,0 +1,109 @@
+// 標準ライブラリ
+use std::path::Path;
+
+// 外部クレート
+use tauri::{AppHandle, Manager};
+
+// 内部モジュール (自作)
+use crate::app::commands::generation::resolve_root;
+use crate::app::state::AppState;
+use crate::core::config::vault::KeyVault;
+use crate::core::types::VaultStatus;
+
+/// 指定されたパスワードを優先し、なければ鍵保管庫からバックアップのルートに対応するものを使う
+/// 保管庫が解錠されていない場合は password をそのまま返す
+pub(crate) fn password_or_vault(
+    app: &AppHandle,
+    path: &Path,
+    password: Option<String>,
+) -> Option<String> {
+    if password.as_deref().is_some_and(|p| !p.is_empty()) {
+        return password;
+    }
+    let state = app.state::<AppState>();
+    let vault = state.vault.lock().unwrap();
+    vault
+        .as_ref()
+        .and_then(|v| v.password_for(path))
+        .map(str::to_string)
+        .or(password)
+}
+
+/// ルートを移した・付け替えた後に、鍵保管庫の登録を新しいルートへ引き継ぐ
+/// 保管庫が解錠されていない場合は何もしない（記述ファイルのあるルートは ID で引けるため）
+pub(crate) fn relocate_vault_entry(app: &AppHandle, old_root: &Path, new_root: &Path) {
+    let state = app.state::<AppState>();
+    let mut vault = state.vault.lock().unwrap();
+    if let Some(v) = vault.as_mut() {
+        if let Err(e) = v.relocate(old_root, new_root) {
+            log::warn!("鍵保管庫の登録を引き継げませんでした: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_vault_status(app: AppHandle) -> VaultStatus {
+    let state = app.state::<AppState>();
+    let vault = state.vault.lock().unwrap();
+    VaultStatus {
+        exists: state.vault_path().exists(),
+        unlocked: vault.is_some(),
+        roots: vault.as_ref().map(|v| v.roots()).unwrap_or_default(),
+    }
+}
+
+/// マスターパスワードで鍵保管庫を解錠する（初回は新しく作る）
+#[tauri::command]
+pub fn unlock_vault(app: AppHandle, master: String) -> Result<VaultStatus, String> {
+    {
+        let state = app.state::<AppState>();
+        let vault = KeyVault::unlock(&state.vault_path(), &master)?;
+        *state.vault.lock().unwrap() = Some(vault);
+    }
+    Ok(get_vault_status(app))
+}
+
+#[tauri::command]
+pub fn lock_vault(app: AppHandle) {
+    let state = app.state::<AppState>();
+    *state.vault.lock().unwrap() = None;
+}
+
+fn with_unlocked_vault<T>(
+    app: &AppHandle,
+    f: impl FnOnce(&mut KeyVault) -> Result<T, String>,
+) -> Result<T, String> {
+    let state = app.state::<AppState>();
+    let mut vault = state.vault.lock().unwrap();
+    let vault = vault
+        .as_mut()
+        .ok_or("鍵保管庫がロックされています。マスターパスワードで解錠してください")?;
+    f(vault)
+}
+
+/// 作業ファイルのバックアップ先に、アーカイブのパスワードを登録する
+#[tauri::command]
+pub fn set_vault_password(
+    app: AppHandle,
+    work_file: String,
+    backup_dir: String,
+    password: String,
+) -> Result<(), String> {
+    let root = resolve_root(&work_file, &backup_dir);
+    with_unlocked_vault(&app, |v| v.set_password(&root, &password))
+}
+
+#[tauri::command]
+pub fn remove_vault_password(
+    app: AppHandle,
+    work_file: String,
+    backup_dir: String,
+) -> Result<bool, String> {
+    let root = resolve_root(&work_file, &backup_dir);
+    with_unlocked_vault(&app, |v| v.remove_password(&root))
+}
+
+#[tauri::command]
+pub fn change_vault_master(app: AppHandle, new_master: String) -> Result<(), String> {
+    with_unlocked_vault(&app, |v| v.change_master(&new_master))
+}
@@ -75,3 +75,15 @@ pub async fn apply_hdiff_wrapper(
     // 3. Sidecar (hpatchz) の実行
     crate::app::hdiff::apply_hdiff(app, &base_full, diff_file, &out_path, strict_hash_check).await
 }
+
+/// 出力先を指定してパッチを適用する（.base の探し方は apply_hdiff_wrapper と同じ）
+pub async fn apply_hdiff_to(
+    app: tauri::AppHandle,
+    work_file: &str,
+    diff_file: &str,
+    out_path: &str,
+    strict_hash_check: bool,
+) -> Result<(), String> {
+    let (base_full, _) = resolve_apply_paths(work_file, diff_file, out_path.to_string())?;
+    crate::app::hdiff::apply_hdiff(app, &base_full, diff_file, out_path, strict_hash_check).await
+}
@@ -3,7 +3,9 @@ use crate::app::menu::*;
 use crate::app::state::AppState;
 use crate::app::tray::*;
 use crate::app::utils;
+use crate::core::backup::root_lock::RootLocks;
 use crate::core::config::loader::*;
+use crate::core::types::SessionData;
 use std::fs;
 use std::sync::Mutex;
 use tauri::App;
@@ -42,12 +44,19 @@ pub fn init(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
         default_config()
     };
 
+    let root_locks = RootLocks::new();
     app.manage(AppState {
         config: Mutex::new(config.clone()),
         config_path,
         i18n: default_i18n(),
+        vault: Mutex::new(None),
+        root_locks: root_locks.clone(),
     });
 
+    // 前回の終了時に中断されたリベース・世代統合の後始末をする（開いていたタブのバックアップ先）
+    let session_path = config_dir.join("session.json");
+    std::thread::spawn(move || recover_session_roots(&root_locks, &session_path));
+
     #[cfg(desktop)]
     {
         let menu = setup_menu(app.handle(), &config)?;
@@ -144,3 +153,17 @@ pub fn init(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// session.json にあるタブのバックアップ先で、中断された作業の後始末をする
+fn recover_session_roots(root_locks: &RootLocks, session_path: &std::path::Path) {
+    let Ok(raw) = fs::read_to_string(session_path) else {
+        return;
+    };
+    let Ok(session) = serde_json::from_str::<SessionData>(&raw) else {
+        return;
+    };
+    for tab in session.tabs.iter().filter(|t| !t.work_file.is_empty()) {
+        let root = crate::app::commands::generation::resolve_root(&tab.work_file, &tab.backup_dir);
+        crate::app::commands::generation::recover_root(root_locks, &root);
+    }
+}
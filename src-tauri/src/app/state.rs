@@ -1,4 +1,6 @@
+use crate::core::backup::root_lock::RootLocks;
 use crate::core::config::assets::*;
+use crate::core::config::vault::{KeyVault, VAULT_FILE};
 use crate::core::types::AppConfig;
 use std::collections::HashMap;
 use std::fs;
@@ -9,6 +11,10 @@ pub struct AppState {
     pub config: Mutex<AppConfig>,
     pub config_path: PathBuf,
     pub i18n: HashMap<String, HashMap<String, String>>,
+    /// 解錠中の鍵保管庫（起動ごとにマスターパスワードで解錠する）
+    pub vault: Mutex<Option<KeyVault>>,
+    /// バックアップルートごとの実行中の作業（リベース・世代統合など）
+    pub root_locks: RootLocks,
 }
 
 impl AppState {
@@ -21,6 +27,13 @@ impl AppState {
         fs::write(&self.config_path, data).map_err(|e: std::io::Error| e.to_string())?;
         Ok(())
     }
+    /// 鍵保管庫のパス（設定ファイルと同じフォルダ）
+    pub fn vault_path(&self) -> PathBuf {
+        self.config_path
+            .parent()
+            .map(|p| p.join(VAULT_FILE))
+            .unwrap_or_else(|| PathBuf::from(VAULT_FILE))
+    }
     pub fn translate(&self, key: &str) -> Result<String, String> {
         let cfg = self.config.lock().unwrap();
         let lang = {
@@ -51,6 +64,8 @@ impl Default for AppState {
             config: Mutex::new(config),
             config_path: PathBuf::new(), // テスト時は空、load_app_config 時に上書き
             i18n,
+            vault: Mutex::new(None),
+            root_locks: RootLocks::new(),
         }
     }
 }
@@ -1,19 +1,226 @@
+use crate::core::backup::crypto;
+use crate::core::backup::volume::{self, VolumeReader};
+use crate::core::ignore::IgnoreMatcher;
+use crate::core::types::AppConfig;
 use crate::core::utils::*;
 use chrono::Local;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
+use liblzma::read::XzDecoder;
+use liblzma::write::XzEncoder;
 use std::fs;
 use std::fs::File;
-use std::io::{self};
-use std::path::{Path, PathBuf};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use tar::Archive;
 use tar::Builder;
-use zip::write::SimpleFileOptions;
+use zip::write::{FileOptions, SimpleFileOptions};
 use zip::ZipArchive;
 use zip::ZipWriter;
 use zip::{AesMode, CompressionMethod};
 
+/// TAR アーカイブの圧縮方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TarCodec {
+    /// .tar.gz / .tgz
+    Gzip,
+    /// .tar.zst / .tzst
+    Zstd,
+    /// .tar.xz / .txz
+    Xz,
+}
+
+impl TarCodec {
+    /// 作成時に付ける拡張子（先頭の `.` なし）
+    pub fn ext(self) -> &'static str {
+        match self {
+            TarCodec::Gzip => "tar.gz",
+            TarCodec::Zstd => "tar.zst",
+            TarCodec::Xz => "tar.xz",
+        }
+    }
+
+    /// 画面の形式名から判定する（`tar` は従来どおり tar.gz）
+    pub fn from_format(format: &str) -> Option<Self> {
+        match format.to_lowercase().as_str() {
+            "tar" | "tar.gz" | "tgz" => Some(TarCodec::Gzip),
+            "tar.zst" | "tzst" | "zst" | "zstd" => Some(TarCodec::Zstd),
+            "tar.xz" | "txz" | "xz" => Some(TarCodec::Xz),
+            _ => None,
+        }
+    }
+
+    /// ファイル名の拡張子から判定する
+    pub fn from_path(name: &str) -> Option<Self> {
+        let lower = name.to_lowercase();
+        TAR_EXTS
+            .iter()
+            .find(|(ext, _)| lower.ends_with(ext))
+            .map(|(_, codec)| *codec)
+    }
+}
+
+/// TAR 系アーカイブの拡張子と圧縮方式
+const TAR_EXTS: &[(&str, TarCodec)] = &[
+    (".tar.gz", TarCodec::Gzip),
+    (".tgz", TarCodec::Gzip),
+    (".tar.zst", TarCodec::Zstd),
+    (".tzst", TarCodec::Zstd),
+    (".tar.xz", TarCodec::Xz),
+    (".txz", TarCodec::Xz),
+];
+
+/// アーカイブとして扱う拡張子
+pub const ARCHIVE_EXTS: &[&str] = &[
+    ".tar.gz", ".tgz", ".tar.zst", ".tzst", ".tar.xz", ".txz", ".zip",
+];
+
+/// ファイル名がアーカイブ（ZIP/TAR 系）の拡張子か（暗号化したもの・分割の1つ目のボリュームを含む）
+pub fn is_archive_name(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    let lower = crypto::strip_encrypted_ext(volume::strip_first_volume_ext(&lower));
+    ARCHIVE_EXTS.iter().any(|ext| lower.ends_with(ext))
+}
+
+/// アーカイブ作成時の設定
+/// level が 0 のときは方式ごとの既定値、threads が 0 のときは CPU 数を使う（gzip は単一スレッド）
+/// volume_size が 0 より大きいときは、そのバイト数ごとのボリューム (`.001`, `.002` …) に分割する
+/// keep_symlinks が true のときはシンボリックリンクを参照先ではなくリンクとして格納する
+/// フォルダは ignore_patterns（タブの除外パターン）とフォルダ直下の `.wbtignore` に一致するものを格納しない
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompressionOptions {
+    pub level: i32,
+    pub threads: u32,
+    pub volume_size: u64,
+    pub keep_symlinks: bool,
+    pub ignore_patterns: Vec<String>,
+}
+
+impl CompressionOptions {
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        Self {
+            level: cfg.archive_compression_level,
+            threads: cfg.archive_compression_threads,
+            volume_size: cfg.archive_volume_size_mb.saturating_mul(1024 * 1024),
+            keep_symlinks: cfg.keep_symlinks,
+            ignore_patterns: Vec::new(),
+        }
+    }
+
+    fn thread_count(&self) -> u32 {
+        if self.threads > 0 {
+            self.threads
+        } else {
+            std::thread::available_parallelism()
+                .map(|n| n.get() as u32)
+                .unwrap_or(1)
+        }
+    }
+}
+
+/// 圧縮方式ごとの書き込み先（finish で圧縮を完了させる）
+enum TarEncoder {
+    Gzip(GzEncoder<File>),
+    Zstd(zstd::Encoder<'static, File>),
+    Xz(XzEncoder<File>),
+}
+
+impl TarEncoder {
+    fn new(file: File, codec: TarCodec, opts: &CompressionOptions) -> Result<Self, String> {
+        match codec {
+            TarCodec::Gzip => {
+                let level = if opts.level > 0 {
+                    Compression::new(opts.level.clamp(1, 9) as u32)
+                } else {
+                    Compression::default()
+                };
+                Ok(TarEncoder::Gzip(GzEncoder::new(file, level)))
+            }
+            TarCodec::Zstd => {
+                let level = if opts.level > 0 {
+                    opts.level.min(zstd::zstd_safe::max_c_level())
+                } else {
+                    zstd::DEFAULT_COMPRESSION_LEVEL
+                };
+                let mut enc = zstd::Encoder::new(file, level).map_err(|e| e.to_string())?;
+                let threads = opts.thread_count();
+                if threads > 1 {
+                    enc.multithread(threads).map_err(|e| e.to_string())?;
+                }
+                Ok(TarEncoder::Zstd(enc))
+            }
+            TarCodec::Xz => {
+                let preset = if opts.level > 0 {
+                    opts.level.min(9) as u32
+                } else {
+                    6
+                };
+                let stream = liblzma::stream::MtStreamBuilder::new()
+                    .preset(preset)
+                    .threads(opts.thread_count())
+                    .check(liblzma::stream::Check::Crc64)
+                    .encoder()
+                    .map_err(|e| e.to_string())?;
+                Ok(TarEncoder::Xz(XzEncoder::new_stream(file, stream)))
+            }
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            TarEncoder::Gzip(enc) => enc.finish().map(|_| ()),
+            TarEncoder::Zstd(enc) => enc.finish().map(|_| ()),
+            TarEncoder::Xz(enc) => enc.finish().map(|_| ()),
+        }
+    }
+}
+
+impl Write for TarEncoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            TarEncoder::Gzip(enc) => enc.write(buf),
+            TarEncoder::Zstd(enc) => enc.write(buf),
+            TarEncoder::Xz(enc) => enc.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            TarEncoder::Gzip(enc) => enc.flush(),
+            TarEncoder::Zstd(enc) => enc.flush(),
+            TarEncoder::Xz(enc) => enc.flush(),
+        }
+    }
+}
+
+/// TAR 系アーカイブを圧縮方式に合わせて開く
+fn open_tar<R: Read + 'static>(file: R, codec: TarCodec) -> Result<Archive<Box<dyn Read>>, String> {
+    let reader: Box<dyn Read> = match codec {
+        TarCodec::Gzip => Box::new(GzDecoder::new(file)),
+        TarCodec::Zstd => Box::new(zstd::Decoder::new(file).map_err(|e| e.to_string())?),
+        TarCodec::Xz => Box::new(XzDecoder::new_multi_decoder(BufReader::new(file))),
+    };
+    Ok(Archive::new(reader))
+}
+
+/// アーカイブを読み込み用に開く（分割アーカイブはボリュームをつなげて1つとして読む）
+/// 戻り値: 読み込み元と、形式の判定に使う名前（小文字・`.001` を除いたもの）
+fn open_archive(archive_path: &Path) -> Result<(VolumeReader, String), String> {
+    let lower = archive_path.to_string_lossy().to_lowercase();
+    let lower = volume::strip_first_volume_ext(&lower).to_string();
+    Ok((VolumeReader::open(archive_path)?, lower))
+}
+
+/// TAR ビルダーの圧縮を完了させる
+fn finish_tar(tar: Builder<TarEncoder>) -> Result<(), String> {
+    tar.into_inner()
+        .map_err(|e| e.to_string())?
+        .finish()
+        .map_err(|e| e.to_string())
+}
+
 /// ファイル単体をZIP圧縮する
 pub fn zip_backup_file(src: &str, backup_dir: &Path, password: &str) -> Result<String, String> {
     let stem = Path::new(src)
@@ -26,12 +233,11 @@ pub fn zip_backup_file(src: &str, backup_dir: &Path, password: &str) -> Result<S
     let file = File::create(&zip_path).map_err(|e| e.to_string())?;
     let mut zip = ZipWriter::new(file);
 
-    let mut options = SimpleFileOptions::default()
-        .compression_method(CompressionMethod::Deflated)
-        .unix_permissions(0o644);
+    let mut options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
     if !password.is_empty() {
         options = options.with_aes_encryption(AesMode::Aes256, password);
     }
+    let options = zip_entry_options(options, fs::metadata(src).ok().as_ref());
 
     let file_name = Path::new(src)
         .file_name()
@@ -49,13 +255,28 @@ pub fn zip_backup_file(src: &str, backup_dir: &Path, password: &str) -> Result<S
 
 /// ファイル単体をTAR.GZ圧縮する
 pub fn tar_backup_file(src: &str, backup_dir: &Path) -> Result<String, String> {
+    tar_backup_file_with(
+        src,
+        backup_dir,
+        TarCodec::Gzip,
+        &CompressionOptions::default(),
+    )
+}
+
+/// ファイル単体を指定した圧縮方式の TAR にする
+pub fn tar_backup_file_with(
+    src: &str,
+    backup_dir: &Path,
+    codec: TarCodec,
+    opts: &CompressionOptions,
+) -> Result<String, String> {
     let stem = Path::new(src).file_stem().unwrap().to_string_lossy();
-    let tar_filename = timestamped_name(&format!("{}.tar.gz", stem));
-    let tar_path = backup_dir.join(tar_filename);
+    // timestamped_name だと `.tar` が名前側に残るため、拡張子全体の前に時刻を入れる
+    let ts = Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let tar_path = backup_dir.join(format!("{}_{}.{}", stem, ts, codec.ext()));
 
     let file = File::create(&tar_path).map_err(|e| e.to_string())?;
-    let enc = GzEncoder::new(file, Compression::default());
-    let mut tar = Builder::new(enc);
+    let mut tar = Builder::new(TarEncoder::new(file, codec, opts)?);
 
     let mut f = File::open(src).map_err(|e| e.to_string())?;
     let file_name = Path::new(src)
@@ -67,16 +288,15 @@ pub fn tar_backup_file(src: &str, backup_dir: &Path) -> Result<String, String> {
     tar.append_file(&file_name, &mut f)
         .map_err(|e| e.to_string())?;
 
-    tar.finish().map_err(|e| e.to_string())?;
+    finish_tar(tar)?;
     Ok(tar_path.to_string_lossy().into_owned())
 }
 
 pub fn restore_archive(archive_path: &str, work_path: &str) -> Result<(), String> {
     let path = Path::new(archive_path);
-    let file_name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+    let (file, file_name) = open_archive(path)?;
 
     if file_name.ends_with(".zip") {
-        let file = File::open(archive_path).map_err(|e| e.to_string())?;
         let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
 
         if archive.len() > 0 {
@@ -84,10 +304,8 @@ pub fn restore_archive(archive_path: &str, work_path: &str) -> Result<(), String
             save_to_work_file(&mut file_in_zip, work_path)?;
             return Ok(());
         }
-    } else if file_name.ends_with(".tar.gz") {
-        let file = File::open(archive_path).map_err(|e| e.to_string())?;
-        let tar_gz = GzDecoder::new(file);
-        let mut archive = Archive::new(tar_gz);
+    } else if let Some(codec) = TarCodec::from_path(&file_name) {
+        let mut archive = open_tar(file, codec)?;
 
         if let Some(Ok(mut entry)) = archive.entries().map_err(|e| e.to_string())?.next() {
             save_to_work_file(&mut entry, work_path)?;
@@ -100,26 +318,41 @@ pub fn restore_archive(archive_path: &str, work_path: &str) -> Result<(), String
 
 /// フォルダをZIP圧縮する
 pub fn compress_dir_zip(src_dir: &Path, dst_file: &Path, password: &str) -> Result<(), String> {
+    compress_dir_zip_with(src_dir, dst_file, password, &CompressionOptions::default())
+}
+
+/// フォルダをZIP圧縮する（更新日時・権限・空のフォルダを格納する）
+/// opts.keep_symlinks が true ならシンボリックリンクをリンクとして格納する
+pub fn compress_dir_zip_with(
+    src_dir: &Path,
+    dst_file: &Path,
+    password: &str,
+    opts: &CompressionOptions,
+) -> Result<(), String> {
     let file = File::create(dst_file).map_err(|e| e.to_string())?;
     let mut zip = zip::ZipWriter::new(file);
 
-    let mut options = zip::write::SimpleFileOptions::default()
-        .compression_method(zip::CompressionMethod::Deflated)
-        .unix_permissions(0o644);
+    let mut base_options =
+        SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
 
     if !password.is_empty() {
-        options = options.with_aes_encryption(zip::AesMode::Aes256, password);
+        base_options = base_options.with_aes_encryption(zip::AesMode::Aes256, password);
     }
 
-    let walk = walkdir::WalkDir::new(src_dir);
-    for entry in walk.into_iter().filter_map(|e| e.ok()) {
+    let parent = src_dir.parent().ok_or("Invalid folder path")?;
+    let ignore = IgnoreMatcher::for_folder(src_dir, &opts.ignore_patterns);
+    let walk = walkdir::WalkDir::new(src_dir).follow_links(!opts.keep_symlinks);
+    for entry in ignore.walk(src_dir, walk).filter_map(|e| e.ok()) {
         let path = entry.path();
-        let name = path
-            .strip_prefix(src_dir.parent().unwrap())
-            .map_err(|e| e.to_string())?;
+        let name = path.strip_prefix(parent).map_err(|e| e.to_string())?;
         let name_str = name.to_string_lossy().to_string();
+        let options = zip_entry_options(base_options, entry.metadata().ok().as_ref());
 
-        if path.is_file() {
+        if entry.file_type().is_symlink() {
+            let target = fs::read_link(path).map_err(|e| e.to_string())?;
+            zip.add_symlink(name_str, target.to_string_lossy(), options)
+                .map_err(|e| e.to_string())?;
+        } else if entry.file_type().is_file() {
             zip.start_file(name_str, options)
                 .map_err(|e| e.to_string())?;
             let mut f = File::open(path).map_err(|e| e.to_string())?;
@@ -133,26 +366,93 @@ pub fn compress_dir_zip(src_dir: &Path, dst_file: &Path, password: &str) -> Resu
     Ok(())
 }
 
+/// ZIP のエントリに元の更新日時と権限を設定する
+fn zip_entry_options<'k>(
+    options: FileOptions<'k, ()>,
+    meta: Option<&fs::Metadata>,
+) -> FileOptions<'k, ()> {
+    let Some(meta) = meta else {
+        return options;
+    };
+    let options = match meta.modified().ok().and_then(system_to_zip_time) {
+        Some(t) => options.last_modified_time(t),
+        None => options,
+    };
+    if meta.file_type().is_symlink() {
+        return options;
+    }
+    options.unix_permissions(unix_mode_of(meta))
+}
+
+#[cfg(unix)]
+fn unix_mode_of(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o777
+}
+
+/// Windows には実行ビットがないので、読み取り専用かどうかだけを反映する
+#[cfg(not(unix))]
+fn unix_mode_of(meta: &fs::Metadata) -> u32 {
+    match (meta.is_dir(), meta.permissions().readonly()) {
+        (true, _) => 0o755,
+        (false, true) => 0o444,
+        (false, false) => 0o644,
+    }
+}
+
 /// フォルダをTAR.GZ圧縮する
 pub fn compress_dir_tar(src_dir: &Path, dst_file: &Path) -> Result<(), String> {
+    compress_dir_tar_with(
+        src_dir,
+        dst_file,
+        TarCodec::Gzip,
+        &CompressionOptions::default(),
+    )
+}
+
+/// フォルダを指定した圧縮方式の TAR にする
+pub fn compress_dir_tar_with(
+    src_dir: &Path,
+    dst_file: &Path,
+    codec: TarCodec,
+    opts: &CompressionOptions,
+) -> Result<(), String> {
     let file = File::create(dst_file).map_err(|e| e.to_string())?;
-    let enc = GzEncoder::new(file, Compression::default());
-    let mut tar = Builder::new(enc);
+    let mut tar = Builder::new(TarEncoder::new(file, codec, opts)?);
 
-    let folder_name = src_dir.file_name().ok_or("Invalid folder name")?;
-    tar.append_dir_all(folder_name, src_dir)
-        .map_err(|e| format!("TAR追加失敗: {}", e))?;
+    // 更新日時・権限・空のフォルダはヘッダに格納される
+    tar.follow_symlinks(!opts.keep_symlinks);
+    let folder_name = Path::new(src_dir.file_name().ok_or("Invalid folder name")?);
+    let ignore = IgnoreMatcher::for_folder(src_dir, &opts.ignore_patterns);
+    let walk = walkdir::WalkDir::new(src_dir).follow_links(!opts.keep_symlinks);
+    for entry in ignore.walk(src_dir, walk) {
+        let entry = entry.map_err(|e| format!("TAR追加失敗: {}", e))?;
+        let rel = entry
+            .path()
+            .strip_prefix(src_dir)
+            .map_err(|e| e.to_string())?;
+        let name = folder_name.join(rel);
+        let added = if entry.file_type().is_dir() {
+            tar.append_dir(&name, entry.path())
+        } else {
+            tar.append_path_with_name(entry.path(), &name)
+        };
+        added.map_err(|e| format!("TAR追加失敗: {}", e))?;
+    }
 
-    tar.finish().map_err(|e| e.to_string())?;
-    Ok(())
+    finish_tar(tar)
 }
 
 /// バックアップを実行する（ファイル・フォルダ両対応）
+/// format: `zip` / `tar`(tar.gz) / `tar.zst` / `tar.xz`
+/// TAR 系でパスワードを指定した場合は、作成したアーカイブを暗号化して `.enc` を付ける
+/// opts.volume_size を指定した場合は、最後にボリュームへ分割して1つ目のパスを返す
 pub fn execute_archive_backup(
     src: &str,
     backup_dir_opt: Option<PathBuf>,
     format: &str,
     password: &str,
+    opts: &CompressionOptions,
 ) -> Result<String, String> {
     let src_path = Path::new(src);
 
@@ -166,36 +466,44 @@ pub fn execute_archive_backup(
             .map_err(|e| format!("アーカイブ先ディレクトリの作成に失敗しました: {}", e))?;
     }
 
-    if src_path.is_dir() {
+    let dest = if src_path.is_dir() {
         // フォルダの場合: compress_dir_zip / compress_dir_tar を使う
         let folder_name = src_path
             .file_name()
             .ok_or("Invalid folder name")?
             .to_string_lossy();
         let ts = Local::now().format("%Y%m%d_%H%M%S").to_string();
-        let (ext, dst_path) = if format == "zip" {
-            let p = target_dir.join(format!("{}_{}.zip", folder_name, ts));
-            ("zip", p)
-        } else {
-            let p = target_dir.join(format!("{}_{}.tar.gz", folder_name, ts));
-            ("tar.gz", p)
-        };
-
-        if ext == "zip" {
-            compress_dir_zip(src_path, &dst_path, password)?;
+        if format == "zip" {
+            let dst_path = target_dir.join(format!("{}_{}.zip", folder_name, ts));
+            compress_dir_zip_with(src_path, &dst_path, password, opts)?;
+            dst_path.to_string_lossy().into_owned()
         } else {
-            compress_dir_tar(src_path, &dst_path)?;
+            let codec = TarCodec::from_format(format).unwrap_or(TarCodec::Gzip);
+            let dst_path = target_dir.join(format!("{}_{}.{}", folder_name, ts, codec.ext()));
+            compress_dir_tar_with(src_path, &dst_path, codec, opts)?;
+            encrypt_if_needed(&dst_path.to_string_lossy(), password)?
         }
-        Ok(dst_path.to_string_lossy().into_owned())
     } else {
         // ファイルの場合: 従来通り
-        let dest_str = if format == "zip" {
+        if format == "zip" {
             zip_backup_file(src, &target_dir, password)?
         } else {
-            tar_backup_file(src, &target_dir)?
-        };
-        Ok(dest_str)
+            let codec = TarCodec::from_format(format).unwrap_or(TarCodec::Gzip);
+            let tar_path = tar_backup_file_with(src, &target_dir, codec, opts)?;
+            encrypt_if_needed(&tar_path, password)?
+        }
+    };
+    let first = volume::split_into_volumes(Path::new(&dest), opts.volume_size)?;
+    Ok(first.to_string_lossy().into_owned())
+}
+
+/// パスワードが指定されていればその場で暗号化し、最終的なパスを返す
+fn encrypt_if_needed(path: &str, password: &str) -> Result<String, String> {
+    if password.is_empty() {
+        return Ok(path.to_string());
     }
+    let encrypted = crypto::encrypt_in_place(Path::new(path), password)?;
+    Ok(encrypted.to_string_lossy().into_owned())
 }
 
 pub fn execute_generation_archive(
@@ -204,6 +512,7 @@ pub fn execute_generation_archive(
     work_file: &str,
     backup_dir: &str,
     password: &str,
+    opts: &CompressionOptions,
 ) -> Result<(), String> {
     let backup_path = if backup_dir.is_empty() {
         default_backup_dir(work_file)
@@ -224,14 +533,22 @@ pub fn execute_generation_archive(
         .ok_or_else(|| format!("世代 {} のフォルダが見つかりません", target_n))?;
 
     let folder_name = src_path.file_name().unwrap().to_string_lossy();
-    let ext = if format == "tar" { "tar.gz" } else { "zip" };
+    let codec = TarCodec::from_format(format);
+    let ext = codec.map_or("zip", TarCodec::ext);
     let dst_path = backup_path.join(format!("{}.{}", folder_name, ext));
 
-    if format == "tar" {
-        compress_dir_tar(&src_path, &dst_path)?;
-    } else {
-        compress_dir_zip(&src_path, &dst_path, password)?;
-    }
+    // ZIP は AES、TAR 系はアーカイブ全体を暗号化する
+    let dst_path = match codec {
+        Some(codec) => {
+            compress_dir_tar_with(&src_path, &dst_path, codec, opts)?;
+            PathBuf::from(encrypt_if_needed(&dst_path.to_string_lossy(), password)?)
+        }
+        None => {
+            compress_dir_zip_with(&src_path, &dst_path, password, opts)?;
+            dst_path
+        }
+    };
+    let dst_path = volume::split_into_volumes(&dst_path, opts.volume_size)?;
 
     if dst_path.exists() && fs::metadata(&dst_path).map(|m| m.len()).unwrap_or(0) > 0 {
         fs::remove_dir_all(&src_path).map_err(|e| format!("フォルダ削除失敗: {}", e))?;
@@ -257,7 +574,9 @@ pub fn clear_cache_directory(cache_root: &Path) -> Result<(), String> {
     Ok(())
 }
 
-/// アーカイブ（ZIP/TAR.GZ）をキャッシュルートへ展開する
+/// アーカイブ（ZIP/TAR 系）をキャッシュルートへ展開する
+/// 暗号化したアーカイブ (`.enc`) は一時フォルダへ復号してから展開する
+/// 分割アーカイブは1つ目のボリューム (`.001`) を指定する
 pub fn extract_to_cache(
     archive_path: &str,
     cache_root: &Path,
@@ -267,14 +586,23 @@ pub fn extract_to_cache(
     if !archive_file.exists() {
         return Err("アーカイブファイルが見つかりません".to_string());
     }
+    let plain_name = volume::strip_first_volume_ext(archive_path);
+    if crypto::is_encrypted_name(plain_name) {
+        let password = password
+            .filter(|p| !p.is_empty())
+            .ok_or("暗号化されたアーカイブです。パスワードを指定してください")?;
+        let tmp = tempfile::tempdir().map_err(|e| e.to_string())?;
+        let plain = crypto::decrypt_to_dir(archive_file, &password, tmp.path())?;
+        return extract_to_cache(&plain.to_string_lossy(), cache_root, None);
+    }
 
     fs::create_dir_all(cache_root).map_err(|e| e.to_string())?;
-    let f_name_lower = archive_path.to_lowercase();
+    let f_name_lower = plain_name.to_lowercase();
 
     if f_name_lower.ends_with(".zip") {
         extract_zip(archive_file, cache_root, password)?;
-    } else if f_name_lower.contains(".tar.gz") || f_name_lower.ends_with(".tgz") {
-        extract_targz(archive_file, cache_root)?;
+    } else if let Some(codec) = TarCodec::from_path(&f_name_lower) {
+        extract_tar(archive_file, cache_root, codec)?;
     }
 
     Ok(cache_root.to_string_lossy().to_string())
@@ -285,8 +613,9 @@ fn extract_zip(
     cache_root: &Path,
     password: Option<String>,
 ) -> Result<(), String> {
-    let file = File::open(archive_file).map_err(|e| e.to_string())?;
+    let file = VolumeReader::open(archive_file)?;
     let mut zip = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let mut dir_times = Vec::new();
 
     for i in 0..zip.len() {
         let mut file = if let Some(ref p) = password {
@@ -296,36 +625,30 @@ fn extract_zip(
         }
         .map_err(|e| format!("展開エラー: {}", e))?;
 
+        let Some(rel_path) = cache_relative_path(&sanitize_entry_name(file.name())) else {
+            continue;
+        };
+        let dest = cache_root.join(rel_path);
         if file.is_dir() {
+            prepare_unpack_path(cache_root, &dest, true)?;
+            push_dir_time(&mut dir_times, dest, zip_entry_time(&file));
             continue;
         }
-
-        let sanitized = file.name().replace('\u{F05C}', "/").replace('\\', "/");
-        let rel_path = Path::new(&sanitized);
-
-        if let Some(base_dir) = find_base_component(rel_path) {
-            if let Some(fname) = rel_path.file_name() {
-                let dest = cache_root.join(base_dir).join(fname);
-                if let Some(p) = dest.parent() {
-                    fs::create_dir_all(p).ok();
-                }
-                let mut outfile = File::create(&dest).map_err(|e| e.to_string())?;
-                std::io::copy(&mut file, &mut outfile).map_err(|e| e.to_string())?;
-            }
-        }
+        unpack_zip_entry(&mut file, cache_root, &dest)?;
     }
+    restore_dir_times(&dir_times);
     Ok(())
 }
 
-fn extract_targz(archive_file: &Path, cache_root: &Path) -> Result<(), String> {
-    let tar_gz = File::open(archive_file).map_err(|e| e.to_string())?;
-    let tar = flate2::read::GzDecoder::new(tar_gz);
-    let mut archive = tar::Archive::new(tar);
+fn extract_tar(archive_file: &Path, cache_root: &Path, codec: TarCodec) -> Result<(), String> {
+    let file = VolumeReader::open(archive_file)?;
+    let mut archive = open_tar(file, codec)?;
     let entries = archive.entries().map_err(|e| e.to_string())?;
+    let mut dir_times = Vec::new();
 
     for entry in entries {
         let mut entry = entry.map_err(|e| e.to_string())?;
-        if !entry.header().entry_type().is_file() {
+        if !is_extractable_tar_entry(&entry) {
             continue;
         }
 
@@ -333,29 +656,545 @@ fn extract_targz(archive_file: &Path, cache_root: &Path) -> Result<(), String> {
             .path()
             .map(|p| p.to_string_lossy().into_owned())
             .unwrap_or_default();
-        let sanitized = path_owned.replace('\u{F05C}', "/").replace('\\', "/");
-        let rel_path = Path::new(&sanitized);
-
-        if let Some(base_dir) = find_base_component(rel_path) {
-            if let Some(fname) = rel_path.file_name() {
-                let dest = cache_root.join(base_dir).join(fname);
-                if let Some(p) = dest.parent() {
-                    fs::create_dir_all(p).ok();
-                }
-                entry.unpack(&dest).map_err(|e| e.to_string())?;
+        let Some(rel_path) = cache_relative_path(&sanitize_entry_name(&path_owned)) else {
+            continue;
+        };
+        let dest = cache_root.join(rel_path);
+        unpack_tar_entry(&mut entry, cache_root, &dest, &mut dir_times)?;
+    }
+    restore_dir_times(&dir_times);
+    Ok(())
+}
+
+/// 展開キャッシュでの相対パス（`baseN_<ts>/` 以下の構成を保つ）
+/// `..` などを含む不正なパスは None
+fn cache_relative_path(name: &str) -> Option<PathBuf> {
+    let mut parts = name.split('/').filter(|s| !s.is_empty());
+    let mut rel = PathBuf::from(parts.find(|s| s.starts_with("base"))?);
+    for part in parts {
+        if part == "." || part == ".." || part.contains(':') {
+            return None;
+        }
+        rel.push(part);
+    }
+    Some(rel)
+}
+
+/// フォルダの更新日時（中身を書き込むと変わるため、展開の最後にまとめて設定する）
+type DirTimes = Vec<(PathBuf, SystemTime)>;
+
+fn push_dir_time(dir_times: &mut DirTimes, dir: PathBuf, time: Option<SystemTime>) {
+    if let Some(t) = time {
+        dir_times.push((dir, t));
+    }
+}
+
+/// 日時を戻せなくても展開した内容は使えるため、失敗はログに残すだけにする
+fn restore_dir_times(dir_times: &DirTimes) {
+    for (dir, t) in dir_times {
+        if let Err(e) = set_modified_time(dir, *t) {
+            log::warn!("{}", e);
+        }
+    }
+}
+
+fn zip_entry_time<R: Read>(entry: &zip::read::ZipFile<'_, R>) -> Option<SystemTime> {
+    entry.last_modified().and_then(zip_time_to_system)
+}
+
+/// 展開先 root の中の out へ書き込めるよう、途中のフォルダを作る（is_dir なら out 自体も）
+/// 途中にシンボリックリンクがあると、リンク先（展開先の外のこともある）へ書き込んでしまうためエラーにする
+/// out 自体が既存のリンクなら、リンク先ではなくリンクを置き換えるよう削除しておく
+fn prepare_unpack_path(root: &Path, out: &Path, is_dir: bool) -> Result<(), String> {
+    let rel = out
+        .strip_prefix(root)
+        .map_err(|_| format!("展開先の外へは書き込めません: {}", out.display()))?;
+    let parts: Vec<_> = rel.components().collect();
+    let dirs = if is_dir {
+        parts.len()
+    } else {
+        parts.len().saturating_sub(1)
+    };
+    let mut current = root.to_path_buf();
+    for part in &parts[..dirs] {
+        current.push(part);
+        match fs::symlink_metadata(&current) {
+            Ok(m) if m.file_type().is_symlink() => {
+                return Err(format!(
+                    "リンクを経由しての展開はできません: {}",
+                    out.display()
+                ))
+            }
+            Ok(m) if m.is_dir() => {}
+            Ok(_) => {
+                return Err(format!(
+                    "フォルダの位置にファイルがあります: {}",
+                    current.display()
+                ))
             }
+            Err(_) => fs::create_dir_all(&current).map_err(|e| e.to_string())?,
         }
     }
+    if !is_dir && fs::symlink_metadata(out).is_ok_and(|m| m.file_type().is_symlink()) {
+        fs::remove_file(out).map_err(|e| e.to_string())?;
+    }
     Ok(())
 }
 
-fn find_base_component(path: &Path) -> Option<String> {
-    path.components().find_map(|c| {
-        let s = c.as_os_str().to_string_lossy();
-        if s.starts_with("base") {
-            Some(s.into_owned())
-        } else {
-            None
+/// リンク先が展開先 root の中に収まるか（絶対パスや、`..` で root の外へ出るものは作らない）
+fn check_link_target(root: &Path, link: &Path, target: &Path) -> Result<(), String> {
+    let outside = || {
+        format!(
+            "展開先の外を指すリンクは作成できません: {} -> {}",
+            link.display(),
+            target.display()
+        )
+    };
+    let parent = link
+        .parent()
+        .and_then(|p| p.strip_prefix(root).ok())
+        .ok_or_else(outside)?;
+    let mut depth = parent.components().count();
+    for part in target.components() {
+        match part {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return Err(outside()),
+        }
+    }
+    Ok(())
+}
+
+/// ZIP のエントリを展開先 root の中の out へ書き出し、更新日時と権限を戻す（リンクはリンクとして作る）
+fn unpack_zip_entry<R: Read>(
+    entry: &mut zip::read::ZipFile<'_, R>,
+    root: &Path,
+    out: &Path,
+) -> Result<(), String> {
+    prepare_unpack_path(root, out, false)?;
+    if entry.is_symlink() {
+        let mut target = String::new();
+        entry
+            .read_to_string(&mut target)
+            .map_err(|e| e.to_string())?;
+        let target = Path::new(&target);
+        check_link_target(root, out, target)?;
+        let is_dir = out.parent().is_some_and(|p| p.join(target).is_dir());
+        return create_symlink(target, out, is_dir)
+            .map_err(|e| format!("リンクの作成に失敗しました {}: {}", out.display(), e));
+    }
+
+    let mut outfile = File::create(out).map_err(|e| e.to_string())?;
+    io::copy(entry, &mut outfile).map_err(|e| e.to_string())?;
+    drop(outfile);
+    if let Some(t) = zip_entry_time(entry) {
+        set_modified_time(out, t)?;
+    }
+    #[cfg(unix)]
+    if let Some(mode) = entry.unix_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(out, fs::Permissions::from_mode(mode & 0o777))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 展開の対象にする TAR のエントリ（ファイル・フォルダ・シンボリックリンク）
+fn is_extractable_tar_entry<R: Read>(entry: &tar::Entry<'_, R>) -> bool {
+    let kind = entry.header().entry_type();
+    kind.is_file() || kind.is_dir() || kind.is_symlink()
+}
+
+/// TAR のエントリを展開先 root の中の out へ書き出す（更新日時・権限・リンクは tar が戻す）
+/// ファイル・フォルダ・シンボリックリンク以外（ハードリンクなど）は書き出さない
+/// フォルダの更新日時は dir_times に積んで、最後に設定する
+fn unpack_tar_entry<R: Read>(
+    entry: &mut tar::Entry<'_, R>,
+    root: &Path,
+    out: &Path,
+    dir_times: &mut DirTimes,
+) -> Result<(), String> {
+    if !is_extractable_tar_entry(entry) {
+        return Ok(());
+    }
+    let kind = entry.header().entry_type();
+    if kind.is_symlink() {
+        let target = entry
+            .link_name()
+            .map_err(|e| e.to_string())?
+            .ok_or("リンク先のないリンクです")?
+            .into_owned();
+        check_link_target(root, out, &target)?;
+    }
+    prepare_unpack_path(root, out, kind.is_dir())?;
+    entry.unpack(out).map_err(|e| e.to_string())?;
+    if entry.header().entry_type().is_dir() {
+        push_dir_time(dir_times, out.to_path_buf(), tar_entry_time(entry.header()));
+    }
+    Ok(())
+}
+
+fn tar_entry_time(header: &tar::Header) -> Option<SystemTime> {
+    header
+        .mtime()
+        .ok()
+        .map(|t| SystemTime::UNIX_EPOCH + Duration::from_secs(t))
+}
+
+/// 世代アーカイブ内のエントリを指す仮想パスの区切り
+/// 例: `<root>/base2_<ts>.zip::base2_<ts>/work.clip.<ts>.hdiff.diff`
+pub const ARCHIVED_ENTRY_SEPARATOR: &str = "::";
+
+/// アーカイブ内エントリの情報（展開せずに読み取ったもの）
+#[derive(Debug, Clone)]
+pub struct ArchiveEntryInfo {
+    pub name: String,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub is_dir: bool,
+}
+
+/// アーカイブの拡張子を除いた名前を返す（対象外の拡張子ならそのまま）
+pub fn strip_archive_ext(name: &str) -> &str {
+    let plain = crypto::strip_encrypted_ext(volume::strip_first_volume_ext(name));
+    for ext in ARCHIVE_EXTS {
+        if let Some(stem) = plain.strip_suffix(ext) {
+            return stem;
         }
-    })
+    }
+    name
+}
+
+/// アーカイブ内エントリの仮想パスを組み立てる
+pub fn archived_entry_path(archive_path: &Path, entry_name: &str) -> String {
+    format!(
+        "{}{}{}",
+        archive_path.to_string_lossy(),
+        ARCHIVED_ENTRY_SEPARATOR,
+        entry_name
+    )
+}
+
+/// 仮想パスを (アーカイブのパス, エントリ名) に分解する
+/// 通常のパスの場合は None
+pub fn split_archived_entry_path(path: &str) -> Option<(PathBuf, String)> {
+    let (archive, entry) = path.split_once(ARCHIVED_ENTRY_SEPARATOR)?;
+    if entry.is_empty() || strip_archive_ext(&archive.to_lowercase()).len() == archive.len() {
+        return None;
+    }
+    Some((PathBuf::from(archive), entry.to_string()))
+}
+
+/// アーカイブ（ZIP/TAR 系）のエントリ一覧を展開せずに取得する
+pub fn list_archive_entries(archive_path: &Path) -> Result<Vec<ArchiveEntryInfo>, String> {
+    let (file, lower) = open_archive(archive_path)?;
+    let mut list = Vec::new();
+
+    if lower.ends_with(".zip") {
+        let mut zip = ZipArchive::new(file).map_err(|e| e.to_string())?;
+        for i in 0..zip.len() {
+            // 暗号化されていてもヘッダは読めるので raw で開く
+            let entry = zip.by_index_raw(i).map_err(|e| e.to_string())?;
+            list.push(ArchiveEntryInfo {
+                name: sanitize_entry_name(entry.name()),
+                size: entry.size(),
+                modified: entry.last_modified().and_then(zip_time_to_system),
+                is_dir: entry.is_dir(),
+            });
+        }
+    } else if let Some(codec) = TarCodec::from_path(&lower) {
+        let mut archive = open_tar(file, codec)?;
+        for entry in archive.entries().map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let header = entry.header();
+            let name = entry
+                .path()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            list.push(ArchiveEntryInfo {
+                name: sanitize_entry_name(&name),
+                size: header.size().unwrap_or(0),
+                modified: tar_entry_time(header),
+                is_dir: header.entry_type().is_dir(),
+            });
+        }
+    } else {
+        return Err("サポートされていないアーカイブ形式です".to_string());
+    }
+    Ok(list)
+}
+
+/// ZIP にパスワード付きのエントリがあるか（分割したものは1つ目のボリュームを指定する）
+pub fn is_zip_encrypted(archive_path: &Path) -> bool {
+    let Ok(file) = VolumeReader::open(archive_path) else {
+        return false;
+    };
+    let Ok(mut zip) = ZipArchive::new(file) else {
+        return false;
+    };
+    (0..zip.len()).any(|i| zip.by_index_raw(i).map(|e| e.encrypted()).unwrap_or(false))
+}
+
+/// 世代アーカイブから .base と指定した差分だけを dest へ取り出す
+/// 世代フォルダ内の構成を保ったまま展開する（フォルダ形式の .base もそのまま）
+/// 戻り値: 取り出した差分のパス
+pub fn extract_diff_with_base(
+    archive_path: &Path,
+    entry_name: &str,
+    dest: &Path,
+    password: Option<&str>,
+) -> Result<PathBuf, String> {
+    let target_rel =
+        generation_relative_path(entry_name).ok_or("世代アーカイブ内の差分ではありません")?;
+    let wanted = |rel: &Path| {
+        rel == target_rel
+            || rel
+                .components()
+                .next()
+                .is_some_and(|c| c.as_os_str().to_string_lossy().ends_with(".base"))
+    };
+
+    fs::create_dir_all(dest).map_err(|e| e.to_string())?;
+    let (file, lower) = open_archive(archive_path)?;
+    let mut found_base = false;
+    let mut dir_times = Vec::new();
+
+    if lower.ends_with(".zip") {
+        let mut zip = ZipArchive::new(file).map_err(|e| e.to_string())?;
+        for i in 0..zip.len() {
+            let mut entry = match password {
+                Some(p) if !p.is_empty() => zip.by_index_decrypt(i, p.as_bytes()),
+                _ => zip.by_index(i),
+            }
+            .map_err(|e| format!("展開エラー: {}", e))?;
+
+            let Some(rel) = generation_relative_path(&sanitize_entry_name(entry.name())) else {
+                continue;
+            };
+            if !wanted(&rel) {
+                continue;
+            }
+            found_base |= rel != target_rel;
+            let out = dest.join(&rel);
+            if entry.is_dir() {
+                prepare_unpack_path(dest, &out, true)?;
+                push_dir_time(&mut dir_times, out, zip_entry_time(&entry));
+                continue;
+            }
+            unpack_zip_entry(&mut entry, dest, &out)?;
+        }
+    } else if let Some(codec) = TarCodec::from_path(&lower) {
+        let mut archive = open_tar(file, codec)?;
+        for entry in archive.entries().map_err(|e| e.to_string())? {
+            let mut entry = entry.map_err(|e| e.to_string())?;
+            let name = entry
+                .path()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let Some(rel) = generation_relative_path(&sanitize_entry_name(&name)) else {
+                continue;
+            };
+            if !wanted(&rel) {
+                continue;
+            }
+            found_base |= rel != target_rel;
+            let out = dest.join(&rel);
+            unpack_tar_entry(&mut entry, dest, &out, &mut dir_times)?;
+        }
+    } else {
+        return Err("サポートされていないアーカイブ形式です".to_string());
+    }
+    restore_dir_times(&dir_times);
+
+    let diff = dest.join(&target_rel);
+    if !diff.is_file() {
+        return Err(format!(
+            "アーカイブ内に差分が見つかりません: {}",
+            entry_name
+        ));
+    }
+    if !found_base {
+        return Err("アーカイブ内に .base が見つかりません".to_string());
+    }
+    Ok(diff)
+}
+
+/// 仮想パスの差分を復元に使える場所へ用意する
+/// 展開キャッシュに同じ世代の差分と .base があればそれを使い、
+/// なければ必要なエントリだけを staging_dir へ取り出す
+pub fn stage_archived_diff(
+    virtual_path: &str,
+    cache_root: &Path,
+    staging_dir: &Path,
+    password: Option<&str>,
+) -> Result<PathBuf, String> {
+    let (archive_path, entry_name) = split_archived_entry_path(virtual_path)
+        .ok_or("アーカイブ内エントリのパスではありません")?;
+
+    let folder_name = strip_archive_ext(
+        &archive_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy(),
+    )
+    .to_string();
+    if let Some(rel) = generation_relative_path(&entry_name) {
+        let cached_gen = cache_root.join(&folder_name);
+        let cached_diff = cached_gen.join(&rel);
+        let has_base = fs::read_dir(&cached_gen)
+            .map(|rd| {
+                rd.flatten()
+                    .any(|e| e.file_name().to_string_lossy().ends_with(".base"))
+            })
+            .unwrap_or(false);
+        if cached_diff.is_file() && has_base {
+            return Ok(cached_diff);
+        }
+    }
+
+    extract_diff_with_base(&archive_path, &entry_name, staging_dir, password)
+}
+
+fn sanitize_entry_name(name: &str) -> String {
+    name.replace(['\u{F05C}', '\\'], "/")
+}
+
+/// `baseN_<ts>/...` 形式のエントリ名から世代フォルダ以下の相対パスを取り出す
+/// `..` などを含む不正なパスは None
+fn generation_relative_path(entry_name: &str) -> Option<PathBuf> {
+    let mut parts = entry_name.split('/').filter(|s| !s.is_empty());
+    parts.find(|s| is_generation_folder_name(s))?;
+    let mut rel = PathBuf::new();
+    for part in parts {
+        if part == "." || part == ".." || part.contains(':') {
+            return None;
+        }
+        rel.push(part);
+    }
+    if rel.as_os_str().is_empty() {
+        None
+    } else {
+        Some(rel)
+    }
+}
+
+pub(crate) fn is_generation_folder_name(name: &str) -> bool {
+    name.strip_prefix("base")
+        .and_then(|s| s.split_once('_'))
+        .is_some_and(|(n, _)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn zip_time_to_system(t: zip::DateTime) -> Option<SystemTime> {
+    let naive = chrono::NaiveDate::from_ymd_opt(t.year() as i32, t.month() as u32, t.day() as u32)?
+        .and_hms_opt(t.hour() as u32, t.minute() as u32, t.second() as u32)?;
+    let local = naive.and_local_timezone(Local).single()?;
+    Some(local.into())
+}
+
+/// ZIP の日時はローカル時刻・2秒単位（1980年より前は表せない）
+fn system_to_zip_time(t: SystemTime) -> Option<zip::DateTime> {
+    use chrono::{Datelike, Timelike};
+    let local = chrono::DateTime::<Local>::from(t);
+    zip::DateTime::from_date_and_time(
+        u16::try_from(local.year()).ok()?,
+        local.month() as u8,
+        local.day() as u8,
+        local.hour() as u8,
+        local.minute() as u8,
+        local.second() as u8,
+    )
+    .ok()
+}
+
+/// フォルダのアーカイブ（`<folder>/...` 形式）に含まれるファイルを、
+/// 先頭のフォルダ名を除いた相対パスで返す
+pub fn list_folder_archive_files(archive_path: &Path) -> Result<Vec<(String, u64)>, String> {
+    let mut list: Vec<(String, u64)> = list_archive_entries(archive_path)?
+        .into_iter()
+        .filter(|e| !e.is_dir)
+        .filter_map(|e| {
+            let (_, rel) = e.name.trim_start_matches('/').split_once('/')?;
+            (!rel.is_empty()).then(|| (rel.to_string(), e.size))
+        })
+        .collect();
+    list.sort();
+    Ok(list)
+}
+
+/// フォルダのアーカイブから、指定した相対パス（ファイルまたはフォルダ）だけを dest へ取り出す
+/// 更新日時・権限・空のフォルダ・シンボリックリンクも戻す
+/// 戻り値: 取り出したファイル数
+pub fn extract_folder_entries(
+    archive_path: &Path,
+    rel_paths: &[PathBuf],
+    dest: &Path,
+    password: Option<&str>,
+) -> Result<usize, String> {
+    let wanted = |name: &str| -> Option<PathBuf> {
+        let (_, rel) = name.trim_start_matches('/').split_once('/')?;
+        let rel = Path::new(rel);
+        if rel
+            .components()
+            .any(|c| !matches!(c, std::path::Component::Normal(_)))
+        {
+            return None;
+        }
+        rel_paths
+            .iter()
+            .any(|w| rel.starts_with(w))
+            .then(|| rel.to_path_buf())
+    };
+
+    fs::create_dir_all(dest).map_err(|e| e.to_string())?;
+    let (file, lower) = open_archive(archive_path)?;
+    let mut count = 0;
+    let mut dir_times = Vec::new();
+
+    if lower.ends_with(".zip") {
+        let mut zip = ZipArchive::new(file).map_err(|e| e.to_string())?;
+        for i in 0..zip.len() {
+            let mut entry = match password {
+                Some(p) if !p.is_empty() => zip.by_index_decrypt(i, p.as_bytes()),
+                _ => zip.by_index(i),
+            }
+            .map_err(|e| format!("展開エラー: {}", e))?;
+            let Some(rel) = wanted(&sanitize_entry_name(entry.name())) else {
+                continue;
+            };
+            let out = dest.join(rel);
+            // 空のフォルダも作る
+            if entry.is_dir() {
+                prepare_unpack_path(dest, &out, true)?;
+                push_dir_time(&mut dir_times, out, zip_entry_time(&entry));
+                continue;
+            }
+            unpack_zip_entry(&mut entry, dest, &out)?;
+            count += 1;
+        }
+    } else if let Some(codec) = TarCodec::from_path(&lower) {
+        let mut archive = open_tar(file, codec)?;
+        for entry in archive.entries().map_err(|e| e.to_string())? {
+            let mut entry = entry.map_err(|e| e.to_string())?;
+            if !is_extractable_tar_entry(&entry) {
+                continue;
+            }
+            let name = entry
+                .path()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let Some(rel) = wanted(&sanitize_entry_name(&name)) else {
+                continue;
+            };
+            let out = dest.join(rel);
+            unpack_tar_entry(&mut entry, dest, &out, &mut dir_times)?;
+            if !entry.header().entry_type().is_dir() {
+                count += 1;
+            }
+        }
+    } else {
+        return Err("サポートされていないアーカイブ形式です".to_string());
+    }
+    restore_dir_times(&dir_times);
+    Ok(count)
 }
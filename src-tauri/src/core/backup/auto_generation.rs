@@ -1,4 +1,5 @@
 use crate::core::types::BackupGenInfo;
+use crate::core::utils;
 use regex::Regex;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -42,6 +43,20 @@ pub fn get_latest_generation(root: &Path) -> Result<Option<BackupGenInfo>, Strin
     }
 }
 
+/// 指定番号の世代フォルダ `baseN_...` を探す（アーカイブ済みのものは対象外）
+pub fn find_generation_dir(root: &Path, idx: u32) -> Result<PathBuf, String> {
+    let prefix = format!("base{}_", idx);
+    fs::read_dir(root)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .find(|e| {
+            e.file_type().map(|t| t.is_dir()).unwrap_or(false)
+                && e.file_name().to_string_lossy().starts_with(&prefix)
+        })
+        .map(|e| e.path())
+        .ok_or_else(|| format!("世代 {} のフォルダが見つかりません", idx))
+}
+
 /// 最新の世代フォルダを取得（なければ作成）
 pub fn resolve_generation_dir(root: &Path, work_path: &str) -> Result<(PathBuf, i32), String> {
     match get_latest_generation(root)? {
@@ -64,6 +79,17 @@ pub fn resolve_generation_dir(root: &Path, work_path: &str) -> Result<(PathBuf,
 ///   └── ...
 pub fn create_new_generation(root: &Path, idx: i32, work_path: &str) -> Result<PathBuf, String> {
     let ts = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+    create_new_generation_at(root, idx, work_path, &ts)
+}
+
+/// タイムスタンプを指定して新しい世代フォルダ `baseN_<ts>` を作成する
+/// インポート時など、過去の時刻で世代を作りたい場合に使う
+pub fn create_new_generation_at(
+    root: &Path,
+    idx: i32,
+    work_path: &str,
+    ts: &str,
+) -> Result<PathBuf, String> {
     let new_dir_name = format!("base{}_{}", idx, ts);
     let new_dir_path = root.join(new_dir_name);
 
@@ -76,17 +102,10 @@ pub fn create_new_generation(root: &Path, idx: i32, work_path: &str) -> Result<P
         .to_string_lossy();
 
     if src.is_dir() {
-        // フォルダの場合: <name>.base/ として丸ごとコピー
+        // フォルダの場合: <name>.base/ として丸ごとコピー（差分の元になるのでリンクは参照先をコピー）
         let base_dir = new_dir_path.join(format!("{}.base", entry_name));
-        fs_extra::dir::copy(
-            src,
-            &base_dir,
-            &fs_extra::dir::CopyOptions {
-                copy_inside: true,
-                ..Default::default()
-            },
-        )
-        .map_err(|e| format!("Failed to copy base folder: {}", e))?;
+        utils::copy_dir_preserving(src, &base_dir, false)
+            .map_err(|e| format!("Failed to copy base folder: {}", e))?;
     } else {
         // ファイルの場合: <name>.base ファイルとしてコピー（従来どおり）
         let base_path = new_dir_path.join(format!("{}.base", entry_name));
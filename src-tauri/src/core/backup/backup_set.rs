@@ -0,0 +1,316 @@
+use crate::core::backup::restore::{self, ConflictPolicy};
+use crate::core::ignore::IgnoreMatcher;
+use crate::core::types::{BackupSetDef, BackupSetManifest, BackupSetMember};
+use crate::core::utils;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// セットのフォルダを置くフォルダ（バックアップルート直下）
+pub const SET_DIR: &str = ".wbt_set";
+
+/// セットのフォルダ直下に置く対応表のファイル名
+pub const SET_MANIFEST: &str = ".wbtset.json";
+
+/// 対応表の形式バージョン
+pub const SET_MANIFEST_VERSION: u32 = 1;
+
+/// セット名として使えるか（フォルダ名になるため区切り文字などは使えない）
+pub fn validate_set_name(name: &str) -> Result<(), String> {
+    let invalid = name.trim().is_empty()
+        || name.starts_with('.')
+        || name
+            .chars()
+            .any(|c| matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'));
+    if invalid {
+        return Err(format!("セット名に使えない文字が含まれています: {}", name));
+    }
+    Ok(())
+}
+
+/// バックアップ先を指定しない場合のルート（1つ目のメンバーの隣の `wbt_backup_<name>/`）
+pub fn default_set_root(def: &BackupSetDef) -> Result<PathBuf, String> {
+    let first = def.members.first().ok_or("セットにメンバーがありません")?;
+    let dir = Path::new(first).parent().unwrap_or_else(|| Path::new("."));
+    Ok(dir.join(format!("wbt_backup_{}", def.name)))
+}
+
+/// セットのフォルダ（タブの作業対象として扱う）
+pub fn set_work_dir(root: &Path, name: &str) -> PathBuf {
+    root.join(SET_DIR).join(name)
+}
+
+/// セットのフォルダかどうか
+pub fn is_set_work_dir(path: &Path) -> bool {
+    path.parent()
+        .and_then(|p| p.file_name())
+        .is_some_and(|n| n == SET_DIR)
+}
+
+/// メンバーのセット内での名前を決める（同じ名前が重なる場合は `<stem>_2.<ext>` のようにずらす）
+pub fn member_names(members: &[String]) -> Vec<BackupSetMember> {
+    let mut used: HashSet<String> = HashSet::new();
+    used.insert(SET_MANIFEST.to_string());
+    members
+        .iter()
+        .map(|source| {
+            let path = Path::new(source);
+            let file_name = path
+                .file_name()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "member".to_string());
+            let mut name = file_name.clone();
+            let mut n = 2;
+            while !used.insert(name.to_lowercase()) {
+                name = match (path.file_stem(), path.extension()) {
+                    (Some(stem), Some(ext)) if !path.is_dir() => {
+                        format!("{}_{}.{}", stem.to_string_lossy(), n, ext.to_string_lossy())
+                    }
+                    _ => format!("{}_{}", file_name, n),
+                };
+                n += 1;
+            }
+            BackupSetMember {
+                name,
+                source: source.clone(),
+                is_folder: path.is_dir(),
+            }
+        })
+        .collect()
+}
+
+/// メンバーの現在の内容をセットのフォルダへ揃え、対応表を書き出す
+/// - 大きさと更新日時が変わったファイルだけをコピーし直す
+/// - フォルダのメンバーは ignore_patterns とそのフォルダの `.wbtignore` に一致するものを除き、
+///   元のフォルダから消えたもの・除外したものはセットのフォルダからも消す
+/// - 1つでも見つからないメンバーがあればエラーにする（揃っていない時点を作らないため）
+/// - セットから外したメンバーはセットのフォルダから消す
+pub fn sync_set(
+    work_dir: &Path,
+    def: &BackupSetDef,
+    keep_symlinks: bool,
+    ignore_patterns: &[String],
+    ts: &str,
+) -> Result<BackupSetManifest, String> {
+    validate_set_name(&def.name)?;
+    if def.members.is_empty() {
+        return Err("セットにメンバーがありません".to_string());
+    }
+    if let Some(missing) = def.members.iter().find(|m| !Path::new(m).exists()) {
+        return Err(format!("セットのメンバーが見つかりません: {}", missing));
+    }
+    let members = member_names(&def.members);
+    fs::create_dir_all(work_dir).map_err(|e| format!("ディレクトリ作成失敗: {}", e))?;
+
+    // 1. 外したメンバーを消す
+    let names: HashSet<&str> = members.iter().map(|m| m.name.as_str()).collect();
+    for entry in fs::read_dir(work_dir).map_err(|e| e.to_string())?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name != SET_MANIFEST && !names.contains(name.as_str()) {
+            remove_path(&entry.path())?;
+        }
+    }
+
+    // 2. メンバーを揃える
+    for member in &members {
+        let src = Path::new(&member.source);
+        let dst = work_dir.join(&member.name);
+        if member.is_folder {
+            let ignore = IgnoreMatcher::for_folder(src, ignore_patterns);
+            sync_folder(src, &dst, keep_symlinks, &ignore)?;
+            if let Ok(modified) = fs::metadata(src).and_then(|m| m.modified()) {
+                utils::set_modified_time(&dst, modified)?;
+            }
+        } else if !same_file_state(src, &dst) {
+            if dst.is_dir() {
+                remove_path(&dst)?;
+            }
+            fs::copy(src, &dst)
+                .map_err(|e| format!("コピーに失敗しました {}: {}", member.source, e))?;
+            if let Ok(modified) = fs::metadata(src).and_then(|m| m.modified()) {
+                utils::set_modified_time(&dst, modified)?;
+            }
+        }
+    }
+
+    // 3. 対応表を書き出す
+    let manifest = BackupSetManifest {
+        version: SET_MANIFEST_VERSION,
+        name: def.name.clone(),
+        updated: ts.to_string(),
+        members,
+    };
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    fs::write(work_dir.join(SET_MANIFEST), json).map_err(|e| e.to_string())?;
+    Ok(manifest)
+}
+
+/// セットのフォルダ（または復元したセット）の対応表を読む
+pub fn load_set_manifest(dir: &Path) -> Result<BackupSetManifest, String> {
+    let data = fs::read_to_string(dir.join(SET_MANIFEST))
+        .map_err(|e| format!("セットの対応表が読めません: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("セットの対応表が壊れています: {}", e))
+}
+
+/// 復元したセットから、メンバーを元の場所（または output フォルダ）へ書き出す
+/// - member を指定した場合はそのメンバーだけ（セット内の名前か元のパスで指定）、None ならすべて
+/// - output が None のときは元のファイル/フォルダの隣へ自動命名 (`<stem>_restored_<ts>`) で出力する
+/// - output を指定した場合は `<output>/<セット内の名前>` へ、conflict に従って配置する
+///
+/// 戻り値: 出力したパス（Skip で書き出さなかったものは含まない）
+pub fn restore_set_members(
+    restored: &Path,
+    member: Option<&str>,
+    output: Option<&str>,
+    conflict: ConflictPolicy,
+) -> Result<Vec<String>, String> {
+    let manifest = load_set_manifest(restored)?;
+    let selected: Vec<&BackupSetMember> = match member.filter(|m| !m.is_empty()) {
+        Some(key) => {
+            let found = manifest
+                .members
+                .iter()
+                .find(|m| m.name == key || m.source == key)
+                .ok_or_else(|| format!("このバックアップのセットに {} は含まれていません", key))?;
+            vec![found]
+        }
+        None => manifest.members.iter().collect(),
+    };
+
+    let mut placed = Vec::new();
+    for m in selected {
+        let staged = restored.join(&m.name);
+        if !staged.exists() {
+            return Err(format!("バックアップにメンバーがありません: {}", m.name));
+        }
+        let dest = match output.filter(|o| !o.is_empty()) {
+            Some(dir) => Path::new(dir).join(&m.name),
+            None => PathBuf::from(utils::auto_output_path(&m.source)),
+        };
+        if let Some(p) = restore::place_restored(&staged, &dest, conflict)? {
+            placed.push(p.to_string_lossy().into_owned());
+        }
+    }
+    Ok(placed)
+}
+
+/// フォルダのメンバーをセットのフォルダへ揃える（変わったファイルだけをコピーし、消えたものを消す）
+fn sync_folder(
+    src: &Path,
+    dst: &Path,
+    keep_symlinks: bool,
+    ignore: &IgnoreMatcher,
+) -> Result<(), String> {
+    let is_plain_dir = fs::symlink_metadata(dst).is_ok_and(|m| m.is_dir());
+    if !is_plain_dir && fs::symlink_metadata(dst).is_ok() {
+        remove_path(dst)?;
+    }
+    fs::create_dir_all(dst).map_err(|e| format!("ディレクトリ作成失敗: {}", e))?;
+
+    // 1. 新しいもの・変わったものをコピーする
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    // ファイルを書き込むとフォルダの更新日時が変わるため、フォルダは最後に設定する
+    let mut dir_times = Vec::new();
+    let walker = walkdir::WalkDir::new(src)
+        .min_depth(1)
+        .follow_links(!keep_symlinks);
+    for entry in ignore.walk(src, walker) {
+        let entry = entry.map_err(|e| format!("フォルダの読み込みに失敗しました: {}", e))?;
+        let rel = entry
+            .path()
+            .strip_prefix(src)
+            .map_err(|e| e.to_string())?
+            .to_path_buf();
+        let out = dst.join(&rel);
+        let existing = fs::symlink_metadata(&out).ok();
+        let file_type = entry.file_type();
+        if file_type.is_symlink() {
+            let target = fs::read_link(entry.path()).map_err(|e| e.to_string())?;
+            let same_link = existing
+                .as_ref()
+                .is_some_and(|m| m.file_type().is_symlink())
+                && fs::read_link(&out).is_ok_and(|t| t == target);
+            if !same_link {
+                if existing.is_some() {
+                    remove_path(&out)?;
+                }
+                utils::create_symlink(&target, &out, entry.path().is_dir())
+                    .map_err(|e| format!("リンクの作成に失敗しました {}: {}", out.display(), e))?;
+            }
+        } else if file_type.is_dir() {
+            if existing.as_ref().is_some_and(|m| !m.is_dir()) {
+                remove_path(&out)?;
+            }
+            fs::create_dir_all(&out).map_err(|e| format!("ディレクトリ作成失敗: {}", e))?;
+            if let Some(t) = entry.metadata().ok().and_then(|m| m.modified().ok()) {
+                dir_times.push((out, t));
+            }
+        } else if !(existing.as_ref().is_some_and(|m| m.is_file())
+            && same_file_state(entry.path(), &out))
+        {
+            if existing.is_some() {
+                remove_path(&out)?;
+            }
+            // fs::copy は権限（実行ビットなど）もコピーする
+            fs::copy(entry.path(), &out).map_err(|e| {
+                format!(
+                    "ファイルのコピーに失敗しました {}: {}",
+                    entry.path().display(),
+                    e
+                )
+            })?;
+            if let Some(t) = entry.metadata().ok().and_then(|m| m.modified().ok()) {
+                utils::set_modified_time(&out, t)?;
+            }
+        }
+        seen.insert(rel);
+    }
+
+    // 2. 元のフォルダから消えたもの・除外したものを消す
+    let mut stale = Vec::new();
+    let mut it = walkdir::WalkDir::new(dst).min_depth(1).into_iter();
+    while let Some(entry) = it.next() {
+        let entry = entry.map_err(|e| format!("フォルダの読み込みに失敗しました: {}", e))?;
+        let rel = entry.path().strip_prefix(dst).map_err(|e| e.to_string())?;
+        if !seen.contains(rel) {
+            if entry.file_type().is_dir() {
+                it.skip_current_dir();
+            }
+            stale.push(entry.path().to_path_buf());
+        }
+    }
+    for path in stale {
+        remove_path(&path)?;
+    }
+
+    for (dir, t) in dir_times {
+        utils::set_modified_time(&dir, t)?;
+    }
+    Ok(())
+}
+
+/// 大きさと更新日時が同じなら、コピーし直さない
+fn same_file_state(src: &Path, dst: &Path) -> bool {
+    match (fs::metadata(src), fs::metadata(dst)) {
+        (Ok(a), Ok(b)) => {
+            b.is_file()
+                && a.len() == b.len()
+                && a.modified().ok().is_some()
+                && a.modified().ok() == b.modified().ok()
+        }
+        _ => false,
+    }
+}
+
+fn remove_path(path: &Path) -> Result<(), String> {
+    let is_dir = fs::symlink_metadata(path)
+        .map(|m| m.is_dir())
+        .unwrap_or(false);
+    if is_dir {
+        fs::remove_dir_all(path)
+    } else {
+        // Windows のフォルダへのリンクは remove_dir で消す
+        fs::remove_file(path).or_else(|_| fs::remove_dir(path))
+    }
+    .map_err(|e| format!("削除に失敗しました {}: {}", path.display(), e))
+}
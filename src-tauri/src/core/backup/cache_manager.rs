@@ -0,0 +1,321 @@
+use crate::core::backup::archive::{self, ArchiveEntryInfo};
+use crate::core::backup::volume;
+use crate::core::types::{
+    ArchiveListing, ArchiveListingCache, ArchiveListingEntry, CacheEntry, CacheIndex,
+};
+use crate::core::utils;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// キャッシュルート直下に置く索引ファイル名
+pub const CACHE_INDEX_FILE: &str = "cache_index.json";
+
+/// キャッシュルート直下に置く、世代アーカイブの中身の一覧のファイル名
+pub const ARCHIVE_LISTING_FILE: &str = "archive_listing.json";
+
+fn unix_secs(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 索引を読み込む。存在しない・壊れている場合は空の索引を返す
+pub fn load_index(cache_root: &Path) -> CacheIndex {
+    fs::read_to_string(cache_root.join(CACHE_INDEX_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_index(cache_root: &Path, index: &CacheIndex) -> Result<(), String> {
+    fs::create_dir_all(cache_root).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(index).map_err(|e| e.to_string())?;
+    fs::write(cache_root.join(CACHE_INDEX_FILE), json).map_err(|e| e.to_string())
+}
+
+/// アーカイブの中身の一覧を返す（サイズと更新日時が前回と同じなら、読み直さずに記録を使う）
+/// 圧縮・暗号化・分割したアーカイブは一覧を得るだけでも全体を読むため、履歴の一覧の更新ごとに読まないようにする
+pub fn list_archive_entries_cached(
+    cache_root: &Path,
+    archive_path: &Path,
+) -> Result<Vec<ArchiveEntryInfo>, String> {
+    let Some((size, mtime)) = archive_stamp(archive_path) else {
+        return archive::list_archive_entries(archive_path);
+    };
+    let path_str = archive_path.to_string_lossy();
+    let mut cache: ArchiveListingCache = fs::read_to_string(cache_root.join(ARCHIVE_LISTING_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    if let Some(listing) = cache
+        .archives
+        .iter()
+        .find(|l| l.archive_path == path_str && l.archive_size == size && l.archive_mtime == mtime)
+    {
+        return Ok(listing.entries.iter().map(from_listing_entry).collect());
+    }
+
+    let entries = archive::list_archive_entries(archive_path)?;
+    // 同じアーカイブの古い記録と、なくなったアーカイブの記録を除いてから追加する
+    cache
+        .archives
+        .retain(|l| l.archive_path != path_str && Path::new(&l.archive_path).exists());
+    cache.archives.push(ArchiveListing {
+        archive_path: path_str.into_owned(),
+        archive_size: size,
+        archive_mtime: mtime,
+        entries: entries.iter().map(to_listing_entry).collect(),
+    });
+    // 記録できなくても一覧は返せる
+    if let Err(e) = save_archive_listing(cache_root, &cache) {
+        log::warn!("アーカイブの一覧を記録できませんでした: {}", e);
+    }
+    Ok(entries)
+}
+
+fn save_archive_listing(cache_root: &Path, cache: &ArchiveListingCache) -> Result<(), String> {
+    fs::create_dir_all(cache_root).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(cache).map_err(|e| e.to_string())?;
+    fs::write(cache_root.join(ARCHIVE_LISTING_FILE), json).map_err(|e| e.to_string())
+}
+
+/// アーカイブのサイズと更新日時（分割アーカイブはボリュームの合計と最も新しいもの）
+fn archive_stamp(archive_path: &Path) -> Option<(u64, u64)> {
+    let mut size = 0;
+    let mut mtime = 0;
+    for p in volume::volume_files(archive_path) {
+        let meta = fs::metadata(&p).ok()?;
+        size += meta.len();
+        mtime = mtime.max(meta.modified().map(unix_secs).ok()?);
+    }
+    Some((size, mtime))
+}
+
+fn to_listing_entry(e: &ArchiveEntryInfo) -> ArchiveListingEntry {
+    ArchiveListingEntry {
+        name: e.name.clone(),
+        size: e.size,
+        modified: e.modified.map(unix_secs),
+        is_dir: e.is_dir,
+    }
+}
+
+fn from_listing_entry(e: &ArchiveListingEntry) -> ArchiveEntryInfo {
+    ArchiveEntryInfo {
+        name: e.name.clone(),
+        size: e.size,
+        modified: e.modified.map(|s| UNIX_EPOCH + Duration::from_secs(s)),
+        is_dir: e.is_dir,
+    }
+}
+
+/// キャッシュ済みの世代がアーカイブと一致しているか（サイズと更新日時で判定）
+pub fn is_entry_valid(cache_root: &Path, entry: &CacheEntry) -> bool {
+    if !cache_root.join(&entry.folder_name).is_dir() {
+        return false;
+    }
+    match fs::metadata(&entry.archive_path) {
+        Ok(meta) => {
+            meta.len() == entry.archive_size
+                && meta.modified().map(unix_secs).ok() == Some(entry.archive_mtime)
+        }
+        Err(_) => false,
+    }
+}
+
+/// archive_path の世代が展開済みで、アーカイブとも一致しているか
+pub fn has_valid_entry(cache_root: &Path, archive_path: &Path) -> bool {
+    load_index(cache_root)
+        .entries
+        .iter()
+        .any(|e| Path::new(&e.archive_path) == archive_path && is_entry_valid(cache_root, e))
+}
+
+/// キャッシュ全体のサイズ（索引ファイルを除く展開済みフォルダの合計）
+pub fn cache_size(cache_root: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(cache_root) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| utils::get_file_size(&e.path().to_string_lossy()).unwrap_or(0) as u64)
+        .sum()
+}
+
+/// 世代アーカイブの展開キャッシュを用意する（初回利用時に展開する）
+/// 既存のキャッシュがアーカイブと食い違っている場合は作り直す
+/// 展開後は max_bytes（0 は無制限）に収まるよう、古いものから追い出す
+/// 戻り値: キャッシュ上の世代フォルダ
+pub fn ensure_cached(
+    cache_root: &Path,
+    archive_path: &Path,
+    password: Option<String>,
+    max_bytes: u64,
+) -> Result<PathBuf, String> {
+    let meta = fs::metadata(archive_path).map_err(|e| e.to_string())?;
+    let folder_name = archive::strip_archive_ext(
+        &archive_path
+            .file_name()
+            .ok_or("Invalid archive path")?
+            .to_string_lossy(),
+    )
+    .to_string();
+    let cached = cache_root.join(&folder_name);
+    let now = unix_secs(SystemTime::now());
+
+    let mut index = load_index(cache_root);
+    let pos = index
+        .entries
+        .iter()
+        .position(|e| e.folder_name == folder_name);
+
+    match pos {
+        Some(i) if is_entry_valid(cache_root, &index.entries[i]) => {
+            index.entries[i].last_access = now;
+        }
+        _ => {
+            // 索引にない・古いキャッシュは消してから展開し直す
+            if let Some(i) = pos {
+                index.entries.remove(i);
+            }
+            if cached.exists() {
+                fs::remove_dir_all(&cached).map_err(|e| format!("キャッシュ削除失敗: {}", e))?;
+            }
+            if let Err(e) =
+                archive::extract_to_cache(&archive_path.to_string_lossy(), cache_root, password)
+            {
+                let _ = fs::remove_dir_all(&cached);
+                return Err(e);
+            }
+            if !cached.is_dir() {
+                return Err(format!(
+                    "アーカイブに世代フォルダが含まれていません: {}",
+                    archive_path.display()
+                ));
+            }
+            index.entries.push(CacheEntry {
+                folder_name: folder_name.clone(),
+                archive_path: archive_path.to_string_lossy().into_owned(),
+                archive_size: meta.len(),
+                archive_mtime: meta.modified().map(unix_secs).unwrap_or(0),
+                size: utils::get_file_size(&cached.to_string_lossy()).unwrap_or(0) as u64,
+                last_access: now,
+            });
+        }
+    }
+
+    evict_entries(cache_root, &mut index, max_bytes, Some(&folder_name))?;
+    save_index(cache_root, &index)?;
+    Ok(cached)
+}
+
+/// 世代フォルダの最終アクセス時刻を更新する
+pub fn touch(cache_root: &Path, folder_name: &str) -> Result<(), String> {
+    let mut index = load_index(cache_root);
+    let Some(entry) = index
+        .entries
+        .iter_mut()
+        .find(|e| e.folder_name == folder_name)
+    else {
+        return Ok(());
+    };
+    entry.last_access = unix_secs(SystemTime::now());
+    save_index(cache_root, &index)
+}
+
+/// max_bytes（0 は無制限）に収まるまで、最終アクセスの古い世代から削除する
+/// 戻り値: 削除した世代フォルダ名
+pub fn evict_to_limit(cache_root: &Path, max_bytes: u64) -> Result<Vec<String>, String> {
+    let mut index = load_index(cache_root);
+    let removed = evict_entries(cache_root, &mut index, max_bytes, None)?;
+    save_index(cache_root, &index)?;
+    Ok(removed)
+}
+
+fn evict_entries(
+    cache_root: &Path,
+    index: &mut CacheIndex,
+    max_bytes: u64,
+    keep: Option<&str>,
+) -> Result<Vec<String>, String> {
+    let mut removed = Vec::new();
+    if max_bytes == 0 {
+        return Ok(removed);
+    }
+
+    // 索引にない世代フォルダ（旧方式で展開されたもの）は最優先で追い出す対象にする
+    // 世代以外のフォルダ（比較画像・サムネイルなど）は対象外
+    let mut candidates: Vec<(u64, String, u64)> = index
+        .entries
+        .iter()
+        .map(|e| (e.last_access, e.folder_name.clone(), e.size))
+        .collect();
+    if let Ok(entries) = fs::read_dir(cache_root) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.path().is_dir()
+                && archive::is_generation_folder_name(&name)
+                && !candidates.iter().any(|(_, n, _)| *n == name)
+            {
+                let size = utils::get_file_size(&entry.path().to_string_lossy()).unwrap_or(0);
+                candidates.push((0, name, size as u64));
+            }
+        }
+    }
+    candidates.sort();
+
+    let mut total: u64 = candidates.iter().map(|(_, _, s)| s).sum();
+    for (_, name, size) in candidates {
+        if total <= max_bytes {
+            break;
+        }
+        if keep == Some(name.as_str()) {
+            continue;
+        }
+        fs::remove_dir_all(cache_root.join(&name))
+            .map_err(|e| format!("キャッシュ削除失敗: {}", e))?;
+        index.entries.retain(|e| e.folder_name != name);
+        total = total.saturating_sub(size);
+        removed.push(name);
+    }
+    Ok(removed)
+}
+
+/// アーカイブが更新・削除された世代のキャッシュを取り除く
+/// 戻り値: 削除した世代フォルダ名
+pub fn remove_stale(cache_root: &Path) -> Result<Vec<String>, String> {
+    let mut index = load_index(cache_root);
+    let mut removed = Vec::new();
+    let mut kept = Vec::new();
+    for entry in index.entries.drain(..) {
+        if is_entry_valid(cache_root, &entry) {
+            kept.push(entry);
+        } else {
+            let dir = cache_root.join(&entry.folder_name);
+            if dir.exists() {
+                fs::remove_dir_all(&dir).map_err(|e| format!("キャッシュ削除失敗: {}", e))?;
+            }
+            removed.push(entry.folder_name);
+        }
+    }
+    index.entries = kept;
+    save_index(cache_root, &index)?;
+    Ok(removed)
+}
+
+/// 世代フォルダのキャッシュを索引ごと削除する
+pub fn remove_cached(cache_root: &Path, folder_name: &str) -> Result<(), String> {
+    let cached = cache_root.join(folder_name);
+    if cached.is_dir() {
+        fs::remove_dir_all(&cached).map_err(|e| format!("キャッシュ削除失敗: {}", e))?;
+    }
+    let mut index = load_index(cache_root);
+    let before = index.entries.len();
+    index.entries.retain(|e| e.folder_name != folder_name);
+    if index.entries.len() != before {
+        save_index(cache_root, &index)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use crate::core::ignore::IgnoreMatcher;
+use crate::core::types::{FileChange, FolderComparison};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+/// ファイル内容の SHA-256 を16進文字列で返す
+pub fn hash_file(path: &Path) -> Result<String, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// フォルダ内のファイルを 相対パス -> (サイズ, 実パス) で集める（ignore に一致するものは除く）
+fn collect_files(
+    root: &Path,
+    ignore: &IgnoreMatcher,
+) -> Result<BTreeMap<String, (u64, std::path::PathBuf)>, String> {
+    if !root.is_dir() {
+        return Err(format!("フォルダではありません: {}", root.display()));
+    }
+    let mut map = BTreeMap::new();
+    for entry in ignore
+        .walk(root, walkdir::WalkDir::new(root).min_depth(1))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let rel = entry.path().strip_prefix(root).map_err(|e| e.to_string())?;
+        let rel = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        map.insert(rel, (size, entry.into_path()));
+    }
+    Ok(map)
+}
+
+/// 2つのフォルダ a（旧）と b（新）を比較する
+/// b にだけあるものを追加、a にだけあるものを削除、内容のハッシュが異なるものを変更とする
+pub fn compare_folders(a: &Path, b: &Path) -> Result<FolderComparison, String> {
+    compare_collected(
+        collect_files(a, &IgnoreMatcher::default())?,
+        collect_files(b, &IgnoreMatcher::default())?,
+    )
+}
+
+/// バックアップのフォルダ backup と現在の作業フォルダ work を比較する
+/// 作業フォルダ側は ignore_patterns（タブの除外パターン）と `.wbtignore` に一致するものを除く
+pub fn compare_with_work(
+    backup: &Path,
+    work: &Path,
+    ignore_patterns: &[String],
+) -> Result<FolderComparison, String> {
+    let ignore = IgnoreMatcher::for_folder(work, ignore_patterns);
+    compare_collected(
+        collect_files(backup, &IgnoreMatcher::default())?,
+        collect_files(work, &ignore)?,
+    )
+}
+
+fn compare_collected(
+    files_a: BTreeMap<String, (u64, std::path::PathBuf)>,
+    files_b: BTreeMap<String, (u64, std::path::PathBuf)>,
+) -> Result<FolderComparison, String> {
+    let mut result = FolderComparison::default();
+
+    for (rel, (size_a, path_a)) in &files_a {
+        let hash_a = hash_file(path_a)?;
+        match files_b.get(rel) {
+            None => result.removed.push(FileChange {
+                rel_path: rel.clone(),
+                size_a: Some(*size_a),
+                size_b: None,
+                size_delta: -(*size_a as i64),
+                hash_a: Some(hash_a),
+                hash_b: None,
+            }),
+            Some((size_b, path_b)) => {
+                let hash_b = hash_file(path_b)?;
+                if hash_a == hash_b {
+                    result.unchanged_count += 1;
+                    continue;
+                }
+                result.modified.push(FileChange {
+                    rel_path: rel.clone(),
+                    size_a: Some(*size_a),
+                    size_b: Some(*size_b),
+                    size_delta: *size_b as i64 - *size_a as i64,
+                    hash_a: Some(hash_a),
+                    hash_b: Some(hash_b),
+                });
+            }
+        }
+    }
+
+    for (rel, (size_b, path_b)) in &files_b {
+        if files_a.contains_key(rel) {
+            continue;
+        }
+        result.added.push(FileChange {
+            rel_path: rel.clone(),
+            size_a: None,
+            size_b: Some(*size_b),
+            size_delta: *size_b as i64,
+            hash_a: None,
+            hash_b: Some(hash_file(path_b)?),
+        });
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,481 @@
+use crate::core::backup::volume;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{KeyInit, OsRng};
+use chacha20poly1305::XChaCha20Poly1305;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// 暗号化したバックアップに付ける拡張子（`work_<ts>.tar.gz.enc` など）
+pub const ENCRYPTED_EXT: &str = ".enc";
+
+/// ファイル先頭のシグネチャ
+const MAGIC: &[u8; 8] = b"WBTENC\x00\x01";
+const FORMAT_VERSION: u8 = 1;
+
+// 鍵導出 (Argon2id) の既定値
+const KDF_MEMORY_KIB: u32 = 19 * 1024;
+const KDF_ITERATIONS: u32 = 2;
+const KDF_PARALLELISM: u32 = 1;
+
+const SALT_LEN: usize = 16;
+/// STREAM 方式で使う nonce の長さ (XChaCha20 の 24 バイト - カウンタ等の 5 バイト)
+const NONCE_PREFIX_LEN: usize = 19;
+/// 平文を区切る単位
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+
+/// 復号の失敗理由（パスワード違いと破損を区別する）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CryptoError {
+    /// パスワードが違う
+    WrongPassword,
+    /// ファイルが壊れている・書き換えられている
+    Corrupted(String),
+    /// 暗号化ファイルではない
+    NotEncrypted,
+    /// 読み書きの失敗
+    Io(String),
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::WrongPassword => write!(f, "パスワードが違います"),
+            CryptoError::Corrupted(detail) => write!(f, "暗号化ファイルが壊れています: {}", detail),
+            CryptoError::NotEncrypted => write!(f, "暗号化されたファイルではありません"),
+            CryptoError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<CryptoError> for String {
+    fn from(e: CryptoError) -> Self {
+        e.to_string()
+    }
+}
+
+impl From<std::io::Error> for CryptoError {
+    fn from(e: std::io::Error) -> Self {
+        CryptoError::Io(e.to_string())
+    }
+}
+
+/// ファイル先頭の固定長ヘッダ
+/// magic | version | memory | iterations | parallelism | salt | nonce | chunk_size | key_check | header_hash
+struct Header {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_PREFIX_LEN],
+    chunk_size: u32,
+    key_check: [u8; 32],
+}
+
+impl Header {
+    fn fields(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(128);
+        buf.extend_from_slice(MAGIC);
+        buf.push(FORMAT_VERSION);
+        buf.extend_from_slice(&self.memory_kib.to_be_bytes());
+        buf.extend_from_slice(&self.iterations.to_be_bytes());
+        buf.extend_from_slice(&self.parallelism.to_be_bytes());
+        buf.extend_from_slice(&self.salt);
+        buf.extend_from_slice(&self.nonce);
+        buf.extend_from_slice(&self.chunk_size.to_be_bytes());
+        buf.extend_from_slice(&self.key_check);
+        buf
+    }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        let fields = self.fields();
+        w.write_all(&fields)?;
+        w.write_all(&Sha256::digest(&fields))
+    }
+
+    fn read_from<R: Read>(r: &mut R) -> Result<Self, CryptoError> {
+        let mut magic = [0u8; 8];
+        if r.read_exact(&mut magic).is_err() || &magic != MAGIC {
+            return Err(CryptoError::NotEncrypted);
+        }
+        let mut version = [0u8; 1];
+        r.read_exact(&mut version)
+            .map_err(|_| CryptoError::Corrupted("ヘッダが途中で切れています".into()))?;
+        if version[0] != FORMAT_VERSION {
+            return Err(CryptoError::Corrupted(format!(
+                "未対応の形式です (version {})",
+                version[0]
+            )));
+        }
+        let mut rest = [0u8; 12 + SALT_LEN + NONCE_PREFIX_LEN + 4 + 32 + 32];
+        r.read_exact(&mut rest)
+            .map_err(|_| CryptoError::Corrupted("ヘッダが途中で切れています".into()))?;
+
+        let u32_at = |i: usize| u32::from_be_bytes(rest[i..i + 4].try_into().unwrap());
+        let mut header = Header {
+            memory_kib: u32_at(0),
+            iterations: u32_at(4),
+            parallelism: u32_at(8),
+            salt: [0; SALT_LEN],
+            nonce: [0; NONCE_PREFIX_LEN],
+            chunk_size: 0,
+            key_check: [0; 32],
+        };
+        let mut i = 12;
+        header.salt.copy_from_slice(&rest[i..i + SALT_LEN]);
+        i += SALT_LEN;
+        header.nonce.copy_from_slice(&rest[i..i + NONCE_PREFIX_LEN]);
+        i += NONCE_PREFIX_LEN;
+        header.chunk_size = u32_at(i);
+        i += 4;
+        header.key_check.copy_from_slice(&rest[i..i + 32]);
+        i += 32;
+
+        // ヘッダ自体の破損はパスワード違いと区別できるよう先に確認する
+        if Sha256::digest(header.fields()).as_slice() != &rest[i..i + 32] {
+            return Err(CryptoError::Corrupted(
+                "ヘッダのチェックサムが一致しません".into(),
+            ));
+        }
+        if header.chunk_size == 0 || header.chunk_size as usize > 16 * CHUNK_SIZE {
+            return Err(CryptoError::Corrupted("チャンクサイズが不正です".into()));
+        }
+        // 極端な値で鍵導出に時間・メモリを取られないよう上限を設ける
+        if header.memory_kib > 1024 * 1024 || header.iterations > 64 || header.parallelism > 64 {
+            return Err(CryptoError::Corrupted(
+                "鍵導出のパラメータが不正です".into(),
+            ));
+        }
+        Ok(header)
+    }
+}
+
+/// パスワードから暗号鍵と照合用の値を導出する
+fn derive_keys(
+    password: &str,
+    salt: &[u8],
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+) -> Result<([u8; 32], [u8; 32]), CryptoError> {
+    let params = Params::new(memory_kib, iterations, parallelism, Some(64))
+        .map_err(|e| CryptoError::Corrupted(format!("鍵導出のパラメータが不正です: {}", e)))?;
+    let mut out = [0u8; 64];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut out)
+        .map_err(|e| CryptoError::Io(e.to_string()))?;
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&out[..32]);
+    // 後半は暗号化には使わず、ハッシュをヘッダに置いてパスワードの照合に使う
+    let key_check: [u8; 32] = Sha256::digest(&out[32..]).into();
+    Ok((key, key_check))
+}
+
+/// ファイル先頭が暗号化ヘッダか
+pub fn is_encrypted(path: &Path) -> bool {
+    let mut magic = [0u8; 8];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok()
+        && &magic == MAGIC
+}
+
+/// 名前が暗号化したバックアップの拡張子で終わるか
+pub fn is_encrypted_name(name: &str) -> bool {
+    name.to_lowercase().ends_with(ENCRYPTED_EXT)
+}
+
+/// 暗号化の拡張子を除いた名前（付いていなければそのまま）
+pub fn strip_encrypted_ext(name: &str) -> &str {
+    if is_encrypted_name(name) {
+        &name[..name.len() - ENCRYPTED_EXT.len()]
+    } else {
+        name
+    }
+}
+
+/// reader の内容を暗号化して writer に書き出す（XChaCha20-Poly1305 の STREAM 方式、鍵は Argon2id で導出）
+fn encrypt_stream<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    password: &str,
+) -> Result<(), String> {
+    if password.is_empty() {
+        return Err("パスワードが空です".to_string());
+    }
+    let mut header = Header {
+        memory_kib: KDF_MEMORY_KIB,
+        iterations: KDF_ITERATIONS,
+        parallelism: KDF_PARALLELISM,
+        salt: [0; SALT_LEN],
+        nonce: [0; NONCE_PREFIX_LEN],
+        chunk_size: CHUNK_SIZE as u32,
+        key_check: [0; 32],
+    };
+    OsRng.fill_bytes(&mut header.salt);
+    OsRng.fill_bytes(&mut header.nonce);
+    let (key, key_check) = derive_keys(
+        password,
+        &header.salt,
+        header.memory_kib,
+        header.iterations,
+        header.parallelism,
+    )?;
+    header.key_check = key_check;
+    header.write_to(writer).map_err(|e| e.to_string())?;
+
+    let cipher = XChaCha20Poly1305::new((&key).into());
+    let mut encryptor = EncryptorBE32::from_aead(cipher, (&header.nonce).into());
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut filled = read_full(reader, &mut buf).map_err(|e| e.to_string())?;
+    loop {
+        let mut next = vec![0u8; CHUNK_SIZE];
+        let next_filled = if filled == CHUNK_SIZE {
+            read_full(reader, &mut next).map_err(|e| e.to_string())?
+        } else {
+            0
+        };
+        if next_filled == 0 {
+            // 最後のチャンクは終端の印付きで暗号化する（切り詰めの検出用）
+            let ct = encryptor
+                .encrypt_last(&buf[..filled])
+                .map_err(|_| "暗号化に失敗しました".to_string())?;
+            writer.write_all(&ct).map_err(|e| e.to_string())?;
+            break;
+        }
+        let ct = encryptor
+            .encrypt_next(&buf[..filled])
+            .map_err(|_| "暗号化に失敗しました".to_string())?;
+        writer.write_all(&ct).map_err(|e| e.to_string())?;
+        buf = next;
+        filled = next_filled;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+/// reader の暗号文を復号して writer に書き出す
+fn decrypt_stream<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    password: &str,
+) -> Result<(), CryptoError> {
+    let header = Header::read_from(reader)?;
+    let (key, key_check) = derive_keys(
+        password,
+        &header.salt,
+        header.memory_kib,
+        header.iterations,
+        header.parallelism,
+    )?;
+    if key_check != header.key_check {
+        return Err(CryptoError::WrongPassword);
+    }
+
+    let cipher = XChaCha20Poly1305::new((&key).into());
+    let mut decryptor = DecryptorBE32::from_aead(cipher, (&header.nonce).into());
+    let block = header.chunk_size as usize + TAG_LEN;
+    let mut buf = vec![0u8; block];
+    let mut filled = read_full(reader, &mut buf)?;
+    loop {
+        if filled < TAG_LEN {
+            return Err(CryptoError::Corrupted("データが途中で切れています".into()));
+        }
+        let mut next = vec![0u8; block];
+        let next_filled = if filled == block {
+            read_full(reader, &mut next)?
+        } else {
+            0
+        };
+        if next_filled == 0 {
+            let pt = decryptor
+                .decrypt_last(&buf[..filled])
+                .map_err(|_| CryptoError::Corrupted("認証タグが一致しません".into()))?;
+            writer.write_all(&pt)?;
+            break;
+        }
+        let pt = decryptor
+            .decrypt_next(&buf[..filled])
+            .map_err(|_| CryptoError::Corrupted("認証タグが一致しません".into()))?;
+        writer.write_all(&pt)?;
+        buf = next;
+        filled = next_filled;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// src を暗号化して dst に書き出す
+pub fn encrypt_file(src: &Path, dst: &Path, password: &str) -> Result<(), String> {
+    let mut reader =
+        BufReader::new(File::open(src).map_err(|e| format!("{}: {}", src.display(), e))?);
+    // 書き込み途中のファイルが残らないよう、一時ファイルから名前を変える
+    let tmp = dst.with_extension("enc.tmp");
+    let mut writer = BufWriter::new(File::create(&tmp).map_err(|e| e.to_string())?);
+    let result = encrypt_stream(&mut reader, &mut writer, password);
+    drop(writer);
+    match result {
+        Ok(()) => fs::rename(&tmp, dst).map_err(|e| e.to_string()),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            Err(e)
+        }
+    }
+}
+
+/// src を復号して dst に書き出す
+/// パスワード違い・ファイルの破損はそれぞれ CryptoError::WrongPassword / Corrupted になる
+pub fn decrypt_file(src: &Path, dst: &Path, password: &str) -> Result<(), CryptoError> {
+    let mut reader = BufReader::new(File::open(src)?);
+    // 失敗した場合は書きかけの出力を残さない
+    let tmp = dst.with_extension("dec.tmp");
+    let result = File::create(&tmp)
+        .map_err(CryptoError::from)
+        .and_then(|f| decrypt_stream(&mut reader, &mut BufWriter::new(f), password));
+    match result {
+        Ok(()) => fs::rename(&tmp, dst).map_err(CryptoError::from),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            Err(e)
+        }
+    }
+}
+
+/// メモリ上のデータを暗号化する（形式はファイルと同じ）
+pub fn encrypt_bytes(data: &[u8], password: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    encrypt_stream(&mut &data[..], &mut out, password)?;
+    Ok(out)
+}
+
+/// encrypt_bytes で暗号化したデータを復号する
+pub fn decrypt_bytes(data: &[u8], password: &str) -> Result<Vec<u8>, CryptoError> {
+    let mut out = Vec::new();
+    decrypt_stream(&mut &data[..], &mut out, password)?;
+    Ok(out)
+}
+
+/// buf が埋まるか終端に達するまで読む
+fn read_full<R: Read>(r: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match r.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// バックアップ1件をその場で暗号化する（`<name>.enc` を作り、元のファイルは削除する）
+/// フォルダは対象外（アーカイブにしてから暗号化する）
+pub fn encrypt_in_place(path: &Path, password: &str) -> Result<PathBuf, String> {
+    if path.is_dir() {
+        return Err("フォルダは暗号化できません。アーカイブにしてから暗号化してください".into());
+    }
+    let name = path
+        .file_name()
+        .ok_or("Invalid path")?
+        .to_string_lossy()
+        .into_owned();
+    if is_encrypted_name(&name) || is_encrypted(path) {
+        return Err(format!("既に暗号化されています: {}", name));
+    }
+    let dst = path.with_file_name(format!("{}{}", name, ENCRYPTED_EXT));
+    encrypt_file(path, &dst, password)?;
+    fs::remove_file(path).map_err(|e| e.to_string())?;
+    Ok(dst)
+}
+
+/// 暗号化されたバックアップを dir へ復号し、そのパスを返す（名前は `.enc` を除いたもの）
+/// 分割したもの (`.enc.001`) はボリュームをつなげてから復号する
+pub fn decrypt_to_dir(path: &Path, password: &str, dir: &Path) -> Result<PathBuf, CryptoError> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    fs::create_dir_all(dir)?;
+    if volume::is_first_volume(&name) {
+        let joined = volume::join_volumes(path, dir).map_err(CryptoError::Io)?;
+        let result = decrypt_to_dir(&joined, password, dir);
+        let _ = fs::remove_file(&joined);
+        return result;
+    }
+    let dst = dir.join(strip_encrypted_ext(&name));
+    decrypt_file(path, &dst, password)?;
+    Ok(dst)
+}
+
+/// フォルダ直下に暗号化したファイルがあるか
+/// 差分を .base から作り直す処理（リベース・世代統合）は、暗号化した差分を扱えないため事前に確認する
+pub fn contains_encrypted(dir: &Path) -> bool {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|e| is_encrypted_name(&e.file_name().to_string_lossy()))
+        })
+        .unwrap_or(false)
+}
+
+/// 差分の適用に復号が必要か（差分そのもの、または同じ世代の .base が暗号化されている）
+pub fn diff_needs_decryption(diff: &Path) -> bool {
+    is_encrypted_name(&diff.to_string_lossy())
+        || diff.parent().map(contains_encrypted_base).unwrap_or(false)
+}
+
+fn contains_encrypted_base(dir: &Path) -> bool {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries.flatten().any(|e| {
+                let name = e.file_name().to_string_lossy().into_owned();
+                is_encrypted_name(&name) && strip_encrypted_ext(&name).ends_with(".base")
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// 差分と同じ世代の .base を staging_dir へ揃え、復号済みの差分のパスを返す
+/// 差分の適用は .base を差分と同じフォルダから探すため、両方を同じ場所に置く
+pub fn stage_encrypted_diff(
+    diff: &Path,
+    password: &str,
+    staging_dir: &Path,
+) -> Result<PathBuf, String> {
+    let gen_dir = diff.parent().ok_or("Invalid diff path")?;
+    fs::create_dir_all(staging_dir).map_err(|e| e.to_string())?;
+    for entry in fs::read_dir(gen_dir).map_err(|e| e.to_string())?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !strip_encrypted_ext(&name).ends_with(".base") {
+            continue;
+        }
+        let path = entry.path();
+        if is_encrypted_name(&name) {
+            decrypt_to_dir(&path, password, staging_dir)?;
+        } else if path.is_dir() {
+            fs_extra::dir::copy(
+                &path,
+                staging_dir,
+                &fs_extra::dir::CopyOptions {
+                    copy_inside: false,
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        } else {
+            fs::copy(&path, staging_dir.join(&name)).map_err(|e| e.to_string())?;
+        }
+    }
+    if is_encrypted_name(&diff.to_string_lossy()) {
+        decrypt_to_dir(diff, password, staging_dir).map_err(String::from)
+    } else {
+        let name = diff.file_name().ok_or("Invalid diff path")?;
+        let dst = staging_dir.join(name);
+        fs::copy(diff, &dst).map_err(|e| e.to_string())?;
+        Ok(dst)
+    }
+}
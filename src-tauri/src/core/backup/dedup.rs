@@ -0,0 +1,249 @@
+use crate::core::types::{ChunkGcReport, DedupFileEntry, DedupManifest, DedupReport};
+use crate::core::ignore::IgnoreMatcher;
+use fastcdc::v2020::StreamCDC;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// チャンク置き場（バックアップ先のルート直下）
+pub const CHUNK_DIR: &str = "chunks";
+
+/// マニフェストの拡張子（`<name>.<ts>.dedup`）
+pub const MANIFEST_EXT: &str = ".dedup";
+
+/// マニフェストの形式バージョン
+pub const MANIFEST_VERSION: u32 = 1;
+
+// チャンクの大きさ（最小・平均・最大）
+const MIN_CHUNK: u32 = 16 * 1024;
+const AVG_CHUNK: u32 = 64 * 1024;
+const MAX_CHUNK: u32 = 256 * 1024;
+
+/// パスが重複排除バックアップのマニフェストか
+pub fn is_manifest(path: &str) -> bool {
+    path.to_lowercase().ends_with(MANIFEST_EXT)
+}
+
+/// チャンクの保存先 `chunks/<先頭2桁>/<sha256>`
+pub fn chunk_path(root: &Path, hash: &str) -> PathBuf {
+    root.join(CHUNK_DIR)
+        .join(&hash[..2.min(hash.len())])
+        .join(hash)
+}
+
+fn to_slash(rel: &Path) -> String {
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// ファイルをチャンクに分割し、未保存のチャンクだけを書き込む
+fn store_file(root: &Path, src: &Path, report: &mut DedupReport) -> Result<Vec<String>, String> {
+    let file = File::open(src).map_err(|e| format!("{}: {}", src.display(), e))?;
+    let mut hashes = Vec::new();
+    for chunk in StreamCDC::new(BufReader::new(file), MIN_CHUNK, AVG_CHUNK, MAX_CHUNK) {
+        let chunk = chunk.map_err(|e| format!("{}: {}", src.display(), e))?;
+        let hash = format!("{:x}", Sha256::digest(&chunk.data));
+        let dest = chunk_path(root, &hash);
+        report.chunk_count += 1;
+        report.total_bytes += chunk.length as u64;
+        if !dest.exists() {
+            // 書き込み途中のチャンクが残らないよう、一時ファイルから名前を変える
+            let parent = dest.parent().ok_or("Invalid chunk path")?;
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            let tmp = parent.join(format!("{}.tmp", hash));
+            fs::write(&tmp, &chunk.data).map_err(|e| e.to_string())?;
+            fs::rename(&tmp, &dest).map_err(|e| e.to_string())?;
+            report.new_chunks += 1;
+            report.new_bytes += chunk.length as u64;
+        }
+        hashes.push(hash);
+    }
+    Ok(hashes)
+}
+
+/// 作業ファイル/フォルダをチャンク単位で保存し、マニフェスト `<name>.<ts>.dedup` を作る
+/// 既に保存済みのチャンクは書き込まないため、変更の少ない時点ほど小さく済む
+pub fn create_dedup_backup(work_path: &str, root: &Path, ts: &str) -> Result<DedupReport, String> {
+    create_dedup_backup_with(work_path, root, ts, &[])
+}
+
+/// create_dedup_backup と同じく、ignore_patterns と作業フォルダの `.wbtignore` に一致するものを除いて保存する
+pub fn create_dedup_backup_with(
+    work_path: &str,
+    root: &Path,
+    ts: &str,
+    ignore_patterns: &[String],
+) -> Result<DedupReport, String> {
+    let src = Path::new(work_path);
+    let name = src
+        .file_name()
+        .ok_or("Invalid work path name")?
+        .to_string_lossy()
+        .into_owned();
+    fs::create_dir_all(root).map_err(|e| e.to_string())?;
+
+    let mut report = DedupReport::default();
+    let mut manifest = DedupManifest {
+        version: MANIFEST_VERSION,
+        name: name.clone(),
+        created: ts.to_string(),
+        is_folder: src.is_dir(),
+        files: Vec::new(),
+        dirs: Vec::new(),
+    };
+
+    if manifest.is_folder {
+        let ignore = IgnoreMatcher::for_folder(src, ignore_patterns);
+        let walker = walkdir::WalkDir::new(src).min_depth(1).sort_by_file_name();
+        for entry in ignore.walk(src, walker).filter_map(|e| e.ok()) {
+            let rel = to_slash(entry.path().strip_prefix(src).map_err(|e| e.to_string())?);
+            if entry.file_type().is_dir() {
+                manifest.dirs.push(rel);
+            } else if entry.file_type().is_file() {
+                let chunks = store_file(root, entry.path(), &mut report)?;
+                manifest.files.push(DedupFileEntry {
+                    rel_path: rel,
+                    size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+                    chunks,
+                });
+            }
+        }
+    } else {
+        let chunks = store_file(root, src, &mut report)?;
+        manifest.files.push(DedupFileEntry {
+            rel_path: String::new(),
+            size: fs::metadata(src).map(|m| m.len()).unwrap_or(0),
+            chunks,
+        });
+    }
+
+    let manifest_path = root.join(format!("{}.{}{}", name, ts, MANIFEST_EXT));
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    fs::write(&manifest_path, json).map_err(|e| e.to_string())?;
+    report.manifest_path = manifest_path.to_string_lossy().into_owned();
+    Ok(report)
+}
+
+/// マニフェストを読み込む
+pub fn load_manifest(manifest_path: &Path) -> Result<DedupManifest, String> {
+    let data = fs::read_to_string(manifest_path)
+        .map_err(|e| format!("{}: {}", manifest_path.display(), e))?;
+    let manifest: DedupManifest =
+        serde_json::from_str(&data).map_err(|e| format!("マニフェストが壊れています: {}", e))?;
+    if manifest.version > MANIFEST_VERSION {
+        return Err(format!(
+            "未対応のマニフェスト形式です (version {})",
+            manifest.version
+        ));
+    }
+    Ok(manifest)
+}
+
+/// チャンクをつなげて1ファイルを書き出す（ハッシュが合わないチャンクはエラー）
+fn write_file_from_chunks(root: &Path, chunks: &[String], dest: &Path) -> Result<(), String> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut out = BufWriter::new(File::create(dest).map_err(|e| e.to_string())?);
+    for hash in chunks {
+        let data = fs::read(chunk_path(root, hash))
+            .map_err(|e| format!("チャンクが見つかりません ({}): {}", hash, e))?;
+        if format!("{:x}", Sha256::digest(&data)) != *hash {
+            return Err(format!("チャンクが壊れています: {}", hash));
+        }
+        out.write_all(&data).map_err(|e| e.to_string())?;
+    }
+    out.flush().map_err(|e| e.to_string())
+}
+
+/// マニフェストから作業ファイル/フォルダを out_path に復元する
+/// チャンク置き場はマニフェストと同じフォルダの `chunks/` を使う
+pub fn restore_dedup(manifest_path: &str, out_path: &str) -> Result<(), String> {
+    let manifest_path = Path::new(manifest_path);
+    let root = manifest_path.parent().ok_or("Invalid manifest path")?;
+    let manifest = load_manifest(manifest_path)?;
+    let out = Path::new(out_path);
+
+    if !manifest.is_folder {
+        let file = manifest
+            .files
+            .first()
+            .ok_or("マニフェストにファイルが含まれていません")?;
+        return write_file_from_chunks(root, &file.chunks, out);
+    }
+
+    fs::create_dir_all(out).map_err(|e| e.to_string())?;
+    for dir in &manifest.dirs {
+        let rel = crate::core::backup::selective::normalize_rel_path(dir)?;
+        fs::create_dir_all(out.join(rel)).map_err(|e| e.to_string())?;
+    }
+    for file in &manifest.files {
+        let rel = crate::core::backup::selective::normalize_rel_path(&file.rel_path)?;
+        write_file_from_chunks(root, &file.chunks, &out.join(rel))?;
+    }
+    Ok(())
+}
+
+/// ルート直下のマニフェストを列挙する
+pub fn list_manifests(root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut list: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && is_manifest(&p.to_string_lossy()))
+        .collect();
+    list.sort();
+    list
+}
+
+/// どのマニフェストからも参照されていないチャンクを削除する
+/// 読めないマニフェストがある場合は、誤って消さないよう何もせずエラーにする
+/// 重複排除バックアップはチャンクを書いてからマニフェストを書くため、同じルートのバックアップと同時に実行しないこと
+pub fn gc_chunks(root: &Path) -> Result<ChunkGcReport, String> {
+    let mut referenced = HashSet::new();
+    let manifests = list_manifests(root);
+    for path in &manifests {
+        let manifest = load_manifest(path)?;
+        for file in manifest.files {
+            referenced.extend(file.chunks);
+        }
+    }
+
+    let mut report = ChunkGcReport {
+        manifest_count: manifests.len(),
+        ..Default::default()
+    };
+    let chunk_root = root.join(CHUNK_DIR);
+    if !chunk_root.exists() {
+        return Ok(report);
+    }
+    for entry in walkdir::WalkDir::new(&chunk_root)
+        .min_depth(2)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if referenced.contains(&name) {
+            report.kept_chunks += 1;
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        fs::remove_file(entry.path()).map_err(|e| e.to_string())?;
+        report.removed_chunks += 1;
+        report.freed_bytes += size;
+    }
+    // 空になったサブフォルダを片付ける
+    if let Ok(dirs) = fs::read_dir(&chunk_root) {
+        for dir in dirs.flatten() {
+            let _ = fs::remove_dir(dir.path());
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,539 @@
+use crate::core::backup::{archive, auto_generation, cache_manager, crypto, rebase, volume};
+use crate::core::utils;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 統合作業用フォルダの接頭辞（世代フォルダと同じ階層に作る）
+pub const MERGE_WORKSPACE_PREFIX: &str = "merge_tmp_";
+
+/// 統合の仕上げの手順を書く作業フォルダ内のファイル名
+const MERGE_JOURNAL: &str = "commit.json";
+
+/// 統合の仕上げで行う置き換えと削除の手順
+/// 途中で中断しても、中断された作業の後始末で続きから実行して仕上げる
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MergeJournal {
+    /// 作業フォルダ内で作り直したアーカイブ -> 置き換える先（元のアーカイブと同じ名前なら上書きする）
+    moves: Vec<(PathBuf, PathBuf)>,
+    /// 置き換えた後に削除するもの（元のアーカイブの残りのボリューム・fold 側の世代）
+    removals: Vec<PathBuf>,
+}
+
+/// 世代の実体（展開済みフォルダ、またはアーカイブ済みファイル）
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenerationEntry {
+    Folder(PathBuf),
+    Archive(PathBuf),
+}
+
+impl GenerationEntry {
+    pub fn path(&self) -> &Path {
+        match self {
+            GenerationEntry::Folder(p) | GenerationEntry::Archive(p) => p,
+        }
+    }
+
+    /// 世代フォルダ名（`baseN_<ts>`）。アーカイブの場合は拡張子を除いた名前
+    pub fn folder_name(&self) -> String {
+        let name = self
+            .path()
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        match self {
+            GenerationEntry::Folder(_) => name,
+            GenerationEntry::Archive(_) => archive::strip_archive_ext(&name).to_string(),
+        }
+    }
+}
+
+/// 指定番号の世代の実体をすべて返す（フォルダとアーカイブが両方ある場合も含む）
+pub fn find_generation_entries(root: &Path, idx: u32) -> Result<Vec<GenerationEntry>, String> {
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+    let re = Regex::new(r"^base(\d+)_").unwrap();
+    let mut list = Vec::new();
+
+    for entry in fs::read_dir(root).map_err(|e| e.to_string())?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let n = re.captures(&name).and_then(|c| c[1].parse::<u32>().ok());
+        if n != Some(idx) {
+            continue;
+        }
+
+        let path = entry.path();
+        if path.is_dir() {
+            list.push(GenerationEntry::Folder(path));
+        } else if archive::strip_archive_ext(&name) != name {
+            list.push(GenerationEntry::Archive(path));
+        }
+    }
+
+    list.sort_by(|a, b| a.path().cmp(b.path()));
+    Ok(list)
+}
+
+/// 最新（現在進行中）の世代かどうか
+fn is_active_generation(root: &Path, entries: &[GenerationEntry]) -> Result<bool, String> {
+    let latest = auto_generation::get_latest_generation(root)?.map(|i| i.dir_path);
+    Ok(entries
+        .iter()
+        .any(|e| matches!(e, GenerationEntry::Folder(p) if Some(p) == latest.as_ref())))
+}
+
+fn load_generation(root: &Path, idx: u32, force: bool) -> Result<Vec<GenerationEntry>, String> {
+    let entries = find_generation_entries(root, idx)?;
+    if entries.is_empty() {
+        return Err(format!("世代 {} が見つかりません", idx));
+    }
+    if !force && is_active_generation(root, &entries)? {
+        return Err(format!(
+            "世代 {} は現在使用中の最新世代です。強制する場合は force を指定してください",
+            idx
+        ));
+    }
+    Ok(entries)
+}
+
+/// 世代に対応する展開キャッシュ（`<cache_root>/baseN_<ts>/`）を削除する
+pub fn remove_generation_cache(cache_root: &Path, folder_name: &str) -> Result<(), String> {
+    cache_manager::remove_cached(cache_root, folder_name)
+}
+
+fn remove_entry(entry: &GenerationEntry) -> Result<(), String> {
+    match entry {
+        GenerationEntry::Folder(p) => fs::remove_dir_all(p),
+        // 分割アーカイブはすべてのボリュームを削除する
+        GenerationEntry::Archive(p) => volume::volume_files(p).iter().try_for_each(fs::remove_file),
+    }
+    .map_err(|e| format!("世代の削除に失敗しました {}: {}", entry.path().display(), e))
+}
+
+/// 世代をまるごと削除する（フォルダ・アーカイブ・展開キャッシュ）
+/// 最新世代は force が true のときのみ削除する
+/// 戻り値: 削除したパス一覧
+pub fn delete_generation(
+    root: &Path,
+    cache_root: &Path,
+    idx: u32,
+    force: bool,
+) -> Result<Vec<String>, String> {
+    let entries = load_generation(root, idx, force)?;
+
+    let mut removed = Vec::new();
+    for entry in &entries {
+        remove_entry(entry)?;
+        remove_generation_cache(cache_root, &entry.folder_name())?;
+        removed.push(entry.path().to_string_lossy().into_owned());
+    }
+    Ok(removed)
+}
+
+/// 2つの世代を1つに統合する計画
+/// fold 側の差分を keep 側の .base からの差分に作り直し、keep 側へ移す
+///
+/// 例:
+///   merge_tmp_base3_20260101_100000/
+///   ├── generation/  keep 側がアーカイブの場合の展開先（統合後は baseN_... フォルダになる）
+///   ├── fold/        fold 側がアーカイブの場合の展開先
+///   ├── snapshots/   fold 側の差分を復元した状態
+///   ├── additions/   keep 側へ追加する差分とメモ
+///   ├── verify/      作り直した差分の検証用
+///   ├── repack/      keep 側がアーカイブの場合に作り直したアーカイブ
+///   └── commit.json  仕上げの手順（書いた後に中断した場合は後始末で続きを実行する）
+#[derive(Debug, Clone)]
+pub struct MergePlan {
+    pub keep_entries: Vec<GenerationEntry>,
+    pub fold_entries: Vec<GenerationEntry>,
+    pub keep_dir: PathBuf,
+    pub fold_dir: PathBuf,
+    pub workspace: PathBuf,
+    pub entry_name: String,
+    pub fold_diffs: Vec<PathBuf>,
+    /// アーカイブの展開に使ったパスワード（keep 側を同じ形式で作り直すときにも使う）
+    pub password: Option<String>,
+}
+
+impl MergePlan {
+    pub fn keep_base(&self) -> PathBuf {
+        self.keep_dir.join(format!("{}.base", self.entry_name))
+    }
+
+    pub fn fold_base(&self) -> PathBuf {
+        self.fold_dir.join(format!("{}.base", self.entry_name))
+    }
+
+    pub fn snapshot_path(&self, diff: &Path) -> PathBuf {
+        let name = diff.file_name().unwrap_or_default().to_string_lossy();
+        self.workspace
+            .join("snapshots")
+            .join(name.trim_end_matches(".diff"))
+    }
+
+    pub fn staged_diff(&self, diff: &Path) -> PathBuf {
+        self.workspace
+            .join("additions")
+            .join(diff.file_name().unwrap_or_default())
+    }
+
+    /// fold 側の .base の状態を keep 側の .base からの差分として残す
+    /// 同じ時刻の差分（世代交代時の差分）が既にある場合は None
+    pub fn fold_base_diff(&self) -> Option<PathBuf> {
+        let gen_name = self
+            .fold_dir
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let ts = gen_name.split_once('_').map(|(_, ts)| ts).unwrap_or("");
+        let prefix = format!("{}.{}.", self.entry_name, ts);
+        let duplicated = self.fold_diffs.iter().any(|d| {
+            d.file_name()
+                .map(|n| n.to_string_lossy().starts_with(&prefix))
+                .unwrap_or(false)
+        });
+        if duplicated {
+            return None;
+        }
+        Some(
+            self.workspace
+                .join("additions")
+                .join(format!("{}hdiff.diff", prefix)),
+        )
+    }
+
+    pub fn verify_path(&self) -> PathBuf {
+        self.workspace.join("verify").join(&self.entry_name)
+    }
+}
+
+/// 世代の実体を作業できるフォルダとして用意する
+/// フォルダはそのまま使い、アーカイブは作業フォルダへ展開する
+fn materialize(
+    entries: &[GenerationEntry],
+    dest: &Path,
+    workspace: &Path,
+    password: Option<String>,
+) -> Result<PathBuf, String> {
+    if let Some(GenerationEntry::Folder(p)) = entries
+        .iter()
+        .find(|e| matches!(e, GenerationEntry::Folder(_)))
+    {
+        return Ok(p.clone());
+    }
+
+    let entry = &entries[0];
+    let extract_root = workspace.join("extract");
+    archive::extract_to_cache(&entry.path().to_string_lossy(), &extract_root, password)?;
+    let extracted = extract_root.join(entry.folder_name());
+    if !extracted.is_dir() {
+        return Err(format!(
+            "アーカイブから世代フォルダを展開できませんでした: {}",
+            entry.path().display()
+        ));
+    }
+    fs::rename(&extracted, dest).map_err(|e| e.to_string())?;
+    let _ = fs::remove_dir_all(&extract_root);
+    Ok(dest.to_path_buf())
+}
+
+/// 中断された統合作業を後始末する
+/// - 仕上げの手順（commit.json）を書いた後なら、残りの置き換えと削除を実行してから作業フォルダを消す
+/// - 書く前なら元の世代はそのままなので、作業フォルダを消せばやり直せる
+///   （keep 側への追加は上書き可能な名前で行う）
+pub fn recover_interrupted_merge(root: &Path) -> Result<(), String> {
+    if !root.exists() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(root).map_err(|e| e.to_string())?.flatten() {
+        if entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(MERGE_WORKSPACE_PREFIX)
+        {
+            if let Some(journal) = load_merge_journal(&entry.path()) {
+                apply_merge_journal(&journal)?;
+            }
+            fs::remove_dir_all(entry.path()).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+fn load_merge_journal(workspace: &Path) -> Option<MergeJournal> {
+    fs::read_to_string(workspace.join(MERGE_JOURNAL))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+}
+
+/// 手順を書き出す（書きかけのものを読まないよう、別名で書いてから名前を変える）
+fn save_merge_journal(workspace: &Path, journal: &MergeJournal) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(journal).map_err(|e| e.to_string())?;
+    let tmp = workspace.join(format!("{}.tmp", MERGE_JOURNAL));
+    fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, workspace.join(MERGE_JOURNAL)).map_err(|e| e.to_string())
+}
+
+/// 手順を実行する（済んでいるものは飛ばすので、何度実行してもよい）
+fn apply_merge_journal(journal: &MergeJournal) -> Result<(), String> {
+    for (src, dst) in &journal.moves {
+        if src.exists() {
+            utils::move_file_safe(src, dst)
+                .map_err(|e| format!("統合した世代の配置に失敗しました: {}", e))?;
+        }
+    }
+    for path in &journal.removals {
+        let removed = match fs::symlink_metadata(path) {
+            Ok(m) if m.is_dir() => fs::remove_dir_all(path),
+            Ok(_) => fs::remove_file(path),
+            Err(_) => continue,
+        };
+        removed.map_err(|e| format!("世代の削除に失敗しました {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// 世代の実体を構成するファイル・フォルダ（分割アーカイブはすべてのボリューム）
+fn entry_paths(entry: &GenerationEntry) -> Vec<PathBuf> {
+    match entry {
+        GenerationEntry::Folder(p) => vec![p.clone()],
+        GenerationEntry::Archive(p) => volume::volume_files(p),
+    }
+}
+
+/// 世代統合の計画を作成し、作業フォルダを用意する
+pub fn plan_merge(
+    root: &Path,
+    work_path: &str,
+    keep_n: u32,
+    fold_n: u32,
+    force: bool,
+    password: Option<String>,
+) -> Result<MergePlan, String> {
+    if keep_n == fold_n {
+        return Err("同じ世代同士は統合できません".to_string());
+    }
+    let keep_entries = load_generation(root, keep_n, force)?;
+    let fold_entries = load_generation(root, fold_n, force)?;
+    let entry_name = Path::new(work_path)
+        .file_name()
+        .ok_or("Invalid work path name")?
+        .to_string_lossy()
+        .into_owned();
+
+    let workspace = root.join(format!(
+        "{}{}",
+        MERGE_WORKSPACE_PREFIX,
+        keep_entries[0].folder_name()
+    ));
+    if workspace.exists() {
+        fs::remove_dir_all(&workspace).map_err(|e| e.to_string())?;
+    }
+    for sub in ["snapshots", "additions", "verify"] {
+        fs::create_dir_all(workspace.join(sub)).map_err(|e| e.to_string())?;
+    }
+
+    let keep_dir = materialize(
+        &keep_entries,
+        &workspace.join("generation"),
+        &workspace,
+        password.clone(),
+    )?;
+    let fold_dir = materialize(
+        &fold_entries,
+        &workspace.join("fold"),
+        &workspace,
+        password.clone(),
+    )?;
+
+    let plan = MergePlan {
+        keep_entries,
+        fold_entries,
+        keep_dir,
+        fold_dir,
+        workspace,
+        entry_name,
+        fold_diffs: Vec::new(),
+        password,
+    };
+    if crypto::contains_encrypted(&plan.keep_dir) || crypto::contains_encrypted(&plan.fold_dir) {
+        let _ = fs::remove_dir_all(&plan.workspace);
+        return Err("暗号化されたファイルを含む世代は統合できません".to_string());
+    }
+    for base in [plan.keep_base(), plan.fold_base()] {
+        if !base.exists() {
+            let _ = fs::remove_dir_all(&plan.workspace);
+            return Err(format!(".base が見つかりません: {}", base.display()));
+        }
+    }
+
+    let fold_diffs = rebase::list_generation_diffs(&plan.fold_dir)?;
+    Ok(MergePlan { fold_diffs, ..plan })
+}
+
+/// fold 側のメモなど差分以外の付随ファイルを追加分として用意する
+pub fn stage_merge_extras(plan: &MergePlan) -> Result<(), String> {
+    for entry in fs::read_dir(&plan.fold_dir)
+        .map_err(|e| e.to_string())?
+        .flatten()
+    {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if path.is_file() && !name.ends_with(".diff") && !name.ends_with(".base") {
+            fs::copy(&path, plan.workspace.join("additions").join(&name))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// 追加分を keep 側へ移し、fold 側の世代とキャッシュを削除する
+/// keep 側がアーカイブだった場合は、統合したフォルダを元と同じ形式のアーカイブに作り直して置き換える
+/// 戻り値: 統合後の世代（フォルダ、またはアーカイブの1つ目のボリューム）のパス
+pub fn commit_merge(
+    plan: &MergePlan,
+    cache_root: &Path,
+    opts: &archive::CompressionOptions,
+) -> Result<String, String> {
+    let additions = plan.workspace.join("additions");
+
+    // 差分の更新日時を元に戻す（履歴一覧の時刻がずれないように）
+    for diff in &plan.fold_diffs {
+        if let Ok(t) = fs::metadata(diff).and_then(|m| m.modified()) {
+            utils::set_modified_time(&plan.staged_diff(diff), t)?;
+        }
+    }
+    if let Some(p) = plan.fold_base_diff().filter(|p| p.exists()) {
+        if let Ok(t) = fs::metadata(plan.fold_base()).and_then(|m| m.modified()) {
+            utils::set_modified_time(&p, t)?;
+        }
+    }
+
+    // 1. keep 側の統合先を決める（アーカイブの場合は作業フォルダ内で世代フォルダ名にする）
+    let keep_name = plan.keep_entries[0].folder_name();
+    let keep_folder = plan.keep_entries.iter().find_map(|e| match e {
+        GenerationEntry::Folder(p) => Some(p.clone()),
+        _ => None,
+    });
+    let final_dir = keep_folder
+        .clone()
+        .unwrap_or_else(|| plan.workspace.join(&keep_name));
+    if final_dir != plan.keep_dir {
+        fs::rename(&plan.keep_dir, &final_dir)
+            .map_err(|e| format!("統合後の世代フォルダの配置に失敗しました: {}", e))?;
+    }
+
+    // 2. 追加分を移動（中断後の再実行で残っていた同名ファイルは上書き）
+    for entry in fs::read_dir(&additions)
+        .map_err(|e| e.to_string())?
+        .flatten()
+    {
+        let dst = final_dir.join(entry.file_name());
+        if dst.exists() {
+            fs::remove_file(&dst).map_err(|e| e.to_string())?;
+        }
+        utils::move_file_safe(entry.path(), &dst)?;
+    }
+
+    // 3. keep 側がアーカイブなら作り直し、元のアーカイブと置き換える手順を用意する
+    //    元のアーカイブは、作り直したものを同じ名前で上書きした後で残りのボリュームだけを消す
+    let mut journal = MergeJournal::default();
+    let merged = match keep_folder {
+        Some(dir) => dir,
+        None => {
+            let original = plan.keep_entries[0].path();
+            let packed = repack_keep_archive(plan, &final_dir, original, opts)?;
+            let dir = original.parent().ok_or("Invalid archive path")?;
+            for p in packed {
+                let dst = dir.join(p.file_name().unwrap_or_default());
+                journal.moves.push((p, dst));
+            }
+            for entry in &plan.keep_entries {
+                journal.removals.extend(
+                    entry_paths(entry)
+                        .into_iter()
+                        .filter(|p| journal.moves.iter().all(|(_, dst)| dst != p)),
+                );
+            }
+            journal
+                .moves
+                .first()
+                .map(|(_, dst)| dst.clone())
+                .ok_or("アーカイブの作成に失敗しました")?
+        }
+    };
+    // fold 側の世代は、keep 側の置き換えが済んでから削除する
+    for entry in &plan.fold_entries {
+        journal.removals.extend(entry_paths(entry));
+    }
+
+    // 4. 手順を書いてから実行する（途中で中断しても後始末で仕上げる）
+    save_merge_journal(&plan.workspace, &journal)?;
+    apply_merge_journal(&journal)?;
+    for entry in &plan.fold_entries {
+        remove_generation_cache(cache_root, &entry.folder_name())?;
+    }
+    remove_generation_cache(cache_root, &keep_name)?;
+
+    let _ = fs::remove_dir_all(&plan.workspace);
+    Ok(merged.to_string_lossy().into_owned())
+}
+
+/// 統合した世代フォルダを、keep 側のアーカイブと同じ形式で作業フォルダ内に作り直す
+/// 暗号化していたもの（`.enc`・パスワード付き ZIP）は同じパスワードで暗号化し、
+/// 分割していたものは同じボリュームサイズで分割する
+/// 戻り値: 作成したファイル（分割した場合はすべてのボリューム）
+fn repack_keep_archive(
+    plan: &MergePlan,
+    src_dir: &Path,
+    original: &Path,
+    opts: &archive::CompressionOptions,
+) -> Result<Vec<PathBuf>, String> {
+    let volumes = volume::volume_files(original);
+    let volume_size = match volumes.as_slice() {
+        [first, _, ..] => fs::metadata(first).map_err(|e| e.to_string())?.len(),
+        _ => 0,
+    };
+    let file_name = original
+        .file_name()
+        .ok_or("Invalid archive path")?
+        .to_string_lossy()
+        .into_owned();
+    let name = volume::strip_first_volume_ext(&file_name);
+    let encrypted = crypto::is_encrypted_name(name) || archive::is_zip_encrypted(original);
+    let password = plan.password.clone().unwrap_or_default();
+    if encrypted && password.is_empty() {
+        return Err("暗号化された世代を作り直すにはパスワードが必要です".to_string());
+    }
+
+    let out_dir = plan.workspace.join("repack");
+    fs::create_dir_all(&out_dir).map_err(|e| e.to_string())?;
+    let plain_name = crypto::strip_encrypted_ext(name);
+    let dst = out_dir.join(plain_name);
+    // ZIP は AES、TAR 系はアーカイブ全体を暗号化する（世代アーカイブの作成と同じ）
+    let dst = match archive::TarCodec::from_path(plain_name) {
+        Some(codec) => {
+            archive::compress_dir_tar_with(src_dir, &dst, codec, opts)?;
+            if encrypted {
+                crypto::encrypt_in_place(&dst, &password)?
+            } else {
+                dst
+            }
+        }
+        None => {
+            let zip_password = if encrypted { password.as_str() } else { "" };
+            archive::compress_dir_zip_with(src_dir, &dst, zip_password, opts)?;
+            dst
+        }
+    };
+    let first = volume::split_into_volumes(&dst, volume_size)?;
+    Ok(volume::volume_files(&first))
+}
+
+/// 統合を中止し、作業フォルダを削除する（元の世代には触れない）
+pub fn abort_merge(plan: &MergePlan) {
+    let _ = fs::remove_dir_all(&plan.workspace);
+}
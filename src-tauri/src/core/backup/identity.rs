@@ -0,0 +1,540 @@
+use crate::core::backup::cache_manager;
+use crate::core::types::{BackupIdentity, OpenedRoot, RelinkCandidate, RelinkReport, RootSettings};
+use crate::core::utils;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+
+/// バックアップルート直下に置く記述ファイル（識別情報・作業対象の相対パス・タブの設定）のファイル名
+pub const IDENTITY_FILE: &str = ".wbt_id.json";
+
+/// 記述ファイルの形式バージョン
+/// - 1: 識別情報のみ
+/// - 2: 作業対象の相対パス・ルートの場所・タブの設定を追加
+pub const IDENTITY_VERSION: u32 = 2;
+
+// 指紋に使う、ファイルの先頭と末尾の大きさ
+const FINGERPRINT_SAMPLE: u64 = 1024 * 1024;
+
+/// 識別情報を読む（ない・壊れている場合は None）
+pub fn load_identity(root: &Path) -> Option<BackupIdentity> {
+    fs::read_to_string(root.join(IDENTITY_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+}
+
+pub fn save_identity(root: &Path, identity: &BackupIdentity) -> Result<(), String> {
+    fs::create_dir_all(root).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(identity).map_err(|e| e.to_string())?;
+    fs::write(root.join(IDENTITY_FILE), json).map_err(|e| e.to_string())
+}
+
+/// 作業ファイル/フォルダの内容の指紋
+/// - ファイル: 大きさと先頭・末尾 1MiB の SHA-256（大きなファイルでも全体は読まない）
+/// - フォルダ: 配下のファイルの相対パスと大きさの一覧の SHA-256
+pub fn content_fingerprint(path: &Path) -> Result<String, String> {
+    let meta = fs::metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    if meta.is_dir() {
+        let mut files: Vec<(String, u64)> = walkdir::WalkDir::new(path)
+            .min_depth(1)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let rel = e.path().strip_prefix(path).ok()?;
+                let len = e.metadata().ok()?.len();
+                Some((rel.to_string_lossy().replace('\\', "/"), len))
+            })
+            .collect();
+        files.sort();
+        for (rel, len) in files {
+            hasher.update(rel.as_bytes());
+            hasher.update([0]);
+            hasher.update(len.to_le_bytes());
+        }
+    } else {
+        let len = meta.len();
+        hasher.update(len.to_le_bytes());
+        let mut file = File::open(path).map_err(|e| e.to_string())?;
+        let mut buf = Vec::new();
+        (&mut file)
+            .take(FINGERPRINT_SAMPLE)
+            .read_to_end(&mut buf)
+            .map_err(|e| e.to_string())?;
+        if len > FINGERPRINT_SAMPLE * 2 {
+            file.seek(SeekFrom::End(-(FINGERPRINT_SAMPLE as i64)))
+                .map_err(|e| e.to_string())?;
+        }
+        file.read_to_end(&mut buf).map_err(|e| e.to_string())?;
+        hasher.update(&buf);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// path を含むバックアップルート（記述ファイルのある最も近い上位フォルダ）
+pub fn find_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|p| p.join(IDENTITY_FILE).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// バックアップ後に識別情報を更新する（なければ作る）
+pub fn record_backup(root: &Path, work_path: &Path, ts: &str) -> Result<BackupIdentity, String> {
+    let work_name = entry_name_of(work_path)?;
+    let mut identity = load_identity(root).unwrap_or_else(|| BackupIdentity {
+        version: IDENTITY_VERSION,
+        id: new_id(),
+        work_name: work_name.clone(),
+        work_path: String::new(),
+        is_folder: work_path.is_dir(),
+        fingerprint: String::new(),
+        names: Vec::new(),
+        updated: String::new(),
+        work_rel: String::new(),
+        root_path: String::new(),
+        settings: RootSettings::default(),
+    });
+    if !identity.names.contains(&work_name) {
+        identity.names.push(work_name.clone());
+    }
+    identity.work_name = work_name;
+    identity.work_path = work_path.to_string_lossy().into_owned();
+    identity.is_folder = work_path.is_dir();
+    identity.fingerprint = content_fingerprint(work_path)?;
+    identity.updated = ts.to_string();
+    identity.version = identity.version.max(IDENTITY_VERSION);
+    set_location(&mut identity, root, work_path);
+    save_identity(root, &identity)?;
+    Ok(identity)
+}
+
+/// タブの設定を記述ファイルへ記録する（バックアップの後に呼ぶ）
+pub fn save_root_settings(root: &Path, settings: &RootSettings) -> Result<(), String> {
+    let mut identity = load_identity(root)
+        .ok_or_else(|| format!("記述ファイルがありません: {}", root.display()))?;
+    identity.settings = settings.clone();
+    save_identity(root, &identity)
+}
+
+/// 既存のバックアップルートを開き、タブを組み立てるための情報を返す
+/// - 作業対象は記述ファイルの相対パスで探し、見つからなければ最後に記録した場所を使う
+/// - 前回と別の場所から開いた場合は、展開キャッシュの索引のアーカイブのパスを書き換える
+/// - 記述ファイルのない古いルートは、`wbt_backup_<stem>` の隣の作業対象から記述ファイルを作る
+pub fn open_root(root: &Path, use_same_dir_for_temp: bool, ts: &str) -> Result<OpenedRoot, String> {
+    if !root.is_dir() {
+        return Err(format!(
+            "バックアップ先が見つかりません: {}",
+            root.display()
+        ));
+    }
+    let mut identity = match load_identity(root) {
+        Some(id) => id,
+        None => {
+            let work = infer_legacy_work(root).ok_or_else(|| {
+                format!(
+                    "バックアップルートの記述ファイルがありません: {}",
+                    root.display()
+                )
+            })?;
+            record_backup(root, &work, ts)?
+        }
+    };
+    if identity.version > IDENTITY_VERSION {
+        return Err(format!(
+            "新しい形式のバックアップルートのため開けません (version {})",
+            identity.version
+        ));
+    }
+
+    let work_path = resolve_work_path(root, &identity);
+    let work_missing = !work_path.exists();
+    let relocated =
+        !identity.root_path.is_empty() && !same_path(Path::new(&identity.root_path), root);
+    if relocated {
+        // 一時フォルダのキャッシュは作業ファイルのパスをキーにしているため、移した先のキーへ移す
+        // （ルートと同じ場所のキャッシュはルートと一緒に移動している）
+        let cache_root = utils::get_cache_root(
+            use_same_dir_for_temp,
+            &root.to_string_lossy(),
+            &work_path.to_string_lossy(),
+        );
+        if !use_same_dir_for_temp {
+            let old_cache = utils::get_cache_root(false, &identity.root_path, &identity.work_path);
+            move_cache(&old_cache, &cache_root)?;
+        }
+        rebase_cache_index(&cache_root, Path::new(&identity.root_path), root)?;
+    }
+
+    // 見つかった場所を記録しておく（次回以降の付け替え候補の判定にも使う）
+    if !work_missing {
+        identity.work_path = work_path.to_string_lossy().into_owned();
+    }
+    set_location(&mut identity, root, &work_path);
+    save_identity(root, &identity)?;
+
+    let work_file = work_path.to_string_lossy().into_owned();
+    let is_default = same_path(&utils::default_backup_dir(&work_file), root);
+    Ok(OpenedRoot {
+        backup_dir: if is_default {
+            String::new()
+        } else {
+            root.to_string_lossy().into_owned()
+        },
+        work_file,
+        work_missing,
+        relocated,
+        settings: identity.settings,
+    })
+}
+
+/// 記述ファイルの相対パス（なければ記録した場所）から作業対象の場所を求める
+pub fn resolve_work_path(root: &Path, identity: &BackupIdentity) -> PathBuf {
+    let recorded = PathBuf::from(&identity.work_path);
+    if identity.work_rel.is_empty() {
+        return recorded;
+    }
+    let relative = normalize(&root.join(&identity.work_rel));
+    if relative.exists() || !recorded.exists() {
+        relative
+    } else {
+        recorded
+    }
+}
+
+/// この履歴でこれまでに使った作業ファイル名の stem（名前の一致判定用）
+pub fn known_stems(root: &Path) -> Vec<String> {
+    load_identity(root)
+        .map(|id| {
+            id.names
+                .iter()
+                .map(|n| work_stem(n, id.is_folder))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 作業ファイルの元の履歴かもしれないバックアップ先を探す
+/// search_dirs 直下（とそれ自身）の識別情報を調べ、次のものを候補にする
+/// - 内容の指紋が一致する（exact）
+/// - 記録された場所に元の作業ファイルがもうなく、ファイル/フォルダの別と拡張子が同じ
+///
+/// 指紋が一致するもの、更新が新しいものの順に並べる
+pub fn find_relink_candidates(work_path: &Path, search_dirs: &[PathBuf]) -> Vec<RelinkCandidate> {
+    let work_str = work_path.to_string_lossy();
+    let is_folder = work_path.is_dir();
+    let fingerprint = content_fingerprint(work_path).ok();
+    let ext = work_path.extension().map(|e| e.to_ascii_lowercase());
+
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for dir in search_dirs {
+        roots.push(dir.clone());
+        if let Ok(entries) = fs::read_dir(dir) {
+            roots.extend(entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()));
+        }
+    }
+    roots.sort();
+    roots.dedup();
+
+    let mut list: Vec<RelinkCandidate> = roots
+        .iter()
+        .filter_map(|root| {
+            let id = load_identity(root)?;
+            if id.work_path == work_str || id.is_folder != is_folder {
+                return None;
+            }
+            let exact = fingerprint.as_deref() == Some(id.fingerprint.as_str());
+            let same_ext = is_folder
+                || Path::new(&id.work_name)
+                    .extension()
+                    .map(|e| e.to_ascii_lowercase())
+                    == ext;
+            let moved = !Path::new(&id.work_path).exists();
+            if !(exact || (moved && same_ext)) {
+                return None;
+            }
+            Some(RelinkCandidate {
+                backup_dir: root.to_string_lossy().into_owned(),
+                work_name: id.work_name,
+                work_path: id.work_path,
+                exact,
+                updated: id.updated,
+            })
+        })
+        .collect();
+    list.sort_by(|a, b| b.exact.cmp(&a.exact).then(b.updated.cmp(&a.updated)));
+    list
+}
+
+/// 履歴を新しい作業ファイルへ付け替える
+/// 1. 世代フォルダとルート直下のバックアップ・メモを新しい名前に付け替える
+///    （世代アーカイブの中身は古い名前のまま。一覧では識別情報の names で一致させる）
+/// 2. ルートが元の作業ファイルの既定の場所 (`wbt_backup_<stem>`) なら、新しい既定の場所へ移す
+///    （移せなかった場合は元の場所のまま使い、結果の warning に理由を入れる）
+/// 3. 展開キャッシュを移し、索引のアーカイブのパスを書き換える
+/// 4. 識別情報を新しい作業ファイルで更新する
+pub fn relink(
+    root: &Path,
+    new_work_path: &Path,
+    use_same_dir_for_temp: bool,
+    ts: &str,
+) -> Result<RelinkReport, String> {
+    let identity =
+        load_identity(root).ok_or_else(|| format!("識別情報がありません: {}", root.display()))?;
+    let old_path = identity.work_path.clone();
+    let new_name = entry_name_of(new_work_path)?;
+    let old_backup_dir = root.to_string_lossy().into_owned();
+    let old_cache = utils::get_cache_root(use_same_dir_for_temp, &old_backup_dir, &old_path);
+
+    // 1. 名前の付け替え
+    let renamed = if identity.work_name != new_name {
+        rename_history(root, &identity.work_name, &new_name, identity.is_folder)?
+    } else {
+        0
+    };
+
+    // 2. 既定の場所のルートは、新しい既定の場所へ移す
+    let new_default = utils::default_backup_dir(&new_work_path.to_string_lossy());
+    let was_default = !old_path.is_empty() && same_path(root, &old_default_dir(&identity));
+    let mut new_root = root.to_path_buf();
+    let mut warning = None;
+    if was_default && !same_path(root, &new_default) {
+        if new_default.exists() {
+            warning = Some(format!(
+                "{} が既にあるため、バックアップ先は元の場所のまま使います: {}",
+                new_default.display(),
+                root.display()
+            ));
+        } else {
+            match fs::rename(root, &new_default) {
+                Ok(()) => new_root = new_default.clone(),
+                Err(e) => {
+                    warning = Some(format!(
+                        "バックアップ先を移せなかったため、元の場所のまま使います: {} ({})",
+                        root.display(),
+                        e
+                    ))
+                }
+            }
+        }
+    }
+    let new_backup_dir = new_root.to_string_lossy().into_owned();
+
+    // 3. 展開キャッシュ
+    let new_cache = utils::get_cache_root(
+        use_same_dir_for_temp,
+        &new_backup_dir,
+        &new_work_path.to_string_lossy(),
+    );
+    let old_cache = if use_same_dir_for_temp {
+        // ルートと一緒に移動している
+        new_root.join(old_cache.strip_prefix(root).unwrap_or(&old_cache))
+    } else {
+        old_cache
+    };
+    move_cache(&old_cache, &new_cache)?;
+    if new_root != root {
+        rebase_cache_index(&new_cache, root, &new_root)?;
+    }
+
+    // 4. 識別情報
+    record_backup(&new_root, new_work_path, ts)?;
+    Ok(RelinkReport {
+        is_default_dir: same_path(&new_root, &new_default),
+        moved_root: new_root != root,
+        backup_dir: new_backup_dir,
+        renamed,
+        previous_path: old_path,
+        warning,
+    })
+}
+
+/// ルート直下と世代フォルダ内の、古い名前のバックアップを新しい名前に付け替える
+/// - `<old_name>.…`（.base・差分・重複排除・メモ）→ `<new_name>.…`
+/// - `<old_stem>_YYYYMMDD_HHMMSS…`（フルコピー・単体アーカイブ）→ `<new_stem>_YYYYMMDD_HHMMSS…`
+///
+/// 付け替え先が既にある場合はそのままにする。戻り値: 付け替えた数
+pub fn rename_history(
+    root: &Path,
+    old_name: &str,
+    new_name: &str,
+    is_folder: bool,
+) -> Result<usize, String> {
+    let new_stem = work_stem(new_name, is_folder);
+    let stem_re = Regex::new(&format!(
+        r"^{}_(\d{{8}}_\d{{6}}.*)$",
+        regex::escape(&work_stem(old_name, is_folder))
+    ))
+    .map_err(|e| e.to_string())?;
+    let renamed_name = |name: &str| -> Option<String> {
+        if let Some(rest) = name.strip_prefix(old_name).filter(|r| r.starts_with('.')) {
+            return Some(format!("{}{}", new_name, rest));
+        }
+        stem_re
+            .captures(name)
+            .map(|c| format!("{}_{}", new_stem, &c[1]))
+    };
+
+    let mut dirs = vec![root.to_path_buf()];
+    dirs.extend(
+        fs::read_dir(root)
+            .map_err(|e| e.to_string())?
+            .flatten()
+            .filter(|e| e.file_name().to_string_lossy().starts_with("base") && e.path().is_dir())
+            .map(|e| e.path()),
+    );
+
+    let mut count = 0;
+    for dir in dirs {
+        for entry in fs::read_dir(&dir).map_err(|e| e.to_string())?.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(new) = renamed_name(&name) else {
+                continue;
+            };
+            let target = dir.join(&new);
+            if target.exists() {
+                continue;
+            }
+            fs::rename(entry.path(), &target)
+                .map_err(|e| format!("名前を変更できませんでした {}: {}", name, e))?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// ルートと作業対象の場所を記述ファイルに記録する
+fn set_location(identity: &mut BackupIdentity, root: &Path, work_path: &Path) {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let work = work_path
+        .canonicalize()
+        .unwrap_or_else(|_| work_path.to_path_buf());
+    identity.work_rel = relative_path(&root, &work).unwrap_or_default();
+    identity.root_path = root.to_string_lossy().into_owned();
+}
+
+/// from フォルダから見た to の相対パス（区切りは '/'）。ドライブが違うなど求められない場合は None
+fn relative_path(from: &Path, to: &Path) -> Option<String> {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    if from.first() != to.first() {
+        return None;
+    }
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    parts.extend(
+        to[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned()),
+    );
+    Some(parts.join("/"))
+}
+
+/// `..` と `.` を取り除く（まだ存在しないパスにも使えるよう、ファイルシステムは見ない）
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for c in path.components() {
+        match c {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+/// 記述ファイルのない `wbt_backup_<stem>` の作業対象を、隣にあるファイル/フォルダから探す
+fn infer_legacy_work(root: &Path) -> Option<PathBuf> {
+    let stem = root
+        .file_name()?
+        .to_string_lossy()
+        .strip_prefix("wbt_backup_")?
+        .to_string();
+    let parent = root.parent()?;
+    let mut found: Vec<PathBuf> = fs::read_dir(parent)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p != root)
+        .filter(|p| {
+            p.file_name()
+                .is_some_and(|n| work_stem(&n.to_string_lossy(), p.is_dir()) == stem)
+        })
+        .collect();
+    found.sort();
+    found.into_iter().next()
+}
+
+/// 展開キャッシュを新しいキーの場所へ移す（移す先が既にある場合は元のまま）
+fn move_cache(old_cache: &Path, new_cache: &Path) -> Result<(), String> {
+    if old_cache == new_cache || !old_cache.is_dir() || new_cache.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = new_cache.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::rename(old_cache, new_cache).map_err(|e| format!("キャッシュを移せませんでした: {}", e))
+}
+
+/// 展開キャッシュの索引にあるアーカイブのパスを、移したルートに合わせて書き換える
+fn rebase_cache_index(cache_root: &Path, old_root: &Path, new_root: &Path) -> Result<(), String> {
+    if !cache_root.is_dir() {
+        return Ok(());
+    }
+    let mut index = cache_manager::load_index(cache_root);
+    for entry in &mut index.entries {
+        if let Ok(rel) = Path::new(&entry.archive_path).strip_prefix(old_root) {
+            entry.archive_path = new_root.join(rel).to_string_lossy().into_owned();
+        }
+    }
+    cache_manager::save_index(cache_root, &index)
+}
+
+/// 元の作業ファイルの既定のバックアップ先（元の場所にもうなくても求められるよう、記録から組み立てる）
+fn old_default_dir(identity: &BackupIdentity) -> PathBuf {
+    let parent = Path::new(&identity.work_path)
+        .parent()
+        .unwrap_or_else(|| Path::new("."));
+    parent.join(format!(
+        "wbt_backup_{}",
+        work_stem(&identity.work_name, identity.is_folder)
+    ))
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// default_backup_dir と同じく、フォルダは名前そのもの、ファイルは拡張子を除いた名前
+fn work_stem(name: &str, is_folder: bool) -> String {
+    if is_folder {
+        return name.to_string();
+    }
+    Path::new(name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| name.to_string())
+}
+
+fn entry_name_of(path: &Path) -> Result<String, String> {
+    Ok(path
+        .file_name()
+        .ok_or("Invalid work path name")?
+        .to_string_lossy()
+        .into_owned())
+}
+
+fn new_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
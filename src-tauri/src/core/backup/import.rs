@@ -0,0 +1,167 @@
+use crate::core::backup::auto_generation;
+use crate::core::backup::workflow::{self, BackupTargetInfo};
+use crate::core::utils;
+use chrono::{DateTime, Duration, Local};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// インポート対象となる手動バックアップ1件
+#[derive(Debug, Clone)]
+pub struct ImportSource {
+    pub path: PathBuf,
+    pub modified: SystemTime,
+    /// バックアップ名に使うタイムスタンプ（同一秒が重ならないよう補正済み）
+    pub ts: String,
+}
+
+/// ワイルドカード（`*` / `?`）をファイル名全体にマッチする正規表現へ変換する
+/// 例: `illustration_final_v*.clip`
+pub fn wildcard_to_regex(pattern: &str) -> Result<Regex, String> {
+    let mut re = String::from("(?i)^");
+    for c in pattern.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            _ => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    Regex::new(&re).map_err(|e| format!("パターンが不正です: {}", e))
+}
+
+/// インポート対象を集めて更新日時の古い順に並べる
+/// - files: 個別に指定されたファイル/フォルダ
+/// - folder + pattern: フォルダ直下でパターンに一致するもの（pattern 省略時は全件）
+pub fn collect_import_sources(
+    files: &[String],
+    folder: Option<&str>,
+    pattern: Option<&str>,
+) -> Result<Vec<ImportSource>, String> {
+    let mut paths: Vec<PathBuf> = files
+        .iter()
+        .filter(|f| !f.is_empty())
+        .map(PathBuf::from)
+        .collect();
+
+    if let Some(dir) = folder.filter(|d| !d.is_empty()) {
+        let re = match pattern.filter(|p| !p.is_empty()) {
+            Some(p) => Some(wildcard_to_regex(p)?),
+            None => None,
+        };
+        for entry in fs::read_dir(dir).map_err(|e| e.to_string())?.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // 既存のバックアップルートは取り込まない
+            if name.starts_with("wbt_backup_") {
+                continue;
+            }
+            if let Some(r) = &re {
+                if !r.is_match(&name) {
+                    continue;
+                }
+            }
+            paths.push(entry.path());
+        }
+    }
+
+    let mut sources = Vec::new();
+    for path in paths {
+        let modified = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .map_err(|e| format!("インポート元を読み込めません {}: {}", path.display(), e))?;
+        sources.push(ImportSource {
+            path,
+            modified,
+            ts: String::new(),
+        });
+    }
+
+    sources.sort_by(|a, b| a.modified.cmp(&b.modified).then(a.path.cmp(&b.path)));
+    sources.dedup_by(|a, b| a.path == b.path);
+
+    // 同じ秒のファイルが並ぶと差分名が衝突するため、1秒ずつずらす
+    let mut last: Option<DateTime<Local>> = None;
+    for src in sources.iter_mut() {
+        let mut dt: DateTime<Local> = src.modified.into();
+        if let Some(prev) = last {
+            if dt.timestamp() <= prev.timestamp() {
+                dt = prev + Duration::seconds(1);
+            }
+        }
+        src.ts = dt.format("%Y%m%d_%H%M%S").to_string();
+        last = Some(dt);
+    }
+
+    Ok(sources)
+}
+
+/// インポート元を作業ファイルと同じ名前でステージング領域へコピーする
+/// `.base` や差分の名前は作業ファイル名から決まるため、手動バックアップ名のままでは使えない
+pub fn stage_import_source(
+    src: &ImportSource,
+    staging_dir: &Path,
+    work_path: &str,
+) -> Result<PathBuf, String> {
+    let entry_name = Path::new(work_path)
+        .file_name()
+        .ok_or("Invalid work path name")?;
+    let staged = staging_dir.join(entry_name);
+
+    if staged.is_dir() {
+        fs::remove_dir_all(&staged).map_err(|e| e.to_string())?;
+    } else if staged.exists() {
+        fs::remove_file(&staged).map_err(|e| e.to_string())?;
+    }
+
+    if src.path.is_dir() {
+        utils::copy_dir_preserving(&src.path, &staged, true)?;
+    } else {
+        utils::copy_file(&src.path.to_string_lossy(), &staged.to_string_lossy())?;
+    }
+
+    Ok(staged)
+}
+
+/// 最初のインポート元から新しい世代 `baseN_<ts>/<name>.base` を作成する
+/// 既存の世代がある場合はその次の番号で作成する
+pub fn seed_import_generation(
+    root: &Path,
+    staged: &Path,
+    src: &ImportSource,
+) -> Result<BackupTargetInfo, String> {
+    let next_idx = auto_generation::get_latest_generation(root)?
+        .map(|info| info.base_idx + 1)
+        .unwrap_or(1);
+    let staged_str = staged.to_string_lossy();
+    let gen_dir = auto_generation::create_new_generation_at(root, next_idx, &staged_str, &src.ts)?;
+
+    let entry_name = staged.file_name().unwrap_or_default().to_string_lossy();
+    utils::set_modified_time(&gen_dir.join(format!("{}.base", entry_name)), src.modified)?;
+    utils::set_modified_time(&gen_dir, src.modified)?;
+
+    workflow::resolve_backup_target(gen_dir, &staged_str)
+}
+
+/// インポートで作成した差分の更新日時を元ファイルの時刻に合わせる
+/// 世代交代が起きた場合は新しい世代フォルダとその `.base` も合わせる
+pub fn backdate_import_entry(
+    created: &Path,
+    staged: &Path,
+    modified: SystemTime,
+    rotated: bool,
+) -> Result<(), String> {
+    utils::set_modified_time(created, modified)?;
+
+    if rotated {
+        if let Some(gen_dir) = created.parent() {
+            let entry_name = staged.file_name().unwrap_or_default().to_string_lossy();
+            let base = gen_dir.join(format!("{}.base", entry_name));
+            if base.exists() {
+                utils::set_modified_time(&base, modified)?;
+            }
+            utils::set_modified_time(gen_dir, modified)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use crate::core::backup::restore;
+use crate::core::types::UndoRecord;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 上書き復元前の退避先と取り消し記録を置くフォルダ（バックアップルート直下）
+pub const UNDO_DIR: &str = ".wbt_undo";
+const UNDO_LOG_FILE: &str = "undo_log.json";
+
+fn work_name(work_path: &Path) -> Result<String, String> {
+    Ok(work_path
+        .file_name()
+        .ok_or("Invalid work path")?
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// 復元内容を一時的に書き出すパス
+/// 入れ替えを rename で行えるよう、作業ファイルと同じフォルダに作る
+pub fn staging_output_path(work_path: &Path, ts: &str) -> Result<PathBuf, String> {
+    let dir = work_path.parent().ok_or("Invalid work path")?;
+    Ok(dir.join(format!(".{}.wbt_restoring_{}", work_name(work_path)?, ts)))
+}
+
+/// 現在の作業ファイル/フォルダを `<root>/.wbt_undo/<ts>/` へ退避する
+pub fn take_safety_snapshot(work_path: &Path, root: &Path, ts: &str) -> Result<PathBuf, String> {
+    if !work_path.exists() {
+        return Err(format!(
+            "作業ファイルが見つかりません: {}",
+            work_path.display()
+        ));
+    }
+    let dest_dir = root.join(UNDO_DIR).join(ts);
+    fs::create_dir_all(&dest_dir).map_err(|e| e.to_string())?;
+    let dest = dest_dir.join(work_name(work_path)?);
+    restore::restore_copy(&work_path.to_string_lossy(), &dest.to_string_lossy())
+        .map_err(|e| format!("退避に失敗しました: {}", e))?;
+    Ok(dest)
+}
+
+/// 作業ファイル/フォルダを復元済みの内容で置き換える
+/// ファイルは rename で一度に置き換え、フォルダは旧フォルダを退避してから入れ替える
+/// 入れ替えに失敗した場合は元の状態に戻す
+pub fn replace_work_target(work_path: &Path, restored: &Path, ts: &str) -> Result<(), String> {
+    if !restored.exists() {
+        return Err(format!("復元結果が見つかりません: {}", restored.display()));
+    }
+
+    if restored.is_file() && !work_path.is_dir() {
+        return fs::rename(restored, work_path)
+            .map_err(|e| format!("作業ファイルの置き換えに失敗しました: {}", e));
+    }
+
+    let parent = work_path.parent().ok_or("Invalid work path")?;
+    let old = parent.join(format!(".{}.wbt_old_{}", work_name(work_path)?, ts));
+    let had_old = work_path.exists();
+    if had_old {
+        fs::rename(work_path, &old)
+            .map_err(|e| format!("作業フォルダの退避に失敗しました: {}", e))?;
+    }
+    if let Err(e) = fs::rename(restored, work_path) {
+        if had_old {
+            let _ = fs::rename(&old, work_path);
+        }
+        return Err(format!("作業フォルダの置き換えに失敗しました: {}", e));
+    }
+    if had_old {
+        if old.is_dir() {
+            fs::remove_dir_all(&old).map_err(|e| e.to_string())?;
+        } else {
+            fs::remove_file(&old).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// 取り消し記録を読み込む（古い順）
+pub fn load_undo_log(root: &Path) -> Vec<UndoRecord> {
+    fs::read_to_string(root.join(UNDO_DIR).join(UNDO_LOG_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_undo_log(root: &Path, log: &[UndoRecord]) -> Result<(), String> {
+    let dir = root.join(UNDO_DIR);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(log).map_err(|e| e.to_string())?;
+    fs::write(dir.join(UNDO_LOG_FILE), json).map_err(|e| e.to_string())
+}
+
+pub fn record_undo(root: &Path, record: UndoRecord) -> Result<(), String> {
+    let mut log = load_undo_log(root);
+    log.push(record);
+    save_undo_log(root, &log)
+}
+
+/// 直近の上書き復元を取り消し、退避しておいた状態に戻す
+/// 戻り値: 取り消した記録
+pub fn undo_last(root: &Path, work_path: &Path, ts: &str) -> Result<UndoRecord, String> {
+    let mut log = load_undo_log(root);
+    let work_str = work_path.to_string_lossy();
+    let pos = log
+        .iter()
+        .rposition(|r| r.work_path == work_str)
+        .ok_or("取り消せる上書き復元がありません")?;
+    let record = log[pos].clone();
+
+    let snapshot = Path::new(&record.snapshot_path);
+    if !snapshot.exists() {
+        return Err(format!(
+            "退避した状態が見つかりません: {}",
+            snapshot.display()
+        ));
+    }
+    let staging = staging_output_path(work_path, ts)?;
+    restore::restore_copy(&record.snapshot_path, &staging.to_string_lossy())?;
+    if let Err(e) = replace_work_target(work_path, &staging, ts) {
+        discard_staging(&staging);
+        return Err(e);
+    }
+
+    if let Some(dir) = snapshot.parent() {
+        let _ = fs::remove_dir_all(dir);
+    }
+    log.remove(pos);
+    save_undo_log(root, &log)?;
+    Ok(record)
+}
+
+/// 途中で失敗した一時出力を片付ける
+pub fn discard_staging(staging: &Path) {
+    if staging.is_dir() {
+        let _ = fs::remove_dir_all(staging);
+    } else if staging.exists() {
+        let _ = fs::remove_file(staging);
+    }
+}
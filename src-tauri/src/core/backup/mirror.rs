@@ -0,0 +1,315 @@
+use crate::core::backup::{archive, compare, dedup, generation_ops, identity, rebase};
+use crate::core::types::{MirrorFailure, MirrorRecord, MirrorState, MirrorStatus};
+use crate::core::utils;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// バックアップルート直下に置くミラーの記録のファイル名
+pub const MIRROR_STATE_FILE: &str = ".wbt_mirror.json";
+
+/// ミラーの記録の形式バージョン
+pub const MIRROR_STATE_VERSION: u32 = 1;
+
+// 書き込み途中のファイルに付ける拡張子（検証が通ってから本来の名前にする）
+const PART_EXT: &str = "wbt_part";
+
+/// ルートのミラー先（`<mirror_dir>/<ルートのフォルダ名>`。写した先も既存のルートとして開ける）
+pub fn mirror_root_for(root: &Path, mirror_dir: &Path) -> PathBuf {
+    match root.file_name() {
+        Some(name) => mirror_dir.join(name),
+        None => mirror_dir.to_path_buf(),
+    }
+}
+
+/// ミラーの記録を読む（ない・壊れている場合は空の記録）
+pub fn load_state(root: &Path) -> MirrorState {
+    fs::read_to_string(root.join(MIRROR_STATE_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_state(root: &Path, state: &MirrorState) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    fs::write(root.join(MIRROR_STATE_FILE), json).map_err(|e| e.to_string())
+}
+
+/// 写す対象のファイル（ルートからの相対パス・実パス）
+/// 展開キャッシュ・取り消し用の退避・セットの作業フォルダなど `.wbt_` で始まるものは、
+/// 記述ファイル（.wbt_id.json）を除いて写さない
+/// リベース・世代統合の作業フォルダと、書き込み途中のチャンク（`chunks/**/<hash>.tmp`）も写さない
+pub fn artifact_files(root: &Path) -> Vec<(String, PathBuf)> {
+    let mut files: Vec<(String, PathBuf)> = walkdir::WalkDir::new(root)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| e.depth() != 1 || is_artifact_top(&e.file_name().to_string_lossy()))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let rel = e.path().strip_prefix(root).ok()?;
+            Some((
+                rel.to_string_lossy().replace('\\', "/"),
+                e.path().to_path_buf(),
+            ))
+        })
+        .filter(|(rel, _)| !is_partial_chunk(rel))
+        .collect();
+    files.sort();
+    files
+}
+
+/// ルート直下の名前が写す対象か
+fn is_artifact_top(name: &str) -> bool {
+    if name.starts_with(".wbt_") {
+        return name == identity::IDENTITY_FILE;
+    }
+    !name.starts_with(rebase::WORKSPACE_PREFIX)
+        && !name.starts_with(generation_ops::MERGE_WORKSPACE_PREFIX)
+}
+
+/// 重複排除のバックアップが書き込み中のチャンクか
+fn is_partial_chunk(rel: &str) -> bool {
+    rel.starts_with(&format!("{}/", dedup::CHUNK_DIR)) && rel.ends_with(".tmp")
+}
+
+/// まだ写していない（記録と大きさ・更新日時が違う）ファイル
+pub fn pending_files(root: &Path, state: &MirrorState) -> Vec<(String, PathBuf)> {
+    artifact_files(root)
+        .into_iter()
+        .filter(|(rel, path)| is_pending(state, rel, path))
+        .collect()
+}
+
+fn is_pending(state: &MirrorState, rel: &str, path: &Path) -> bool {
+    match (state.files.get(rel), file_stamp(path)) {
+        (Some(rec), Some((size, mtime))) => rec.size != size || rec.mtime != mtime,
+        _ => true,
+    }
+}
+
+/// ルートの新しいもの・変わったものをミラー先へ写す
+/// - ミラー先のフォルダ（USB ドライブやネットワークの場所）が見つからない場合は何もせずエラーにする
+/// - 1つずつ `.wbt_part` に書き込み、大きさと SHA-256 が一致してから本来の名前にする
+/// - 写せなかったものは記録に残し、次のミラーで再試行する
+/// - ルートで消したもの（世代の削除・ローテーション）はミラー先に残す
+pub fn sync_mirror(root: &Path, mirror_dir: &Path, ts: &str) -> Result<MirrorStatus, String> {
+    if !root.is_dir() {
+        return Err(format!(
+            "バックアップ先が見つかりません: {}",
+            root.display()
+        ));
+    }
+    let mirror_root = mirror_root_for(root, mirror_dir);
+    let mirror_root_str = mirror_root.to_string_lossy().into_owned();
+    let mut state = load_state(root);
+    if state.mirror_root != mirror_root_str {
+        // ミラー先を変えた場合は最初から写し直す
+        state = MirrorState {
+            version: MIRROR_STATE_VERSION,
+            mirror_root: mirror_root_str,
+            ..Default::default()
+        };
+    }
+
+    if !mirror_dir.is_dir() {
+        let err = format!("ミラー先が見つかりません: {}", mirror_dir.display());
+        state.last_error = err.clone();
+        save_state(root, &state)?;
+        return Err(err);
+    }
+    let (real_root, real_mirror) = match (root.canonicalize(), mirror_dir.canonicalize()) {
+        (Ok(r), Ok(m)) => (r.clone(), mirror_root_for(&r, &m)),
+        _ => (root.to_path_buf(), mirror_root.clone()),
+    };
+    if real_mirror.starts_with(&real_root) || real_root.starts_with(&real_mirror) {
+        return Err("ミラー先にバックアップ先と重なる場所は指定できません".to_string());
+    }
+
+    // 前回までに失敗したものも未了として写し直す（ルートから消えたものは記録から外れる）
+    let attempts: HashMap<String, u32> = state
+        .failed
+        .drain(..)
+        .map(|f| (f.path, f.attempts))
+        .collect();
+    let mut copied = 0;
+    for (rel, src) in pending_files(root, &state) {
+        match copy_verified(&src, &mirror_root.join(&rel)) {
+            Ok(record) => {
+                state.files.insert(rel, record);
+                copied += 1;
+            }
+            Err(e) => {
+                state.files.remove(&rel);
+                state.failed.push(MirrorFailure {
+                    attempts: attempts.get(&rel).copied().unwrap_or(0) + 1,
+                    path: rel,
+                    error: e,
+                    last_attempt: ts.to_string(),
+                });
+            }
+        }
+    }
+
+    state.version = MIRROR_STATE_VERSION;
+    state.last_sync = ts.to_string();
+    state.last_error = state
+        .failed
+        .first()
+        .map(|f| format!("{}: {}", f.path, f.error))
+        .unwrap_or_default();
+    save_state(root, &state)?;
+    let mut status = status_of(root, &state);
+    status.copied = copied;
+    Ok(status)
+}
+
+/// ミラーの現在の状態
+pub fn mirror_status(root: &Path) -> MirrorStatus {
+    status_of(root, &load_state(root))
+}
+
+/// 履歴の各項目のミラー状態（ルートを一度だけ走査して判定する）
+pub struct MirrorCheck {
+    root: PathBuf,
+    cache_root: PathBuf,
+    /// 世代フォルダ名 -> ルート直下の世代アーカイブ（展開キャッシュの項目を元のアーカイブで判定する）
+    archives: HashMap<String, PathBuf>,
+    failed: HashSet<String>,
+    pending: HashSet<String>,
+}
+
+impl MirrorCheck {
+    pub fn new(root: &Path, cache_root: &Path) -> Self {
+        let state = load_state(root);
+        let archives = fs::read_dir(root)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|e| e.path().is_file())
+                    .filter_map(|e| {
+                        let name = e.file_name().to_string_lossy().into_owned();
+                        archive::is_archive_name(&name)
+                            .then(|| (archive::strip_archive_ext(&name).to_string(), e.path()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        MirrorCheck {
+            root: root.to_path_buf(),
+            cache_root: cache_root.to_path_buf(),
+            archives,
+            failed: state.failed.iter().map(|f| f.path.clone()).collect(),
+            pending: pending_files(root, &state)
+                .into_iter()
+                .map(|(rel, _)| rel)
+                .collect(),
+        }
+    }
+
+    /// 項目（ファイル・フォルダ・世代アーカイブ内のエントリ・展開キャッシュ内のファイル）の状態
+    /// フォルダは配下に1つでも失敗・未了のものがあればその状態にする
+    pub fn item_status(&self, item_path: &str) -> &'static str {
+        let path = match archive::split_archived_entry_path(item_path) {
+            Some((archive_path, _)) => archive_path,
+            None => PathBuf::from(item_path),
+        };
+        // 展開キャッシュのものは、展開元のアーカイブの状態にする
+        let path = match path.strip_prefix(&self.cache_root) {
+            Ok(rel) => {
+                let folder = rel
+                    .components()
+                    .next()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                match self.archives.get(&folder) {
+                    Some(archive_path) => archive_path.clone(),
+                    None => return "pending",
+                }
+            }
+            Err(_) => path,
+        };
+        let Ok(rel) = path.strip_prefix(&self.root) else {
+            return "pending";
+        };
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        let prefix = format!("{}/", rel);
+        let hit = |set: &HashSet<String>| set.iter().any(|p| *p == rel || p.starts_with(&prefix));
+        if hit(&self.failed) {
+            "failed"
+        } else if hit(&self.pending) {
+            "pending"
+        } else {
+            "synced"
+        }
+    }
+}
+
+fn status_of(root: &Path, state: &MirrorState) -> MirrorStatus {
+    let files = artifact_files(root);
+    let pending = files
+        .iter()
+        .filter(|(rel, path)| is_pending(state, rel, path))
+        .count();
+    MirrorStatus {
+        mirror_root: state.mirror_root.clone(),
+        last_sync: state.last_sync.clone(),
+        last_error: state.last_error.clone(),
+        copied: 0,
+        synced: files.len() - pending,
+        pending,
+        failed: state.failed.clone(),
+    }
+}
+
+/// 1つのファイルを写し、大きさと SHA-256 で確かめる
+fn copy_verified(src: &Path, dst: &Path) -> Result<MirrorRecord, String> {
+    let (size, mtime) = file_stamp(src).ok_or_else(|| format!("{} が読めません", src.display()))?;
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("ディレクトリ作成失敗: {}", e))?;
+    }
+    let part = PathBuf::from(format!("{}.{}", dst.to_string_lossy(), PART_EXT));
+    let result = (|| {
+        let src_hash = compare::hash_file(src)?;
+        fs::copy(src, &part).map_err(|e| format!("コピーに失敗しました: {}", e))?;
+        let copied_size = fs::metadata(&part).map_err(|e| e.to_string())?.len();
+        if copied_size != size {
+            return Err(format!(
+                "大きさが一致しません ({} / {} bytes)",
+                copied_size, size
+            ));
+        }
+        if compare::hash_file(&part)? != src_hash {
+            return Err("ハッシュが一致しません".to_string());
+        }
+        if let Ok(modified) = fs::metadata(src).and_then(|m| m.modified()) {
+            utils::set_modified_time(&part, modified)?;
+        }
+        if dst.exists() {
+            fs::remove_file(dst).map_err(|e| format!("上書きに失敗しました: {}", e))?;
+        }
+        fs::rename(&part, dst).map_err(|e| format!("名前の変更に失敗しました: {}", e))?;
+        Ok(MirrorRecord {
+            size,
+            mtime,
+            sha256: src_hash,
+        })
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&part);
+    }
+    result
+}
+
+/// 大きさと更新日時（UNIX 秒）
+fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    let mtime = meta
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Some((meta.len(), mtime))
+}
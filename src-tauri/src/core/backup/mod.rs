@@ -1,4 +1,21 @@
 pub mod archive;
 pub mod auto_generation;
+pub mod backup_set;
+pub mod cache_manager;
+pub mod compare;
+pub mod crypto;
+pub mod dedup;
+pub mod generation_ops;
+pub mod identity;
+pub mod import;
+pub mod in_place;
+pub mod mirror;
+pub mod rebase;
+pub mod restore;
+pub mod root_lock;
+pub mod rotation;
 pub mod scanner;
+pub mod selective;
+pub mod stats;
+pub mod volume;
 pub mod workflow;
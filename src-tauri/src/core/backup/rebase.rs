@@ -0,0 +1,347 @@
+use crate::core::backup::{auto_generation, crypto};
+use crate::core::types::RebaseReport;
+use crate::core::utils;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// リベース作業用フォルダの接頭辞（世代フォルダと同じ階層に作る）
+pub const WORKSPACE_PREFIX: &str = "rebase_tmp_";
+
+/// 新しい .base をどのスナップショットから作るか
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RebaseStrategy {
+    Latest,
+    Median,
+}
+
+impl FromStr for RebaseStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" | "latest" => Ok(RebaseStrategy::Latest),
+            "median" => Ok(RebaseStrategy::Median),
+            _ => Err(format!("不明なリベース方式です: {}", s)),
+        }
+    }
+}
+
+/// 1世代分のリベース計画
+/// 作業はすべて `rebase_tmp_<世代名>/` の中で行い、最後にフォルダを入れ替える
+///
+/// 例:
+///   rebase_tmp_base2_20260101_100000/
+///   ├── snapshots/   各差分を復元した状態
+///   ├── generation/  新しい世代フォルダ（入れ替え後に baseN_... になる）
+///   ├── verify/      再エンコード後の差分の検証用
+///   └── old/         入れ替え中の旧世代フォルダ
+#[derive(Debug, Clone)]
+pub struct RebasePlan {
+    pub generation: i32,
+    pub gen_dir: PathBuf,
+    pub workspace: PathBuf,
+    pub entry_name: String,
+    pub old_base: PathBuf,
+    pub diffs: Vec<PathBuf>,
+    pub new_base_idx: usize,
+    pub before_bytes: u64,
+}
+
+impl RebasePlan {
+    /// 差分を復元したスナップショットの置き場所
+    pub fn snapshot_path(&self, diff: &Path) -> PathBuf {
+        let name = diff.file_name().unwrap_or_default().to_string_lossy();
+        self.workspace
+            .join("snapshots")
+            .join(name.trim_end_matches(".diff"))
+    }
+
+    pub fn staged_generation(&self) -> PathBuf {
+        self.workspace.join("generation")
+    }
+
+    pub fn staged_base(&self) -> PathBuf {
+        self.staged_generation()
+            .join(format!("{}.base", self.entry_name))
+    }
+
+    /// 再エンコード後の差分（ファイル名は元と同じにしてメモ等の対応を保つ）
+    pub fn staged_diff(&self, diff: &Path) -> PathBuf {
+        self.staged_generation()
+            .join(diff.file_name().unwrap_or_default())
+    }
+
+    /// 旧 .base の状態を失わないよう、新しい .base からの差分として残す
+    /// 名前は世代フォルダ作成時刻を使う: `<name>.<世代ts>.hdiff.diff`
+    /// 世代交代時は同じ時刻の差分（= .base と同じ状態）が既にあるため None を返す
+    pub fn original_base_diff(&self) -> Option<PathBuf> {
+        let gen_name = self
+            .gen_dir
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let ts = gen_name.split_once('_').map(|(_, ts)| ts).unwrap_or("");
+        let prefix = format!("{}.{}.", self.entry_name, ts);
+        let duplicated = self.diffs.iter().any(|d| {
+            d.file_name()
+                .map(|n| n.to_string_lossy().starts_with(&prefix))
+                .unwrap_or(false)
+        });
+        if duplicated {
+            return None;
+        }
+        Some(
+            self.staged_generation()
+                .join(format!("{}hdiff.diff", prefix)),
+        )
+    }
+
+    pub fn verify_path(&self) -> PathBuf {
+        self.workspace.join("verify").join(&self.entry_name)
+    }
+
+    pub fn new_base_source(&self) -> PathBuf {
+        self.snapshot_path(&self.diffs[self.new_base_idx])
+    }
+}
+
+fn workspace_for(gen_dir: &Path) -> PathBuf {
+    let gen_name = gen_dir.file_name().unwrap_or_default().to_string_lossy();
+    gen_dir.with_file_name(format!("{}{}", WORKSPACE_PREFIX, gen_name))
+}
+
+/// 世代フォルダ内の差分をタイムスタンプ順（ファイル名順）に返す
+pub fn list_generation_diffs(gen_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut diffs: Vec<PathBuf> = fs::read_dir(gen_dir)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && p.file_name()
+                    .map(|n| n.to_string_lossy().ends_with(".diff"))
+                    .unwrap_or(false)
+        })
+        .collect();
+    diffs.sort();
+    Ok(diffs)
+}
+
+/// 新しい .base にする差分の位置を選ぶ
+pub fn pick_new_base(diff_count: usize, strategy: RebaseStrategy) -> Option<usize> {
+    if diff_count == 0 {
+        return None;
+    }
+    match strategy {
+        RebaseStrategy::Latest => Some(diff_count - 1),
+        RebaseStrategy::Median => Some(diff_count / 2),
+    }
+}
+
+/// 中断されたリベースの後始末をする
+/// - 世代フォルダが入れ替え途中で消えている場合は旧フォルダを戻す
+/// - それ以外は作業フォルダを削除する
+pub fn recover_interrupted_rebase(root: &Path) -> Result<(), String> {
+    if !root.exists() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(root).map_err(|e| e.to_string())?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(gen_name) = name.strip_prefix(WORKSPACE_PREFIX) else {
+            continue;
+        };
+        let workspace = entry.path();
+        let gen_dir = root.join(gen_name);
+        let old = workspace.join("old");
+
+        if !gen_dir.exists() && old.exists() {
+            fs::rename(&old, &gen_dir)
+                .map_err(|e| format!("中断されたリベースの復旧に失敗しました: {}", e))?;
+        }
+        fs::remove_dir_all(&workspace).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// リベース計画を作成し、作業フォルダを用意する
+pub fn plan_rebase(
+    root: &Path,
+    target_n: u32,
+    work_path: &str,
+    strategy: RebaseStrategy,
+) -> Result<RebasePlan, String> {
+    let gen_dir = auto_generation::find_generation_dir(root, target_n)?;
+    let entry_name = Path::new(work_path)
+        .file_name()
+        .ok_or("Invalid work path name")?
+        .to_string_lossy()
+        .into_owned();
+
+    let old_base = gen_dir.join(format!("{}.base", entry_name));
+    if !old_base.exists() {
+        return Err(format!(
+            "世代 {} に .base が見つかりません: {}",
+            target_n,
+            old_base.display()
+        ));
+    }
+
+    if crypto::contains_encrypted(&gen_dir) {
+        return Err(format!(
+            "世代 {} には暗号化されたファイルがあるためリベースできません",
+            target_n
+        ));
+    }
+    let diffs = list_generation_diffs(&gen_dir)?;
+    let new_base_idx = pick_new_base(diffs.len(), strategy)
+        .ok_or_else(|| format!("世代 {} には差分がないためリベース不要です", target_n))?;
+
+    let before_bytes = utils::get_file_size(&gen_dir.to_string_lossy())? as u64;
+
+    let workspace = workspace_for(&gen_dir);
+    if workspace.exists() {
+        fs::remove_dir_all(&workspace).map_err(|e| e.to_string())?;
+    }
+    for sub in ["snapshots", "generation", "verify"] {
+        fs::create_dir_all(workspace.join(sub)).map_err(|e| e.to_string())?;
+    }
+
+    Ok(RebasePlan {
+        generation: target_n as i32,
+        gen_dir,
+        workspace,
+        entry_name,
+        old_base,
+        diffs,
+        new_base_idx,
+        before_bytes,
+    })
+}
+
+/// 選んだスナップショットを新しい .base として配置し、メモなど差分以外の付随ファイルを移す
+pub fn stage_new_base(plan: &RebasePlan) -> Result<(), String> {
+    copy_entry(&plan.new_base_source(), &plan.staged_base())?;
+
+    for entry in fs::read_dir(&plan.gen_dir)
+        .map_err(|e| e.to_string())?
+        .flatten()
+    {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if path.is_file() && !name.ends_with(".diff") && !name.ends_with(".base") {
+            fs::copy(&path, plan.staged_generation().join(&name)).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// 作業フォルダの世代と現在の世代を入れ替え、結果を返す
+/// 入れ替え前に差分の更新日時を元に戻しておく（履歴一覧の時刻がずれないように）
+pub fn commit_rebase(plan: &RebasePlan) -> Result<RebaseReport, String> {
+    for diff in &plan.diffs {
+        if let Ok(t) = fs::metadata(diff).and_then(|m| m.modified()) {
+            utils::set_modified_time(&plan.staged_diff(diff), t)?;
+        }
+    }
+    if let Some(original_base_diff) = plan.original_base_diff().filter(|p| p.exists()) {
+        if let Ok(t) = fs::metadata(&plan.old_base).and_then(|m| m.modified()) {
+            utils::set_modified_time(&original_base_diff, t)?;
+        }
+    }
+
+    let staged = plan.staged_generation();
+    let after_bytes = utils::get_file_size(&staged.to_string_lossy())? as u64;
+
+    let old = plan.workspace.join("old");
+    fs::rename(&plan.gen_dir, &old)
+        .map_err(|e| format!("世代フォルダの退避に失敗しました: {}", e))?;
+    if let Err(e) = fs::rename(&staged, &plan.gen_dir) {
+        // 入れ替えに失敗したら元に戻す
+        let _ = fs::rename(&old, &plan.gen_dir);
+        return Err(format!("世代フォルダの入れ替えに失敗しました: {}", e));
+    }
+    let _ = fs::remove_dir_all(&plan.workspace);
+
+    Ok(RebaseReport {
+        generation: plan.generation,
+        diff_count: plan.diffs.len(),
+        new_base_from: plan.diffs[plan.new_base_idx]
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+        before_bytes: plan.before_bytes,
+        after_bytes,
+        saved_bytes: plan.before_bytes as i64 - after_bytes as i64,
+    })
+}
+
+/// リベースを中止し、作業フォルダを削除する（元の世代には触れない）
+pub fn abort_rebase(plan: &RebasePlan) {
+    let _ = fs::remove_dir_all(&plan.workspace);
+}
+
+/// 2つのパス（ファイルまたはフォルダ）の内容が同一か判定する
+pub fn same_content(a: &Path, b: &Path) -> Result<bool, String> {
+    if a.is_dir() != b.is_dir() {
+        return Ok(false);
+    }
+    if a.is_file() {
+        return files_equal(a, b);
+    }
+
+    let list = |root: &Path| -> Vec<PathBuf> {
+        let mut v: Vec<PathBuf> = walkdir::WalkDir::new(root)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| e.path().strip_prefix(root).ok().map(|p| p.to_path_buf()))
+            .collect();
+        v.sort();
+        v
+    };
+    let (la, lb) = (list(a), list(b));
+    if la != lb {
+        return Ok(false);
+    }
+    for rel in la {
+        if !files_equal(&a.join(&rel), &b.join(&rel))? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn files_equal(a: &Path, b: &Path) -> Result<bool, String> {
+    let (ma, mb) = (
+        fs::metadata(a).map_err(|e| e.to_string())?,
+        fs::metadata(b).map_err(|e| e.to_string())?,
+    );
+    if ma.len() != mb.len() {
+        return Ok(false);
+    }
+    let mut fa = fs::File::open(a).map_err(|e| e.to_string())?;
+    let mut fb = fs::File::open(b).map_err(|e| e.to_string())?;
+    let mut ba = vec![0u8; 64 * 1024];
+    let mut bb = vec![0u8; 64 * 1024];
+    loop {
+        let n = fa.read(&mut ba).map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Ok(true);
+        }
+        fb.read_exact(&mut bb[..n]).map_err(|e| e.to_string())?;
+        if ba[..n] != bb[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+fn copy_entry(src: &Path, dst: &Path) -> Result<(), String> {
+    if src.is_dir() {
+        utils::copy_dir_preserving(src, dst, true)
+    } else {
+        utils::copy_file(&src.to_string_lossy(), &dst.to_string_lossy())
+    }
+}
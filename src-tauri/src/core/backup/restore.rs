@@ -0,0 +1,309 @@
+use crate::core::backup::{archive, crypto, in_place, scanner, volume};
+use crate::core::utils;
+use chrono::{DateTime, Local, NaiveDateTime};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// 復元ポイントの種類（種類ごとに復元方法が異なる）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreKind {
+    /// 差分 (.diff)。世代アーカイブ内の差分（仮想パス）を含む
+    Diff,
+    /// 単体アーカイブ (.zip / .tar.gz / .tar.zst / .tar.xz)
+    Archive,
+    /// フルコピー（ファイルまたはフォルダ）
+    Copy,
+    /// 重複排除バックアップのマニフェスト (.dedup)
+    Dedup,
+}
+
+/// パスから復元ポイントの種類を判定する
+/// 暗号化したもの (`.enc`)・分割したもの (`.001`) は中身の種類で判定する
+pub fn restore_kind_of(path: &str) -> RestoreKind {
+    let lower = path.to_lowercase();
+    let lower = crypto::strip_encrypted_ext(volume::strip_first_volume_ext(&lower));
+    if lower.ends_with(".diff") {
+        RestoreKind::Diff
+    } else if lower.ends_with(".dedup") {
+        RestoreKind::Dedup
+    } else if archive::is_archive_name(lower) {
+        RestoreKind::Archive
+    } else {
+        RestoreKind::Copy
+    }
+}
+
+/// 時刻指定の復元で候補になるバックアップ
+#[derive(Debug, Clone)]
+pub struct RestorePoint {
+    pub path: String,
+    pub time: NaiveDateTime,
+    pub kind: RestoreKind,
+}
+
+/// 復元したい時刻の文字列を解釈する（ローカル時刻）
+/// 受け付ける形式: `YYYY-MM-DD HH:MM[:SS]`, `YYYY-MM-DDTHH:MM[:SS]`, `YYYYMMDD_HHMMSS`, RFC 3339
+pub fn parse_restore_instant(s: &str) -> Result<NaiveDateTime, String> {
+    let s = s.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.with_timezone(&Local).naive_local());
+    }
+    for fmt in [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y%m%d_%H%M%S",
+    ] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Ok(dt);
+        }
+    }
+    Err(format!("日時の形式が正しくありません: {}", s))
+}
+
+/// 名前に含まれる `YYYYMMDD_HHMMSS` を取り出す
+fn time_from_name(name: &str) -> Option<NaiveDateTime> {
+    let re = Regex::new(r"(\d{8}_\d{6})").unwrap();
+    re.captures_iter(name)
+        .filter_map(|c| NaiveDateTime::parse_from_str(&c[1], "%Y%m%d_%H%M%S").ok())
+        .last()
+}
+
+fn modified_time(path: &Path) -> Option<NaiveDateTime> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(DateTime::<Local>::from(modified).naive_local())
+}
+
+/// 復元候補をすべて集める
+/// 差分・単体アーカイブ・世代アーカイブ内の差分は scan_backups の結果を使い、
+/// フルコピー（`<stem>_<ts>.<ext>` / `<name>_<ts>/`）はここで追加で拾う
+pub fn collect_restore_points(
+    work_path: &str,
+    backup_dir: &str,
+    strict_match: bool,
+    use_same_dir_for_temp: bool,
+) -> Vec<RestorePoint> {
+    let mut points = Vec::new();
+
+    for item in scanner::scan_backups(work_path, backup_dir, strict_match, use_same_dir_for_temp) {
+        let kind = restore_kind_of(&item.file_name);
+        let time = time_from_name(&item.file_name)
+            .or_else(|| NaiveDateTime::parse_from_str(&item.timestamp, "%Y-%m-%d %H:%M:%S").ok());
+        if let Some(time) = time {
+            points.push(RestorePoint {
+                path: item.file_path,
+                time,
+                kind,
+            });
+        }
+    }
+
+    let root = if backup_dir.is_empty() {
+        utils::default_backup_dir(work_path)
+    } else {
+        PathBuf::from(backup_dir)
+    };
+    let copy_re = full_copy_regex(work_path);
+
+    if let (Ok(copy_re), Ok(entries)) = (copy_re, fs::read_dir(&root)) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(caps) = copy_re.captures(&name) else {
+                continue;
+            };
+            let path = entry.path();
+            let time = NaiveDateTime::parse_from_str(&caps[1], "%Y%m%d_%H%M%S")
+                .ok()
+                .or_else(|| modified_time(&path));
+            if let Some(time) = time {
+                points.push(RestorePoint {
+                    path: path.to_string_lossy().into_owned(),
+                    time,
+                    kind: RestoreKind::Copy,
+                });
+            }
+        }
+    }
+
+    points.sort_by(|a, b| a.time.cmp(&b.time).then_with(|| a.path.cmp(&b.path)));
+    points
+}
+
+/// フルコピー（`<stem>_<ts>.<ext>` / `<name>_<ts>`）の名前に一致する正規表現
+/// キャプチャ 1 はタイムスタンプ
+pub fn full_copy_regex(work_path: &str) -> Result<Regex, String> {
+    let work = Path::new(work_path);
+    let (stem, ext) = if work.is_dir() {
+        (work.file_name(), None)
+    } else {
+        (work.file_stem(), work.extension())
+    };
+    let stem = stem
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let pattern = match ext {
+        Some(ext) => format!(
+            r"^{}_(\d{{8}}_\d{{6}})\.{}(\.enc)?$",
+            regex::escape(&stem),
+            regex::escape(&ext.to_string_lossy())
+        ),
+        None => format!(r"^{}_(\d{{8}}_\d{{6}})$", regex::escape(&stem)),
+    };
+    Regex::new(&pattern).map_err(|e| e.to_string())
+}
+
+/// 指定時刻以前で最も新しい復元ポイントを返す
+pub fn find_restore_point(points: &[RestorePoint], at: NaiveDateTime) -> Option<&RestorePoint> {
+    points
+        .iter()
+        .filter(|p| p.time <= at)
+        .max_by(|a, b| a.time.cmp(&b.time).then_with(|| a.path.cmp(&b.path)))
+}
+
+/// フルコピーを出力先へ書き出す（フォルダのコピーにも対応）
+pub fn restore_copy(src: &str, dst: &str) -> Result<(), String> {
+    let src_path = Path::new(src);
+    if !src_path.is_dir() {
+        return utils::copy_file(src, dst);
+    }
+    // バックアップ内のリンクはリンクのまま戻す
+    utils::copy_dir_preserving(src_path, Path::new(dst), true)
+}
+
+/// 出力先に同名のファイル/フォルダがある場合の扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// 上書きする（フォルダは中身を上書きしながら統合）
+    Overwrite,
+    /// 既存のものを残す（フォルダは既存ファイル以外だけを追加）
+    Skip,
+    /// `<name>_1` のような空いている名前で出力する
+    Rename,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" | "rename" => Ok(ConflictPolicy::Rename),
+            "overwrite" => Ok(ConflictPolicy::Overwrite),
+            "skip" => Ok(ConflictPolicy::Skip),
+            _ => Err(format!("不明な競合時の動作です: {}", s)),
+        }
+    }
+}
+
+/// 指定された出力先から実際の出力パスを決める
+/// 既存のフォルダ、または末尾が区切り文字の場合はその中へ自動命名 (`<stem>_restored_<ts>`) で出力する
+pub fn resolve_output_target(work_path: &str, output: &str) -> PathBuf {
+    let out = Path::new(output);
+    let is_dir_target = output.ends_with('/') || output.ends_with('\\') || out.is_dir();
+    if !is_dir_target {
+        return out.to_path_buf();
+    }
+    let auto = utils::auto_output_path(work_path);
+    let name = Path::new(&auto).file_name().unwrap_or_default().to_owned();
+    out.join(name)
+}
+
+/// `<stem>_N.<ext>` の形で、まだ存在しないパスを返す
+pub fn next_available_path(dest: &Path) -> PathBuf {
+    if !dest.exists() {
+        return dest.to_path_buf();
+    }
+    let parent = dest.parent().unwrap_or_else(|| Path::new("."));
+    let (stem, ext) = if dest.is_dir() {
+        (dest.file_name(), None)
+    } else {
+        (dest.file_stem(), dest.extension())
+    };
+    let stem = stem.unwrap_or_default().to_string_lossy();
+    (1..)
+        .map(|n| match ext {
+            Some(ext) => parent.join(format!("{}_{}.{}", stem, n, ext.to_string_lossy())),
+            None => parent.join(format!("{}_{}", stem, n)),
+        })
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+/// 一時出力した復元結果を出力先へ配置する
+/// 戻り値: 配置したパス。Skip で何も置かなかった場合は None
+pub fn place_restored(
+    staged: &Path,
+    dest: &Path,
+    policy: ConflictPolicy,
+) -> Result<Option<PathBuf>, String> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("ディレクトリ作成失敗: {}", e))?;
+    }
+    if !dest.exists() {
+        move_path(staged, dest)?;
+        return Ok(Some(dest.to_path_buf()));
+    }
+
+    match policy {
+        ConflictPolicy::Rename => {
+            let renamed = next_available_path(dest);
+            move_path(staged, &renamed)?;
+            Ok(Some(renamed))
+        }
+        ConflictPolicy::Overwrite | ConflictPolicy::Skip if staged.is_dir() && dest.is_dir() => {
+            merge_dir(staged, dest, policy == ConflictPolicy::Overwrite)?;
+            in_place::discard_staging(staged);
+            Ok(Some(dest.to_path_buf()))
+        }
+        ConflictPolicy::Overwrite => {
+            if dest.is_dir() {
+                fs::remove_dir_all(dest).map_err(|e| e.to_string())?;
+            }
+            move_path(staged, dest)?;
+            Ok(Some(dest.to_path_buf()))
+        }
+        ConflictPolicy::Skip => {
+            in_place::discard_staging(staged);
+            Ok(None)
+        }
+    }
+}
+
+/// rename で移動し、別ドライブなどで失敗した場合はコピーしてから削除する
+fn move_path(src: &Path, dst: &Path) -> Result<(), String> {
+    if fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+    restore_copy(&src.to_string_lossy(), &dst.to_string_lossy())?;
+    in_place::discard_staging(src);
+    Ok(())
+}
+
+/// staged フォルダの中身を dest へ統合する
+fn merge_dir(staged: &Path, dest: &Path, overwrite: bool) -> Result<(), String> {
+    for entry in walkdir::WalkDir::new(staged)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let rel = entry
+            .path()
+            .strip_prefix(staged)
+            .map_err(|e| e.to_string())?;
+        let target = dest.join(rel);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+            continue;
+        }
+        if target.exists() && !overwrite {
+            continue;
+        }
+        if target.is_dir() {
+            fs::remove_dir_all(&target).map_err(|e| e.to_string())?;
+        }
+        utils::copy_file(&entry.path().to_string_lossy(), &target.to_string_lossy())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// バックアップルートごとの実行中の作業
+/// 世代を作り直す作業（リベース・世代統合）やチャンクの整理は同じルートで1つだけ実行し、
+/// その間は中断された作業の後始末やバックアップで作業中のものに触れないようにする
+/// バックアップ同士は同時に実行できる（共有）
+#[derive(Debug, Default, Clone)]
+pub struct RootLocks {
+    busy: Arc<Mutex<HashMap<PathBuf, Holder>>>,
+}
+
+/// ルートを使っている作業（作業名と、共有の場合は実行中の数）
+#[derive(Debug)]
+enum Holder {
+    Exclusive(String),
+    Shared(String, usize),
+}
+
+impl Holder {
+    fn task(&self) -> &str {
+        match self {
+            Holder::Exclusive(task) | Holder::Shared(task, _) => task,
+        }
+    }
+}
+
+/// 作業が終わる（drop される）までルートを使用中にする
+#[derive(Debug)]
+pub struct RootGuard {
+    busy: Arc<Mutex<HashMap<PathBuf, Holder>>>,
+    key: PathBuf,
+}
+
+impl Drop for RootGuard {
+    fn drop(&mut self) {
+        let mut busy = self.busy.lock().unwrap();
+        match busy.get_mut(&self.key) {
+            Some(Holder::Shared(_, count)) if *count > 1 => *count -= 1,
+            _ => {
+                busy.remove(&self.key);
+            }
+        }
+    }
+}
+
+impl RootLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ルートを排他で使用中にする（他の作業が実行中ならエラー）
+    /// task はエラーメッセージに出す作業名
+    pub fn acquire(&self, root: &Path, task: &str) -> Result<RootGuard, String> {
+        let key = lock_key(root);
+        let mut busy = self.busy.lock().unwrap();
+        if let Some(holder) = busy.get(&key) {
+            return Err(busy_message(holder.task()));
+        }
+        busy.insert(key.clone(), Holder::Exclusive(task.to_string()));
+        Ok(self.guard(key))
+    }
+
+    /// ルートを共有で使用中にする（共有の作業同士は同時に実行でき、排他の作業が実行中ならエラー）
+    pub fn acquire_shared(&self, root: &Path, task: &str) -> Result<RootGuard, String> {
+        let key = lock_key(root);
+        let mut busy = self.busy.lock().unwrap();
+        match busy.get_mut(&key) {
+            Some(Holder::Exclusive(running)) => return Err(busy_message(running)),
+            Some(Holder::Shared(_, count)) => *count += 1,
+            None => {
+                busy.insert(key.clone(), Holder::Shared(task.to_string(), 1));
+            }
+        }
+        Ok(self.guard(key))
+    }
+
+    /// ルートで作業を実行中か
+    pub fn is_busy(&self, root: &Path) -> bool {
+        self.busy.lock().unwrap().contains_key(&lock_key(root))
+    }
+
+    fn guard(&self, key: PathBuf) -> RootGuard {
+        RootGuard {
+            busy: Arc::clone(&self.busy),
+            key,
+        }
+    }
+}
+
+fn busy_message(running: &str) -> String {
+    format!(
+        "このバックアップ先では{}を実行中です。終わってからやり直してください",
+        running
+    )
+}
+
+/// 同じルートを別の表記で指定しても同じものとして扱う
+/// まだないルート（最初のバックアップの前）は、ある所までの親を正規化して残りをつなげる
+fn lock_key(root: &Path) -> PathBuf {
+    root.ancestors()
+        .find_map(|p| {
+            let rest = root.strip_prefix(p).ok()?;
+            Some(p.canonicalize().ok()?.join(rest))
+        })
+        .unwrap_or_else(|| root.to_path_buf())
+}
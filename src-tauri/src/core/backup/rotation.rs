@@ -0,0 +1,222 @@
+use crate::core::backup::{crypto, workflow};
+use crate::core::types::{AppConfig, RotationReason, RotationRecord};
+use crate::core::utils;
+use chrono::NaiveDateTime;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// 世代交代の記録（バックアップ先のルート直下）
+pub const ROTATION_LOG_FILE: &str = ".wbt_rotation_log.json";
+
+/// 世代交代の条件（0 の項目は判定しない）
+#[derive(Debug, Clone, PartialEq)]
+pub struct RotationPolicy {
+    /// 差分1つが 作業サイズ × threshold を超えたら交代
+    pub threshold: f64,
+    /// 世代内の差分の合計が ベースサイズ × cumulative_ratio を超えたら交代
+    pub cumulative_ratio: f64,
+    /// 世代内の差分の数の上限
+    pub max_diffs: usize,
+    /// 世代を作ってからの日数の上限
+    pub max_age_days: u64,
+    /// 作業サイズが ベースサイズ × shrink_ratio を下回ったら交代
+    pub shrink_ratio: f64,
+    /// 作業ファイルの種類が変わったら交代
+    pub type_changed: bool,
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 0.8,
+            cumulative_ratio: 0.0,
+            max_diffs: 0,
+            max_age_days: 0,
+            shrink_ratio: 0.0,
+            type_changed: false,
+        }
+    }
+}
+
+impl RotationPolicy {
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        Self {
+            threshold: if cfg.auto_base_generation_threshold <= 0.0 {
+                0.8
+            } else {
+                cfg.auto_base_generation_threshold
+            },
+            cumulative_ratio: cfg.rotate_cumulative_ratio.max(0.0),
+            max_diffs: cfg.rotate_max_diffs,
+            max_age_days: cfg.rotate_max_age_days,
+            shrink_ratio: cfg.rotate_shrink_ratio.max(0.0),
+            type_changed: cfg.rotate_on_type_change,
+        }
+    }
+}
+
+/// 判定に使う世代の現状
+#[derive(Debug, Clone, Default)]
+pub struct GenerationState {
+    pub base_size: u64,
+    pub base_is_dir: bool,
+    pub base_format: Option<&'static str>,
+    pub diff_count: usize,
+    pub diff_bytes: u64,
+    pub created: Option<NaiveDateTime>,
+}
+
+/// ファイル先頭のシグネチャから形式を判定する（不明なら None）
+pub fn detect_format(path: &Path) -> Option<&'static str> {
+    let mut head = [0u8; 12];
+    let n = File::open(path).and_then(|mut f| f.read(&mut head)).ok()?;
+    let head = &head[..n];
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"CSFCHUNK", "clip"),
+        (b"8BPS", "psd"),
+        (b"\x89PNG", "png"),
+        (b"\xFF\xD8\xFF", "jpeg"),
+        (b"GIF8", "gif"),
+        (b"%PDF", "pdf"),
+        (b"PK\x03\x04", "zip"),
+        (b"SQLite format 3", "sqlite"),
+    ];
+    if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP".as_slice()) {
+        return Some("webp");
+    }
+    SIGNATURES
+        .iter()
+        .find(|(sig, _)| head.starts_with(sig))
+        .map(|(_, name)| *name)
+}
+
+/// 世代フォルダ `baseN_<ts>` の作成時刻
+fn generation_created(gen_dir: &Path) -> Option<NaiveDateTime> {
+    let name = gen_dir.file_name()?.to_string_lossy().into_owned();
+    let (_, ts) = name.strip_prefix("base")?.split_once('_')?;
+    NaiveDateTime::parse_from_str(ts, "%Y%m%d_%H%M%S").ok()
+}
+
+/// 世代フォルダのベース・差分の状態を調べる（暗号化した差分 `.diff.enc` も数える）
+pub fn inspect_generation(gen_dir: &Path, entry_name: &str) -> GenerationState {
+    let base = gen_dir.join(format!("{}.base", entry_name));
+    let diffs: Vec<_> = fs::read_dir(gen_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && p.file_name().is_some_and(|n| {
+                    crypto::strip_encrypted_ext(&n.to_string_lossy()).ends_with(".diff")
+                })
+        })
+        .collect();
+    GenerationState {
+        base_size: utils::get_file_size(&base.to_string_lossy()).unwrap_or(0) as u64,
+        base_is_dir: base.is_dir(),
+        base_format: if base.is_file() {
+            detect_format(&base)
+        } else {
+            None
+        },
+        diff_count: diffs.len(),
+        diff_bytes: diffs
+            .iter()
+            .map(|p| fs::metadata(p).map(|m| m.len()).unwrap_or(0))
+            .sum(),
+        created: generation_created(gen_dir),
+    }
+}
+
+/// 新しい差分を追加する前に、世代交代すべきか判定する
+/// 戻り値: 交代する場合はその理由と説明
+pub fn decide_rotation(
+    policy: &RotationPolicy,
+    state: &GenerationState,
+    work_path: &Path,
+    work_size: u64,
+    new_diff_size: u64,
+    now: NaiveDateTime,
+) -> Option<(RotationReason, String)> {
+    // 1. 種類が変わった・大きく縮んだ場合は、差分が役に立たないので交代する
+    let work_is_dir = work_path.is_dir();
+    let work_format = if work_is_dir {
+        None
+    } else {
+        detect_format(work_path)
+    };
+    if policy.type_changed && (work_is_dir != state.base_is_dir || work_format != state.base_format)
+    {
+        return Some((
+            RotationReason::TypeChanged,
+            format!(
+                "{} -> {}",
+                state.base_format.unwrap_or("unknown"),
+                work_format.unwrap_or("unknown")
+            ),
+        ));
+    }
+    if policy.shrink_ratio > 0.0
+        && state.base_size > 0
+        && (work_size as f64) < state.base_size as f64 * policy.shrink_ratio
+    {
+        return Some((
+            RotationReason::WorkShrunk,
+            format!("{} -> {} bytes", state.base_size, work_size),
+        ));
+    }
+
+    // 2. 差分1つの大きさ（従来の判定）
+    if workflow::should_transition_to_next_gen(work_size, new_diff_size, policy.threshold) {
+        return Some((
+            RotationReason::DiffSize,
+            format!("{} / {} bytes", new_diff_size, work_size),
+        ));
+    }
+
+    // 3. 世代全体の差分の量・数・経過日数
+    let total = state.diff_bytes + new_diff_size;
+    if policy.cumulative_ratio > 0.0
+        && state.base_size > 0
+        && (total as f64) > state.base_size as f64 * policy.cumulative_ratio
+    {
+        return Some((
+            RotationReason::CumulativeDiff,
+            format!("{} / {} bytes", total, state.base_size),
+        ));
+    }
+    if policy.max_diffs > 0 && state.diff_count + 1 > policy.max_diffs {
+        return Some((
+            RotationReason::MaxDiffs,
+            format!("{} diffs", state.diff_count + 1),
+        ));
+    }
+    if policy.max_age_days > 0 {
+        if let Some(created) = state.created {
+            let days = (now - created).num_days();
+            if days >= policy.max_age_days as i64 {
+                return Some((RotationReason::MaxAge, format!("{} days", days)));
+            }
+        }
+    }
+    None
+}
+
+/// 世代交代の記録を読み込む（存在しない・壊れている場合は空）
+pub fn load_rotation_log(root: &Path) -> Vec<RotationRecord> {
+    fs::read_to_string(root.join(ROTATION_LOG_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// 世代交代を記録する
+pub fn record_rotation(root: &Path, record: RotationRecord) -> Result<(), String> {
+    let mut log = load_rotation_log(root);
+    log.retain(|r| r.generation != record.generation);
+    log.push(record);
+    let json = serde_json::to_string_pretty(&log).map_err(|e| e.to_string())?;
+    fs::write(root.join(ROTATION_LOG_FILE), json).map_err(|e| e.to_string())
+}
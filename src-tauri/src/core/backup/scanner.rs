@@ -1,12 +1,13 @@
-use crate::core::backup::auto_generation;
+use crate::core::backup::{archive, auto_generation, cache_manager, crypto, identity, volume};
 use crate::core::types::BackupItem;
 use crate::core::utils;
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDateTime};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 /// バックアップディレクトリとキャッシュディレクトリを走査してアイテム一覧を返す
-/// フルコピー（.diff / .zip / .tar.gz 以外）は復元操作が成立しないため返さない
+/// フルコピー（.diff / .zip / .tar.gz / .tar.zst / .tar.xz 以外）は復元操作が成立しないため返さない
 pub fn scan_backups(
     work_path: &str,
     backup_dir: &str,
@@ -39,17 +40,36 @@ pub fn scan_backups(
                 .and_then(|s| s.to_str())
                 .unwrap_or(""),
         );
-    let base_lower = base_name_only.to_lowercase();
+    // 作業ファイルの名前を変えて付け替えた履歴は、世代アーカイブの中などに以前の名前が残る
+    let mut known_lowers = vec![base_name_only.to_lowercase()];
+    known_lowers.extend(
+        identity::known_stems(&root)
+            .iter()
+            .map(|s| s.to_lowercase()),
+    );
+    let name_matches = |name: &str| -> bool {
+        let n = name.to_lowercase();
+        known_lowers.iter().any(|k| n.contains(k.as_str()))
+    };
 
     // 復元可能な拡張子のみ（フルコピーは除外）
-    let restorable_exts: &[&str] = &[".diff", ".zip", ".tar.gz"];
+    let restorable_exts: &[&str] = &[".diff", ".zip", ".tar.gz", ".tar.zst", ".tar.xz", ".dedup"];
 
     // 拡張子判定ヘルパー（復元可能なもののみ）
+    // 暗号化したもの (`.enc`)・分割アーカイブの1つ目のボリューム (`.001`) は中身の拡張子で判定する
+    // 2つ目以降のボリュームは1つ目にまとめて扱うので対象外
     let is_restorable = |name: &str| -> bool {
         let n = name.to_lowercase();
+        let n = crypto::strip_encrypted_ext(volume::strip_first_volume_ext(&n));
         restorable_exts.iter().any(|ext| n.ends_with(ext))
     };
 
+    // 世代アーカイブ判定用
+    let gen_archive_re = regex::Regex::new(
+        r"^base(\d+)_.*\.(zip|tar\.gz|tgz|tar\.zst|tzst|tar\.xz|txz)(\.enc)?(\.001)?$",
+    )
+    .unwrap();
+
     // 走査対象：(スキャンするディレクトリ, アーカイブ展開フラグ)
     let scan_roots = vec![(&root, false), (&cache_root, true)];
 
@@ -88,8 +108,7 @@ pub fn scan_backups(
                                 }
 
                                 // 復元可能な拡張子のみ対象
-                                if (!strict_match || f_name.to_lowercase().contains(&base_lower))
-                                    && is_restorable(f_name)
+                                if (!strict_match || name_matches(f_name)) && is_restorable(f_name)
                                 {
                                     if let Ok(metadata) = fs::metadata(&gen_path) {
                                         list.push(create_backup_item(
@@ -105,16 +124,62 @@ pub fn scan_backups(
                             }
                         }
                     }
+                } else if !is_archived_flag && gen_archive_re.is_match(file_name) {
+                    // --- 世代アーカイブ (baseN_....zip 等) ---
+                    // 展開キャッシュがあればそちらの走査で列挙されるので、ここでは扱わない
+                    let folder_name = archive::strip_archive_ext(file_name);
+                    if cache_root.join(folder_name).is_dir() {
+                        continue;
+                    }
+                    let gen_idx = gen_archive_re
+                        .captures(file_name)
+                        .and_then(|c| c[1].parse::<i32>().ok())
+                        .unwrap_or(0);
+                    let archive_meta = fs::metadata(&path).ok();
+                    match cache_manager::list_archive_entries_cached(&cache_root, &path) {
+                        Ok(inner) => {
+                            for e in inner.iter().filter(|e| !e.is_dir) {
+                                let f_name = e.name.rsplit('/').next().unwrap_or("");
+                                // .base 配下や diff 以外（メモなど）は除外
+                                if e.name.contains(".base/") || !f_name.ends_with(".diff") {
+                                    continue;
+                                }
+                                if strict_match && !name_matches(f_name) {
+                                    continue;
+                                }
+                                let modified = diff_time_from_name(f_name)
+                                    .or(e.modified)
+                                    .or_else(|| archive_meta.as_ref()?.modified().ok());
+                                list.push(create_archived_entry_item(
+                                    f_name,
+                                    &archive::archived_entry_path(&path, &e.name),
+                                    e.size,
+                                    modified,
+                                    gen_idx,
+                                ));
+                            }
+                        }
+                        Err(_) => {
+                            // 中身を読めない場合は従来どおりアーカイブ自体を返す
+                            if let Some(metadata) = archive_meta {
+                                list.push(create_backup_item(
+                                    file_name, &path, &metadata, 0, false, false,
+                                ));
+                            }
+                        }
+                    }
                 } else {
                     // --- ルート直下のファイルをスキャン ---
                     // フルコピーを含まない復元可能なものだけ
-                    if (!strict_match || file_name.to_lowercase().contains(&base_lower))
-                        && is_restorable(file_name)
-                    {
+                    if (!strict_match || name_matches(file_name)) && is_restorable(file_name) {
                         if let Ok(metadata) = fs::metadata(&path) {
-                            list.push(create_backup_item(
-                                file_name, &path, &metadata, 0, false, false,
-                            ));
+                            let mut item =
+                                create_backup_item(file_name, &path, &metadata, 0, false, false);
+                            // 分割アーカイブはボリュームの合計サイズを表示する
+                            if volume::is_first_volume(file_name) {
+                                item.file_size = volume::volume_set_size(&path) as i64;
+                            }
+                            list.push(item);
                         }
                     }
                 }
@@ -199,5 +264,39 @@ fn create_backup_item(
         generation: gen,
         is_archived,
         is_folder,
+        thumbnail: None,
+        mirror: None,
     }
 }
+
+// ヘルパー関数: 世代アーカイブ内の差分のアイテム生成
+fn create_archived_entry_item(
+    name: &str,
+    virtual_path: &str,
+    size: u64,
+    modified: Option<SystemTime>,
+    gen: i32,
+) -> BackupItem {
+    let modified: DateTime<Local> = modified.unwrap_or_else(SystemTime::now).into();
+    BackupItem {
+        file_name: name.to_string(),
+        file_path: virtual_path.to_string(),
+        timestamp: modified.format("%Y-%m-%d %H:%M:%S").to_string(),
+        file_size: size as i64,
+        generation: gen,
+        is_archived: true,
+        is_folder: false,
+        thumbnail: None,
+        mirror: None,
+    }
+}
+
+// ヘルパー関数: 差分ファイル名 (<name>.<YYYYMMDD_HHMMSS>.<algo>.diff) から作成時刻を得る
+// ZIP 内のエントリ時刻はアーカイブ作成時刻になるため、こちらを優先する
+fn diff_time_from_name(name: &str) -> Option<SystemTime> {
+    name.split('.').find_map(|part| {
+        let naive = NaiveDateTime::parse_from_str(part, "%Y%m%d_%H%M%S").ok()?;
+        let local = naive.and_local_timezone(Local).single()?;
+        Some(local.into())
+    })
+}
@@ -0,0 +1,102 @@
+use crate::core::backup::restore::{next_available_path, ConflictPolicy};
+use crate::core::types::BackupFileEntry;
+use crate::core::utils;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// 相対パスを検証して正規化する（`..` や絶対パスは拒否）
+pub fn normalize_rel_path(rel: &str) -> Result<PathBuf, String> {
+    let rel = rel.replace('\\', "/");
+    let path = PathBuf::from(rel.trim_matches('/'));
+    if path.as_os_str().is_empty()
+        || path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(format!("不正な相対パスです: {}", rel));
+    }
+    Ok(path)
+}
+
+fn to_slash(rel: &Path) -> String {
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// フォルダに含まれるファイルを相対パスで列挙する
+pub fn list_folder_files(root: &Path) -> Result<Vec<BackupFileEntry>, String> {
+    if !root.is_dir() {
+        return Err(format!(
+            "フォルダのバックアップではありません: {}",
+            root.display()
+        ));
+    }
+    let mut list = Vec::new();
+    for entry in walkdir::WalkDir::new(root)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let rel = entry.path().strip_prefix(root).map_err(|e| e.to_string())?;
+        list.push(BackupFileEntry {
+            rel_path: to_slash(rel),
+            file_size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+        });
+    }
+    list.sort_by(|a, b| a.rel_path.cmp(&b.rel_path));
+    Ok(list)
+}
+
+/// src_root から選択した相対パス（ファイルまたはフォルダ）だけを dest_root へコピーする
+/// 既存ファイルとぶつかった場合は policy に従う
+/// 戻り値: 書き出したファイルのパス
+pub fn copy_selected(
+    src_root: &Path,
+    rel_paths: &[PathBuf],
+    dest_root: &Path,
+    policy: ConflictPolicy,
+) -> Result<Vec<String>, String> {
+    let mut written = Vec::new();
+    for rel in rel_paths {
+        let src = src_root.join(rel);
+        if !src.exists() {
+            return Err(format!("バックアップに含まれていません: {}", to_slash(rel)));
+        }
+        let files: Vec<PathBuf> = if src.is_dir() {
+            walkdir::WalkDir::new(&src)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .map(|e| e.into_path())
+                .collect()
+        } else {
+            vec![src]
+        };
+
+        for file in files {
+            let file_rel = file.strip_prefix(src_root).map_err(|e| e.to_string())?;
+            let mut dest = dest_root.join(file_rel);
+            if dest.exists() {
+                match policy {
+                    ConflictPolicy::Skip => continue,
+                    ConflictPolicy::Rename => dest = next_available_path(&dest),
+                    ConflictPolicy::Overwrite => {}
+                }
+            }
+            utils::copy_file(&file.to_string_lossy(), &dest.to_string_lossy())?;
+            written.push(dest.to_string_lossy().into_owned());
+        }
+    }
+    Ok(written)
+}
+
+/// 選択復元の出力先フォルダを作る
+pub fn prepare_dest_root(dest_root: &Path) -> Result<(), String> {
+    if dest_root.is_file() {
+        return Err(format!("出力先がファイルです: {}", dest_root.display()));
+    }
+    fs::create_dir_all(dest_root).map_err(|e| format!("ディレクトリ作成失敗: {}", e))
+}
@@ -0,0 +1,204 @@
+use crate::core::backup::{
+    archive, cache_manager, crypto, dedup, rebase, restore, rotation, volume,
+};
+use crate::core::types::{BackupStats, GenerationStats};
+use crate::core::utils;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+fn path_size(path: &Path) -> u64 {
+    utils::get_file_size(&path.to_string_lossy()).unwrap_or(0) as u64
+}
+
+/// 圧縮率 = 保存サイズ / (作業ファイルのサイズ × 復元できる時点の数)
+/// 1.0 より小さいほど、全時点をフルコピーで持つより小さく済んでいる
+fn compression_ratio(stored_bytes: u64, work_size: u64, points: usize) -> f64 {
+    let raw = work_size as f64 * points as f64;
+    if raw == 0.0 {
+        0.0
+    } else {
+        stored_bytes as f64 / raw
+    }
+}
+
+fn generation_index(name: &str) -> Option<i32> {
+    let (n, _) = name.strip_prefix("base")?.split_once('_')?;
+    n.parse().ok()
+}
+
+/// 展開済みの世代フォルダを集計する
+fn folder_generation_stats(dir: &Path, idx: i32, work_size: u64) -> GenerationStats {
+    let name = dir
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let base_size = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| e.file_name().to_string_lossy().ends_with(".base"))
+                .map(|e| path_size(&e.path()))
+                .sum()
+        })
+        .unwrap_or(0);
+    let diffs: Vec<(String, u64)> = rebase::list_generation_diffs(dir)
+        .unwrap_or_default()
+        .iter()
+        .map(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            (name.into_owned(), path_size(p))
+        })
+        .collect();
+    let stored_bytes = path_size(dir);
+    build_generation_stats(name, idx, false, base_size, diffs, stored_bytes, work_size)
+}
+
+/// アーカイブ済みの世代を、アーカイブ内のエントリから集計する
+fn archive_generation_stats(
+    path: &Path,
+    idx: i32,
+    work_size: u64,
+) -> Result<GenerationStats, String> {
+    let name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let mut base_size = 0;
+    let mut diffs = Vec::new();
+    // 暗号化されたアーカイブは中身を読めないため、保存サイズだけを集計する
+    let entries = if crypto::is_encrypted_name(volume::strip_first_volume_ext(&name)) {
+        Vec::new()
+    } else {
+        archive::list_archive_entries(path)?
+    };
+    for entry in entries {
+        if entry.is_dir {
+            continue;
+        }
+        let entry_name = entry.name.rsplit('/').next().unwrap_or_default();
+        if entry_name.ends_with(".diff") {
+            diffs.push((entry_name.to_string(), entry.size));
+        } else if entry.name.split('/').any(|c| c.ends_with(".base")) {
+            base_size += entry.size;
+        }
+    }
+    let stored_bytes = volume::volume_set_size(path);
+    Ok(build_generation_stats(
+        name,
+        idx,
+        true,
+        base_size,
+        diffs,
+        stored_bytes,
+        work_size,
+    ))
+}
+
+fn build_generation_stats(
+    name: String,
+    generation: i32,
+    archived: bool,
+    base_size: u64,
+    diffs: Vec<(String, u64)>,
+    stored_bytes: u64,
+    work_size: u64,
+) -> GenerationStats {
+    let largest = diffs.iter().max_by_key(|(_, size)| *size);
+    GenerationStats {
+        name,
+        generation,
+        archived,
+        base_size,
+        diff_count: diffs.len(),
+        diff_bytes: diffs.iter().map(|(_, size)| size).sum(),
+        largest_diff_bytes: largest.map(|(_, size)| *size).unwrap_or(0),
+        largest_diff_name: largest.map(|(name, _)| name.clone()),
+        stored_bytes,
+        compression_ratio: compression_ratio(stored_bytes, work_size, diffs.len() + 1),
+        rotation_reason: None,
+    }
+}
+
+/// バックアップ先の容量の内訳を集計する
+/// - 世代ごと: ベースサイズ、差分の数と合計、最大の差分、アーカイブ済みか、圧縮率
+/// - 全体: フルコピー・単体アーカイブ・重複排除バックアップ・展開キャッシュの合計
+pub fn collect_backup_stats(
+    work_path: &str,
+    root: &Path,
+    cache_root: &Path,
+) -> Result<BackupStats, String> {
+    let work_size = utils::get_work_size(work_path, &[]).unwrap_or(0).max(0) as u64;
+    let mut stats = BackupStats {
+        work_size,
+        cache_bytes: cache_manager::cache_size(cache_root),
+        ..Default::default()
+    };
+
+    if root.exists() {
+        let copy_re = restore::full_copy_regex(work_path)?;
+        let work = Path::new(work_path);
+        let stem = if work.is_dir() {
+            work.file_name()
+        } else {
+            work.file_stem()
+        }
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+        let archive_re = Regex::new(&format!(
+            r"^{}_\d{{8}}_\d{{6}}\.(zip|tar\.gz|tgz|tar\.zst|tzst|tar\.xz|txz)(\.enc)?(\.001)?$",
+            regex::escape(&stem)
+        ))
+        .map_err(|e| e.to_string())?;
+
+        for entry in fs::read_dir(root).map_err(|e| e.to_string())?.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = entry.path();
+            let is_generation_archive = archive::strip_archive_ext(&name) != name;
+
+            if let Some(idx) = generation_index(&name) {
+                if path.is_dir() {
+                    stats
+                        .generations
+                        .push(folder_generation_stats(&path, idx, work_size));
+                } else if is_generation_archive {
+                    stats
+                        .generations
+                        .push(archive_generation_stats(&path, idx, work_size)?);
+                }
+            } else if name == dedup::CHUNK_DIR && path.is_dir() {
+                stats.dedup_bytes += path_size(&path);
+            } else if dedup::is_manifest(&name) {
+                stats.dedup_count += 1;
+                stats.dedup_bytes += path_size(&path);
+            } else if archive_re.is_match(&name) {
+                stats.archive_count += 1;
+                stats.archive_bytes += volume::volume_set_size(&path);
+            } else if copy_re.is_match(&name) {
+                stats.copy_count += 1;
+                stats.copy_bytes += path_size(&path);
+            }
+        }
+    }
+
+    let log = rotation::load_rotation_log(root);
+    for gen in &mut stats.generations {
+        gen.rotation_reason = log
+            .iter()
+            .find(|r| r.generation == gen.generation)
+            .map(|r| r.reason);
+    }
+    stats
+        .generations
+        .sort_by(|a, b| a.generation.cmp(&b.generation).then(a.name.cmp(&b.name)));
+    stats.generation_bytes = stats.generations.iter().map(|g| g.stored_bytes).sum();
+    stats.total_bytes = stats.generation_bytes
+        + stats.copy_bytes
+        + stats.archive_bytes
+        + stats.dedup_bytes
+        + stats.cache_bytes;
+    Ok(stats)
+}
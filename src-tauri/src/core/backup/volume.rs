@@ -0,0 +1,275 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// ボリューム数の上限（拡張子は3桁: `name.zip.001`, `.002` …）
+const MAX_VOLUMES: u64 = 999;
+
+/// 名前の末尾がボリューム番号 `.NNN` なら、(番号を除いた名前, 番号) を返す
+pub fn split_volume_name(name: &str) -> Option<(&str, u32)> {
+    let (stem, num) = name.rsplit_once('.')?;
+    if stem.is_empty() || num.len() != 3 || !num.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((stem, num.parse().ok()?))
+}
+
+/// 分割アーカイブの1つ目のボリュームか
+pub fn is_first_volume(name: &str) -> bool {
+    matches!(split_volume_name(name), Some((_, 1)))
+}
+
+/// 1つ目のボリュームの拡張子を除いた名前（それ以外はそのまま）
+pub fn strip_first_volume_ext(name: &str) -> &str {
+    match split_volume_name(name) {
+        Some((stem, 1)) => stem,
+        _ => name,
+    }
+}
+
+fn volume_path(dir: &Path, stem: &str, index: u64) -> PathBuf {
+    dir.join(format!("{}.{:03}", stem, index))
+}
+
+/// ファイルを volume_size ごとのボリュームに分割し、1つ目のボリュームのパスを返す
+/// volume_size が 0、またはファイルが収まる場合は分割せずそのまま返す
+/// 最後のボリュームは必ず volume_size より小さくする（ちょうど割り切れる場合は空のボリュームを付ける）
+/// こうしておくと、末尾のボリュームが欠けていても読み込み時に検出できる
+pub fn split_into_volumes(path: &Path, volume_size: u64) -> Result<PathBuf, String> {
+    let len = fs::metadata(path).map_err(|e| e.to_string())?.len();
+    if volume_size == 0 || len <= volume_size {
+        return Ok(path.to_path_buf());
+    }
+    let count = len / volume_size + 1;
+    if count > MAX_VOLUMES {
+        return Err(format!(
+            "ボリュームが多すぎます ({} 個)。ボリュームサイズを大きくしてください",
+            count
+        ));
+    }
+    let dir = path.parent().ok_or("Invalid archive path")?;
+    let stem = path
+        .file_name()
+        .ok_or("Invalid archive path")?
+        .to_string_lossy()
+        .into_owned();
+
+    let mut src = File::open(path).map_err(|e| e.to_string())?;
+    let mut written = Vec::new();
+    let result = (|| {
+        for index in 1..=count {
+            let dst = volume_path(dir, &stem, index);
+            let mut out = BufWriter::new(File::create(&dst).map_err(|e| e.to_string())?);
+            written.push(dst);
+            io::copy(&mut (&mut src).take(volume_size), &mut out).map_err(|e| e.to_string())?;
+            out.flush().map_err(|e| e.to_string())?;
+        }
+        Ok::<(), String>(())
+    })();
+    if let Err(e) = result {
+        for p in &written {
+            let _ = fs::remove_file(p);
+        }
+        return Err(e);
+    }
+    drop(src);
+    fs::remove_file(path).map_err(|e| e.to_string())?;
+    Ok(volume_path(dir, &stem, 1))
+}
+
+/// 1つ目のボリュームから、揃っているボリュームを順に返す
+/// 途中・末尾のボリュームが欠けている場合は、その名前を示すエラーにする
+pub fn list_volumes(first: &Path) -> Result<Vec<PathBuf>, String> {
+    let name = first
+        .file_name()
+        .ok_or("Invalid archive path")?
+        .to_string_lossy()
+        .into_owned();
+    let (stem, _) = split_volume_name(&name)
+        .filter(|(_, n)| *n == 1)
+        .ok_or_else(|| format!("分割アーカイブの1つ目のボリュームではありません: {}", name))?;
+    let dir = first.parent().ok_or("Invalid archive path")?;
+
+    let mut indices: Vec<u64> = fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .filter_map(|e| {
+            let n = e.file_name().to_string_lossy().into_owned();
+            match split_volume_name(&n) {
+                Some((s, i)) if s == stem => Some(i as u64),
+                _ => None,
+            }
+        })
+        .collect();
+    indices.sort_unstable();
+    let missing = |index: u64| {
+        format!(
+            "分割アーカイブのボリュームが見つかりません: {}.{:03}",
+            stem, index
+        )
+    };
+    if indices.first() != Some(&1) {
+        return Err(missing(1));
+    }
+    for (expected, index) in (1u64..).zip(&indices) {
+        if *index != expected {
+            return Err(missing(expected));
+        }
+    }
+
+    let volumes: Vec<PathBuf> = indices.iter().map(|i| volume_path(dir, stem, *i)).collect();
+    let sizes: Vec<u64> = volumes
+        .iter()
+        .map(|p| fs::metadata(p).map(|m| m.len()).map_err(|e| e.to_string()))
+        .collect::<Result<_, _>>()?;
+    let volume_size = sizes[0];
+    let last = sizes.len() - 1;
+    if let Some(i) = sizes[..last].iter().position(|s| *s != volume_size) {
+        return Err(format!(
+            "分割アーカイブのボリュームのサイズが揃っていません（壊れている可能性があります）: {}",
+            volumes[i].display()
+        ));
+    }
+    if sizes[last] >= volume_size {
+        return Err(missing(indices.len() as u64 + 1));
+    }
+    Ok(volumes)
+}
+
+/// ボリュームの合計サイズ（分割されていなければファイルのサイズ）
+pub fn volume_set_size(path: &Path) -> u64 {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if is_first_volume(&name) {
+        if let Ok(volumes) = list_volumes(path) {
+            return volumes
+                .iter()
+                .map(|p| fs::metadata(p).map(|m| m.len()).unwrap_or(0))
+                .sum();
+        }
+    }
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// 分割アーカイブのすべてのボリューム（分割されていなければそのファイルだけ）
+/// 世代の削除などで、ボリュームを残さず扱うために使う
+pub fn volume_files(path: &Path) -> Vec<PathBuf> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if !is_first_volume(&name) {
+        return vec![path.to_path_buf()];
+    }
+    let Some((stem, _)) = split_volume_name(&name) else {
+        return vec![path.to_path_buf()];
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut list: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| {
+                    let n = e.file_name().to_string_lossy().into_owned();
+                    matches!(split_volume_name(&n), Some((s, _)) if s == stem)
+                })
+                .map(|e| e.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    list.sort();
+    list
+}
+
+/// ボリュームをつなげて1つのファイルとして読む（分割されていないファイルもそのまま読める）
+pub struct VolumeReader {
+    files: Vec<(File, u64)>,
+    total: u64,
+    pos: u64,
+}
+
+impl VolumeReader {
+    /// path が1つ目のボリュームならボリューム一式を、それ以外はそのファイルだけを開く
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let paths = if is_first_volume(&name) {
+            list_volumes(path)?
+        } else {
+            vec![path.to_path_buf()]
+        };
+        let mut files = Vec::with_capacity(paths.len());
+        let mut total = 0;
+        for p in paths {
+            let file = File::open(&p).map_err(|e| format!("{}: {}", p.display(), e))?;
+            let len = file.metadata().map_err(|e| e.to_string())?.len();
+            total += len;
+            files.push((file, len));
+        }
+        Ok(Self {
+            files,
+            total,
+            pos: 0,
+        })
+    }
+
+    /// 全体の大きさ
+    pub fn len(&self) -> u64 {
+        self.total
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+}
+
+impl Read for VolumeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut start = 0;
+        for (file, len) in &mut self.files {
+            let len = *len;
+            if self.pos < start + len {
+                let offset = self.pos - start;
+                file.seek(SeekFrom::Start(offset))?;
+                let want = buf.len().min((len - offset) as usize);
+                let n = file.read(&mut buf[..want])?;
+                self.pos += n as u64;
+                return Ok(n);
+            }
+            start += len;
+        }
+        Ok(0)
+    }
+}
+
+impl Seek for VolumeReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let next = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.total.checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+        };
+        match next {
+            Some(n) => {
+                self.pos = n;
+                Ok(n)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )),
+        }
+    }
+}
+
+/// ボリュームをつなげたファイルを dir に書き出し、そのパスを返す（名前は `.001` を除いたもの）
+/// 暗号化したアーカイブなど、ファイルとして読む必要がある場合に使う
+pub fn join_volumes(first: &Path, dir: &Path) -> Result<PathBuf, String> {
+    let name = first
+        .file_name()
+        .ok_or("Invalid archive path")?
+        .to_string_lossy()
+        .into_owned();
+    let mut reader = VolumeReader::open(first)?;
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let dst = dir.join(strip_first_volume_ext(&name));
+    let mut out = BufWriter::new(File::create(&dst).map_err(|e| e.to_string())?);
+    io::copy(&mut reader, &mut out).map_err(|e| e.to_string())?;
+    out.flush().map_err(|e| e.to_string())?;
+    Ok(dst)
+}
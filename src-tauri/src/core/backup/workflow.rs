@@ -1,4 +1,7 @@
 use crate::core::backup::auto_generation;
+use crate::core::backup::rotation::{self, RotationPolicy};
+use crate::core::ignore::{self, IgnoreMatcher};
+use crate::core::types::RotationRecord;
 use crate::core::utils;
 use regex::Regex;
 use std::fs;
@@ -77,21 +80,66 @@ pub fn prepare_initial_plan(
     }
 }
 
+/// 差分の新しい側に渡す作業フォルダを用意する
+/// 除外パターン（タブのパターンと `.wbtignore`）を hdiffz の -g で表せない場合は、
+/// 除外するものを除いたコピーを一時フォルダに作って返す（表せる場合とファイルの場合は None）
+/// 返したフォルダは差分の作成後に呼び出し側で消す
+pub fn stage_filtered_work(
+    work_path: &str,
+    ts: &str,
+    ignore_list: &[String],
+) -> Result<Option<PathBuf>, String> {
+    let work = Path::new(work_path);
+    if !work.is_dir() {
+        return Ok(None);
+    }
+    let mut lines = ignore_list.to_vec();
+    lines.extend(ignore::read_ignore_file(work));
+    if ignore::hdiff_can_express(&lines) {
+        return Ok(None);
+    }
+
+    let entry_name = work.file_name().unwrap().to_string_lossy();
+    let stage = std::env::temp_dir().join(format!("{}.{}.stage", entry_name, ts));
+    if stage.exists() {
+        fs::remove_dir_all(&stage).map_err(|e| e.to_string())?;
+    }
+    let matcher = IgnoreMatcher::new(&lines);
+    if let Err(e) = utils::copy_dir_filtered(work, &stage, false, &matcher) {
+        let _ = fs::remove_dir_all(&stage);
+        return Err(e);
+    }
+    Ok(Some(stage))
+}
+
 /// フェーズ2: 一時ファイルの判定を行い、必要なら「次（世代交代）のプラン」を、不要なら「維持（移動）」を行う
 pub fn finalize_or_next_plan(
     work_path: &str,
     temp_diff: PathBuf,
     target: &BackupTargetInfo,
-    threshold: f64,
+    policy: &RotationPolicy,
     algo: &str,
     ts: &str,
+    ignore_list: &[String],
 ) -> Result<(String, Option<(PathBuf, PathBuf, PathBuf)>), String> {
-    // フォルダの場合はサイズを再帰的に合算する
-    let work_size = path_size(Path::new(work_path))?;
+    // フォルダの場合はサイズを再帰的に合算する（差分と同じく除外パターンに一致するものは数えない）
+    let work_size = path_size(Path::new(work_path), ignore_list)?;
     let diff_size = fs::metadata(&temp_diff).map_err(|e| e.to_string())?.len();
     let entry_name = Path::new(work_path).file_name().unwrap().to_string_lossy();
 
-    if should_transition_to_next_gen(work_size, diff_size, threshold) {
+    let state = rotation::inspect_generation(&target.target_dir, &entry_name);
+    let now = chrono::NaiveDateTime::parse_from_str(ts, "%Y%m%d_%H%M%S")
+        .unwrap_or_else(|_| chrono::Local::now().naive_local());
+    let decision = rotation::decide_rotation(
+        policy,
+        &state,
+        Path::new(work_path),
+        work_size,
+        diff_size,
+        now,
+    );
+
+    if let Some((reason, detail)) = decision {
         // --- 世代交代プランの作成 ---
         let _ = fs::remove_file(&temp_diff);
 
@@ -99,11 +147,25 @@ pub fn finalize_or_next_plan(
             Some(info) if info.base_idx > target.current_idx => (info.dir_path, info.base_idx),
             _ => {
                 let next_idx = target.current_idx + 1;
-                let path = auto_generation::create_new_generation(
+                let path = auto_generation::create_new_generation_at(
                     &target.project_root,
                     next_idx,
                     work_path,
+                    ts,
                 )?;
+                // 統計表示用に交代の理由を残す（失敗してもバックアップは続行）
+                if let Err(e) = rotation::record_rotation(
+                    &target.project_root,
+                    RotationRecord {
+                        generation: next_idx,
+                        from_generation: target.current_idx,
+                        reason,
+                        detail,
+                        time: ts.to_string(),
+                    },
+                ) {
+                    log::warn!("世代交代の記録に失敗: {}", e);
+                }
                 (path, next_idx)
             }
         };
@@ -146,6 +208,19 @@ pub fn detect_diff_algo(path: &str) -> DiffAlgo {
 /// シンプルなコピーバックアップのための準備と実行
 /// ファイル・フォルダ両対応。保存先のパスを返す。
 pub fn execute_copy_backup(src: &str, backup_dir: Option<PathBuf>) -> Result<String, String> {
+    execute_copy_backup_with(src, backup_dir, false, &[])
+}
+
+/// コピーバックアップを実行する
+/// フォルダは更新日時・権限・空のフォルダを保ったままコピーし、
+/// keep_symlinks が true ならシンボリックリンクもリンクのまま保存する
+/// ignore_patterns（タブの除外パターン）と作業フォルダの `.wbtignore` に一致するものはコピーしない
+pub fn execute_copy_backup_with(
+    src: &str,
+    backup_dir: Option<PathBuf>,
+    keep_symlinks: bool,
+    ignore_patterns: &[String],
+) -> Result<String, String> {
     let src_path = Path::new(src);
 
     // 1. ターゲットディレクトリの決定
@@ -167,15 +242,8 @@ pub fn execute_copy_backup(src: &str, backup_dir: Option<PathBuf>) -> Result<Str
         let dest_path = target_dir.join(new_dirname);
         let dest_str = dest_path.to_string_lossy().into_owned();
 
-        fs_extra::dir::copy(
-            src_path,
-            &dest_path,
-            &fs_extra::dir::CopyOptions {
-                copy_inside: true,
-                ..Default::default()
-            },
-        )
-        .map_err(|e| format!("フォルダコピーに失敗しました: {}", e))?;
+        let ignore = IgnoreMatcher::for_folder(src_path, ignore_patterns);
+        utils::copy_dir_filtered(src_path, &dest_path, keep_symlinks, &ignore)?;
 
         Ok(dest_str)
     } else {
@@ -189,21 +257,10 @@ pub fn execute_copy_backup(src: &str, backup_dir: Option<PathBuf>) -> Result<Str
 }
 
 /// パス（ファイルまたはフォルダ）のサイズを返す
-/// フォルダの場合は配下のファイルサイズを再帰的に合算する
-fn path_size(p: &Path) -> Result<u64, String> {
-    if p.is_file() {
-        Ok(fs::metadata(p).map_err(|e| e.to_string())?.len())
-    } else if p.is_dir() {
-        let mut total = 0u64;
-        for entry in walkdir::WalkDir::new(p)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-        {
-            total += entry.metadata().map_err(|e| e.to_string())?.len();
-        }
-        Ok(total)
-    } else {
-        Err(format!("パスが存在しません: {}", p.display()))
+/// フォルダの場合は配下のファイルサイズを再帰的に合算する（除外パターン・`.wbtignore` に一致するものは除く）
+fn path_size(p: &Path, ignore_list: &[String]) -> Result<u64, String> {
+    if !p.exists() {
+        return Err(format!("パスが存在しません: {}", p.display()));
     }
+    Ok(utils::get_work_size(&p.to_string_lossy(), ignore_list)?.max(0) as u64)
 }
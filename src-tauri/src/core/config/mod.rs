@@ -1,2 +1,3 @@
 pub mod assets;
 pub mod loader;
+pub mod vault;
@@ -0,0 +1,181 @@
+use crate::core::backup::crypto::{self, CryptoError};
+use crate::core::backup::identity;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 鍵保管庫のファイル名（設定フォルダ直下）
+pub const VAULT_FILE: &str = "KeyVault.enc";
+
+/// 記述ファイルのあるルートを ID で登録するときのキーの接頭辞
+const ID_KEY_PREFIX: &str = "id:";
+
+/// 保管庫に保存する内容（暗号化して書き出す）
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct VaultData {
+    /// バックアップ先ルート -> アーカイブのパスワード
+    /// 記述ファイルのあるルートは ID (`id:<ID>`)、ないルートはパス表記をキーにする
+    #[serde(default)]
+    passwords: BTreeMap<String, String>,
+    /// ID で登録したルートの、表示用のパス表記
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+}
+
+/// バックアップ先ごとのパスワードを保管する
+/// ファイルはマスターパスワードで暗号化し、解錠中だけメモリ上に平文を持つ
+pub struct KeyVault {
+    path: PathBuf,
+    master: String,
+    data: VaultData,
+}
+
+/// 保管庫のキーにするため、ルートのパス表記を揃える
+pub fn vault_key(root: &Path) -> String {
+    let s = root.to_string_lossy().replace('\\', "/");
+    let s = s.trim_end_matches('/');
+    if cfg!(windows) {
+        s.to_lowercase()
+    } else {
+        s.to_string()
+    }
+}
+
+/// ルートを登録するキー（ルートを移したり作業ファイルを付け替えたりしても変わらないよう、ID を優先する）
+fn entry_key(root: &Path) -> String {
+    match identity::load_identity(root) {
+        Some(id) => format!("{}{}", ID_KEY_PREFIX, id.id),
+        None => vault_key(root),
+    }
+}
+
+impl KeyVault {
+    /// 保管庫を解錠する（ファイルがなければ空の保管庫を作る）
+    pub fn unlock(path: &Path, master: &str) -> Result<Self, String> {
+        if master.is_empty() {
+            return Err("マスターパスワードが空です".to_string());
+        }
+        let data = if path.exists() {
+            let bytes = fs::read(path).map_err(|e| e.to_string())?;
+            let plain = crypto::decrypt_bytes(&bytes, master).map_err(|e| match e {
+                CryptoError::WrongPassword => "マスターパスワードが違います".to_string(),
+                e => format!("鍵保管庫を開けません: {}", e),
+            })?;
+            serde_json::from_slice(&plain).map_err(|e| format!("鍵保管庫が壊れています: {}", e))?
+        } else {
+            VaultData::default()
+        };
+        let vault = Self {
+            path: path.to_path_buf(),
+            master: master.to_string(),
+            data,
+        };
+        if !path.exists() {
+            vault.save()?;
+        }
+        Ok(vault)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_vec(&self.data).map_err(|e| e.to_string())?;
+        let bytes = crypto::encrypt_bytes(&json, &self.master)?;
+        // 書き込み途中で壊れないよう、一時ファイルから名前を変える
+        let tmp = self.path.with_extension("enc.tmp");
+        fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &self.path).map_err(|e| e.to_string())
+    }
+
+    /// ルートのパスワードを登録・更新する
+    pub fn set_password(&mut self, root: &Path, password: &str) -> Result<(), String> {
+        if password.is_empty() {
+            return Err("パスワードが空です".to_string());
+        }
+        let key = entry_key(root);
+        let path_key = vault_key(root);
+        if key != path_key {
+            self.data.passwords.remove(&path_key);
+            self.data.labels.insert(key.clone(), path_key);
+        }
+        self.data.passwords.insert(key, password.to_string());
+        self.save()
+    }
+
+    /// ルートのパスワードを削除する（戻り値: 登録されていたか）
+    pub fn remove_password(&mut self, root: &Path) -> Result<bool, String> {
+        let mut removed = false;
+        for key in [entry_key(root), vault_key(root)] {
+            removed |= self.data.passwords.remove(&key).is_some();
+            self.data.labels.remove(&key);
+        }
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// ルートを移した・付け替えた後に、登録を新しいルートへ引き継ぐ
+    /// パス表記で登録していたものは ID のキーへ移し、表示用のパス表記を新しい場所にする
+    /// 戻り値: 登録を書き換えたか
+    pub fn relocate(&mut self, old_root: &Path, new_root: &Path) -> Result<bool, String> {
+        let key = entry_key(new_root);
+        let mut changed = false;
+        for old_key in [vault_key(old_root), vault_key(new_root)] {
+            if old_key == key {
+                continue;
+            }
+            if let Some(pw) = self.data.passwords.remove(&old_key) {
+                self.data.passwords.entry(key.clone()).or_insert(pw);
+                changed = true;
+            }
+        }
+        let label = vault_key(new_root);
+        if key != label
+            && self.data.passwords.contains_key(&key)
+            && self.data.labels.get(&key) != Some(&label)
+        {
+            self.data.labels.insert(key, label);
+            changed = true;
+        }
+        if changed {
+            self.save()?;
+        }
+        Ok(changed)
+    }
+
+    /// バックアップ（またはその中のファイル）のパスから、登録済みのルートのパスワードを探す
+    /// 世代フォルダ内の差分・アーカイブ内のエントリも、親をたどってルートを見つける
+    pub fn password_for(&self, path: &Path) -> Option<&str> {
+        path.ancestors()
+            .find_map(|p| {
+                self.data.passwords.get(&vault_key(p)).or_else(|| {
+                    p.join(identity::IDENTITY_FILE)
+                        .is_file()
+                        .then(|| self.data.passwords.get(&entry_key(p)))
+                        .flatten()
+                })
+            })
+            .map(|s| s.as_str())
+    }
+
+    /// 登録済みのルートの一覧
+    pub fn roots(&self) -> Vec<String> {
+        self.data
+            .passwords
+            .keys()
+            .map(|k| self.data.labels.get(k).unwrap_or(k).clone())
+            .collect()
+    }
+
+    /// マスターパスワードを変更する（保管庫を新しいパスワードで暗号化し直す）
+    pub fn change_master(&mut self, new_master: &str) -> Result<(), String> {
+        if new_master.is_empty() {
+            return Err("マスターパスワードが空です".to_string());
+        }
+        self.master = new_master.to_string();
+        self.save()
+    }
+}
@@ -0,0 +1,223 @@
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+/// 作業フォルダ直下に置く除外パターンのファイル名
+pub const IGNORE_FILE: &str = ".wbtignore";
+
+/// gitignore 形式の1行分のパターン
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    regex: Regex,
+    /// `!` で始まる（除外を取り消す）
+    negated: bool,
+    /// `/` で終わる（フォルダだけに一致）
+    dir_only: bool,
+}
+
+/// gitignore 形式の除外パターン
+/// - `#` で始まる行と空行は無視する
+/// - `*` `?` `[abc]` はパス区切りをまたがない。`**` はまたぐ
+/// - `/` を含むパターンはフォルダ直下からのパス、含まないものはどの階層の名前にも一致する
+/// - `/` で終わるパターンはフォルダだけ、`!` で始まるパターンは除外の取り消し（後に書いたものが優先）
+/// - 除外したフォルダの中身は、取り消しのパターンがあってもすべて除外する
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreMatcher {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreMatcher {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        let rules = patterns
+            .iter()
+            .filter_map(|p| compile_rule(p.as_ref()))
+            .collect();
+        Self { rules }
+    }
+
+    /// タブのパターンに、フォルダ直下の `.wbtignore` を加えたもの（ファイルの場合はパターンだけ）
+    pub fn for_folder<S: AsRef<str>>(root: &Path, patterns: &[S]) -> Self {
+        let mut lines: Vec<String> = patterns.iter().map(|p| p.as_ref().to_string()).collect();
+        lines.extend(read_ignore_file(root));
+        Self::new(&lines)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// フォルダからの相対パスが除外対象か（親フォルダが除外されていれば中身も除外）
+    pub fn is_ignored(&self, rel: &Path, is_dir: bool) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        let rel = to_slash(rel);
+        let mut end = 0;
+        while let Some(pos) = rel[end..].find('/') {
+            end += pos;
+            if self.matches(&rel[..end], true) {
+                return true;
+            }
+            end += 1;
+        }
+        self.matches(&rel, is_dir)
+    }
+
+    /// 後に書いたパターンほど優先する
+    fn matches(&self, rel: &str, is_dir: bool) -> bool {
+        if rel.is_empty() {
+            return false;
+        }
+        self.rules
+            .iter()
+            .rev()
+            .find(|r| (!r.dir_only || is_dir) && r.regex.is_match(rel))
+            .is_some_and(|r| !r.negated)
+    }
+
+    /// root 以下を走査し、除外対象のファイル・フォルダを飛ばす（除外したフォルダの中には入らない）
+    pub fn walk<'a>(
+        &'a self,
+        root: &'a Path,
+        walker: walkdir::WalkDir,
+    ) -> impl Iterator<Item = walkdir::Result<walkdir::DirEntry>> + 'a {
+        walker.into_iter().filter_entry(move |e| {
+            let rel = e.path().strip_prefix(root).unwrap_or(Path::new(""));
+            !self.matches(&to_slash(rel), e.file_type().is_dir())
+        })
+    }
+}
+
+/// フォルダ直下の `.wbtignore` の行を読む（ない場合は空）
+pub fn read_ignore_file(root: &Path) -> Vec<String> {
+    if !root.is_dir() {
+        return Vec::new();
+    }
+    fs::read_to_string(root.join(IGNORE_FILE))
+        .map(|s| s.lines().map(|l| l.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// hdiffz の -g に渡せる形にする（`*` `?` だけを解釈するため、取り消しやコメントは渡さない）
+pub fn to_hdiff_patterns<S: AsRef<str>>(patterns: &[S]) -> Vec<String> {
+    patterns
+        .iter()
+        .map(|p| p.as_ref().trim())
+        .filter(|p| !p.is_empty() && !p.starts_with('#') && !p.starts_with('!'))
+        .map(|p| p.trim_matches('/').to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// hdiffz の -g だけで同じものを除外できるか
+/// 取り消し（`!`）・フォルダ直下からのパス（`/` を含む）・`[abc]`・`\` のエスケープは -g では表せない
+pub fn hdiff_can_express<S: AsRef<str>>(patterns: &[S]) -> bool {
+    patterns
+        .iter()
+        .map(|p| p.as_ref().trim())
+        .filter(|p| !p.is_empty() && !p.starts_with('#'))
+        .all(|p| {
+            let body = p.trim_end_matches('/');
+            !p.starts_with('!')
+                && !body.contains('/')
+                && !body.contains('[')
+                && !body.contains('\\')
+        })
+}
+
+fn to_slash(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+fn compile_rule(line: &str) -> Option<IgnoreRule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negated, pattern) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let (dir_only, pattern) = match pattern.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let anchored = pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    if pattern.is_empty() {
+        return None;
+    }
+
+    let mut re = String::from(if cfg!(windows) { "(?i)^" } else { "^" });
+    if !anchored {
+        re.push_str("(?:.*/)?");
+    }
+    re.push_str(&glob_to_regex(pattern));
+    re.push('$');
+    let regex = Regex::new(&re).ok()?;
+    Some(IgnoreRule {
+        regex,
+        negated,
+        dir_only,
+    })
+}
+
+fn glob_to_regex(pattern: &str) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut re = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let at_start = i == 0 || chars[i - 1] == '/';
+                let at_end = i + 2 == chars.len();
+                if at_start && chars.get(i + 2) == Some(&'/') {
+                    // `**/` は0個以上のフォルダ
+                    re.push_str("(?:.*/)?");
+                    i += 3;
+                } else if at_start && at_end {
+                    // 末尾の `/**` は中身すべて
+                    re.push_str(".*");
+                    i += 2;
+                } else {
+                    re.push_str("[^/]*");
+                    i += 2;
+                }
+            }
+            '*' => {
+                re.push_str("[^/]*");
+                i += 1;
+            }
+            '?' => {
+                re.push_str("[^/]");
+                i += 1;
+            }
+            '[' => match chars[i + 1..].iter().position(|c| *c == ']') {
+                Some(len) if len > 0 => {
+                    let class: String = chars[i + 1..i + 1 + len].iter().collect();
+                    let class = match class.strip_prefix('!') {
+                        Some(rest) => format!("^{}", rest),
+                        None => class,
+                    };
+                    re.push('[');
+                    re.push_str(&class.replace('\\', "\\\\"));
+                    re.push(']');
+                    i += len + 2;
+                }
+                _ => {
+                    re.push_str("\\[");
+                    i += 1;
+                }
+            },
+            '\\' if i + 1 < chars.len() => {
+                re.push_str(&regex::escape(&chars[i + 1].to_string()));
+                i += 2;
+            }
+            c => {
+                re.push_str(&regex::escape(&c.to_string()));
+                i += 1;
+            }
+        }
+    }
+    re
+}
@@ -1,5 +1,7 @@
 pub mod backup;
 pub mod config;
 pub mod ext;
+pub mod ignore;
+pub mod preview;
 pub mod types;
 pub mod utils;
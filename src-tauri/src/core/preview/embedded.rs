@@ -0,0 +1,110 @@
+use crate::core::preview::psd;
+use image::DynamicImage;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use zip::ZipArchive;
+
+/// プレビューを取り出せる拡張子（小文字）
+pub const PREVIEW_EXTS: &[&str] = &["png", "jpg", "jpeg", "webp", "clip", "kra", "ora", "psd"];
+
+/// Krita / OpenRaster の統合画像・サムネイルの候補（先にあるものを優先）
+const ZIP_PREVIEW_ENTRIES: &[&str] =
+    &["mergedimage.png", "Thumbnails/thumbnail.png", "preview.png"];
+
+fn lower_ext(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// 拡張子からプレビューに対応しているか判定する
+pub fn is_preview_supported(path: &Path) -> bool {
+    PREVIEW_EXTS.contains(&lower_ext(path).as_str())
+}
+
+/// 画像ファイル、または制作ファイルに埋め込まれたプレビューを読み込む
+/// - png/jpg/webp: そのまま
+/// - clip: 内部 SQLite の CanvasPreview
+/// - kra/ora: mergedimage.png または Thumbnails/
+/// - psd: 統合画像
+pub fn load_preview(path: &Path) -> Result<DynamicImage, String> {
+    match lower_ext(path).as_str() {
+        "png" | "jpg" | "jpeg" | "webp" => {
+            image::open(path).map_err(|e| format!("{}: {}", path.display(), e))
+        }
+        "clip" => decode_bytes(&extract_clip_preview(path)?),
+        "kra" | "ora" => decode_bytes(&extract_zip_preview(path)?),
+        "psd" => Ok(DynamicImage::ImageRgba8(psd::decode_composite(path)?)),
+        ext => Err(format!("プレビューに対応していない形式です: .{}", ext)),
+    }
+}
+
+fn decode_bytes(bytes: &[u8]) -> Result<DynamicImage, String> {
+    image::load_from_memory(bytes).map_err(|e| format!("プレビュー画像の読み込みに失敗: {}", e))
+}
+
+/// Krita (.kra) / OpenRaster (.ora) から統合画像またはサムネイルを取り出す
+pub fn extract_zip_preview(path: &Path) -> Result<Vec<u8>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| e.to_string())?;
+    for name in ZIP_PREVIEW_ENTRIES {
+        if let Ok(mut entry) = zip.by_name(name) {
+            let mut buf = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut buf).map_err(|e| e.to_string())?;
+            return Ok(buf);
+        }
+    }
+    Err(format!(
+        "プレビュー画像が含まれていません: {}",
+        path.display()
+    ))
+}
+
+/// CLIP STUDIO (.clip) に埋め込まれた SQLite から CanvasPreview の画像を取り出す
+/// .clip は `CSFCHUNK` ヘッダーの後に `CHNK<種別><長さ(u64 BE)><データ>` が並ぶ
+pub fn extract_clip_preview(path: &Path) -> Result<Vec<u8>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut r = BufReader::new(file);
+
+    let mut header = [0u8; 24];
+    r.read_exact(&mut header).map_err(|e| e.to_string())?;
+    if &header[..8] != b"CSFCHUNK" {
+        return Err("CLIP STUDIO のファイルではありません".to_string());
+    }
+
+    let sqlite_len = loop {
+        let mut chunk = [0u8; 16];
+        if r.read_exact(&mut chunk).is_err() {
+            return Err("SQLite データが見つかりません".to_string());
+        }
+        if &chunk[..4] != b"CHNK" {
+            return Err("CLIP STUDIO のファイルが壊れています".to_string());
+        }
+        let mut len = [0u8; 8];
+        len.copy_from_slice(&chunk[8..]);
+        let len = u64::from_be_bytes(len);
+        if &chunk[4..8] == b"SQLi" {
+            break len;
+        }
+        r.seek(SeekFrom::Current(len as i64))
+            .map_err(|e| e.to_string())?;
+    };
+
+    // rusqlite はファイルから開くため、一時ファイルへ書き出す
+    let mut tmp = tempfile::NamedTempFile::new().map_err(|e| e.to_string())?;
+    let copied = io::copy(&mut r.by_ref().take(sqlite_len), &mut tmp).map_err(|e| e.to_string())?;
+    if copied != sqlite_len {
+        return Err("CLIP STUDIO のファイルが壊れています".to_string());
+    }
+
+    let conn = rusqlite::Connection::open_with_flags(
+        tmp.path(),
+        rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+    )
+    .map_err(|e| e.to_string())?;
+    conn.query_row("SELECT ImageData FROM CanvasPreview LIMIT 1", [], |row| {
+        row.get::<_, Vec<u8>>(0)
+    })
+    .map_err(|e| format!("キャンバスのプレビューを読み込めません: {}", e))
+}
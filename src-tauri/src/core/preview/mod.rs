@@ -0,0 +1,26 @@
+use crate::core::backup::archive;
+use sha2::{Digest, Sha256};
+use std::fs;
+
+pub mod embedded;
+pub mod psd;
+pub mod thumbnail;
+pub mod visual;
+
+/// バックアップのパス・サイズ・更新日時をハッシュに加える（キャッシュ名の生成用）
+/// 世代アーカイブ内の差分（仮想パス）はアーカイブ本体のサイズ・更新日時を使う
+pub(crate) fn hash_source(hasher: &mut Sha256, path: &str) {
+    let meta = match archive::split_archived_entry_path(path) {
+        Some((archive_path, _)) => fs::metadata(archive_path).ok(),
+        None => fs::metadata(path).ok(),
+    };
+    let mtime = meta
+        .as_ref()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    hasher.update(path.as_bytes());
+    hasher.update(meta.map(|m| m.len()).unwrap_or(0).to_be_bytes());
+    hasher.update(mtime.to_be_bytes());
+}
@@ -0,0 +1,196 @@
+use image::RgbaImage;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const COLOR_GRAYSCALE: u16 = 1;
+const COLOR_RGB: u16 = 3;
+const COLOR_CMYK: u16 = 4;
+
+/// 幅・高さの上限（PSD は 30,000px、PSB は 300,000px まで）
+const MAX_PSD_SIDE: usize = 30_000;
+const MAX_PSB_SIDE: usize = 300_000;
+/// プレビューとして読み込む画素数の上限（RGBA で約 1GB）
+const MAX_PIXELS: usize = 256 * 1024 * 1024;
+/// チャンネル数の上限（仕様上 56）
+const MAX_CHANNELS: usize = 56;
+
+fn read_u16<R: Read>(r: &mut R) -> Result<u16, String> {
+    let mut buf = [0u8; 2];
+    r.read_exact(&mut buf).map_err(|e| e.to_string())?;
+    Ok(u16::from_be_bytes(buf))
+}
+
+fn read_u32<R: Read>(r: &mut R) -> Result<u32, String> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf).map_err(|e| e.to_string())?;
+    Ok(u32::from_be_bytes(buf))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> Result<u64, String> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf).map_err(|e| e.to_string())?;
+    Ok(u64::from_be_bytes(buf))
+}
+
+/// PackBits で圧縮された1行を展開する
+fn unpack_bits(src: &[u8], out_len: usize) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(out_len);
+    let mut i = 0;
+    while i < src.len() && out.len() < out_len {
+        let n = src[i] as i8;
+        i += 1;
+        if n >= 0 {
+            let count = n as usize + 1;
+            let end = (i + count).min(src.len());
+            out.extend_from_slice(&src[i..end]);
+            i = end;
+        } else if n != -128 {
+            let count = (1 - n as isize) as usize;
+            let Some(&b) = src.get(i) else {
+                break;
+            };
+            out.extend(std::iter::repeat(b).take(count));
+            i += 1;
+        }
+    }
+    if out.len() < out_len {
+        return Err("PSD の画像データが壊れています".to_string());
+    }
+    out.truncate(out_len);
+    Ok(out)
+}
+
+/// PSD/PSB の統合画像（ファイル末尾の合成済み画像データ）を RGBA で読み込む
+/// 対応: 8/16bit のグレースケール・RGB・CMYK、無圧縮と RLE
+pub fn decode_composite(path: &Path) -> Result<RgbaImage, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut r = BufReader::new(file);
+
+    let mut sig = [0u8; 4];
+    r.read_exact(&mut sig).map_err(|e| e.to_string())?;
+    if &sig != b"8BPS" {
+        return Err("PSD ファイルではありません".to_string());
+    }
+    let version = read_u16(&mut r)?;
+    if version != 1 && version != 2 {
+        return Err(format!("未対応の PSD のバージョンです: {}", version));
+    }
+    let is_psb = version == 2;
+    r.seek(SeekFrom::Current(6)).map_err(|e| e.to_string())?;
+    let channels = read_u16(&mut r)? as usize;
+    let height = read_u32(&mut r)? as usize;
+    let width = read_u32(&mut r)? as usize;
+    let depth = read_u16(&mut r)?;
+    let color_mode = read_u16(&mut r)?;
+    if depth != 8 && depth != 16 {
+        return Err(format!("未対応のビット深度です: {}bit", depth));
+    }
+    let min_channels = match color_mode {
+        COLOR_GRAYSCALE => 1,
+        COLOR_RGB => 3,
+        COLOR_CMYK => 4,
+        _ => return Err(format!("未対応のカラーモードです: {}", color_mode)),
+    };
+    if channels < min_channels || channels > MAX_CHANNELS {
+        return Err(format!("PSD のチャンネル数が不正です: {}", channels));
+    }
+    let max_side = if is_psb { MAX_PSB_SIDE } else { MAX_PSD_SIDE };
+    if width == 0 || height == 0 || width > max_side || height > max_side {
+        return Err(format!("PSD の画像サイズが不正です: {}x{}", width, height));
+    }
+    if width.checked_mul(height).map_or(true, |n| n > MAX_PIXELS) {
+        return Err(format!(
+            "画像が大きすぎるためプレビューできません: {}x{}",
+            width, height
+        ));
+    }
+
+    // カラーモードデータ・画像リソース・レイヤー情報を読み飛ばす
+    let color_data_len = read_u32(&mut r)? as i64;
+    r.seek(SeekFrom::Current(color_data_len))
+        .map_err(|e| e.to_string())?;
+    let resources_len = read_u32(&mut r)? as i64;
+    r.seek(SeekFrom::Current(resources_len))
+        .map_err(|e| e.to_string())?;
+    let layer_len = if is_psb {
+        read_u64(&mut r)? as i64
+    } else {
+        read_u32(&mut r)? as i64
+    };
+    r.seek(SeekFrom::Current(layer_len))
+        .map_err(|e| e.to_string())?;
+
+    let compression = read_u16(&mut r)?;
+    let bytes_per_sample = depth as usize / 8;
+    let too_large = || "PSD の画像サイズが大きすぎます".to_string();
+    let row_len = width.checked_mul(bytes_per_sample).ok_or_else(too_large)?;
+    let plane_len = row_len.checked_mul(height).ok_or_else(too_large)?;
+    // PackBits は最悪でも 128 バイトごとに 1 バイト増える程度なので、それを大きく超える行は壊れている
+    let max_packed = row_len + row_len / 64 + 2;
+
+    let mut planes: Vec<Vec<u8>> = Vec::with_capacity(channels);
+    match compression {
+        0 => {
+            for _ in 0..channels {
+                let mut plane = vec![0u8; plane_len];
+                r.read_exact(&mut plane).map_err(|e| e.to_string())?;
+                planes.push(plane);
+            }
+        }
+        1 => {
+            let mut counts = Vec::with_capacity(channels * height);
+            for _ in 0..channels * height {
+                counts.push(if is_psb {
+                    read_u32(&mut r)? as usize
+                } else {
+                    read_u16(&mut r)? as usize
+                });
+            }
+            for rows in counts.chunks(height) {
+                let mut plane = Vec::with_capacity(plane_len);
+                for &count in rows {
+                    if count > max_packed {
+                        return Err("PSD の画像データが壊れています".to_string());
+                    }
+                    let mut packed = vec![0u8; count];
+                    r.read_exact(&mut packed).map_err(|e| e.to_string())?;
+                    plane.extend(unpack_bits(&packed, row_len)?);
+                }
+                planes.push(plane);
+            }
+        }
+        other => return Err(format!("未対応の圧縮形式です: {}", other)),
+    }
+
+    // 16bit は上位バイトだけを使う
+    let sample = |c: usize, i: usize| -> u8 { planes[c][i * bytes_per_sample] };
+    let mut img = RgbaImage::new(width as u32, height as u32);
+    for (i, px) in img.pixels_mut().enumerate() {
+        px.0 = match color_mode {
+            COLOR_GRAYSCALE => {
+                let g = sample(0, i);
+                let a = if channels >= 2 { sample(1, i) } else { 255 };
+                [g, g, g, a]
+            }
+            COLOR_RGB if channels >= 3 => {
+                let a = if channels >= 4 { sample(3, i) } else { 255 };
+                [sample(0, i), sample(1, i), sample(2, i), a]
+            }
+            COLOR_CMYK if channels >= 4 => {
+                // PSD の CMYK は反転して保存されている (255 = インクなし)
+                let k = sample(3, i) as u32;
+                let to_rgb = |v: u8| (v as u32 * k / 255) as u8;
+                let a = if channels >= 5 { sample(4, i) } else { 255 };
+                [
+                    to_rgb(sample(0, i)),
+                    to_rgb(sample(1, i)),
+                    to_rgb(sample(2, i)),
+                    a,
+                ]
+            }
+            _ => return Err("PSD のチャンネル数が不足しています".to_string()),
+        };
+    }
+    Ok(img)
+}
@@ -0,0 +1,48 @@
+use crate::core::preview::{embedded, hash_source};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// キャッシュルート直下のサムネイル置き場
+pub const THUMB_DIR: &str = "thumbs";
+
+/// サムネイルの長辺 (px)
+pub const THUMB_SIZE: u32 = 256;
+
+/// バックアップ1件のサムネイルのキャッシュ名（パス・サイズ・更新日時から決まる）
+pub fn thumbnail_key(path: &str) -> String {
+    let mut hasher = Sha256::new();
+    hash_source(&mut hasher, path);
+    format!("{:x}", hasher.finalize())[..16].to_string()
+}
+
+/// バックアップ1件のサムネイルのパス（生成済みかどうかは問わない）
+pub fn thumbnail_path(cache_root: &Path, path: &str) -> PathBuf {
+    cache_root
+        .join(THUMB_DIR)
+        .join(format!("{}.png", thumbnail_key(path)))
+}
+
+/// 生成済みのサムネイルがあればそのパスを返す
+pub fn cached_thumbnail(cache_root: &Path, path: &str) -> Option<PathBuf> {
+    let thumb = thumbnail_path(cache_root, path);
+    thumb.is_file().then_some(thumb)
+}
+
+/// 画像または制作ファイルのプレビューを縮小して dest に PNG で書き出す
+/// 縦横比は保ち、長辺を max_size 以下にする（元が小さい場合は拡大しない）
+pub fn render_thumbnail(src: &Path, dest: &Path, max_size: u32) -> Result<(), String> {
+    let img = embedded::load_preview(src)?;
+    let thumb = if img.width() > max_size || img.height() > max_size {
+        img.thumbnail(max_size, max_size)
+    } else {
+        img
+    };
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    thumb
+        .to_rgba8()
+        .save_with_format(dest, image::ImageFormat::Png)
+        .map_err(|e| format!("サムネイルの保存に失敗: {}", e))
+}
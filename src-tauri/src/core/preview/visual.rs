@@ -0,0 +1,114 @@
+use crate::core::preview::{embedded, hash_source};
+use crate::core::types::VisualComparison;
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+/// 横並び画像の左右の間隔 (px)
+const GAP: u32 = 16;
+
+/// 2つのバックアップの組み合わせから比較結果のキャッシュ名を作る
+/// パス・サイズ・更新日時が同じなら同じ名前になる（世代アーカイブ内の差分はアーカイブで判定）
+pub fn comparison_key(a: &str, b: &str) -> String {
+    let mut hasher = Sha256::new();
+    hash_source(&mut hasher, a);
+    hash_source(&mut hasher, b);
+    format!("{:x}", hasher.finalize())[..16].to_string()
+}
+
+/// 2枚の画像を左右に並べる（高さの足りない側は上寄せ、余白は透明）
+pub fn side_by_side(a: &RgbaImage, b: &RgbaImage) -> RgbaImage {
+    let width = a.width() + GAP + b.width();
+    let height = a.height().max(b.height());
+    let mut canvas = RgbaImage::new(width, height);
+    imageops::replace(&mut canvas, a, 0, 0);
+    imageops::replace(&mut canvas, b, (a.width() + GAP) as i64, 0);
+    canvas
+}
+
+/// 画素ごとの差をヒートマップにする
+/// 変化のない画素は a を薄いグレーで、変化した画素は差の大きさに応じて黄→赤で塗る
+/// b のサイズが a と異なる場合は a に合わせて拡縮してから比較する
+/// 戻り値: (ヒートマップ, 変化した画素数)
+pub fn diff_heatmap(a: &RgbaImage, b: &RgbaImage) -> (RgbaImage, u64) {
+    let resized;
+    let b = if a.dimensions() == b.dimensions() {
+        b
+    } else {
+        resized = imageops::resize(b, a.width(), a.height(), FilterType::Triangle);
+        &resized
+    };
+
+    let mut changed = 0u64;
+    let mut heat = RgbaImage::new(a.width(), a.height());
+    for ((pa, pb), out) in a.pixels().zip(b.pixels()).zip(heat.pixels_mut()) {
+        let d =
+            pa.0.iter()
+                .zip(pb.0.iter())
+                .map(|(x, y)| x.abs_diff(*y))
+                .max()
+                .unwrap_or(0);
+        *out = if d == 0 {
+            let luma = (pa[0] as u32 * 299 + pa[1] as u32 * 587 + pa[2] as u32 * 114) / 1000;
+            let g = (160 + luma * 95 / 255) as u8;
+            Rgba([g, g, g, 255])
+        } else {
+            changed += 1;
+            Rgba([255, 255 - d, 0, 255])
+        };
+    }
+    (heat, changed)
+}
+
+/// 2つの画像（または制作ファイルのプレビュー）を比較し、横並び画像とヒートマップを out_dir に書き出す
+/// out_dir に同じ key の結果があればそれを返す
+pub fn render_comparison(
+    a: &Path,
+    b: &Path,
+    out_dir: &Path,
+    key: &str,
+) -> Result<VisualComparison, String> {
+    if let Some(cached) = load_cached(out_dir, key) {
+        return Ok(cached);
+    }
+
+    let img_a = embedded::load_preview(a)?.to_rgba8();
+    let img_b = embedded::load_preview(b)?.to_rgba8();
+    let (heat, changed) = diff_heatmap(&img_a, &img_b);
+    let pixels = heat.width() as u64 * heat.height() as u64;
+
+    fs::create_dir_all(out_dir).map_err(|e| e.to_string())?;
+    let side_path = out_dir.join(format!("{}_side.png", key));
+    let heat_path = out_dir.join(format!("{}_heatmap.png", key));
+    side_by_side(&img_a, &img_b)
+        .save(&side_path)
+        .map_err(|e| format!("比較画像の保存に失敗: {}", e))?;
+    heat.save(&heat_path)
+        .map_err(|e| format!("比較画像の保存に失敗: {}", e))?;
+
+    let result = VisualComparison {
+        side_by_side_path: side_path.to_string_lossy().into_owned(),
+        heatmap_path: heat_path.to_string_lossy().into_owned(),
+        width: img_a.width(),
+        height: img_a.height(),
+        changed_pixels: changed,
+        changed_ratio: if pixels == 0 {
+            0.0
+        } else {
+            changed as f64 / pixels as f64
+        },
+    };
+    let json = serde_json::to_string_pretty(&result).map_err(|e| e.to_string())?;
+    fs::write(out_dir.join(format!("{}.json", key)), json).map_err(|e| e.to_string())?;
+    Ok(result)
+}
+
+/// キャッシュ済みの比較結果を読む（画像が欠けている場合は None）
+pub fn load_cached(out_dir: &Path, key: &str) -> Option<VisualComparison> {
+    let json = fs::read_to_string(out_dir.join(format!("{}.json", key))).ok()?;
+    let cached: VisualComparison = serde_json::from_str(&json).ok()?;
+    (Path::new(&cached.side_by_side_path).is_file() && Path::new(&cached.heatmap_path).is_file())
+        .then_some(cached)
+}
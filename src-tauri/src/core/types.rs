@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 
@@ -19,7 +20,46 @@ pub struct AppConfig {
     pub startup_cache_limit: usize,
     pub show_memo_after_backup: bool,
     pub strict_file_name_match: bool,
-    pub hdiff_strict_hash_check: bool
+    pub hdiff_strict_hash_check: bool,
+    /// 展開キャッシュの上限 (MB)。0 で無制限
+    #[serde(default = "default_cache_max_size_mb")]
+    pub cache_max_size_mb: u64,
+    /// 世代アーカイブから復元するときに世代全体を展開キャッシュへ展開する
+    /// false の場合は展開済みのキャッシュがあるときだけ使い、なければ必要なエントリだけを取り出す
+    #[serde(default)]
+    pub cache_archived_generations: bool,
+    /// 世代内の差分の合計が ベースサイズ × この値 を超えたら世代交代。0 で無効
+    #[serde(default)]
+    pub rotate_cumulative_ratio: f64,
+    /// 世代内の差分の数の上限。0 で無制限
+    #[serde(default)]
+    pub rotate_max_diffs: usize,
+    /// 世代を作ってからの日数の上限。0 で無制限
+    #[serde(default)]
+    pub rotate_max_age_days: u64,
+    /// 作業サイズが ベースサイズ × この値 を下回ったら世代交代。0 で無効
+    #[serde(default)]
+    pub rotate_shrink_ratio: f64,
+    /// 作業ファイルの種類（形式・ファイルかフォルダか）が変わったら世代交代する
+    #[serde(default)]
+    pub rotate_on_type_change: bool,
+    /// tar.gz / tar.zst / tar.xz の圧縮レベル。0 で方式ごとの既定値
+    #[serde(default)]
+    pub archive_compression_level: i32,
+    /// tar.zst / tar.xz の圧縮スレッド数。0 で CPU 数
+    #[serde(default)]
+    pub archive_compression_threads: u32,
+    /// アーカイブを分割するボリュームの大きさ (MB)。0 で分割しない
+    #[serde(default)]
+    pub archive_volume_size_mb: u64,
+    /// フォルダのバックアップでシンボリックリンクを参照先ではなくリンクとして保存する
+    #[serde(default)]
+    pub keep_symlinks: bool,
+}
+
+// 展開キャッシュの上限の既定値（同梱の AppConfig.json と揃える。古い設定ファイルにない場合に使う）
+fn default_cache_max_size_mb() -> u64 {
+    2048
 }
 
 // 差分ファイル情報
@@ -43,6 +83,10 @@ pub struct BackupItem {
     pub generation: i32,
     pub is_archived: bool,
     pub is_folder: bool,
+    #[serde(default)]
+    pub thumbnail: Option<String>, // 生成済みサムネイルのパス（未生成なら None）
+    #[serde(default)]
+    pub mirror: Option<String>, // ミラー先の状態 "synced" / "pending" / "failed"（ミラーなしなら None）
 }
 
 // session.json のタブ1件を表す構造体（セッション更新コマンド用）
@@ -64,6 +108,12 @@ pub struct TabSession {
     /// hdiffz -g オプションに渡す除外パターンリスト（タブごと）
     #[serde(default)]
     pub hdiff_ignore_list: Vec<String>,
+    /// 複数のファイル/フォルダをまとめてバックアップする場合のセット（work_file はセットのフォルダ）
+    #[serde(default)]
+    pub backup_set: Option<BackupSetDef>,
+    /// バックアップ後に新しいものを写す2つ目の保存先（空ならミラーしない）
+    #[serde(default)]
+    pub mirror_dir: String,
 }
 
 // session.json のルート構造体
@@ -90,3 +140,374 @@ pub struct BackupGenInfo {
     pub dir_path: PathBuf,
     pub base_idx: i32,
 }
+
+// 世代のリベース（ベース再作成）結果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RebaseReport {
+    pub generation: i32,
+    pub diff_count: usize,
+    pub new_base_from: String, // 新しい .base の元になった差分名
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+    pub saved_bytes: i64,
+}
+
+// 展開キャッシュ1件分の管理情報（世代アーカイブ1つに対応）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntry {
+    pub folder_name: String,  // baseN_<ts>
+    pub archive_path: String, // 展開元のアーカイブ
+    pub archive_size: u64,
+    pub archive_mtime: u64, // UNIX 秒
+    pub size: u64,          // 展開後の合計サイズ
+    pub last_access: u64,   // UNIX 秒
+}
+
+// 展開キャッシュの索引 (<cache_root>/cache_index.json)
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheIndex {
+    pub entries: Vec<CacheEntry>,
+}
+
+// 世代アーカイブの中身の一覧 (<cache_root>/archive_listing.json)
+// 履歴の一覧を更新するたびにアーカイブ全体を読まないよう、サイズと更新日時が同じ間は使い回す
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveListingCache {
+    pub archives: Vec<ArchiveListing>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveListing {
+    pub archive_path: String,
+    pub archive_size: u64,  // 分割アーカイブはボリュームの合計
+    pub archive_mtime: u64, // UNIX 秒（分割アーカイブは最も新しいボリューム）
+    pub entries: Vec<ArchiveListingEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveListingEntry {
+    pub name: String,
+    pub size: u64,
+    pub modified: Option<u64>, // UNIX 秒
+    pub is_dir: bool,
+}
+
+// 上書き復元の取り消し用記録 (<backup_root>/.wbt_undo/undo_log.json)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoRecord {
+    pub id: String,            // 復元した時刻 (YYYYMMDD_HHMMSS)
+    pub work_path: String,     // 上書きした作業ファイル/フォルダ
+    pub snapshot_path: String, // 上書き前の状態の退避先
+    pub restored_from: String, // 復元に使ったバックアップ
+}
+
+// フォルダのバックアップに含まれるファイル1件（選択復元用）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupFileEntry {
+    pub rel_path: String, // 作業フォルダからの相対パス（区切りは '/'）
+    pub file_size: u64,
+}
+
+// フォルダ比較で見つかった差異1件
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChange {
+    pub rel_path: String,       // 作業フォルダからの相対パス（区切りは '/'）
+    pub size_a: Option<u64>,    // 比較元のサイズ（追加されたファイルは None）
+    pub size_b: Option<u64>,    // 比較先のサイズ（削除されたファイルは None）
+    pub size_delta: i64,        // size_b - size_a
+    pub hash_a: Option<String>, // SHA-256
+    pub hash_b: Option<String>,
+}
+
+// 2つのフォルダ状態の比較結果
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderComparison {
+    pub added: Vec<FileChange>,
+    pub removed: Vec<FileChange>,
+    pub modified: Vec<FileChange>,
+    pub unchanged_count: usize,
+}
+
+// 2つのバックアップの画像比較結果（キャッシュ: <cache_root>/visual/<key>.json）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VisualComparison {
+    pub side_by_side_path: String, // 左右に並べた画像 (PNG)
+    pub heatmap_path: String,      // 画素差のヒートマップ (PNG)
+    pub width: u32,                // 比較元 (a) の大きさ
+    pub height: u32,
+    pub changed_pixels: u64,
+    pub changed_ratio: f64, // 変化した画素の割合 (0.0〜1.0)
+}
+
+// 世代1つ分の容量の内訳
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationStats {
+    pub name: String, // baseN_<ts>（アーカイブ済みなら拡張子付き）
+    pub generation: i32,
+    pub archived: bool,
+    pub base_size: u64,
+    pub diff_count: usize,
+    pub diff_bytes: u64,
+    pub largest_diff_bytes: u64,
+    pub largest_diff_name: Option<String>,
+    pub stored_bytes: u64,      // ディスク上のサイズ（アーカイブ済みなら圧縮後）
+    pub compression_ratio: f64, // stored_bytes / (作業ファイルのサイズ × 時点数)
+    pub rotation_reason: Option<RotationReason>, // この世代が作られた理由（記録がなければ None）
+}
+
+// バックアップ先全体の容量の内訳
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupStats {
+    pub work_size: u64,
+    pub generations: Vec<GenerationStats>,
+    pub generation_bytes: u64,
+    pub copy_count: usize,
+    pub copy_bytes: u64,
+    pub archive_count: usize,
+    pub archive_bytes: u64,
+    pub dedup_count: usize, // 重複排除バックアップのマニフェスト数
+    pub dedup_bytes: u64,   // マニフェストとチャンク置き場の合計
+    pub cache_bytes: u64,
+    pub total_bytes: u64,
+}
+
+// 世代交代の理由
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RotationReason {
+    DiffSize,       // 差分1つが大きすぎる
+    CumulativeDiff, // 世代内の差分の合計が大きすぎる
+    MaxDiffs,       // 世代内の差分が多すぎる
+    MaxAge,         // 世代が古い
+    WorkShrunk,     // 作業ファイルが大きく縮んだ
+    TypeChanged,    // 作業ファイルの種類が変わった
+}
+
+// 世代交代1回分の記録 (<backup_root>/.wbt_rotation_log.json)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationRecord {
+    pub generation: i32,      // 新しく作った世代
+    pub from_generation: i32, // 交代前の世代
+    pub reason: RotationReason,
+    pub detail: String,
+    pub time: String, // YYYYMMDD_HHMMSS
+}
+
+// 重複排除バックアップのファイル1件（フォルダの場合は相対パス、単一ファイルは空文字）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DedupFileEntry {
+    pub rel_path: String,
+    pub size: u64,
+    pub chunks: Vec<String>, // チャンクの SHA-256（先頭から順）
+}
+
+// 重複排除バックアップの1時点 (<backup_root>/<name>.<ts>.dedup)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DedupManifest {
+    pub version: u32,
+    pub name: String,    // 作業ファイル/フォルダ名
+    pub created: String, // YYYYMMDD_HHMMSS
+    pub is_folder: bool,
+    pub files: Vec<DedupFileEntry>,
+    #[serde(default)]
+    pub dirs: Vec<String>, // 空のフォルダも復元できるよう、フォルダの相対パスも残す
+}
+
+// 重複排除バックアップの作成結果
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DedupReport {
+    pub manifest_path: String,
+    pub chunk_count: usize,
+    pub new_chunks: usize,
+    pub total_bytes: u64,
+    pub new_bytes: u64, // 今回新たに書き込んだチャンクの合計
+}
+
+// 未参照チャンクの削除結果
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkGcReport {
+    pub manifest_count: usize,
+    pub kept_chunks: usize,
+    pub removed_chunks: usize,
+    pub freed_bytes: u64,
+}
+
+// 鍵保管庫の状態
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultStatus {
+    pub exists: bool,
+    pub unlocked: bool,
+    pub roots: Vec<String>,
+}
+
+// バックアップセットの定義（1つのタブでまとめてバックアップするファイル/フォルダ）
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSetDef {
+    pub name: String,
+    pub members: Vec<String>, // 元のファイル/フォルダのパス
+}
+
+// バックアップセットのメンバー1件
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSetMember {
+    pub name: String,   // セットのフォルダ内での名前
+    pub source: String, // 元のファイル/フォルダのパス
+    pub is_folder: bool,
+}
+
+// セットのフォルダ直下に置く対応表 (<backup_root>/.wbt_set/<name>/.wbtset.json)
+// バックアップにも含まれるので、復元時はその時点のメンバー構成が分かる
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSetManifest {
+    pub version: u32,
+    pub name: String,
+    pub updated: String, // YYYYMMDD_HHMMSS
+    pub members: Vec<BackupSetMember>,
+}
+
+// セットを揃えた結果（タブの作業対象・バックアップ先として使う）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSetInfo {
+    pub work_file: String,
+    pub backup_dir: String,
+    pub members: Vec<BackupSetMember>,
+}
+
+// バックアップルートの記述ファイル (<backup_root>/.wbt_id.json)
+// 作業ファイルの名前や場所が変わっても、同じ履歴だと分かるようにする
+// ルートごと別のドライブ・別の PC へ移しても、作業対象とタブの設定を辿れるようにする
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupIdentity {
+    pub version: u32,
+    pub id: String,        // 作成時に決めるランダムな ID
+    pub work_name: String, // 現在の作業ファイル/フォルダ名
+    pub work_path: String, // 最後にバックアップしたときの場所
+    pub is_folder: bool,
+    pub fingerprint: String, // 最後にバックアップしたときの内容の指紋
+    #[serde(default)]
+    pub names: Vec<String>, // これまでに使った作業ファイル名（古い順）
+    pub updated: String,    // YYYYMMDD_HHMMSS
+    #[serde(default)]
+    pub work_rel: String, // ルートから見た作業対象の相対パス（区切りは '/'、別ドライブなどで求められない場合は空）
+    #[serde(default)]
+    pub root_path: String, // 最後に使ったときのルートの場所（移されたかの判定用）
+    #[serde(default)]
+    pub settings: RootSettings,
+}
+
+// バックアップルートに記録するタブの設定（ルートを開いたときにタブを組み立て直す）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RootSettings {
+    pub backup_mode: String,
+    pub compress_mode: String,
+    pub diff_algo: String,
+    pub archive_format: String,
+    pub hdiff_ignore_list: Vec<String>,
+    pub backup_set: Option<BackupSetDef>,
+    pub mirror_dir: String,
+}
+
+// 既存のバックアップルートを開いた結果（タブの組み立てに使う）
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenedRoot {
+    pub work_file: String,
+    pub backup_dir: String,   // 作業対象の既定のバックアップ先なら空
+    pub work_missing: bool,   // 作業対象が見つからない（復元だけできる）
+    pub relocated: bool,      // 前回と別の場所から開いた
+    pub settings: RootSettings,
+}
+
+// 付け替え先の候補（名前の変わった作業ファイルの履歴かもしれないもの）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RelinkCandidate {
+    pub backup_dir: String,
+    pub work_name: String,
+    pub work_path: String,
+    pub exact: bool, // 内容の指紋が一致した
+    pub updated: String,
+}
+
+// 履歴の付け替え結果
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RelinkReport {
+    pub backup_dir: String,
+    pub is_default_dir: bool, // 新しい作業ファイルの既定のバックアップ先へ移した
+    pub moved_root: bool,     // バックアップ先のフォルダを移した
+    pub renamed: usize,       // 名前を付け替えたバックアップの数
+    pub previous_path: String,
+    pub warning: Option<String>, // 既定の場所へ移せず、元の場所のまま使う場合の理由
+}
+
+// ミラー先へ写した記録 (<backup_root>/.wbt_mirror.json)
+// 記録と大きさ・更新日時が違うものを、次のミラーで写し直す
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorState {
+    pub version: u32,
+    pub mirror_root: String, // 写した先（<mirror_dir>/<ルートのフォルダ名>）
+    pub last_sync: String,   // YYYYMMDD_HHMMSS
+    #[serde(default)]
+    pub last_error: String,
+    #[serde(default)]
+    pub files: BTreeMap<String, MirrorRecord>, // ルートからの相対パス（区切りは '/'）
+    #[serde(default)]
+    pub failed: Vec<MirrorFailure>, // 写せなかったもの（次のミラーで再試行する）
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorRecord {
+    pub size: u64,
+    pub mtime: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorFailure {
+    pub path: String,
+    pub error: String,
+    pub attempts: u32,
+    pub last_attempt: String,
+}
+
+// ミラーの状態（ミラーの結果・履歴の表示に使う）
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorStatus {
+    pub mirror_root: String,
+    pub last_sync: String,
+    pub last_error: String,
+    pub copied: usize,  // 今回写した数（状態の取得では 0）
+    pub synced: usize,  // 写し終えている数
+    pub pending: usize, // まだ写していない数（失敗したものを含む）
+    pub failed: Vec<MirrorFailure>,
+}
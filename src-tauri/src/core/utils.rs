@@ -1,10 +1,13 @@
+use crate::core::ignore::IgnoreMatcher;
 use chrono::Local;
+use filetime::FileTime;
 use std::collections::hash_map::DefaultHasher;
 use std::fs;
 use std::fs::File;
 use std::hash::{Hash, Hasher};
 use std::io::{self, Read};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 /// ファイルを安全に移動させる。
 pub fn move_file_safe<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> Result<(), String> {
@@ -145,6 +148,91 @@ pub fn copy_file(src: &str, dst: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// フォルダの中身を dst へコピーする（dst がなければ作る。同名のファイルは上書き）
+/// 更新日時・実行権限・空のフォルダも保つ（dst 自身の更新日時はそのまま）
+/// keep_symlinks が true ならシンボリックリンクはリンクのまま、false なら参照先をコピーする
+pub fn copy_dir_preserving(src: &Path, dst: &Path, keep_symlinks: bool) -> Result<(), String> {
+    copy_dir_filtered(src, dst, keep_symlinks, &IgnoreMatcher::default())
+}
+
+/// copy_dir_preserving と同じく、ignore に一致するファイル・フォルダを除いてコピーする
+pub fn copy_dir_filtered(
+    src: &Path,
+    dst: &Path,
+    keep_symlinks: bool,
+    ignore: &IgnoreMatcher,
+) -> Result<(), String> {
+    fs::create_dir_all(dst).map_err(|e| format!("ディレクトリ作成失敗: {}", e))?;
+    // ファイルを書き込むとフォルダの更新日時が変わるため、フォルダは最後に設定する
+    let mut dir_times = Vec::new();
+
+    let walker = walkdir::WalkDir::new(src)
+        .min_depth(1)
+        .follow_links(!keep_symlinks);
+    for entry in ignore.walk(src, walker) {
+        let entry = entry.map_err(|e| format!("フォルダの読み込みに失敗しました: {}", e))?;
+        let rel = entry.path().strip_prefix(src).map_err(|e| e.to_string())?;
+        let out = dst.join(rel);
+        let is_link_out = fs::symlink_metadata(&out).is_ok_and(|m| m.file_type().is_symlink());
+        if is_link_out {
+            // 既存のリンクの先へ書き込まないよう、先に消す
+            remove_symlink(&out)
+                .map_err(|e| format!("リンクの削除に失敗しました {}: {}", out.display(), e))?;
+        }
+
+        let file_type = entry.file_type();
+        if file_type.is_symlink() {
+            let target = fs::read_link(entry.path()).map_err(|e| e.to_string())?;
+            create_symlink(&target, &out, entry.path().is_dir())
+                .map_err(|e| format!("リンクの作成に失敗しました {}: {}", out.display(), e))?;
+        } else if file_type.is_dir() {
+            fs::create_dir_all(&out).map_err(|e| format!("ディレクトリ作成失敗: {}", e))?;
+            if let Some(t) = entry.metadata().ok().and_then(|m| m.modified().ok()) {
+                dir_times.push((out, t));
+            }
+        } else {
+            // fs::copy は権限（実行ビットなど）もコピーする
+            fs::copy(entry.path(), &out).map_err(|e| {
+                format!(
+                    "ファイルのコピーに失敗しました {}: {}",
+                    entry.path().display(),
+                    e
+                )
+            })?;
+            if let Some(t) = entry.metadata().ok().and_then(|m| m.modified().ok()) {
+                set_modified_time(&out, t)?;
+            }
+        }
+    }
+
+    for (dir, t) in dir_times {
+        set_modified_time(&dir, t)?;
+    }
+    Ok(())
+}
+
+/// シンボリックリンクを作る（Windows ではリンク先がフォルダかどうかで作り方が変わる）
+pub fn create_symlink(target: &Path, link: &Path, is_dir: bool) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let _ = is_dir;
+        std::os::unix::fs::symlink(target, link)
+    }
+    #[cfg(windows)]
+    {
+        if is_dir {
+            std::os::windows::fs::symlink_dir(target, link)
+        } else {
+            std::os::windows::fs::symlink_file(target, link)
+        }
+    }
+}
+
+fn remove_symlink(link: &Path) -> io::Result<()> {
+    // Windows のフォルダへのリンクは remove_dir で消す
+    fs::remove_file(link).or_else(|_| fs::remove_dir(link))
+}
+
 /// Readerの内容をターゲットファイルに書き出す
 pub fn save_to_work_file<R: Read>(mut reader: R, target_file: &str) -> Result<(), String> {
     let mut out = File::create(target_file)
@@ -187,6 +275,18 @@ pub fn get_cache_root(use_same_dir: bool, backup_dir: &str, work_path: &str) ->
 /// ファイルまたはフォルダのサイズを返す
 /// フォルダの場合は配下のファイルサイズを合算
 pub fn get_file_size(path: &str) -> Result<i64, String> {
+    get_file_size_filtered(path, &IgnoreMatcher::default())
+}
+
+/// 作業ファイル/フォルダのサイズ（フォルダは ignore に一致するものを除いて合算）
+/// バックアップに含まれる分だけを数えるため、タブの除外パターンと `.wbtignore` を使う
+pub fn get_work_size(path: &str, ignore_patterns: &[String]) -> Result<i64, String> {
+    let ignore = IgnoreMatcher::for_folder(Path::new(path), ignore_patterns);
+    get_file_size_filtered(path, &ignore)
+}
+
+/// ignore に一致するファイル・フォルダを除いてサイズを合算する
+pub fn get_file_size_filtered(path: &str, ignore: &IgnoreMatcher) -> Result<i64, String> {
     if path.is_empty() {
         return Err("path is empty".to_string());
     }
@@ -195,8 +295,8 @@ pub fn get_file_size(path: &str) -> Result<i64, String> {
 
     if metadata.is_dir() {
         let mut total = 0i64;
-        for entry in walkdir::WalkDir::new(p)
-            .into_iter()
+        for entry in ignore
+            .walk(p, walkdir::WalkDir::new(p))
             .filter_map(|e| e.ok())
             .filter(|e| e.file_type().is_file())
         {
@@ -208,6 +308,13 @@ pub fn get_file_size(path: &str) -> Result<i64, String> {
     }
 }
 
+/// ファイルまたはフォルダの更新日時を設定する
+/// インポートした過去のバックアップを元ファイルの時刻に合わせる用途
+pub fn set_modified_time(path: &Path, time: SystemTime) -> Result<(), String> {
+    filetime::set_file_mtime(path, FileTime::from_system_time(time))
+        .map_err(|e| format!("更新日時の設定に失敗しました {}: {}", path.display(), e))
+}
+
 /// テキストファイルを読み込む
 pub fn read_text_file(path: &str) -> Result<String, String> {
     let p = Path::new(path);
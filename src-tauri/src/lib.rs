@@ -27,6 +27,18 @@ pub fn run() {
             apply_multi_diff,
             copy_backup_file,
             archive_backup_file,
+            dedup_backup,
+            sync_backup_set,
+            get_backup_set_members,
+            restore_backup_set,
+            gc_dedup_chunks,
+            encrypt_backup_files,
+            get_vault_status,
+            unlock_vault,
+            lock_vault,
+            set_vault_password,
+            remove_vault_password,
+            change_vault_master,
             dir_exists,
             file_exists,
             restore_backup,
@@ -48,7 +60,26 @@ pub fn run() {
             get_startup_cache_limit,
             get_config,
             update_config_value,
-            update_session_tab_value
+            update_session_tab_value,
+            import_manual_backups,
+            find_relink_candidates,
+            relink_backup_history,
+            save_root_settings,
+            open_backup_root,
+            sync_mirror,
+            get_mirror_status,
+            rebase_generation,
+            delete_generation,
+            merge_generations,
+            restore_at,
+            restore_in_place,
+            undo_in_place_restore,
+            list_backup_files,
+            restore_selected_files,
+            compare_backups,
+            compare_images,
+            get_backup_thumbnail,
+            get_backup_stats
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
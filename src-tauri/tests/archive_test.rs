@@ -1,6 +1,6 @@
 use std::fs;
 use tempfile::tempdir;
-use work_backup_tool::core::backup::archive;
+use work_backup_tool::core::backup::archive::{self, CompressionOptions, TarCodec};
 
 // =====================================================================
 // zip_backup_file / tar_backup_file
@@ -78,6 +78,7 @@ fn test_execute_archive_backup_tar() {
         Some(dir.path().to_path_buf()),
         "tar",
         "",
+        &CompressionOptions::default(),
     )
     .unwrap();
 
@@ -97,7 +98,14 @@ fn test_execute_archive_backup_uses_default_dir_when_none() {
     fs::write(&src, b"data").unwrap();
 
     // backup_dir_opt = None → wbt_backup_work フォルダが自動作成されるはず
-    archive::execute_archive_backup(&src.to_string_lossy(), None, "zip", "").unwrap();
+    archive::execute_archive_backup(
+        &src.to_string_lossy(),
+        None,
+        "zip",
+        "",
+        &CompressionOptions::default(),
+    )
+    .unwrap();
 
     let default_dir = dir.path().join("wbt_backup_work");
     assert!(default_dir.exists());
@@ -229,6 +237,7 @@ fn test_execute_generation_archive_zip() {
         "/dummy/work.clip",
         &dir.path().to_string_lossy(),
         "",
+        &CompressionOptions::default(),
     )
     .unwrap();
 
@@ -251,6 +260,7 @@ fn test_execute_generation_archive_not_found() {
         "/dummy/work.clip",
         &dir.path().to_string_lossy(),
         "",
+        &CompressionOptions::default(),
     );
     assert!(result.is_err());
 }
@@ -309,3 +319,416 @@ fn test_extract_to_cache_nonexistent_archive() {
     let result = archive::extract_to_cache("/no/such/archive.zip", &cache, None);
     assert!(result.is_err());
 }
+
+// =====================================================================
+// 世代アーカイブからの直接復元 (list_archive_entries / extract_diff_with_base)
+// =====================================================================
+
+fn make_generation_archive(root: &std::path::Path, tar: bool) -> std::path::PathBuf {
+    let gen_dir = root.join("base2_20260101_110000");
+    fs::create_dir(&gen_dir).unwrap();
+    fs::write(gen_dir.join("work.clip.base"), b"base").unwrap();
+    fs::write(gen_dir.join("work.clip.20260101_110500.hdiff.diff"), b"d1").unwrap();
+    fs::write(gen_dir.join("work.clip.20260101_111000.hdiff.diff"), b"d2").unwrap();
+    let dst = if tar {
+        root.join("base2_20260101_110000.tar.gz")
+    } else {
+        root.join("base2_20260101_110000.zip")
+    };
+    if tar {
+        archive::compress_dir_tar(&gen_dir, &dst).unwrap();
+    } else {
+        archive::compress_dir_zip(&gen_dir, &dst, "").unwrap();
+    }
+    fs::remove_dir_all(&gen_dir).unwrap();
+    dst
+}
+
+#[test]
+fn test_archived_entry_path_roundtrip() {
+    let archive_path = std::path::Path::new("/b/base2_20260101_110000.zip");
+    let v = archive::archived_entry_path(archive_path, "base2_20260101_110000/a.diff");
+    let (a, e) = archive::split_archived_entry_path(&v).unwrap();
+    assert_eq!(a, archive_path);
+    assert_eq!(e, "base2_20260101_110000/a.diff");
+
+    assert!(archive::split_archived_entry_path("/b/base2/a.diff").is_none());
+    assert!(archive::split_archived_entry_path("/b/x.txt::a.diff").is_none());
+}
+
+#[test]
+fn test_list_archive_entries_zip_and_tar() {
+    for tar in [false, true] {
+        let dir = tempdir().unwrap();
+        let path = make_generation_archive(dir.path(), tar);
+
+        let entries = archive::list_archive_entries(&path).unwrap();
+        let files: Vec<_> = entries.iter().filter(|e| !e.is_dir).collect();
+        assert_eq!(files.len(), 3);
+        assert!(files.iter().any(|e| e.name
+            == "base2_20260101_110000/work.clip.20260101_110500.hdiff.diff"
+            && e.size == 2));
+    }
+}
+
+#[test]
+fn test_extract_diff_with_base_only_needed_entries() {
+    for tar in [false, true] {
+        let dir = tempdir().unwrap();
+        let path = make_generation_archive(dir.path(), tar);
+        let dest = dir.path().join("staging");
+
+        let diff = archive::extract_diff_with_base(
+            &path,
+            "base2_20260101_110000/work.clip.20260101_111000.hdiff.diff",
+            &dest,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(fs::read(&diff).unwrap(), b"d2");
+        assert_eq!(fs::read(dest.join("work.clip.base")).unwrap(), b"base");
+        assert!(!dest.join("work.clip.20260101_110500.hdiff.diff").exists());
+    }
+}
+
+#[test]
+fn test_extract_diff_with_base_keeps_folder_base() {
+    let dir = tempdir().unwrap();
+    let gen_dir = dir.path().join("base1_20260101_100000");
+    fs::create_dir_all(gen_dir.join("world.base/sub")).unwrap();
+    fs::write(gen_dir.join("world.base/sub/page.txt"), b"page").unwrap();
+    fs::write(gen_dir.join("world.20260101_100500.hdiff.diff"), b"d").unwrap();
+    let zip_path = dir.path().join("base1_20260101_100000.zip");
+    archive::compress_dir_zip(&gen_dir, &zip_path, "").unwrap();
+
+    let dest = dir.path().join("staging");
+    archive::extract_diff_with_base(
+        &zip_path,
+        "base1_20260101_100000/world.20260101_100500.hdiff.diff",
+        &dest,
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(
+        fs::read(dest.join("world.base/sub/page.txt")).unwrap(),
+        b"page"
+    );
+}
+
+#[test]
+fn test_extract_diff_with_base_missing_entry() {
+    let dir = tempdir().unwrap();
+    let path = make_generation_archive(dir.path(), false);
+    let result = archive::extract_diff_with_base(
+        &path,
+        "base2_20260101_110000/work.clip.20990101_000000.hdiff.diff",
+        &dir.path().join("staging"),
+        None,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_stage_archived_diff_prefers_cache() {
+    let dir = tempdir().unwrap();
+    let path = make_generation_archive(dir.path(), false);
+    let entry = "base2_20260101_110000/work.clip.20260101_110500.hdiff.diff";
+    let v = archive::archived_entry_path(&path, entry);
+    let cache = dir.path().join(".wbt_cache");
+    let staging = dir.path().join("staging");
+
+    // キャッシュなし → staging へ取り出す
+    let staged = archive::stage_archived_diff(&v, &cache, &staging, None).unwrap();
+    assert!(staged.starts_with(&staging));
+
+    // キャッシュあり → キャッシュの差分をそのまま使う
+    archive::extract_to_cache(&path.to_string_lossy(), &cache, None).unwrap();
+    let staged = archive::stage_archived_diff(&v, &cache, &staging, None).unwrap();
+    assert!(staged.starts_with(&cache));
+}
+
+// =====================================================================
+// tar.zst / tar.xz
+// =====================================================================
+
+#[test]
+fn test_tar_codec_from_format_and_path() {
+    assert_eq!(TarCodec::from_format("tar"), Some(TarCodec::Gzip));
+    assert_eq!(TarCodec::from_format("tar.zst"), Some(TarCodec::Zstd));
+    assert_eq!(TarCodec::from_format("tar.xz"), Some(TarCodec::Xz));
+    assert_eq!(TarCodec::from_format("zip"), None);
+    assert_eq!(TarCodec::from_path("a.TGZ"), Some(TarCodec::Gzip));
+    assert_eq!(TarCodec::from_path("base1_x.tar.zst"), Some(TarCodec::Zstd));
+    assert_eq!(TarCodec::from_path("base1_x.txz"), Some(TarCodec::Xz));
+    assert_eq!(TarCodec::from_path("base1_x.zip"), None);
+    assert_eq!(
+        archive::strip_archive_ext("base1_20260101_100000.tar.xz"),
+        "base1_20260101_100000"
+    );
+}
+
+#[test]
+fn test_execute_archive_backup_zst_and_xz_restore() {
+    for (format, ext) in [("tar.zst", ".tar.zst"), ("tar.xz", ".tar.xz")] {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("work.clip");
+        fs::write(&src, b"painting data ".repeat(1000)).unwrap();
+        let out_dir = dir.path().join("out");
+
+        let opts = CompressionOptions {
+            level: 19,
+            threads: 2,
+            ..Default::default()
+        };
+        let dst = archive::execute_archive_backup(
+            &src.to_string_lossy(),
+            Some(out_dir.clone()),
+            format,
+            "",
+            &opts,
+        )
+        .unwrap();
+        let name = std::path::Path::new(&dst)
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        assert!(name.starts_with("work_") && name.ends_with(ext), "{}", name);
+        assert!(fs::metadata(&dst).unwrap().len() < 14000);
+
+        let restored = dir.path().join("restored.clip");
+        archive::restore_archive(&dst, &restored.to_string_lossy()).unwrap();
+        assert_eq!(fs::read(&restored).unwrap(), b"painting data ".repeat(1000));
+    }
+}
+
+#[test]
+fn test_generation_archive_zst_and_xz_extract_to_cache() {
+    for format in ["tar.zst", "tar.xz"] {
+        let dir = tempdir().unwrap();
+        let gen_dir = dir.path().join("base3_20260101_120000");
+        fs::create_dir(&gen_dir).unwrap();
+        fs::write(gen_dir.join("work.clip.base"), b"base").unwrap();
+        fs::write(
+            gen_dir.join("work.clip.20260101_120500.hdiff.diff"),
+            b"diff",
+        )
+        .unwrap();
+
+        archive::execute_generation_archive(
+            3,
+            format,
+            "/dummy/work.clip",
+            &dir.path().to_string_lossy(),
+            "",
+            &CompressionOptions::default(),
+        )
+        .unwrap();
+        assert!(!gen_dir.exists());
+        let archive_path = dir.path().join(format!("base3_20260101_120000.{}", format));
+        assert!(archive_path.is_file());
+
+        let entries = archive::list_archive_entries(&archive_path).unwrap();
+        assert!(entries.iter().any(|e| e.name.ends_with(".hdiff.diff")));
+
+        let cache = dir.path().join(".wbt_cache");
+        archive::extract_to_cache(&archive_path.to_string_lossy(), &cache, None).unwrap();
+        assert_eq!(
+            fs::read(
+                cache
+                    .join("base3_20260101_120000")
+                    .join("work.clip.20260101_120500.hdiff.diff")
+            )
+            .unwrap(),
+            b"diff"
+        );
+    }
+}
+
+// =====================================================================
+// 更新日時・権限・空のフォルダの保持
+// =====================================================================
+
+/// ZIP の日時は2秒単位なので偶数秒にする
+fn old_time() -> std::time::SystemTime {
+    std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000)
+}
+
+fn make_project_with_metadata(root: &std::path::Path) -> std::path::PathBuf {
+    let project = root.join("project");
+    fs::create_dir_all(project.join("empty")).unwrap();
+    fs::create_dir_all(project.join("sub")).unwrap();
+    fs::write(project.join("sub").join("a.txt"), b"a").unwrap();
+    fs::write(project.join("run.sh"), b"#!/bin/sh").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(project.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        std::os::unix::fs::symlink("run.sh", project.join("link")).unwrap();
+    }
+    work_backup_tool::core::utils::set_modified_time(
+        &project.join("sub").join("a.txt"),
+        old_time(),
+    )
+    .unwrap();
+    work_backup_tool::core::utils::set_modified_time(&project.join("sub"), old_time()).unwrap();
+    project
+}
+
+#[test]
+fn test_folder_archive_preserves_metadata() {
+    for format in ["zip", "tar.gz", "tar.zst"] {
+        let dir = tempdir().unwrap();
+        let project = make_project_with_metadata(dir.path());
+        let opts = CompressionOptions {
+            keep_symlinks: true,
+            ..Default::default()
+        };
+        let dst = archive::execute_archive_backup(
+            &project.to_string_lossy(),
+            Some(dir.path().join("backup")),
+            format,
+            "",
+            &opts,
+        )
+        .unwrap();
+
+        let out = dir.path().join("out");
+        let wanted: Vec<std::path::PathBuf> = ["sub", "empty", "run.sh", "link"]
+            .iter()
+            .map(Into::into)
+            .collect();
+        archive::extract_folder_entries(std::path::Path::new(&dst), &wanted, &out, None).unwrap();
+
+        assert!(out.join("empty").is_dir(), "{}", format);
+        let a = out.join("sub").join("a.txt");
+        assert_eq!(
+            fs::metadata(&a).unwrap().modified().unwrap(),
+            old_time(),
+            "{}",
+            format
+        );
+        assert_eq!(
+            fs::metadata(out.join("sub")).unwrap().modified().unwrap(),
+            old_time(),
+            "{}",
+            format
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(out.join("run.sh"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o755, "{}", format);
+            assert_eq!(
+                fs::read_link(out.join("link")).unwrap(),
+                std::path::Path::new("run.sh"),
+                "{}",
+                format
+            );
+        }
+    }
+}
+
+#[test]
+fn test_extract_to_cache_keeps_folder_base_structure() {
+    let dir = tempdir().unwrap();
+    let gen_dir = dir.path().join("base1_20260101_100000");
+    let base = gen_dir.join("project.base");
+    fs::create_dir_all(base.join("empty")).unwrap();
+    fs::create_dir_all(base.join("sub")).unwrap();
+    fs::write(base.join("sub").join("a.txt"), b"a").unwrap();
+    work_backup_tool::core::utils::set_modified_time(&base.join("sub").join("a.txt"), old_time())
+        .unwrap();
+
+    for (tar, ext) in [(false, "zip"), (true, "tar.gz")] {
+        let archive_path = dir.path().join(format!("base1_20260101_100000.{}", ext));
+        if tar {
+            archive::compress_dir_tar(&gen_dir, &archive_path).unwrap();
+        } else {
+            archive::compress_dir_zip(&gen_dir, &archive_path, "").unwrap();
+        }
+        let cache_root = dir.path().join(format!("cache_{}", ext));
+        archive::extract_to_cache(&archive_path.to_string_lossy(), &cache_root, None).unwrap();
+
+        let cached = cache_root
+            .join("base1_20260101_100000")
+            .join("project.base");
+        assert!(cached.join("empty").is_dir(), "{}", ext);
+        let a = cached.join("sub").join("a.txt");
+        assert_eq!(fs::read(&a).unwrap(), b"a");
+        assert_eq!(fs::metadata(&a).unwrap().modified().unwrap(), old_time());
+    }
+}
+
+/// リンク `base1_<ts>/a -> link_target` と、そのリンクを経由するファイル `base1_<ts>/a/x` を持つアーカイブ
+#[cfg(unix)]
+fn make_link_escape_archive(path: &std::path::Path, link_target: &str) {
+    use std::io::Write;
+    if path.extension().is_some_and(|e| e == "zip") {
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        let opts = zip::write::SimpleFileOptions::default();
+        zip.add_symlink("base1_20260101_100000/a", link_target, opts)
+            .unwrap();
+        zip.start_file("base1_20260101_100000/a/x", opts).unwrap();
+        zip.write_all(b"escaped").unwrap();
+        zip.finish().unwrap();
+    } else {
+        let gz = flate2::write::GzEncoder::new(
+            fs::File::create(path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut tar = tar::Builder::new(gz);
+        let mut link = tar::Header::new_gnu();
+        link.set_entry_type(tar::EntryType::Symlink);
+        link.set_size(0);
+        link.set_mode(0o777);
+        tar.append_link(&mut link, "base1_20260101_100000/a", link_target)
+            .unwrap();
+        let mut file = tar::Header::new_gnu();
+        file.set_size(7);
+        file.set_mode(0o644);
+        tar.append_data(&mut file, "base1_20260101_100000/a/x", &b"escaped"[..])
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_refuses_links_leaving_the_destination() {
+    let dir = tempdir().unwrap();
+    let outside = dir.path().join("outside");
+    fs::create_dir_all(&outside).unwrap();
+
+    for ext in ["zip", "tar.gz"] {
+        // 絶対パス・`..` で展開先の外を指すリンクは作らない
+        for target in [
+            outside.to_string_lossy().into_owned(),
+            "../../outside".into(),
+        ] {
+            let archive_path = dir.path().join(format!("base1_20260101_100000.{}", ext));
+            make_link_escape_archive(&archive_path, &target);
+            let cache_root = dir.path().join("cache");
+            let err = archive::extract_to_cache(&archive_path.to_string_lossy(), &cache_root, None)
+                .unwrap_err();
+            assert!(err.contains("リンク"), "{}: {}", ext, err);
+            assert!(!outside.join("x").exists(), "{}", ext);
+            fs::remove_dir_all(&cache_root).ok();
+        }
+
+        // 展開先の中を指すリンクでも、リンクを経由して書き込まない
+        let archive_path = dir.path().join(format!("base1_20260101_100000.{}", ext));
+        make_link_escape_archive(&archive_path, ".");
+        let cache_root = dir.path().join("cache");
+        let err = archive::extract_to_cache(&archive_path.to_string_lossy(), &cache_root, None)
+            .unwrap_err();
+        assert!(err.contains("リンク"), "{}: {}", ext, err);
+        assert!(!cache_root.join("base1_20260101_100000").join("x").exists());
+        fs::remove_dir_all(&cache_root).ok();
+    }
+}
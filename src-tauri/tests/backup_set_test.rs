@@ -0,0 +1,245 @@
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::archive::{self, CompressionOptions};
+use work_backup_tool::core::backup::backup_set;
+use work_backup_tool::core::backup::restore::{self, ConflictPolicy};
+use work_backup_tool::core::backup::workflow;
+use work_backup_tool::core::types::BackupSetDef;
+use work_backup_tool::core::utils;
+
+/// `.clip`・資料フォルダ・書き出した PNG の3つからなるセット
+fn make_members(root: &Path) -> BackupSetDef {
+    let work = root.join("work");
+    fs::create_dir_all(work.join("refs").join("sub")).unwrap();
+    fs::write(work.join("illust.clip"), b"clip data").unwrap();
+    fs::write(work.join("refs").join("pose.jpg"), b"pose").unwrap();
+    fs::write(work.join("refs").join("sub").join("color.png"), b"color").unwrap();
+    fs::write(work.join("refs").join("cache.tmp"), b"tmp").unwrap();
+    let export = root.join("export");
+    fs::create_dir_all(&export).unwrap();
+    fs::write(export.join("illust.png"), b"png").unwrap();
+
+    BackupSetDef {
+        name: "illust_set".to_string(),
+        members: vec![
+            work.join("illust.clip").to_string_lossy().into_owned(),
+            work.join("refs").to_string_lossy().into_owned(),
+            export.join("illust.png").to_string_lossy().into_owned(),
+        ],
+    }
+}
+
+#[test]
+fn test_set_name_and_member_names() {
+    assert!(backup_set::validate_set_name("illust_set").is_ok());
+    assert!(backup_set::validate_set_name("").is_err());
+    assert!(backup_set::validate_set_name(".hidden").is_err());
+    assert!(backup_set::validate_set_name("a/b").is_err());
+
+    let members = backup_set::member_names(&[
+        "/a/cover.png".to_string(),
+        "/b/cover.png".to_string(),
+        "/c/COVER.png".to_string(),
+        "/d/refs".to_string(),
+    ]);
+    let names: Vec<&str> = members.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["cover.png", "cover_2.png", "COVER_3.png", "refs"]
+    );
+
+    let def = BackupSetDef {
+        name: "s".to_string(),
+        members: vec!["/x/y/a.clip".to_string()],
+    };
+    assert_eq!(
+        backup_set::default_set_root(&def).unwrap(),
+        Path::new("/x/y").join("wbt_backup_s")
+    );
+    let work_dir = backup_set::set_work_dir(Path::new("/r"), "s");
+    assert!(backup_set::is_set_work_dir(&work_dir));
+    assert!(!backup_set::is_set_work_dir(Path::new("/r/s")));
+}
+
+#[test]
+fn test_sync_set_mirrors_members() {
+    let dir = tempdir().unwrap();
+    let mut def = make_members(dir.path());
+    let work_dir = backup_set::set_work_dir(&dir.path().join("backup"), &def.name);
+
+    let manifest = backup_set::sync_set(
+        &work_dir,
+        &def,
+        false,
+        &["*.tmp".to_string()],
+        "20260101_100000",
+    )
+    .unwrap();
+    assert_eq!(manifest.members.len(), 3);
+    assert!(manifest.members[1].is_folder);
+    assert_eq!(
+        fs::read(work_dir.join("illust.clip")).unwrap(),
+        b"clip data"
+    );
+    assert!(work_dir.join("refs").join("sub").join("color.png").exists());
+    assert!(!work_dir.join("refs").join("cache.tmp").exists());
+    assert_eq!(backup_set::load_set_manifest(&work_dir).unwrap(), manifest);
+
+    // メンバーを外すとセットのフォルダからも消え、内容の変更は反映される
+    fs::write(&def.members[0], b"clip v2").unwrap();
+    def.members.pop();
+    backup_set::sync_set(&work_dir, &def, false, &[], "20260101_110000").unwrap();
+    assert!(!work_dir.join("illust.png").exists());
+    assert_eq!(fs::read(work_dir.join("illust.clip")).unwrap(), b"clip v2");
+
+    // 見つからないメンバーがあれば揃えない
+    def.members.push(
+        dir.path()
+            .join("missing.psd")
+            .to_string_lossy()
+            .into_owned(),
+    );
+    let err = backup_set::sync_set(&work_dir, &def, false, &[], "20260101_120000").unwrap_err();
+    assert!(err.contains("missing.psd"), "{}", err);
+}
+
+#[test]
+fn test_sync_set_updates_folder_members_incrementally() {
+    let dir = tempdir().unwrap();
+    let def = make_members(dir.path());
+    let refs = Path::new(&def.members[1]).to_path_buf();
+    let work_dir = backup_set::set_work_dir(&dir.path().join("backup"), &def.name);
+    let patterns = ["*.tmp".to_string()];
+    backup_set::sync_set(&work_dir, &def, false, &patterns, "20260101_100000").unwrap();
+
+    // 大きさと更新日時が同じファイルはコピーし直さない（セット側だけの書き換えが残る）
+    let synced = work_dir.join("refs").join("sub").join("color.png");
+    let mtime = fs::metadata(refs.join("sub").join("color.png"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    fs::write(&synced, b"COLOR").unwrap();
+    utils::set_modified_time(&synced, mtime).unwrap();
+
+    // 消したファイル・フォルダは消え、増えたファイルと変わったファイルは反映される
+    fs::remove_file(refs.join("pose.jpg")).unwrap();
+    fs::create_dir_all(refs.join("new")).unwrap();
+    fs::write(refs.join("new").join("memo.txt"), b"memo").unwrap();
+    fs::write(work_dir.join("refs").join("stale.txt"), b"stale").unwrap();
+    fs::create_dir_all(work_dir.join("refs").join("old").join("deep")).unwrap();
+    backup_set::sync_set(&work_dir, &def, false, &patterns, "20260101_110000").unwrap();
+
+    assert_eq!(fs::read(&synced).unwrap(), b"COLOR");
+    assert!(!work_dir.join("refs").join("pose.jpg").exists());
+    assert!(!work_dir.join("refs").join("stale.txt").exists());
+    assert!(!work_dir.join("refs").join("old").exists());
+    assert!(!work_dir.join("refs").join("cache.tmp").exists());
+    assert_eq!(
+        fs::read(work_dir.join("refs").join("new").join("memo.txt")).unwrap(),
+        b"memo"
+    );
+
+    // 中身が変わったファイルはコピーし直す
+    fs::write(refs.join("sub").join("color.png"), b"color v2").unwrap();
+    backup_set::sync_set(&work_dir, &def, false, &patterns, "20260101_120000").unwrap();
+    assert_eq!(fs::read(&synced).unwrap(), b"color v2");
+}
+
+#[test]
+fn test_copy_backup_of_set_restores_whole_and_single_member() {
+    let dir = tempdir().unwrap();
+    let def = make_members(dir.path());
+    let root = dir.path().join("backup");
+    let work_dir = backup_set::set_work_dir(&root, &def.name);
+    backup_set::sync_set(&work_dir, &def, false, &[], "20260101_100000").unwrap();
+
+    let dst = workflow::execute_copy_backup_with(
+        &work_dir.to_string_lossy(),
+        Some(root.clone()),
+        false,
+        &[],
+    )
+    .unwrap();
+
+    // セット全体を output フォルダへ
+    let restored = dir.path().join("restored_all");
+    restore::restore_copy(&dst, &restored.to_string_lossy()).unwrap();
+    let out = dir.path().join("out");
+    let placed = backup_set::restore_set_members(
+        &restored,
+        None,
+        Some(&out.to_string_lossy()),
+        ConflictPolicy::Rename,
+    )
+    .unwrap();
+    assert_eq!(placed.len(), 3);
+    assert_eq!(fs::read(out.join("illust.clip")).unwrap(), b"clip data");
+    assert_eq!(
+        fs::read(out.join("refs").join("sub").join("color.png")).unwrap(),
+        b"color"
+    );
+    assert_eq!(fs::read(out.join("illust.png")).unwrap(), b"png");
+
+    // 1つのメンバーだけを元の場所の隣へ
+    let restored = dir.path().join("restored_one");
+    restore::restore_copy(&dst, &restored.to_string_lossy()).unwrap();
+    let placed = backup_set::restore_set_members(
+        &restored,
+        Some(&def.members[2]),
+        None,
+        ConflictPolicy::Rename,
+    )
+    .unwrap();
+    assert_eq!(placed.len(), 1);
+    let placed = Path::new(&placed[0]);
+    assert_eq!(placed.parent().unwrap(), dir.path().join("export"));
+    assert!(placed
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .starts_with("illust_restored_"));
+    assert_eq!(fs::read(placed).unwrap(), b"png");
+
+    let err =
+        backup_set::restore_set_members(&restored, Some("nothing"), None, ConflictPolicy::Rename)
+            .unwrap_err();
+    assert!(err.contains("nothing"), "{}", err);
+}
+
+#[test]
+fn test_archive_backup_of_set_contains_all_members() {
+    let dir = tempdir().unwrap();
+    let def = make_members(dir.path());
+    let root = dir.path().join("backup");
+    let work_dir = backup_set::set_work_dir(&root, &def.name);
+    backup_set::sync_set(&work_dir, &def, false, &[], "20260101_100000").unwrap();
+
+    let dst = archive::execute_archive_backup(
+        &work_dir.to_string_lossy(),
+        Some(root.clone()),
+        "zip",
+        "",
+        &CompressionOptions::default(),
+    )
+    .unwrap();
+    assert!(Path::new(&dst).starts_with(&root));
+
+    let mut names: Vec<String> = archive::list_folder_archive_files(Path::new(&dst))
+        .unwrap()
+        .into_iter()
+        .map(|(name, _)| name.replace('\\', "/"))
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            backup_set::SET_MANIFEST,
+            "illust.clip",
+            "illust.png",
+            "refs/cache.tmp",
+            "refs/pose.jpg",
+            "refs/sub/color.png",
+        ]
+    );
+}
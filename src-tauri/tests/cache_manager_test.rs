@@ -0,0 +1,239 @@
+use std::fs;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::{archive, cache_manager};
+use work_backup_tool::core::config::loader;
+use work_backup_tool::core::types::AppConfig;
+
+fn make_archive(root: &std::path::Path, name: &str, payload: &[u8]) -> std::path::PathBuf {
+    let gen = root.join(name);
+    fs::create_dir_all(&gen).unwrap();
+    fs::write(gen.join("work.clip.base"), payload).unwrap();
+    fs::write(gen.join("work.clip.20260101_100500.hdiff.diff"), b"d").unwrap();
+    let zip = root.join(format!("{}.zip", name));
+    archive::compress_dir_zip(&gen, &zip, "").unwrap();
+    fs::remove_dir_all(&gen).unwrap();
+    zip
+}
+
+// =====================================================================
+// ensure_cached
+// =====================================================================
+
+#[test]
+fn test_ensure_cached_extracts_and_records_entry() {
+    let dir = tempdir().unwrap();
+    let zip = make_archive(dir.path(), "base1_20260101_100000", b"base");
+    let cache = dir.path().join(".wbt_cache");
+
+    let cached = cache_manager::ensure_cached(&cache, &zip, None, 0).unwrap();
+
+    assert_eq!(cached, cache.join("base1_20260101_100000"));
+    assert!(cached.join("work.clip.base").exists());
+    let index = cache_manager::load_index(&cache);
+    assert_eq!(index.entries.len(), 1);
+    assert_eq!(
+        index.entries[0].archive_size,
+        fs::metadata(&zip).unwrap().len()
+    );
+    assert!(cache_manager::is_entry_valid(&cache, &index.entries[0]));
+}
+
+#[test]
+fn test_ensure_cached_reuses_valid_cache() {
+    let dir = tempdir().unwrap();
+    let zip = make_archive(dir.path(), "base1_20260101_100000", b"base");
+    let cache = dir.path().join(".wbt_cache");
+    let cached = cache_manager::ensure_cached(&cache, &zip, None, 0).unwrap();
+
+    // 展開済みなら再展開しない（目印のファイルが残る）
+    fs::write(cached.join("marker"), b"").unwrap();
+    cache_manager::ensure_cached(&cache, &zip, None, 0).unwrap();
+    assert!(cached.join("marker").exists());
+}
+
+#[test]
+fn test_has_valid_entry_only_for_extracted_archive() {
+    let dir = tempdir().unwrap();
+    let zip = make_archive(dir.path(), "base1_20260101_100000", b"base");
+    let cache = dir.path().join(".wbt_cache");
+    assert!(!cache_manager::has_valid_entry(&cache, &zip));
+
+    cache_manager::ensure_cached(&cache, &zip, None, 0).unwrap();
+    assert!(cache_manager::has_valid_entry(&cache, &zip));
+
+    // 展開したフォルダが消えていれば使えない
+    fs::remove_dir_all(cache.join("base1_20260101_100000")).unwrap();
+    assert!(!cache_manager::has_valid_entry(&cache, &zip));
+}
+
+#[test]
+fn test_ensure_cached_rebuilds_stale_cache() {
+    let dir = tempdir().unwrap();
+    let zip = make_archive(dir.path(), "base1_20260101_100000", b"base");
+    let cache = dir.path().join(".wbt_cache");
+    let cached = cache_manager::ensure_cached(&cache, &zip, None, 0).unwrap();
+    fs::write(cached.join("marker"), b"").unwrap();
+
+    // アーカイブを作り直す（サイズが変わる）
+    fs::remove_file(&zip).unwrap();
+    make_archive(
+        dir.path(),
+        "base1_20260101_100000",
+        b"a much longer base payload",
+    );
+
+    cache_manager::ensure_cached(&cache, &zip, None, 0).unwrap();
+    assert!(!cached.join("marker").exists());
+    assert_eq!(
+        fs::read(cached.join("work.clip.base")).unwrap(),
+        b"a much longer base payload"
+    );
+}
+
+#[test]
+fn test_ensure_cached_adopts_untracked_folder() {
+    // 索引のない旧方式のキャッシュは作り直す
+    let dir = tempdir().unwrap();
+    let zip = make_archive(dir.path(), "base1_20260101_100000", b"base");
+    let cache = dir.path().join(".wbt_cache");
+    fs::create_dir_all(cache.join("base1_20260101_100000")).unwrap();
+    fs::write(cache.join("base1_20260101_100000/stale"), b"").unwrap();
+
+    let cached = cache_manager::ensure_cached(&cache, &zip, None, 0).unwrap();
+    assert!(!cached.join("stale").exists());
+    assert!(cached.join("work.clip.base").exists());
+}
+
+// =====================================================================
+// evict_to_limit / remove_stale
+// =====================================================================
+
+#[test]
+fn test_ensure_cached_evicts_least_recently_used() {
+    let dir = tempdir().unwrap();
+    let payload = vec![b'x'; 4096];
+    let zip1 = make_archive(dir.path(), "base1_20260101_100000", &payload);
+    let zip2 = make_archive(dir.path(), "base2_20260101_110000", &payload);
+    let zip3 = make_archive(dir.path(), "base3_20260101_120000", &payload);
+    let cache = dir.path().join(".wbt_cache");
+
+    cache_manager::ensure_cached(&cache, &zip1, None, 0).unwrap();
+    cache_manager::ensure_cached(&cache, &zip2, None, 0).unwrap();
+    // base1 を最後に使った状態にする
+    let mut index = cache_manager::load_index(&cache);
+    for e in index.entries.iter_mut() {
+        e.last_access = if e.folder_name.starts_with("base1") {
+            200
+        } else {
+            100
+        };
+    }
+    cache_manager::save_index(&cache, &index).unwrap();
+
+    // 2 世代分しか入らない上限で base3 を展開 → base2 が追い出される
+    cache_manager::ensure_cached(&cache, &zip3, None, 2 * 4096 + 100).unwrap();
+
+    assert!(cache.join("base1_20260101_100000").exists());
+    assert!(!cache.join("base2_20260101_110000").exists());
+    assert!(cache.join("base3_20260101_120000").exists());
+    assert_eq!(cache_manager::load_index(&cache).entries.len(), 2);
+}
+
+#[test]
+fn test_evict_to_limit_zero_is_unlimited() {
+    let dir = tempdir().unwrap();
+    let zip = make_archive(dir.path(), "base1_20260101_100000", b"base");
+    let cache = dir.path().join(".wbt_cache");
+    cache_manager::ensure_cached(&cache, &zip, None, 0).unwrap();
+
+    assert!(cache_manager::evict_to_limit(&cache, 0).unwrap().is_empty());
+    assert_eq!(cache_manager::evict_to_limit(&cache, 1).unwrap().len(), 1);
+    assert_eq!(cache_manager::cache_size(&cache), 0);
+}
+
+#[test]
+fn test_evict_keeps_non_generation_folders() {
+    let dir = tempdir().unwrap();
+    let cache = dir.path().join(".wbt_cache");
+    fs::create_dir_all(cache.join("visual")).unwrap();
+    fs::write(cache.join("visual/abc_side.png"), b"png").unwrap();
+
+    assert!(cache_manager::evict_to_limit(&cache, 1).unwrap().is_empty());
+    assert!(cache.join("visual/abc_side.png").exists());
+}
+
+#[test]
+fn test_remove_stale_drops_missing_archive() {
+    let dir = tempdir().unwrap();
+    let zip1 = make_archive(dir.path(), "base1_20260101_100000", b"base");
+    let zip2 = make_archive(dir.path(), "base2_20260101_110000", b"base");
+    let cache = dir.path().join(".wbt_cache");
+    cache_manager::ensure_cached(&cache, &zip1, None, 0).unwrap();
+    cache_manager::ensure_cached(&cache, &zip2, None, 0).unwrap();
+
+    fs::remove_file(&zip1).unwrap();
+    let removed = cache_manager::remove_stale(&cache).unwrap();
+
+    assert_eq!(removed, vec!["base1_20260101_100000".to_string()]);
+    assert!(!cache.join("base1_20260101_100000").exists());
+    assert!(cache.join("base2_20260101_110000").exists());
+}
+
+// =====================================================================
+// list_archive_entries_cached
+// =====================================================================
+
+#[test]
+fn test_archive_listing_is_reused_until_archive_changes() {
+    let dir = tempdir().unwrap();
+    let zip = make_archive(dir.path(), "base1_20260101_100000", b"base");
+    let cache = dir.path().join(".wbt_cache");
+    let names = |cache: &std::path::Path| -> Vec<String> {
+        let mut names: Vec<String> = cache_manager::list_archive_entries_cached(cache, &zip)
+            .unwrap()
+            .into_iter()
+            .filter(|e| !e.is_dir)
+            .map(|e| e.name)
+            .collect();
+        names.sort();
+        names
+    };
+
+    let first = names(&cache);
+    assert!(first.contains(&"base1_20260101_100000/work.clip.base".to_string()));
+    let listing = cache.join(cache_manager::ARCHIVE_LISTING_FILE);
+    assert!(listing.exists());
+
+    // サイズと更新日時が同じ間は記録を使う（記録を書き換えると、その内容が返る）
+    let json = fs::read_to_string(&listing)
+        .unwrap()
+        .replace("work.clip.base", "recorded.base");
+    fs::write(&listing, json).unwrap();
+    assert!(names(&cache).contains(&"base1_20260101_100000/recorded.base".to_string()));
+
+    // アーカイブが変わったら読み直す
+    fs::remove_file(&zip).unwrap();
+    make_archive(
+        dir.path(),
+        "base1_20260101_100000",
+        b"a much longer base payload",
+    );
+    assert_eq!(names(&cache), first);
+}
+
+#[test]
+fn test_cache_limit_default_matches_bundled_config() {
+    // 上限の設定がない古い設定ファイルでも、同梱の設定と同じ上限になる（無制限にならない）
+    let mut value = serde_json::to_value(loader::default_config()).unwrap();
+    assert!(value
+        .as_object_mut()
+        .unwrap()
+        .remove("cacheMaxSizeMb")
+        .is_some());
+    let old: AppConfig = serde_json::from_value(value).unwrap();
+    assert_eq!(
+        old.cache_max_size_mb,
+        loader::default_config().cache_max_size_mb
+    );
+    assert_eq!(old.cache_max_size_mb, 2048);
+}
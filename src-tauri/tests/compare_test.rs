@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::PathBuf;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::{archive, compare};
+
+#[test]
+fn test_hash_file() {
+    let dir = tempdir().unwrap();
+    let f = dir.path().join("a.txt");
+    fs::write(&f, b"abc").unwrap();
+    assert_eq!(
+        compare::hash_file(&f).unwrap(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
+
+#[test]
+fn test_compare_folders() {
+    let dir = tempdir().unwrap();
+    let a = dir.path().join("a");
+    let b = dir.path().join("b");
+    fs::create_dir_all(a.join("pages")).unwrap();
+    fs::create_dir_all(b.join("pages")).unwrap();
+    fs::write(a.join("pages/page_01.clip"), b"same").unwrap();
+    fs::write(b.join("pages/page_01.clip"), b"same").unwrap();
+    fs::write(a.join("pages/page_02.clip"), b"old").unwrap();
+    fs::write(b.join("pages/page_02.clip"), b"newer").unwrap();
+    fs::write(a.join("memo.txt"), b"memo").unwrap();
+    fs::write(b.join("cover.png"), b"cover").unwrap();
+
+    let result = compare::compare_folders(&a, &b).unwrap();
+    assert_eq!(result.unchanged_count, 1);
+
+    assert_eq!(result.added.len(), 1);
+    assert_eq!(result.added[0].rel_path, "cover.png");
+    assert_eq!(result.added[0].size_delta, 5);
+    assert!(result.added[0].hash_a.is_none());
+
+    assert_eq!(result.removed.len(), 1);
+    assert_eq!(result.removed[0].rel_path, "memo.txt");
+    assert_eq!(result.removed[0].size_delta, -4);
+
+    assert_eq!(result.modified.len(), 1);
+    let m = &result.modified[0];
+    assert_eq!(m.rel_path, "pages/page_02.clip");
+    assert_eq!((m.size_a, m.size_b, m.size_delta), (Some(3), Some(5), 2));
+    assert_ne!(m.hash_a, m.hash_b);
+
+    assert!(compare::compare_folders(&a, &b.join("cover.png")).is_err());
+}
+
+#[test]
+fn test_compare_with_work_skips_ignored() {
+    let dir = tempdir().unwrap();
+    let backup = dir.path().join("backup");
+    let work = dir.path().join("work");
+    fs::create_dir_all(&backup).unwrap();
+    fs::create_dir_all(work.join("autosave")).unwrap();
+    fs::write(backup.join("page.clip"), b"page").unwrap();
+    fs::write(backup.join(".wbtignore"), b"autosave/\n").unwrap();
+    fs::write(work.join("page.clip"), b"page").unwrap();
+    fs::write(work.join(".wbtignore"), b"autosave/\n").unwrap();
+    fs::write(work.join("render.tmp"), b"tmp").unwrap();
+    fs::write(work.join("autosave").join("old.clip"), b"old").unwrap();
+    fs::write(work.join("cover.png"), b"cover").unwrap();
+
+    // タブのパターンと .wbtignore に一致するものは追加として出さない
+    let result = compare::compare_with_work(&backup, &work, &["*.tmp".to_string()]).unwrap();
+    assert_eq!(result.unchanged_count, 2);
+    assert!(result.removed.is_empty());
+    assert!(result.modified.is_empty());
+    let added: Vec<_> = result.added.iter().map(|c| c.rel_path.as_str()).collect();
+    assert_eq!(added, ["cover.png"]);
+
+    // パターンなしで比べると除外したものも出る
+    assert_eq!(
+        compare::compare_folders(&backup, &work)
+            .unwrap()
+            .added
+            .len(),
+        3
+    );
+}
+
+#[test]
+fn test_compare_extracted_archive_with_folder() {
+    let dir = tempdir().unwrap();
+    let proj = dir.path().join("comic");
+    fs::create_dir_all(proj.join("pages")).unwrap();
+    fs::write(proj.join("pages/page_01.clip"), b"p1").unwrap();
+    let zip = dir.path().join("comic_20260101_100000.zip");
+    archive::compress_dir_zip(&proj, &zip, "").unwrap();
+
+    fs::write(proj.join("pages/page_01.clip"), b"p1 edited").unwrap();
+    let out = dir.path().join("restored");
+    archive::extract_folder_entries(&zip, &[PathBuf::new()], &out, None).unwrap();
+
+    let result = compare::compare_folders(&out, &proj).unwrap();
+    assert!(result.added.is_empty() && result.removed.is_empty());
+    assert_eq!(result.modified.len(), 1);
+    assert_eq!(result.modified[0].size_delta, 7);
+}
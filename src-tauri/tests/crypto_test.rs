@@ -0,0 +1,199 @@
+use std::fs;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::archive::{self, CompressionOptions};
+use work_backup_tool::core::backup::crypto::{self, CryptoError};
+use work_backup_tool::core::backup::restore::{self, RestoreKind};
+
+fn roundtrip(data: &[u8]) -> Vec<u8> {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("plain.bin");
+    let enc = dir.path().join("plain.bin.enc");
+    let out = dir.path().join("out.bin");
+    fs::write(&src, data).unwrap();
+    crypto::encrypt_file(&src, &enc, "secret").unwrap();
+    assert!(crypto::is_encrypted(&enc));
+    crypto::decrypt_file(&enc, &out, "secret").unwrap();
+    fs::read(&out).unwrap()
+}
+
+#[test]
+fn test_encrypt_decrypt_roundtrip() {
+    assert_eq!(roundtrip(b""), b"");
+    assert_eq!(roundtrip(b"hello"), b"hello");
+    // チャンク境界ちょうど・複数チャンクにまたがる場合
+    let exact = vec![7u8; 64 * 1024];
+    assert_eq!(roundtrip(&exact), exact);
+    let large: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    assert_eq!(roundtrip(&large), large);
+}
+
+#[test]
+fn test_decrypt_wrong_password() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("a.txt");
+    let enc = dir.path().join("a.txt.enc");
+    fs::write(&src, b"content").unwrap();
+    crypto::encrypt_file(&src, &enc, "secret").unwrap();
+
+    let err = crypto::decrypt_file(&enc, &dir.path().join("out"), "wrong").unwrap_err();
+    assert_eq!(err, CryptoError::WrongPassword);
+    assert!(!dir.path().join("out").exists());
+}
+
+#[test]
+fn test_decrypt_detects_tampering() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("a.txt");
+    let enc = dir.path().join("a.txt.enc");
+    fs::write(&src, vec![1u8; 100_000]).unwrap();
+    crypto::encrypt_file(&src, &enc, "secret").unwrap();
+    let original = fs::read(&enc).unwrap();
+    let out = dir.path().join("out");
+
+    // 本文の書き換え
+    let mut body = original.clone();
+    let last = body.len() - 1;
+    body[last] ^= 0xFF;
+    fs::write(&enc, &body).unwrap();
+    assert!(matches!(
+        crypto::decrypt_file(&enc, &out, "secret"),
+        Err(CryptoError::Corrupted(_))
+    ));
+
+    // ヘッダ（ソルト）の書き換えはパスワード違いではなく破損として扱う
+    let mut header = original.clone();
+    header[30] ^= 0xFF;
+    fs::write(&enc, &header).unwrap();
+    assert!(matches!(
+        crypto::decrypt_file(&enc, &out, "secret"),
+        Err(CryptoError::Corrupted(_))
+    ));
+
+    // 最後のチャンクを切り落とす
+    fs::write(&enc, &original[..original.len() - 1000]).unwrap();
+    assert!(matches!(
+        crypto::decrypt_file(&enc, &out, "secret"),
+        Err(CryptoError::Corrupted(_))
+    ));
+
+    // 暗号化ファイルでないもの
+    fs::write(&enc, b"plain text").unwrap();
+    assert_eq!(
+        crypto::decrypt_file(&enc, &out, "secret"),
+        Err(CryptoError::NotEncrypted)
+    );
+}
+
+#[test]
+fn test_encrypt_in_place_and_decrypt_to_dir() {
+    let dir = tempdir().unwrap();
+    let diff = dir.path().join("work.clip.20260101_100000.hdiff.diff");
+    fs::write(&diff, b"diff data").unwrap();
+
+    let enc = crypto::encrypt_in_place(&diff, "secret").unwrap();
+    assert!(!diff.exists());
+    assert!(enc.ends_with("work.clip.20260101_100000.hdiff.diff.enc"));
+    assert_eq!(
+        restore::restore_kind_of(&enc.to_string_lossy()),
+        RestoreKind::Diff
+    );
+    // 二重には暗号化しない
+    assert!(crypto::encrypt_in_place(&enc, "secret").is_err());
+
+    let out_dir = dir.path().join("out");
+    let dec = crypto::decrypt_to_dir(&enc, "secret", &out_dir).unwrap();
+    assert_eq!(dec, out_dir.join("work.clip.20260101_100000.hdiff.diff"));
+    assert_eq!(fs::read(&dec).unwrap(), b"diff data");
+}
+
+#[test]
+fn test_stage_encrypted_diff_with_encrypted_base() {
+    let dir = tempdir().unwrap();
+    let gen = dir.path().join("base1_20260101_100000");
+    fs::create_dir_all(&gen).unwrap();
+    let base = gen.join("work.clip.base");
+    let diff = gen.join("work.clip.20260101_110000.hdiff.diff");
+    fs::write(&base, b"base data").unwrap();
+    fs::write(&diff, b"diff data").unwrap();
+    assert!(!crypto::diff_needs_decryption(&diff));
+
+    crypto::encrypt_in_place(&base, "secret").unwrap();
+    assert!(crypto::diff_needs_decryption(&diff));
+
+    let staging = dir.path().join("staging");
+    let staged = crypto::stage_encrypted_diff(&diff, "secret", &staging).unwrap();
+    assert_eq!(staged, staging.join("work.clip.20260101_110000.hdiff.diff"));
+    assert_eq!(
+        fs::read(staging.join("work.clip.base")).unwrap(),
+        b"base data"
+    );
+
+    let err = crypto::stage_encrypted_diff(&diff, "wrong", &dir.path().join("s2")).unwrap_err();
+    assert!(err.contains("パスワードが違います"));
+}
+
+#[test]
+fn test_encrypted_tar_archive_backup() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("project");
+    fs::create_dir_all(&work).unwrap();
+    fs::write(work.join("a.txt"), b"hello").unwrap();
+    let backup_dir = dir.path().join("backup");
+
+    let path = archive::execute_archive_backup(
+        &work.to_string_lossy(),
+        Some(backup_dir.clone()),
+        "tar.zst",
+        "secret",
+        &CompressionOptions::default(),
+    )
+    .unwrap();
+    assert!(path.ends_with(".tar.zst.enc"));
+    assert!(crypto::is_encrypted(std::path::Path::new(&path)));
+    assert!(archive::is_archive_name(&path));
+    assert_eq!(restore::restore_kind_of(&path), RestoreKind::Archive);
+}
+
+#[test]
+fn test_encrypted_generation_archive_extract_to_cache() {
+    let dir = tempdir().unwrap();
+    let gen_dir = dir.path().join("base1_20260101_100000");
+    fs::create_dir(&gen_dir).unwrap();
+    fs::write(gen_dir.join("work.clip.base"), b"base data").unwrap();
+    fs::write(
+        gen_dir.join("work.clip.20260101_110000.hdiff.diff"),
+        b"diff",
+    )
+    .unwrap();
+
+    archive::execute_generation_archive(
+        1,
+        "tar",
+        "/dummy/work.clip",
+        &dir.path().to_string_lossy(),
+        "secret",
+        &CompressionOptions::default(),
+    )
+    .unwrap();
+    let archived = fs::read_dir(dir.path())
+        .unwrap()
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| p.to_string_lossy().ends_with(".tar.gz.enc"))
+        .expect("encrypted generation archive");
+    let archived = archived.to_string_lossy().into_owned();
+
+    let cache_root = dir.path().join("cache");
+    assert!(archive::extract_to_cache(&archived, &cache_root, None).is_err());
+    let err = archive::extract_to_cache(&archived, &cache_root, Some("wrong".into())).unwrap_err();
+    assert!(err.contains("パスワードが違います"));
+
+    let extracted =
+        archive::extract_to_cache(&archived, &cache_root, Some("secret".into())).unwrap();
+    let base = walkdir::WalkDir::new(&extracted)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .find(|e| e.file_name() == "work.clip.base")
+        .expect("base extracted");
+    assert_eq!(fs::read(base.path()).unwrap(), b"base data");
+}
@@ -0,0 +1,158 @@
+use std::fs;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::restore::{self, RestoreKind};
+use work_backup_tool::core::backup::{dedup, scanner};
+
+/// 内容の偏りがない疑似乱数データ（チャンク境界が内容で決まることを確かめるため）
+fn pseudo_random(len: usize, seed: u64) -> Vec<u8> {
+    let mut x = seed;
+    (0..len)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x as u8
+        })
+        .collect()
+}
+
+#[test]
+fn test_dedup_backup_and_restore_file() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.clip");
+    let root = dir.path().join("wbt_backup_work");
+
+    let v1 = pseudo_random(1024 * 1024, 1);
+    fs::write(&work, &v1).unwrap();
+    let r1 = dedup::create_dedup_backup(&work.to_string_lossy(), &root, "20260101_100000").unwrap();
+    assert_eq!(r1.total_bytes, v1.len() as u64);
+    assert_eq!(r1.new_bytes, r1.total_bytes);
+    assert!(r1
+        .manifest_path
+        .ends_with("work.clip.20260101_100000.dedup"));
+
+    // 中ほどを少し書き換える: 大半のチャンクは再利用される
+    let mut v2 = v1.clone();
+    v2.splice(500_000..500_010, b"0123456789abcdefghij".iter().copied());
+    fs::write(&work, &v2).unwrap();
+    let r2 = dedup::create_dedup_backup(&work.to_string_lossy(), &root, "20260101_110000").unwrap();
+    assert!(r2.new_chunks < r2.chunk_count);
+    assert!(r2.new_bytes < r2.total_bytes / 2);
+
+    // 両方の時点を復元できる
+    let out1 = dir.path().join("out1.clip");
+    let out2 = dir.path().join("out2.clip");
+    dedup::restore_dedup(&r1.manifest_path, &out1.to_string_lossy()).unwrap();
+    dedup::restore_dedup(&r2.manifest_path, &out2.to_string_lossy()).unwrap();
+    assert_eq!(fs::read(&out1).unwrap(), v1);
+    assert_eq!(fs::read(&out2).unwrap(), v2);
+
+    assert_eq!(
+        restore::restore_kind_of(&r1.manifest_path),
+        RestoreKind::Dedup
+    );
+}
+
+#[test]
+fn test_dedup_backup_folder_keeps_empty_dirs() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("project");
+    fs::create_dir_all(work.join("sub")).unwrap();
+    fs::create_dir_all(work.join("empty")).unwrap();
+    fs::write(work.join("a.txt"), b"hello").unwrap();
+    fs::write(work.join("sub/b.txt"), b"hello").unwrap();
+    let root = dir.path().join("wbt_backup_project");
+
+    let report =
+        dedup::create_dedup_backup(&work.to_string_lossy(), &root, "20260101_100000").unwrap();
+    // 同じ内容のファイルは1チャンクだけ保存される
+    assert_eq!(report.chunk_count, 2);
+    assert_eq!(report.new_chunks, 1);
+
+    let out = dir.path().join("restored");
+    dedup::restore_dedup(&report.manifest_path, &out.to_string_lossy()).unwrap();
+    assert_eq!(fs::read(out.join("a.txt")).unwrap(), b"hello");
+    assert_eq!(fs::read(out.join("sub/b.txt")).unwrap(), b"hello");
+    assert!(out.join("empty").is_dir());
+}
+
+#[test]
+fn test_restore_dedup_detects_corrupt_chunk() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.txt");
+    fs::write(&work, b"original content").unwrap();
+    let root = dir.path().join("wbt_backup_work");
+    let report =
+        dedup::create_dedup_backup(&work.to_string_lossy(), &root, "20260101_100000").unwrap();
+
+    let manifest = dedup::load_manifest(std::path::Path::new(&report.manifest_path)).unwrap();
+    let hash = &manifest.files[0].chunks[0];
+    fs::write(dedup::chunk_path(&root, hash), b"tampered").unwrap();
+
+    let out = dir.path().join("out.txt");
+    let err = dedup::restore_dedup(&report.manifest_path, &out.to_string_lossy()).unwrap_err();
+    assert!(err.contains("壊れています"));
+}
+
+#[test]
+fn test_gc_chunks_removes_only_unreferenced() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.txt");
+    let root = dir.path().join("wbt_backup_work");
+
+    fs::write(&work, b"first version").unwrap();
+    let r1 = dedup::create_dedup_backup(&work.to_string_lossy(), &root, "20260101_100000").unwrap();
+    fs::write(&work, b"second version").unwrap();
+    let r2 = dedup::create_dedup_backup(&work.to_string_lossy(), &root, "20260101_110000").unwrap();
+
+    // 何も消していなければ削除されない
+    let report = dedup::gc_chunks(&root).unwrap();
+    assert_eq!(report.manifest_count, 2);
+    assert_eq!(report.removed_chunks, 0);
+    assert_eq!(report.kept_chunks, 2);
+
+    // 古い時点を消すと、そのチャンクだけが削除される
+    fs::remove_file(&r1.manifest_path).unwrap();
+    let report = dedup::gc_chunks(&root).unwrap();
+    assert_eq!(report.removed_chunks, 1);
+    assert_eq!(report.freed_bytes, b"first version".len() as u64);
+
+    let out = dir.path().join("out.txt");
+    dedup::restore_dedup(&r2.manifest_path, &out.to_string_lossy()).unwrap();
+    assert_eq!(fs::read(&out).unwrap(), b"second version");
+}
+
+#[test]
+fn test_gc_chunks_aborts_on_broken_manifest() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.txt");
+    let root = dir.path().join("wbt_backup_work");
+    fs::write(&work, b"content").unwrap();
+    dedup::create_dedup_backup(&work.to_string_lossy(), &root, "20260101_100000").unwrap();
+    fs::write(root.join("work.txt.20260101_110000.dedup"), b"{ broken").unwrap();
+
+    assert!(dedup::gc_chunks(&root).is_err());
+    assert_eq!(
+        fs::read_dir(root.join(dedup::CHUNK_DIR)).unwrap().count(),
+        1
+    );
+}
+
+#[test]
+fn test_scan_backups_lists_dedup_manifests() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.clip");
+    fs::write(&work, b"content").unwrap();
+    let root = dir.path().join("wbt_backup_work");
+    dedup::create_dedup_backup(&work.to_string_lossy(), &root, "20260101_100000").unwrap();
+
+    let items = scanner::scan_backups(
+        &work.to_string_lossy(),
+        &root.to_string_lossy(),
+        false,
+        true,
+    );
+    // チャンク置き場は一覧に出ない
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].file_name, "work.clip.20260101_100000.dedup");
+}
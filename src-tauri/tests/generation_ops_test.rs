@@ -0,0 +1,288 @@
+use std::fs;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::generation_ops::{self, GenerationEntry};
+use work_backup_tool::core::backup::{archive, crypto};
+
+fn make_generation(root: &std::path::Path, name: &str, diffs: &[&str]) -> std::path::PathBuf {
+    let gen = root.join(name);
+    fs::create_dir_all(&gen).unwrap();
+    fs::write(gen.join("work.clip.base"), name.as_bytes()).unwrap();
+    for d in diffs {
+        fs::write(
+            gen.join(format!("work.clip.{}.hdiff.diff", d)),
+            d.as_bytes(),
+        )
+        .unwrap();
+    }
+    gen
+}
+
+/// base1 (フォルダ) / base2 (zip) / base3 (フォルダ・最新) の構成を作る
+fn setup_tree(root: &std::path::Path) {
+    make_generation(root, "base1_20260101_100000", &["20260101_100500"]);
+    let g2 = make_generation(root, "base2_20260101_110000", &["20260101_110500"]);
+    archive::compress_dir_zip(&g2, &root.join("base2_20260101_110000.zip"), "").unwrap();
+    fs::remove_dir_all(&g2).unwrap();
+    make_generation(root, "base3_20260101_120000", &["20260101_120500"]);
+}
+
+// =====================================================================
+// find_generation_entries
+// =====================================================================
+
+#[test]
+fn test_find_generation_entries_folder_and_archive() {
+    let dir = tempdir().unwrap();
+    setup_tree(dir.path());
+
+    let g1 = generation_ops::find_generation_entries(dir.path(), 1).unwrap();
+    assert!(matches!(g1.as_slice(), [GenerationEntry::Folder(_)]));
+
+    let g2 = generation_ops::find_generation_entries(dir.path(), 2).unwrap();
+    assert!(matches!(g2.as_slice(), [GenerationEntry::Archive(_)]));
+    assert_eq!(g2[0].folder_name(), "base2_20260101_110000");
+
+    assert!(generation_ops::find_generation_entries(dir.path(), 9)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_find_generation_entries_does_not_match_prefix_number() {
+    // base1 を探すときに base10 / base11 を拾わない
+    let dir = tempdir().unwrap();
+    make_generation(dir.path(), "base10_20260101_100000", &[]);
+    make_generation(dir.path(), "base1_20260101_090000", &[]);
+
+    let g1 = generation_ops::find_generation_entries(dir.path(), 1).unwrap();
+    assert_eq!(g1.len(), 1);
+    assert_eq!(g1[0].folder_name(), "base1_20260101_090000");
+}
+
+// =====================================================================
+// delete_generation
+// =====================================================================
+
+#[test]
+fn test_delete_generation_folder_and_cache() {
+    let dir = tempdir().unwrap();
+    setup_tree(dir.path());
+    let cache = dir.path().join(".wbt_cache");
+    fs::create_dir_all(cache.join("base1_20260101_100000")).unwrap();
+
+    let removed = generation_ops::delete_generation(dir.path(), &cache, 1, false).unwrap();
+
+    assert_eq!(removed.len(), 1);
+    assert!(!dir.path().join("base1_20260101_100000").exists());
+    assert!(!cache.join("base1_20260101_100000").exists());
+}
+
+#[test]
+fn test_delete_generation_archive() {
+    let dir = tempdir().unwrap();
+    setup_tree(dir.path());
+    let cache = dir.path().join(".wbt_cache");
+    fs::create_dir_all(cache.join("base2_20260101_110000")).unwrap();
+
+    generation_ops::delete_generation(dir.path(), &cache, 2, false).unwrap();
+
+    assert!(!dir.path().join("base2_20260101_110000.zip").exists());
+    assert!(!cache.join("base2_20260101_110000").exists());
+}
+
+#[test]
+fn test_delete_generation_refuses_latest_unless_forced() {
+    let dir = tempdir().unwrap();
+    setup_tree(dir.path());
+    let cache = dir.path().join(".wbt_cache");
+
+    assert!(generation_ops::delete_generation(dir.path(), &cache, 3, false).is_err());
+    assert!(dir.path().join("base3_20260101_120000").exists());
+
+    generation_ops::delete_generation(dir.path(), &cache, 3, true).unwrap();
+    assert!(!dir.path().join("base3_20260101_120000").exists());
+}
+
+#[test]
+fn test_delete_generation_missing() {
+    let dir = tempdir().unwrap();
+    let cache = dir.path().join(".wbt_cache");
+    assert!(generation_ops::delete_generation(dir.path(), &cache, 4, true).is_err());
+}
+
+// =====================================================================
+// plan_merge / commit_merge
+// =====================================================================
+
+#[test]
+fn test_plan_merge_rejects_same_and_latest() {
+    let dir = tempdir().unwrap();
+    setup_tree(dir.path());
+    let work = dir.path().join("work.clip");
+    let w = work.to_string_lossy();
+
+    assert!(generation_ops::plan_merge(dir.path(), &w, 1, 1, false, None).is_err());
+    assert!(generation_ops::plan_merge(dir.path(), &w, 1, 3, false, None).is_err());
+}
+
+#[test]
+fn test_merge_folder_into_folder() {
+    let dir = tempdir().unwrap();
+    setup_tree(dir.path());
+    make_generation(dir.path(), "base4_20260101_130000", &[]);
+    let work = dir.path().join("work.clip");
+    let cache = dir.path().join(".wbt_cache");
+
+    let plan =
+        generation_ops::plan_merge(dir.path(), &work.to_string_lossy(), 1, 3, false, None).unwrap();
+    assert_eq!(plan.fold_diffs.len(), 1);
+    assert_eq!(plan.keep_dir, dir.path().join("base1_20260101_100000"));
+
+    // hdiffz の代わりに作り直した差分を直接置く
+    for diff in &plan.fold_diffs {
+        fs::write(plan.staged_diff(diff), b"re").unwrap();
+    }
+    fs::write(plan.fold_base_diff().unwrap(), b"re-base").unwrap();
+    generation_ops::stage_merge_extras(&plan).unwrap();
+
+    let merged = generation_ops::commit_merge(&plan, &cache, &Default::default()).unwrap();
+
+    let keep = dir.path().join("base1_20260101_100000");
+    assert_eq!(std::path::PathBuf::from(&merged), keep);
+    assert!(keep.join("work.clip.20260101_100500.hdiff.diff").exists());
+    assert!(keep.join("work.clip.20260101_120500.hdiff.diff").exists());
+    assert!(keep.join("work.clip.20260101_120000.hdiff.diff").exists());
+    assert!(!dir.path().join("base3_20260101_120000").exists());
+    assert!(!plan.workspace.exists());
+}
+
+#[test]
+fn test_merge_archived_keep_stays_archive() {
+    let dir = tempdir().unwrap();
+    setup_tree(dir.path());
+    let work = dir.path().join("work.clip");
+    let cache = dir.path().join(".wbt_cache");
+
+    let plan =
+        generation_ops::plan_merge(dir.path(), &work.to_string_lossy(), 2, 1, false, None).unwrap();
+    assert!(plan.keep_base().exists());
+
+    for diff in &plan.fold_diffs {
+        fs::write(plan.staged_diff(diff), b"re").unwrap();
+    }
+    let merged = generation_ops::commit_merge(&plan, &cache, &Default::default()).unwrap();
+
+    let zip = dir.path().join("base2_20260101_110000.zip");
+    assert_eq!(std::path::Path::new(&merged), zip);
+    assert!(!dir.path().join("base2_20260101_110000").exists());
+    assert!(!dir.path().join("base1_20260101_100000").exists());
+    assert!(!plan.workspace.exists());
+
+    let names: Vec<String> = archive::list_archive_entries(&zip)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    assert!(names.contains(&"base2_20260101_110000/work.clip.base".to_string()));
+    assert!(
+        names.contains(&"base2_20260101_110000/work.clip.20260101_100500.hdiff.diff".to_string())
+    );
+}
+
+#[test]
+fn test_merge_encrypted_keep_is_reencrypted() {
+    let dir = tempdir().unwrap();
+    make_generation(dir.path(), "base1_20260101_100000", &["20260101_100500"]);
+    let g2 = make_generation(dir.path(), "base2_20260101_110000", &["20260101_110500"]);
+    let tar = dir.path().join("base2_20260101_110000.tar.gz");
+    archive::compress_dir_tar(&g2, &tar).unwrap();
+    let enc = crypto::encrypt_in_place(&tar, "secret").unwrap();
+    fs::remove_dir_all(&g2).unwrap();
+    make_generation(dir.path(), "base3_20260101_120000", &["20260101_120500"]);
+    let work = dir.path().join("work.clip");
+    let cache = dir.path().join(".wbt_cache");
+
+    // パスワードがなければ展開できない
+    assert!(
+        generation_ops::plan_merge(dir.path(), &work.to_string_lossy(), 2, 1, false, None).is_err()
+    );
+
+    let plan = generation_ops::plan_merge(
+        dir.path(),
+        &work.to_string_lossy(),
+        2,
+        1,
+        false,
+        Some("secret".to_string()),
+    )
+    .unwrap();
+    for diff in &plan.fold_diffs {
+        fs::write(plan.staged_diff(diff), b"re").unwrap();
+    }
+    let merged = generation_ops::commit_merge(&plan, &cache, &Default::default()).unwrap();
+
+    // 平文のフォルダ・アーカイブを残さず、同じパスワードで暗号化し直す
+    assert_eq!(std::path::Path::new(&merged), enc);
+    assert!(crypto::is_encrypted(&enc));
+    assert!(!tar.exists());
+    assert!(!dir.path().join("base2_20260101_110000").exists());
+
+    let out = dir.path().join("out");
+    archive::extract_to_cache(&enc.to_string_lossy(), &out, Some("secret".to_string())).unwrap();
+    let keep = out.join("base2_20260101_110000");
+    assert!(keep.join("work.clip.base").exists());
+    assert_eq!(
+        fs::read(keep.join("work.clip.20260101_100500.hdiff.diff")).unwrap(),
+        b"re"
+    );
+}
+
+#[test]
+fn test_recover_interrupted_merge_removes_workspace() {
+    let dir = tempdir().unwrap();
+    setup_tree(dir.path());
+    let ws = dir.path().join("merge_tmp_base1_20260101_100000");
+    fs::create_dir_all(ws.join("additions")).unwrap();
+
+    generation_ops::recover_interrupted_merge(dir.path()).unwrap();
+
+    assert!(!ws.exists());
+    assert!(dir.path().join("base1_20260101_100000").exists());
+}
+
+#[test]
+fn test_recover_interrupted_merge_finishes_written_commit() {
+    let dir = tempdir().unwrap();
+    setup_tree(dir.path());
+    let zip = dir.path().join("base2_20260101_110000.zip");
+    let fold = dir.path().join("base1_20260101_100000");
+
+    // 作り直したアーカイブを置く前に中断した状態（仕上げの手順は書き終えている）
+    let ws = dir.path().join("merge_tmp_base1_20260101_100000");
+    let merged = make_generation(
+        &ws,
+        "base2_20260101_110000",
+        &["20260101_100500", "20260101_110500"],
+    );
+    let repacked = ws.join("repack").join("base2_20260101_110000.zip");
+    fs::create_dir_all(repacked.parent().unwrap()).unwrap();
+    archive::compress_dir_zip(&merged, &repacked, "").unwrap();
+    let journal = serde_json::json!({
+        "moves": [[repacked, zip]],
+        "removals": [fold],
+    });
+    fs::write(ws.join("commit.json"), journal.to_string()).unwrap();
+
+    generation_ops::recover_interrupted_merge(dir.path()).unwrap();
+
+    assert!(!ws.exists());
+    assert!(!fold.exists());
+    let names: Vec<String> = archive::list_archive_entries(&zip)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    assert!(
+        names.contains(&"base2_20260101_110000/work.clip.20260101_100500.hdiff.diff".to_string())
+    );
+}
@@ -0,0 +1,371 @@
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::{cache_manager, identity, scanner};
+use work_backup_tool::core::types::{CacheEntry, CacheIndex, RootSettings};
+use work_backup_tool::core::utils;
+
+/// `illust.clip` の既定のバックアップ先に、各モードのバックアップを1つずつ作る
+fn make_history(dir: &Path) -> std::path::PathBuf {
+    let work = dir.join("illust.clip");
+    fs::write(&work, b"clip content").unwrap();
+    let root = dir.join("wbt_backup_illust");
+    let gen = root.join("base1_20260101_100000");
+    fs::create_dir_all(&gen).unwrap();
+    fs::write(gen.join("illust.clip.base"), b"base").unwrap();
+    fs::write(gen.join("illust.clip.20260101_110000.diff"), b"diff").unwrap();
+    fs::write(gen.join("illust.clip.20260101_110000.diff.note"), b"memo").unwrap();
+    fs::write(root.join("illust_20260101_120000.clip"), b"copy").unwrap();
+    fs::write(root.join("illust_20260101_130000.zip"), b"zip").unwrap();
+    fs::write(root.join("illust.clip.20260101_140000.dedup"), b"{}").unwrap();
+    fs::write(root.join("other_20260101_120000.clip"), b"other").unwrap();
+    identity::record_backup(&root, &work, "20260101_140000").unwrap();
+    root
+}
+
+#[test]
+fn test_record_backup_and_fingerprint() {
+    let dir = tempdir().unwrap();
+    let root = make_history(dir.path());
+    let work = dir.path().join("illust.clip");
+
+    let id = identity::load_identity(&root).unwrap();
+    assert_eq!(id.id.len(), 32);
+    assert_eq!(id.work_name, "illust.clip");
+    assert_eq!(id.names, vec!["illust.clip"]);
+    assert_eq!(
+        id.fingerprint,
+        identity::content_fingerprint(&work).unwrap()
+    );
+
+    // 2回目以降も ID は変わらない
+    fs::write(&work, b"clip content v2").unwrap();
+    let again = identity::record_backup(&root, &work, "20260101_150000").unwrap();
+    assert_eq!(again.id, id.id);
+    assert_ne!(again.fingerprint, id.fingerprint);
+
+    // 同じ内容なら場所が違っても同じ指紋
+    let copy = dir.path().join("copy.clip");
+    fs::copy(&work, &copy).unwrap();
+    assert_eq!(
+        identity::content_fingerprint(&copy).unwrap(),
+        again.fingerprint
+    );
+
+    // フォルダは構成で判定する
+    let folder = dir.path().join("project");
+    fs::create_dir_all(folder.join("sub")).unwrap();
+    fs::write(folder.join("sub").join("a.txt"), b"a").unwrap();
+    let fp = identity::content_fingerprint(&folder).unwrap();
+    fs::write(folder.join("b.txt"), b"b").unwrap();
+    assert_ne!(identity::content_fingerprint(&folder).unwrap(), fp);
+}
+
+#[test]
+fn test_relink_renamed_work_file() {
+    let dir = tempdir().unwrap();
+    let root = make_history(dir.path());
+    let old_work = dir.path().join("illust.clip");
+    let new_work = dir.path().join("illust_final.clip");
+    fs::rename(&old_work, &new_work).unwrap();
+
+    let candidates = identity::find_relink_candidates(&new_work, &[dir.path().to_path_buf()]);
+    assert_eq!(candidates.len(), 1);
+    assert!(candidates[0].exact);
+    assert_eq!(candidates[0].work_name, "illust.clip");
+
+    let report = identity::relink(&root, &new_work, true, "20260102_100000").unwrap();
+    let new_root = dir.path().join("wbt_backup_illust_final");
+    assert!(report.is_default_dir);
+    assert!(report.moved_root);
+    assert_eq!(report.warning, None);
+    assert_eq!(Path::new(&report.backup_dir), new_root);
+    assert_eq!(report.renamed, 6);
+    assert_eq!(report.previous_path, old_work.to_string_lossy());
+    assert!(!root.exists());
+
+    let gen = new_root.join("base1_20260101_100000");
+    assert!(gen.join("illust_final.clip.base").exists());
+    assert!(gen.join("illust_final.clip.20260101_110000.diff").exists());
+    assert!(gen
+        .join("illust_final.clip.20260101_110000.diff.note")
+        .exists());
+    assert!(new_root.join("illust_final_20260101_120000.clip").exists());
+    assert!(new_root.join("illust_final_20260101_130000.zip").exists());
+    assert!(new_root
+        .join("illust_final.clip.20260101_140000.dedup")
+        .exists());
+    // 別の作業ファイルのものはそのまま
+    assert!(new_root.join("other_20260101_120000.clip").exists());
+
+    let id = identity::load_identity(&new_root).unwrap();
+    assert_eq!(id.work_name, "illust_final.clip");
+    assert_eq!(id.names, vec!["illust.clip", "illust_final.clip"]);
+
+    // 付け替えた後は候補に出ない
+    assert!(identity::find_relink_candidates(&new_work, &[dir.path().to_path_buf()]).is_empty());
+
+    // 厳密な名前の一致でも、以前の名前の差分が一覧に出る
+    let old_name_diff = gen.join("illust.clip.20260101_120000.diff");
+    fs::write(&old_name_diff, b"diff").unwrap();
+    let items = scanner::scan_backups(&new_work.to_string_lossy(), "", true, true);
+    let names: Vec<&str> = items.iter().map(|i| i.file_name.as_str()).collect();
+    assert!(
+        names.contains(&"illust.clip.20260101_120000.diff"),
+        "{:?}",
+        names
+    );
+    assert!(
+        names.contains(&"illust_final.clip.20260101_110000.diff"),
+        "{:?}",
+        names
+    );
+}
+
+#[test]
+fn test_relink_keeps_root_when_default_dir_is_taken() {
+    let dir = tempdir().unwrap();
+    let root = make_history(dir.path());
+    let new_work = dir.path().join("illust_final.clip");
+    fs::rename(dir.path().join("illust.clip"), &new_work).unwrap();
+    let taken = dir.path().join("wbt_backup_illust_final");
+    fs::create_dir_all(&taken).unwrap();
+
+    let report = identity::relink(&root, &new_work, true, "20260102_100000").unwrap();
+    assert!(!report.moved_root);
+    assert!(!report.is_default_dir);
+    assert_eq!(Path::new(&report.backup_dir), root);
+    let warning = report.warning.unwrap();
+    assert!(warning.contains("元の場所のまま"), "{}", warning);
+    assert!(root
+        .join("base1_20260101_100000")
+        .join("illust_final.clip.base")
+        .exists());
+}
+
+#[test]
+fn test_relink_candidates_for_moved_and_edited_file() {
+    let dir = tempdir().unwrap();
+    let root = make_history(dir.path());
+    let old_work = dir.path().join("illust.clip");
+
+    // 元のファイルが残っていて内容も違うものは候補にしない
+    let other = dir.path().join("moved");
+    fs::create_dir_all(&other).unwrap();
+    let new_work = other.join("illust.clip");
+    fs::write(&new_work, b"edited").unwrap();
+    let search = [dir.path().to_path_buf()];
+    assert!(identity::find_relink_candidates(&new_work, &search).is_empty());
+
+    // 元のファイルがなくなっていれば、内容が違っても拡張子が同じなら候補にする
+    fs::remove_file(&old_work).unwrap();
+    let candidates = identity::find_relink_candidates(&new_work, &search);
+    assert_eq!(candidates.len(), 1);
+    assert!(!candidates[0].exact);
+    let psd = other.join("illust.psd");
+    fs::write(&psd, b"edited").unwrap();
+    assert!(identity::find_relink_candidates(&psd, &search).is_empty());
+
+    // 別のフォルダへ移した場合、名前が同じなら付け替える名前はない
+    let report = identity::relink(&root, &new_work, true, "20260102_100000").unwrap();
+    assert_eq!(report.renamed, 0);
+    assert!(report.is_default_dir);
+    assert!(other
+        .join("wbt_backup_illust")
+        .join(identity::IDENTITY_FILE)
+        .exists());
+}
+
+#[test]
+fn test_relink_migrates_temp_cache() {
+    let dir = tempdir().unwrap();
+    let root = make_history(dir.path());
+    let old_work = dir.path().join("illust.clip");
+    let new_work = dir.path().join("renamed.clip");
+
+    let old_cache = utils::get_cache_root(false, "", &old_work.to_string_lossy());
+    let archive = root.join("base1_20260101_100000.zip");
+    fs::write(&archive, b"zip").unwrap();
+    fs::create_dir_all(old_cache.join("base1_20260101_100000")).unwrap();
+    cache_manager::save_index(
+        &old_cache,
+        &CacheIndex {
+            entries: vec![CacheEntry {
+                folder_name: "base1_20260101_100000".to_string(),
+                archive_path: archive.to_string_lossy().into_owned(),
+                archive_size: 3,
+                archive_mtime: 0,
+                size: 0,
+                last_access: 0,
+            }],
+        },
+    )
+    .unwrap();
+
+    fs::rename(&old_work, &new_work).unwrap();
+    let report = identity::relink(&root, &new_work, false, "20260102_100000").unwrap();
+    let new_cache = utils::get_cache_root(false, "", &new_work.to_string_lossy());
+    assert!(!old_cache.exists());
+    assert!(new_cache.join("base1_20260101_100000").is_dir());
+    let index = cache_manager::load_index(&new_cache);
+    assert_eq!(
+        Path::new(&index.entries[0].archive_path),
+        Path::new(&report.backup_dir).join("base1_20260101_100000.zip")
+    );
+    fs::remove_dir_all(&new_cache).unwrap();
+}
+
+#[test]
+fn test_open_relocated_root_with_relative_reference() {
+    let dir = tempdir().unwrap();
+    let root = make_history(dir.path());
+    let settings = RootSettings {
+        backup_mode: "archive".to_string(),
+        archive_format: "7z".to_string(),
+        hdiff_ignore_list: vec!["*.tmp".to_string()],
+        ..Default::default()
+    };
+    identity::save_root_settings(&root, &settings).unwrap();
+    let id = identity::load_identity(&root).unwrap();
+    assert_eq!(id.version, identity::IDENTITY_VERSION);
+    assert_eq!(id.work_rel, "../illust.clip");
+
+    // 同じ場所に置いたキャッシュの索引
+    let archive = root.join("base1_20260101_100000.zip");
+    fs::write(&archive, b"zip").unwrap();
+    let cache = utils::get_cache_root(true, &root.to_string_lossy(), "");
+    save_cache_entry(&cache, &archive);
+
+    // 作業ファイルとルートをまとめて別のドライブへ移したつもり
+    let moved = dir.path().join("drive_d").join("project");
+    fs::create_dir_all(&moved).unwrap();
+    fs::rename(dir.path().join("illust.clip"), moved.join("illust.clip")).unwrap();
+    let new_root = moved.join("wbt_backup_illust");
+    fs::rename(&root, &new_root).unwrap();
+
+    let opened = identity::open_root(&new_root, true, "20260102_100000").unwrap();
+    assert_eq!(Path::new(&opened.work_file), moved.join("illust.clip"));
+    assert_eq!(opened.backup_dir, "");
+    assert!(!opened.work_missing);
+    assert!(opened.relocated);
+    assert_eq!(opened.settings, settings);
+
+    let index = cache_manager::load_index(&new_root.join(".wbt_cache"));
+    assert_eq!(
+        Path::new(&index.entries[0].archive_path),
+        new_root
+            .canonicalize()
+            .unwrap()
+            .join("base1_20260101_100000.zip")
+    );
+    let id = identity::load_identity(&new_root).unwrap();
+    assert_eq!(id.work_path, opened.work_file);
+
+    // 2回目は移動していない
+    let again = identity::open_root(&new_root, true, "20260102_110000").unwrap();
+    assert!(!again.relocated);
+
+    // アーカイブからルートを辿れる
+    assert_eq!(
+        identity::find_root(&new_root.join("base1_20260101_100000.zip")).unwrap(),
+        new_root
+    );
+}
+
+#[test]
+fn test_open_root_with_missing_work_and_custom_location() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work").join("cover.psd");
+    fs::create_dir_all(work.parent().unwrap()).unwrap();
+    fs::write(&work, b"psd").unwrap();
+    let root = dir.path().join("backups").join("cover");
+    identity::record_backup(&root, &work, "20260101_100000").unwrap();
+
+    // 既定以外の場所はそのまま返す
+    let opened = identity::open_root(&root, true, "20260101_110000").unwrap();
+    assert_eq!(Path::new(&opened.backup_dir), root);
+    assert!(!opened.work_missing);
+
+    // 作業ファイルがなくても、復元用に開ける
+    fs::remove_file(&work).unwrap();
+    let opened = identity::open_root(&root, true, "20260101_120000").unwrap();
+    assert!(opened.work_missing);
+    assert_eq!(
+        Path::new(&opened.work_file),
+        work.canonicalize().unwrap_or(work.clone())
+    );
+
+    // 記述ファイルがなく、名前からも辿れないフォルダは開けない
+    let plain = dir.path().join("plain");
+    fs::create_dir_all(&plain).unwrap();
+    assert!(identity::open_root(&plain, true, "20260101_130000").is_err());
+}
+
+#[test]
+fn test_open_legacy_root_and_move_temp_cache() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("scene.blend");
+    fs::write(&work, b"blend").unwrap();
+    let root = dir.path().join("wbt_backup_scene");
+    fs::create_dir_all(&root).unwrap();
+
+    // 記述ファイルのない古いルートは、隣の作業ファイルから記述ファイルを作る
+    let opened = identity::open_root(&root, false, "20260101_100000").unwrap();
+    assert_eq!(
+        Path::new(&opened.work_file),
+        dir.path().canonicalize().unwrap().join("scene.blend")
+    );
+    assert_eq!(opened.backup_dir, "");
+    assert!(root.join(identity::IDENTITY_FILE).exists());
+
+    // 一時フォルダのキャッシュは作業ファイルのパスをキーにする（記述ファイルがあっても変わらない）
+    let old_cache = utils::get_cache_root(false, "", &opened.work_file);
+    let archive = root.join("base1_20260101_100000.zip");
+    fs::write(&archive, b"zip").unwrap();
+    save_cache_entry(&old_cache, &archive);
+
+    // 作業ファイルとルートをまとめて移すと、キャッシュも新しいキーへ移る
+    let moved = dir.path().join("moved");
+    fs::create_dir_all(&moved).unwrap();
+    fs::rename(&work, moved.join("scene.blend")).unwrap();
+    let new_root = moved.join("wbt_backup_scene");
+    fs::rename(&root, &new_root).unwrap();
+
+    let opened = identity::open_root(&new_root, false, "20260102_100000").unwrap();
+    assert!(opened.relocated);
+    let new_cache = utils::get_cache_root(false, "", &opened.work_file);
+    assert_ne!(new_cache, old_cache);
+    assert!(!old_cache.exists());
+    assert!(new_cache.join("base1_20260101_100000").is_dir());
+    let index = cache_manager::load_index(&new_cache);
+    assert_eq!(
+        Path::new(&index.entries[0].archive_path),
+        new_root
+            .canonicalize()
+            .unwrap()
+            .join("base1_20260101_100000.zip")
+    );
+    fs::remove_dir_all(&new_cache).unwrap();
+}
+
+fn save_cache_entry(cache: &Path, archive: &Path) {
+    fs::create_dir_all(cache.join("base1_20260101_100000")).unwrap();
+    cache_manager::save_index(
+        cache,
+        &CacheIndex {
+            entries: vec![CacheEntry {
+                folder_name: "base1_20260101_100000".to_string(),
+                archive_path: archive
+                    .canonicalize()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned(),
+                archive_size: 3,
+                archive_mtime: 0,
+                size: 0,
+                last_access: 0,
+            }],
+        },
+    )
+    .unwrap();
+}
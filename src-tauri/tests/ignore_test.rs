@@ -0,0 +1,235 @@
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::archive::{self, CompressionOptions};
+use work_backup_tool::core::backup::{dedup, workflow};
+use work_backup_tool::core::ignore::{self, IgnoreMatcher};
+use work_backup_tool::core::utils;
+
+fn patterns(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+/// 除外の確認用の作業フォルダ
+fn make_work(root: &Path) -> std::path::PathBuf {
+    let work = root.join("project");
+    fs::create_dir_all(work.join("src")).unwrap();
+    fs::create_dir_all(work.join("autosave")).unwrap();
+    fs::write(work.join("main.txt"), b"main").unwrap();
+    fs::write(work.join("src").join("a.txt"), b"aaa").unwrap();
+    fs::write(work.join("src").join(".DS_Store"), b"junk").unwrap();
+    fs::write(work.join("autosave").join("old.txt"), b"autosave").unwrap();
+    fs::write(work.join("render.tmp"), b"tmp").unwrap();
+    work
+}
+
+#[test]
+fn test_ignore_matcher_patterns() {
+    let m = IgnoreMatcher::new(&[
+        "# comment",
+        "",
+        "*.tmp",
+        "!keep.tmp",
+        "/build",
+        "cache/",
+        "docs/**/*.bak",
+        "a?c.txt",
+        "[0-9]*.log",
+    ]);
+    // 名前だけのパターンはどの階層にも一致する
+    assert!(m.is_ignored(Path::new("x.tmp"), false));
+    assert!(m.is_ignored(Path::new("sub/deep/x.tmp"), false));
+    // 取り消し
+    assert!(!m.is_ignored(Path::new("sub/keep.tmp"), false));
+    // 先頭の / はフォルダ直下だけ
+    assert!(m.is_ignored(Path::new("build"), true));
+    assert!(!m.is_ignored(Path::new("sub/build"), true));
+    // 除外したフォルダの中身も除外
+    assert!(m.is_ignored(Path::new("build/out/app.bin"), false));
+    // / で終わるパターンはフォルダだけ
+    assert!(m.is_ignored(Path::new("sub/cache"), true));
+    assert!(!m.is_ignored(Path::new("sub/cache"), false));
+    assert!(m.is_ignored(Path::new("sub/cache/x.bin"), false));
+    // ** はフォルダをまたぐ
+    assert!(m.is_ignored(Path::new("docs/x.bak"), false));
+    assert!(m.is_ignored(Path::new("docs/a/b/x.bak"), false));
+    assert!(!m.is_ignored(Path::new("other/x.bak"), false));
+    // ? と [..]
+    assert!(m.is_ignored(Path::new("abc.txt"), false));
+    assert!(!m.is_ignored(Path::new("abbc.txt"), false));
+    assert!(m.is_ignored(Path::new("1.log"), false));
+    assert!(!m.is_ignored(Path::new("a.log"), false));
+    // * はパス区切りをまたがない
+    assert!(!IgnoreMatcher::new(&["/*.txt"]).is_ignored(Path::new("src/a.txt"), false));
+
+    assert!(IgnoreMatcher::default().is_empty());
+    assert!(!IgnoreMatcher::default().is_ignored(Path::new("x.tmp"), false));
+}
+
+#[test]
+fn test_wbtignore_and_hdiff_patterns() {
+    let dir = tempdir().unwrap();
+    let work = make_work(dir.path());
+    fs::write(work.join(ignore::IGNORE_FILE), "# local\nautosave/\n").unwrap();
+
+    let m = IgnoreMatcher::for_folder(&work, &patterns(&["*.tmp"]));
+    assert!(m.is_ignored(Path::new("autosave"), true));
+    assert!(m.is_ignored(Path::new("render.tmp"), false));
+    assert!(!m.is_ignored(Path::new("main.txt"), false));
+
+    // ファイルの場合は .wbtignore を読まない
+    assert!(ignore::read_ignore_file(&work.join("main.txt")).is_empty());
+
+    assert_eq!(
+        ignore::to_hdiff_patterns(&patterns(&[
+            "# c",
+            "!keep.tmp",
+            "autosave/",
+            "/build",
+            "*.tmp"
+        ])),
+        patterns(&["autosave", "build", "*.tmp"])
+    );
+
+    // 取り消し・フォルダ直下からのパス・文字クラスは -g では表せない
+    assert!(ignore::hdiff_can_express(&patterns(&[
+        "# c",
+        "autosave/",
+        "*.tmp",
+        "a?c"
+    ])));
+    assert!(!ignore::hdiff_can_express(&patterns(&[
+        "*.tmp",
+        "!keep.tmp"
+    ])));
+    assert!(!ignore::hdiff_can_express(&patterns(&["/build"])));
+    assert!(!ignore::hdiff_can_express(&patterns(&["docs/*.bak"])));
+    assert!(!ignore::hdiff_can_express(&patterns(&["[0-9]*.log"])));
+}
+
+#[test]
+fn test_stage_filtered_work_for_inexpressible_patterns() {
+    let dir = tempdir().unwrap();
+    let work = make_work(dir.path());
+    let work_str = work.to_string_lossy().into_owned();
+
+    // -g で表せるなら作業フォルダをそのまま使う
+    let staged =
+        workflow::stage_filtered_work(&work_str, "20260101_100000", &patterns(&["*.tmp"])).unwrap();
+    assert!(staged.is_none());
+
+    // 表せない場合は除外したもの抜きのコピーを作る
+    fs::write(work.join("keep.tmp"), b"keep").unwrap();
+    fs::write(work.join(ignore::IGNORE_FILE), "/autosave\n!keep.tmp\n").unwrap();
+    let stage = workflow::stage_filtered_work(&work_str, "20260101_100001", &patterns(&["*.tmp"]))
+        .unwrap()
+        .unwrap();
+    assert!(stage.join("main.txt").exists());
+    assert!(stage.join("src").join("a.txt").exists());
+    assert!(stage.join("keep.tmp").exists());
+    assert!(!stage.join("render.tmp").exists());
+    assert!(!stage.join("autosave").exists());
+    fs::remove_dir_all(&stage).unwrap();
+
+    // ファイルは対象外
+    let file = work.join("main.txt").to_string_lossy().into_owned();
+    assert!(
+        workflow::stage_filtered_work(&file, "20260101_100002", &patterns(&["!x"]))
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn test_copy_backup_skips_ignored() {
+    let dir = tempdir().unwrap();
+    let work = make_work(dir.path());
+    fs::write(work.join(ignore::IGNORE_FILE), ".DS_Store\n").unwrap();
+    let backup_dir = dir.path().join("backup");
+
+    let dst = workflow::execute_copy_backup_with(
+        &work.to_string_lossy(),
+        Some(backup_dir),
+        false,
+        &patterns(&["*.tmp", "autosave/"]),
+    )
+    .unwrap();
+    let dst = Path::new(&dst);
+    assert!(dst.join("main.txt").exists());
+    assert!(dst.join("src").join("a.txt").exists());
+    assert!(!dst.join("src").join(".DS_Store").exists());
+    assert!(!dst.join("autosave").exists());
+    assert!(!dst.join("render.tmp").exists());
+}
+
+#[test]
+fn test_archive_backup_skips_ignored() {
+    for format in ["zip", "tar.gz"] {
+        let dir = tempdir().unwrap();
+        let work = make_work(dir.path());
+        let backup_dir = dir.path().join("backup");
+        let opts = CompressionOptions {
+            ignore_patterns: patterns(&["*.tmp", "autosave/", ".DS_Store"]),
+            ..Default::default()
+        };
+        let dst = archive::execute_archive_backup(
+            &work.to_string_lossy(),
+            Some(backup_dir),
+            format,
+            "",
+            &opts,
+        )
+        .unwrap();
+
+        let files = archive::list_folder_archive_files(Path::new(&dst)).unwrap();
+        let mut names: Vec<String> = files
+            .iter()
+            .map(|(name, _)| name.replace('\\', "/"))
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["main.txt", "src/a.txt"], "{}", format);
+    }
+}
+
+#[test]
+fn test_dedup_backup_skips_ignored() {
+    let dir = tempdir().unwrap();
+    let work = make_work(dir.path());
+    let root = dir.path().join("backup");
+    let report = dedup::create_dedup_backup_with(
+        &work.to_string_lossy(),
+        &root,
+        "20260101_100000",
+        &patterns(&["*.tmp", "autosave/", ".DS_Store"]),
+    )
+    .unwrap();
+
+    let out = dir.path().join("restored");
+    dedup::restore_dedup(&report.manifest_path, &out.to_string_lossy()).unwrap();
+    assert!(out.join("main.txt").exists());
+    assert!(out.join("src").join("a.txt").exists());
+    assert!(!out.join("src").join(".DS_Store").exists());
+    assert!(!out.join("autosave").exists());
+    assert!(!out.join("render.tmp").exists());
+}
+
+#[test]
+fn test_work_size_excludes_ignored() {
+    let dir = tempdir().unwrap();
+    let work = make_work(dir.path());
+    let path = work.to_string_lossy();
+
+    // main(4) + a(3) + .DS_Store(4) + autosave(8) + tmp(3)
+    assert_eq!(utils::get_file_size(&path).unwrap(), 22);
+    assert_eq!(
+        utils::get_work_size(&path, &patterns(&["*.tmp", "autosave/"])).unwrap(),
+        11
+    );
+
+    // .wbtignore 自体もサイズに入る
+    fs::write(work.join(ignore::IGNORE_FILE), ".DS_Store\n").unwrap();
+    assert_eq!(
+        utils::get_work_size(&path, &patterns(&["*.tmp", "autosave/"])).unwrap(),
+        7 + 10
+    );
+}
@@ -0,0 +1,198 @@
+use std::fs;
+use std::time::{Duration, SystemTime};
+use tempfile::tempdir;
+use work_backup_tool::core::backup::import;
+use work_backup_tool::core::utils;
+
+fn write_with_mtime(path: &std::path::Path, data: &[u8], secs_ago: u64) {
+    fs::write(path, data).unwrap();
+    let t = SystemTime::now() - Duration::from_secs(secs_ago);
+    utils::set_modified_time(path, t).unwrap();
+}
+
+// =====================================================================
+// wildcard_to_regex
+// =====================================================================
+
+#[test]
+fn test_wildcard_to_regex_star() {
+    let re = import::wildcard_to_regex("illust_final_v*.clip").unwrap();
+    assert!(re.is_match("illust_final_v3.clip"));
+    assert!(re.is_match("illust_final_v12.clip"));
+    assert!(!re.is_match("illust_final_v3.psd"));
+}
+
+#[test]
+fn test_wildcard_to_regex_question_and_escape() {
+    // "." はリテラルとして扱われ、"?" は1文字に一致する
+    let re = import::wildcard_to_regex("v?.clip").unwrap();
+    assert!(re.is_match("v1.clip"));
+    assert!(!re.is_match("v1xclip"));
+    assert!(!re.is_match("v10.clip"));
+}
+
+// =====================================================================
+// collect_import_sources
+// =====================================================================
+
+#[test]
+fn test_collect_import_sources_sorted_by_mtime() {
+    let dir = tempdir().unwrap();
+    write_with_mtime(&dir.path().join("a_v2.clip"), b"2", 100);
+    write_with_mtime(&dir.path().join("a_v1.clip"), b"1", 300);
+    write_with_mtime(&dir.path().join("a_v3.clip"), b"3", 10);
+
+    let sources =
+        import::collect_import_sources(&[], Some(&dir.path().to_string_lossy()), Some("a_v*.clip"))
+            .unwrap();
+
+    let names: Vec<_> = sources
+        .iter()
+        .map(|s| s.path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, vec!["a_v1.clip", "a_v2.clip", "a_v3.clip"]);
+}
+
+#[test]
+fn test_collect_import_sources_pattern_filters() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("a_v1.clip"), b"1").unwrap();
+    fs::write(dir.path().join("notes.txt"), b"x").unwrap();
+    fs::create_dir(dir.path().join("wbt_backup_a")).unwrap();
+
+    let sources =
+        import::collect_import_sources(&[], Some(&dir.path().to_string_lossy()), Some("*.clip"))
+            .unwrap();
+    assert_eq!(sources.len(), 1);
+
+    // パターン省略時も既存のバックアップルートは除外される
+    let all =
+        import::collect_import_sources(&[], Some(&dir.path().to_string_lossy()), None).unwrap();
+    assert_eq!(all.len(), 2);
+}
+
+#[test]
+fn test_collect_import_sources_unique_timestamps() {
+    // 同じ秒のファイルはタイムスタンプが1秒ずつずらされる
+    let dir = tempdir().unwrap();
+    let t = SystemTime::now() - Duration::from_secs(60);
+    for name in ["x1.clip", "x2.clip", "x3.clip"] {
+        let p = dir.path().join(name);
+        fs::write(&p, b"d").unwrap();
+        utils::set_modified_time(&p, t).unwrap();
+    }
+    let files: Vec<String> = ["x1.clip", "x2.clip", "x3.clip"]
+        .iter()
+        .map(|n| dir.path().join(n).to_string_lossy().into_owned())
+        .collect();
+
+    let sources = import::collect_import_sources(&files, None, None).unwrap();
+    assert_eq!(sources.len(), 3);
+    assert!(sources[0].ts < sources[1].ts);
+    assert!(sources[1].ts < sources[2].ts);
+}
+
+#[test]
+fn test_collect_import_sources_missing_file() {
+    let result = import::collect_import_sources(&["/no/such/file.clip".to_string()], None, None);
+    assert!(result.is_err());
+}
+
+// =====================================================================
+// stage_import_source / seed_import_generation / backdate_import_entry
+// =====================================================================
+
+#[test]
+fn test_stage_import_source_uses_work_name() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("illust_final_v3.clip");
+    fs::write(&src, b"v3").unwrap();
+    let staging = dir.path().join("staging");
+    fs::create_dir(&staging).unwrap();
+
+    let sources =
+        import::collect_import_sources(&[src.to_string_lossy().into_owned()], None, None).unwrap();
+    let work = dir.path().join("illust.clip");
+    let staged =
+        import::stage_import_source(&sources[0], &staging, &work.to_string_lossy()).unwrap();
+
+    assert_eq!(staged, staging.join("illust.clip"));
+    assert_eq!(fs::read(&staged).unwrap(), b"v3");
+}
+
+#[test]
+fn test_seed_import_generation_backdated() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("illust_v1.clip");
+    write_with_mtime(&src, b"v1", 3600);
+    let staging = dir.path().join("staging");
+    fs::create_dir(&staging).unwrap();
+    let root = dir.path().join("wbt_backup_illust");
+
+    let sources =
+        import::collect_import_sources(&[src.to_string_lossy().into_owned()], None, None).unwrap();
+    let work = dir.path().join("illust.clip");
+    let staged =
+        import::stage_import_source(&sources[0], &staging, &work.to_string_lossy()).unwrap();
+    let target = import::seed_import_generation(&root, &staged, &sources[0]).unwrap();
+
+    assert_eq!(target.current_idx, 1);
+    let gen_name = target
+        .target_dir
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+    assert_eq!(gen_name, format!("base1_{}", sources[0].ts));
+
+    let base = target.target_dir.join("illust.clip.base");
+    assert_eq!(fs::read(&base).unwrap(), b"v1");
+    let mtime = fs::metadata(&base).unwrap().modified().unwrap();
+    let diff = mtime
+        .duration_since(sources[0].modified)
+        .unwrap_or_else(|e| e.duration());
+    assert!(diff < Duration::from_secs(1));
+}
+
+#[test]
+fn test_seed_import_generation_appends_after_existing() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("wbt_backup_illust");
+    fs::create_dir_all(root.join("base2_20260101_100000")).unwrap();
+
+    let src = dir.path().join("illust_v1.clip");
+    fs::write(&src, b"v1").unwrap();
+    let staging = dir.path().join("staging");
+    fs::create_dir(&staging).unwrap();
+
+    let sources =
+        import::collect_import_sources(&[src.to_string_lossy().into_owned()], None, None).unwrap();
+    let staged = import::stage_import_source(
+        &sources[0],
+        &staging,
+        &dir.path().join("illust.clip").to_string_lossy(),
+    )
+    .unwrap();
+    let target = import::seed_import_generation(&root, &staged, &sources[0]).unwrap();
+
+    assert_eq!(target.current_idx, 3);
+}
+
+#[test]
+fn test_backdate_import_entry_rotated() {
+    let dir = tempdir().unwrap();
+    let gen = dir.path().join("base2_20250101_100000");
+    fs::create_dir(&gen).unwrap();
+    let diff = gen.join("illust.clip.20250101_100000.hdiff.diff");
+    let base = gen.join("illust.clip.base");
+    fs::write(&diff, b"d").unwrap();
+    fs::write(&base, b"b").unwrap();
+
+    let t = SystemTime::now() - Duration::from_secs(86400);
+    import::backdate_import_entry(&diff, &dir.path().join("illust.clip"), t, true).unwrap();
+
+    for p in [&diff, &base] {
+        let m = fs::metadata(p).unwrap().modified().unwrap();
+        assert!(m <= t + Duration::from_secs(1));
+    }
+}
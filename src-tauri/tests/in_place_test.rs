@@ -0,0 +1,116 @@
+use std::fs;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::in_place;
+use work_backup_tool::core::types::UndoRecord;
+
+// =====================================================================
+// take_safety_snapshot / replace_work_target
+// =====================================================================
+
+#[test]
+fn test_take_safety_snapshot_file() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.clip");
+    fs::write(&work, b"current").unwrap();
+    let root = dir.path().join("wbt_backup_work");
+
+    let snap = in_place::take_safety_snapshot(&work, &root, "20260101_120000").unwrap();
+
+    assert_eq!(snap, root.join(".wbt_undo/20260101_120000/work.clip"));
+    assert_eq!(fs::read(&snap).unwrap(), b"current");
+}
+
+#[test]
+fn test_take_safety_snapshot_missing_work() {
+    let dir = tempdir().unwrap();
+    let result = in_place::take_safety_snapshot(
+        &dir.path().join("none.clip"),
+        dir.path(),
+        "20260101_120000",
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_replace_work_target_file() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.clip");
+    fs::write(&work, b"current").unwrap();
+    let staging = in_place::staging_output_path(&work, "20260101_120000").unwrap();
+    fs::write(&staging, b"restored").unwrap();
+
+    in_place::replace_work_target(&work, &staging, "20260101_120000").unwrap();
+
+    assert_eq!(fs::read(&work).unwrap(), b"restored");
+    assert!(!staging.exists());
+}
+
+#[test]
+fn test_replace_work_target_folder() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("world");
+    fs::create_dir_all(work.join("old_only")).unwrap();
+    fs::write(work.join("page.txt"), b"current").unwrap();
+    let staging = in_place::staging_output_path(&work, "20260101_120000").unwrap();
+    fs::create_dir_all(&staging).unwrap();
+    fs::write(staging.join("page.txt"), b"restored").unwrap();
+
+    in_place::replace_work_target(&work, &staging, "20260101_120000").unwrap();
+
+    assert_eq!(fs::read(work.join("page.txt")).unwrap(), b"restored");
+    assert!(!work.join("old_only").exists());
+    assert!(!staging.exists());
+    // 退避した旧フォルダは残らない
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_replace_work_target_missing_restored_keeps_work() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.clip");
+    fs::write(&work, b"current").unwrap();
+
+    let result = in_place::replace_work_target(&work, &dir.path().join("none"), "ts");
+    assert!(result.is_err());
+    assert_eq!(fs::read(&work).unwrap(), b"current");
+}
+
+// =====================================================================
+// record_undo / undo_last
+// =====================================================================
+
+#[test]
+fn test_undo_last_restores_snapshot() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.clip");
+    let root = dir.path().join("wbt_backup_work");
+    fs::write(&work, b"before").unwrap();
+
+    let snap = in_place::take_safety_snapshot(&work, &root, "20260101_120000").unwrap();
+    fs::write(&work, b"restored").unwrap();
+    in_place::record_undo(
+        &root,
+        UndoRecord {
+            id: "20260101_120000".to_string(),
+            work_path: work.to_string_lossy().into_owned(),
+            snapshot_path: snap.to_string_lossy().into_owned(),
+            restored_from: "x.diff".to_string(),
+        },
+    )
+    .unwrap();
+
+    let undone = in_place::undo_last(&root, &work, "20260101_120100").unwrap();
+
+    assert_eq!(undone.id, "20260101_120000");
+    assert_eq!(fs::read(&work).unwrap(), b"before");
+    assert!(in_place::load_undo_log(&root).is_empty());
+    assert!(!snap.exists());
+}
+
+#[test]
+fn test_undo_last_without_record() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.clip");
+    fs::write(&work, b"x").unwrap();
+    assert!(in_place::undo_last(dir.path(), &work, "ts").is_err());
+}
@@ -0,0 +1,201 @@
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::{identity, mirror};
+
+/// 差分の世代・メモ・フルコピー・アーカイブと、写さないキャッシュ類のあるバックアップ先
+fn make_root(dir: &Path) -> std::path::PathBuf {
+    let root = dir.join("wbt_backup_illust");
+    let gen = root.join("base1_20260101_100000");
+    fs::create_dir_all(&gen).unwrap();
+    fs::write(gen.join("illust.clip.base"), b"base").unwrap();
+    fs::write(gen.join("illust.clip.20260101_110000.diff"), b"diff").unwrap();
+    fs::write(gen.join("illust.clip.20260101_110000.diff.note"), b"memo").unwrap();
+    fs::write(root.join("illust_20260101_120000.clip"), b"copy").unwrap();
+    fs::write(root.join("illust_20260101_130000.zip"), b"zip").unwrap();
+    fs::write(root.join(identity::IDENTITY_FILE), b"{}").unwrap();
+    fs::create_dir_all(root.join(".wbt_cache").join("base1")).unwrap();
+    fs::write(root.join(".wbt_cache").join("base1").join("x"), b"cache").unwrap();
+    fs::create_dir_all(root.join(".wbt_set").join("s")).unwrap();
+    fs::write(root.join(".wbt_set").join("s").join("a.png"), b"set").unwrap();
+    root
+}
+
+#[test]
+fn test_sync_mirror_copies_artifacts_incrementally() {
+    let dir = tempdir().unwrap();
+    let root = make_root(dir.path());
+    let usb = dir.path().join("usb");
+    fs::create_dir_all(&usb).unwrap();
+
+    let status = mirror::sync_mirror(&root, &usb, "20260101_140000").unwrap();
+    assert_eq!(status.copied, 6);
+    assert_eq!(status.synced, 6);
+    assert_eq!(status.pending, 0);
+    assert!(status.failed.is_empty());
+
+    let dst = usb.join("wbt_backup_illust");
+    assert_eq!(Path::new(&status.mirror_root), dst);
+    assert_eq!(
+        fs::read(
+            dst.join("base1_20260101_100000")
+                .join("illust.clip.20260101_110000.diff.note")
+        )
+        .unwrap(),
+        b"memo"
+    );
+    assert!(dst.join(identity::IDENTITY_FILE).exists());
+    assert!(!dst.join(".wbt_cache").exists());
+    assert!(!dst.join(".wbt_set").exists());
+    assert!(!dst.join(mirror::MIRROR_STATE_FILE).exists());
+
+    // 2回目は新しい世代・書き換えたメモだけを写す
+    let gen2 = root.join("base2_20260102_100000");
+    fs::create_dir_all(&gen2).unwrap();
+    fs::write(gen2.join("illust.clip.base"), b"base2").unwrap();
+    let note = root
+        .join("base1_20260101_100000")
+        .join("illust.clip.20260101_110000.diff.note");
+    fs::write(&note, b"edited memo").unwrap();
+    let status = mirror::sync_mirror(&root, &usb, "20260102_110000").unwrap();
+    assert_eq!(status.copied, 2);
+    assert_eq!(status.synced, 7);
+    assert!(dst
+        .join("base2_20260102_100000")
+        .join("illust.clip.base")
+        .exists());
+
+    // ルートで消したものはミラー先に残す
+    fs::remove_file(root.join("illust_20260101_120000.clip")).unwrap();
+    let status = mirror::sync_mirror(&root, &usb, "20260102_120000").unwrap();
+    assert_eq!(status.copied, 0);
+    assert!(dst.join("illust_20260101_120000.clip").exists());
+}
+
+#[test]
+fn test_sync_mirror_queues_failures_and_retries() {
+    let dir = tempdir().unwrap();
+    let root = make_root(dir.path());
+    let usb = dir.path().join("usb");
+
+    // ミラー先がない（USB ドライブを外している）
+    let err = mirror::sync_mirror(&root, &usb, "20260101_140000").unwrap_err();
+    assert!(err.contains("usb"), "{}", err);
+    let status = mirror::mirror_status(&root);
+    assert_eq!(status.pending, 6);
+    assert_eq!(status.last_error, err);
+
+    // 1つだけ写せない（同じ名前のフォルダがある）
+    let dst = usb.join("wbt_backup_illust");
+    fs::create_dir_all(dst.join("illust_20260101_130000.zip")).unwrap();
+    let status = mirror::sync_mirror(&root, &usb, "20260101_150000").unwrap();
+    assert_eq!(status.copied, 5);
+    assert_eq!(status.pending, 1);
+    assert_eq!(status.failed.len(), 1);
+    assert_eq!(status.failed[0].path, "illust_20260101_130000.zip");
+    assert_eq!(status.failed[0].attempts, 1);
+    assert!(!status.last_error.is_empty());
+
+    let check = mirror::MirrorCheck::new(&root, &root.join(".wbt_cache"));
+    assert_eq!(
+        check.item_status(&root.join("illust_20260101_130000.zip").to_string_lossy()),
+        "failed"
+    );
+    assert_eq!(
+        check.item_status(&root.join("illust_20260101_120000.clip").to_string_lossy()),
+        "synced"
+    );
+
+    // 再試行で写せるようになれば外れる
+    let status = mirror::sync_mirror(&root, &usb, "20260101_160000").unwrap();
+    assert_eq!(status.failed[0].attempts, 2);
+    fs::remove_dir(dst.join("illust_20260101_130000.zip")).unwrap();
+    let status = mirror::sync_mirror(&root, &usb, "20260101_170000").unwrap();
+    assert_eq!(status.copied, 1);
+    assert_eq!(status.pending, 0);
+    assert!(status.failed.is_empty());
+    assert!(status.last_error.is_empty());
+    assert_eq!(
+        fs::read(dst.join("illust_20260101_130000.zip")).unwrap(),
+        b"zip"
+    );
+}
+
+#[test]
+fn test_mirror_check_for_folders_and_overlap() {
+    let dir = tempdir().unwrap();
+    let root = make_root(dir.path());
+    let usb = dir.path().join("usb");
+    fs::create_dir_all(&usb).unwrap();
+    mirror::sync_mirror(&root, &usb, "20260101_140000").unwrap();
+
+    // 世代フォルダは配下に未了のものがあれば未了
+    let gen = root.join("base1_20260101_100000");
+    fs::write(gen.join("illust.clip.20260101_150000.diff"), b"diff2").unwrap();
+    let check = mirror::MirrorCheck::new(&root, &root.join(".wbt_cache"));
+    assert_eq!(check.item_status(&gen.to_string_lossy()), "pending");
+    assert_eq!(
+        check.item_status(&gen.join("illust.clip.base").to_string_lossy()),
+        "synced"
+    );
+
+    // バックアップ先と重なる場所は指定できない
+    assert!(mirror::sync_mirror(&root, &root, "20260101_150000").is_err());
+    assert!(mirror::sync_mirror(&root, dir.path(), "20260101_150000").is_err());
+}
+
+#[test]
+fn test_artifact_files_skip_workspaces_and_partial_chunks() {
+    let dir = tempdir().unwrap();
+    let root = make_root(dir.path());
+    for ws in [
+        "rebase_tmp_base1_20260101_100000",
+        "merge_tmp_base1_20260101_100000",
+    ] {
+        fs::create_dir_all(root.join(ws)).unwrap();
+        fs::write(root.join(ws).join("illust.clip.base"), b"tmp").unwrap();
+    }
+    let chunk_dir = root.join("chunks").join("ab");
+    fs::create_dir_all(&chunk_dir).unwrap();
+    fs::write(chunk_dir.join("abcd"), b"chunk").unwrap();
+    fs::write(chunk_dir.join("abef.tmp"), b"partial").unwrap();
+
+    let rels: Vec<String> = mirror::artifact_files(&root)
+        .into_iter()
+        .map(|(rel, _)| rel)
+        .collect();
+    assert!(rels.contains(&"chunks/ab/abcd".to_string()));
+    assert!(!rels.iter().any(|r| r.ends_with(".tmp")));
+    assert!(!rels.iter().any(|r| r.contains("_tmp_")));
+    assert_eq!(rels.len(), 7);
+}
+
+#[test]
+fn test_mirror_check_maps_cache_items_to_archive() {
+    let dir = tempdir().unwrap();
+    let root = make_root(dir.path());
+    let archive = root.join("base2_20260101_110000.zip");
+    fs::write(&archive, b"zip2").unwrap();
+    let usb = dir.path().join("usb");
+    fs::create_dir_all(&usb).unwrap();
+
+    // 同じ場所のキャッシュ・一時フォルダのキャッシュのどちらも、展開元のアーカイブで判定する
+    let temp_cache = dir.path().join("wbt_cache_illust_0000");
+    for cache in [root.join(".wbt_cache"), temp_cache] {
+        let cached = cache.join("base2_20260101_110000").join("illust.clip.base");
+        let cached = cached.to_string_lossy();
+        let check = mirror::MirrorCheck::new(&root, &cache);
+        assert_eq!(check.item_status(&cached), "pending");
+
+        mirror::sync_mirror(&root, &usb, "20260101_140000").unwrap();
+        let check = mirror::MirrorCheck::new(&root, &cache);
+        assert_eq!(check.item_status(&cached), "synced");
+        // 展開元のないキャッシュは写していない扱い
+        assert_eq!(
+            check.item_status(&cache.join("base9_20260101_000000").to_string_lossy()),
+            "pending"
+        );
+
+        fs::write(&archive, b"zip2 rebuilt").unwrap();
+    }
+}
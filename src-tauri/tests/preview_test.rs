@@ -0,0 +1,243 @@
+use image::{Rgba, RgbaImage};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tempfile::tempdir;
+use work_backup_tool::core::preview::{embedded, psd, thumbnail, visual};
+
+fn png_bytes(img: &RgbaImage) -> Vec<u8> {
+    let mut buf = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut buf, image::ImageFormat::Png).unwrap();
+    buf.into_inner()
+}
+
+/// 2x1 の RGB の PSD を作る（compression: 0 = 無圧縮, 1 = RLE）
+fn write_psd(path: &Path, compression: u16) {
+    let mut d = Vec::new();
+    d.extend_from_slice(b"8BPS");
+    d.extend_from_slice(&1u16.to_be_bytes());
+    d.extend_from_slice(&[0; 6]);
+    d.extend_from_slice(&3u16.to_be_bytes()); // channels
+    d.extend_from_slice(&1u32.to_be_bytes()); // height
+    d.extend_from_slice(&2u32.to_be_bytes()); // width
+    d.extend_from_slice(&8u16.to_be_bytes()); // depth
+    d.extend_from_slice(&3u16.to_be_bytes()); // RGB
+    d.extend_from_slice(&0u32.to_be_bytes()); // color mode data
+    d.extend_from_slice(&4u32.to_be_bytes()); // image resources
+    d.extend_from_slice(&[0xAA; 4]);
+    d.extend_from_slice(&0u32.to_be_bytes()); // layer and mask
+    d.extend_from_slice(&compression.to_be_bytes());
+    let planes: [[u8; 2]; 3] = [[255, 0], [0, 255], [10, 10]];
+    if compression == 0 {
+        for p in planes {
+            d.extend_from_slice(&p);
+        }
+    } else {
+        let rows: [Vec<u8>; 3] = [vec![1, 255, 0], vec![1, 0, 255], vec![0xFF, 10]];
+        for r in &rows {
+            d.extend_from_slice(&(r.len() as u16).to_be_bytes());
+        }
+        for r in &rows {
+            d.extend_from_slice(r);
+        }
+    }
+    fs::write(path, d).unwrap();
+}
+
+#[test]
+fn test_psd_composite_raw_and_rle() {
+    let dir = tempdir().unwrap();
+    for compression in [0u16, 1] {
+        let path = dir.path().join(format!("art_{}.psd", compression));
+        write_psd(&path, compression);
+        let img = psd::decode_composite(&path).unwrap();
+        assert_eq!(img.dimensions(), (2, 1));
+        assert_eq!(img.get_pixel(0, 0), &Rgba([255, 0, 10, 255]));
+        assert_eq!(img.get_pixel(1, 0), &Rgba([0, 255, 10, 255]));
+    }
+
+    let bad = dir.path().join("bad.psd");
+    fs::write(&bad, b"not a psd").unwrap();
+    assert!(psd::decode_composite(&bad).is_err());
+}
+
+/// ヘッダーだけの PSD/PSB を作る（画像データは RLE の行の長さを1つだけ置く）
+fn write_psd_header(path: &Path, version: u16, channels: u16, size: (u32, u32), mode: u16) {
+    let mut d = Vec::new();
+    d.extend_from_slice(b"8BPS");
+    d.extend_from_slice(&version.to_be_bytes());
+    d.extend_from_slice(&[0; 6]);
+    d.extend_from_slice(&channels.to_be_bytes());
+    d.extend_from_slice(&size.1.to_be_bytes()); // height
+    d.extend_from_slice(&size.0.to_be_bytes()); // width
+    d.extend_from_slice(&8u16.to_be_bytes());
+    d.extend_from_slice(&mode.to_be_bytes());
+    d.extend_from_slice(&0u32.to_be_bytes());
+    d.extend_from_slice(&0u32.to_be_bytes());
+    if version == 2 {
+        d.extend_from_slice(&0u64.to_be_bytes());
+    } else {
+        d.extend_from_slice(&0u32.to_be_bytes());
+    }
+    d.extend_from_slice(&1u16.to_be_bytes());
+    d.extend_from_slice(&u16::MAX.to_be_bytes());
+    fs::write(path, d).unwrap();
+}
+
+#[test]
+fn test_psd_rejects_malformed_header() {
+    let dir = tempdir().unwrap();
+    let cases: [(u16, u16, (u32, u32), u16); 8] = [
+        (1, 0, (2, 1), 1),             // グレースケールでチャンネルなし
+        (1, 2, (2, 1), 3),             // RGB に足りない
+        (1, 3, (2, 1), 4),             // CMYK に足りない
+        (1, 3, (0, 1), 3),             // 幅 0
+        (1, 3, (30_001, 1), 3),        // PSD の上限超え
+        (2, 3, (300_001, 1), 3),       // PSB の上限超え
+        (2, 3, (300_000, 300_000), 3), // 画素数が多すぎる
+        (3, 3, (2, 1), 3),             // 未知のバージョン
+    ];
+    for (i, (version, channels, size, mode)) in cases.into_iter().enumerate() {
+        let path = dir.path().join(format!("bad_{}.psd", i));
+        write_psd_header(&path, version, channels, size, mode);
+        assert!(psd::decode_composite(&path).is_err(), "case {}", i);
+    }
+
+    // 行の長さが画像の幅に見合わない RLE
+    let path = dir.path().join("bad_rle.psd");
+    write_psd_header(&path, 1, 1, (2, 1), 1);
+    assert!(psd::decode_composite(&path).is_err());
+}
+
+#[test]
+fn test_clip_preview() {
+    let dir = tempdir().unwrap();
+    let preview = png_bytes(&RgbaImage::from_pixel(3, 2, Rgba([1, 2, 3, 255])));
+
+    let db_path = dir.path().join("canvas.sqlite");
+    {
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+        conn.execute(
+            "CREATE TABLE CanvasPreview (MainId INTEGER, ImageData BLOB)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO CanvasPreview (MainId, ImageData) VALUES (1, ?1)",
+            [&preview],
+        )
+        .unwrap();
+    }
+    let db = fs::read(&db_path).unwrap();
+
+    let clip = dir.path().join("page.clip");
+    let mut f = fs::File::create(&clip).unwrap();
+    f.write_all(b"CSFCHUNK").unwrap();
+    f.write_all(&[0; 16]).unwrap();
+    f.write_all(b"CHNKHead").unwrap();
+    f.write_all(&4u64.to_be_bytes()).unwrap();
+    f.write_all(&[0; 4]).unwrap();
+    f.write_all(b"CHNKSQLi").unwrap();
+    f.write_all(&(db.len() as u64).to_be_bytes()).unwrap();
+    f.write_all(&db).unwrap();
+    f.write_all(b"CHNKFoot").unwrap();
+    f.write_all(&0u64.to_be_bytes()).unwrap();
+    drop(f);
+
+    assert_eq!(embedded::extract_clip_preview(&clip).unwrap(), preview);
+    let img = embedded::load_preview(&clip).unwrap();
+    assert_eq!((img.width(), img.height()), (3, 2));
+}
+
+#[test]
+fn test_kra_preview() {
+    let dir = tempdir().unwrap();
+    let preview = png_bytes(&RgbaImage::from_pixel(4, 4, Rgba([9, 9, 9, 255])));
+    let kra = dir.path().join("art.kra");
+    let mut zip = zip::ZipWriter::new(fs::File::create(&kra).unwrap());
+    let opts = zip::write::SimpleFileOptions::default();
+    zip.start_file("mimetype", opts).unwrap();
+    zip.write_all(b"application/x-krita").unwrap();
+    zip.start_file("mergedimage.png", opts).unwrap();
+    zip.write_all(&preview).unwrap();
+    zip.finish().unwrap();
+
+    assert_eq!(embedded::extract_zip_preview(&kra).unwrap(), preview);
+    assert!(embedded::is_preview_supported(Path::new("x.KRA")));
+    assert!(!embedded::is_preview_supported(Path::new("x.txt")));
+}
+
+#[test]
+fn test_diff_heatmap_and_side_by_side() {
+    let a = RgbaImage::from_pixel(4, 2, Rgba([0, 0, 0, 255]));
+    let mut b = a.clone();
+    b.put_pixel(1, 1, Rgba([255, 0, 0, 255]));
+
+    let (heat, changed) = visual::diff_heatmap(&a, &b);
+    assert_eq!(changed, 1);
+    assert_eq!(heat.get_pixel(1, 1), &Rgba([255, 0, 0, 255]));
+    assert_ne!(heat.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+
+    let side = visual::side_by_side(&a, &RgbaImage::new(2, 5));
+    assert_eq!(side.height(), 5);
+    assert!(side.width() > 6);
+}
+
+#[test]
+fn test_render_comparison_is_cached() {
+    let dir = tempdir().unwrap();
+    let a = dir.path().join("a.png");
+    let b = dir.path().join("b.png");
+    RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255]))
+        .save(&a)
+        .unwrap();
+    RgbaImage::from_pixel(2, 2, Rgba([0, 0, 50, 255]))
+        .save(&b)
+        .unwrap();
+
+    let out = dir.path().join("visual");
+    let key = visual::comparison_key(&a.to_string_lossy(), &b.to_string_lossy());
+    let result = visual::render_comparison(&a, &b, &out, &key).unwrap();
+    assert_eq!(result.changed_pixels, 4);
+    assert_eq!(result.changed_ratio, 1.0);
+    assert!(Path::new(&result.heatmap_path).is_file());
+
+    // 入力を消してもキャッシュから返る
+    fs::remove_file(&a).unwrap();
+    let cached = visual::render_comparison(&a, &b, &out, &key).unwrap();
+    assert_eq!(cached, result);
+}
+
+#[test]
+fn test_render_thumbnail() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("page.png");
+    RgbaImage::from_pixel(1000, 500, Rgba([0, 128, 255, 255]))
+        .save(&src)
+        .unwrap();
+
+    let cache = dir.path().join(".wbt_cache");
+    let key_path = src.to_string_lossy().into_owned();
+    assert!(thumbnail::cached_thumbnail(&cache, &key_path).is_none());
+
+    let dest = thumbnail::thumbnail_path(&cache, &key_path);
+    assert!(dest.starts_with(cache.join(thumbnail::THUMB_DIR)));
+    thumbnail::render_thumbnail(&src, &dest, thumbnail::THUMB_SIZE).unwrap();
+    let thumb = image::open(&dest).unwrap();
+    assert_eq!((thumb.width(), thumb.height()), (256, 128));
+    assert_eq!(
+        thumbnail::cached_thumbnail(&cache, &key_path),
+        Some(dest.clone())
+    );
+
+    // 更新されたバックアップは別のサムネイルになる
+    RgbaImage::from_pixel(10, 10, Rgba([0, 0, 0, 255]))
+        .save(&src)
+        .unwrap();
+    assert_ne!(thumbnail::thumbnail_path(&cache, &key_path), dest);
+
+    // 小さい画像は拡大しない
+    let small = dir.path().join("small.png");
+    thumbnail::render_thumbnail(&src, &small, thumbnail::THUMB_SIZE).unwrap();
+    assert_eq!(image::open(&small).unwrap().width(), 10);
+}
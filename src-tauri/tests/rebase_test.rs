@@ -0,0 +1,332 @@
+use std::fs;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::rebase::{self, RebaseStrategy};
+use work_backup_tool::core::backup::root_lock::RootLocks;
+
+fn setup_generation(root: &std::path::Path) -> std::path::PathBuf {
+    let gen = root.join("base2_20260101_100000");
+    fs::create_dir_all(&gen).unwrap();
+    fs::write(gen.join("work.clip.base"), b"base").unwrap();
+    fs::write(gen.join("work.clip.20260101_100500.hdiff.diff"), b"d1").unwrap();
+    fs::write(gen.join("work.clip.20260101_101000.hdiff.diff"), b"d2-long").unwrap();
+    fs::write(
+        gen.join("work.clip.20260101_101500.hdiff.diff"),
+        b"d3-longer",
+    )
+    .unwrap();
+    fs::write(
+        gen.join("work.clip.20260101_101000.hdiff.diff.note"),
+        b"memo",
+    )
+    .unwrap();
+    gen
+}
+
+// =====================================================================
+// RebaseStrategy / pick_new_base
+// =====================================================================
+
+#[test]
+fn test_rebase_strategy_parse() {
+    assert_eq!(
+        "latest".parse::<RebaseStrategy>().unwrap(),
+        RebaseStrategy::Latest
+    );
+    assert_eq!(
+        "".parse::<RebaseStrategy>().unwrap(),
+        RebaseStrategy::Latest
+    );
+    assert_eq!(
+        "median".parse::<RebaseStrategy>().unwrap(),
+        RebaseStrategy::Median
+    );
+    assert!("oldest".parse::<RebaseStrategy>().is_err());
+}
+
+#[test]
+fn test_pick_new_base() {
+    assert_eq!(rebase::pick_new_base(0, RebaseStrategy::Latest), None);
+    assert_eq!(rebase::pick_new_base(5, RebaseStrategy::Latest), Some(4));
+    assert_eq!(rebase::pick_new_base(5, RebaseStrategy::Median), Some(2));
+    assert_eq!(rebase::pick_new_base(1, RebaseStrategy::Median), Some(0));
+}
+
+// =====================================================================
+// plan_rebase
+// =====================================================================
+
+#[test]
+fn test_list_generation_diffs_sorted_and_filtered() {
+    let dir = tempdir().unwrap();
+    let gen = setup_generation(dir.path());
+
+    let diffs = rebase::list_generation_diffs(&gen).unwrap();
+    assert_eq!(diffs.len(), 3);
+    assert!(diffs[0].to_string_lossy().contains("100500"));
+    assert!(diffs[2].to_string_lossy().contains("101500"));
+}
+
+#[test]
+fn test_plan_rebase_creates_workspace() {
+    let dir = tempdir().unwrap();
+    setup_generation(dir.path());
+    let work = dir.path().join("work.clip");
+
+    let plan = rebase::plan_rebase(
+        dir.path(),
+        2,
+        &work.to_string_lossy(),
+        RebaseStrategy::Latest,
+    )
+    .unwrap();
+
+    assert_eq!(plan.generation, 2);
+    assert_eq!(plan.diffs.len(), 3);
+    assert_eq!(plan.new_base_idx, 2);
+    assert!(plan.before_bytes > 0);
+    assert!(plan.workspace.join("snapshots").is_dir());
+    assert!(plan.staged_generation().is_dir());
+    // 作業フォルダは世代フォルダとして認識されない名前であること
+    assert!(!plan
+        .workspace
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .starts_with("base"));
+}
+
+#[test]
+fn test_plan_rebase_no_diffs() {
+    let dir = tempdir().unwrap();
+    let gen = dir.path().join("base1_20260101_100000");
+    fs::create_dir(&gen).unwrap();
+    fs::write(gen.join("work.clip.base"), b"base").unwrap();
+    let work = dir.path().join("work.clip");
+
+    let result = rebase::plan_rebase(
+        dir.path(),
+        1,
+        &work.to_string_lossy(),
+        RebaseStrategy::Latest,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_plan_rebase_missing_generation() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.clip");
+    let result = rebase::plan_rebase(
+        dir.path(),
+        9,
+        &work.to_string_lossy(),
+        RebaseStrategy::Latest,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_original_base_diff_uses_generation_timestamp() {
+    let dir = tempdir().unwrap();
+    setup_generation(dir.path());
+    let work = dir.path().join("work.clip");
+    let plan = rebase::plan_rebase(
+        dir.path(),
+        2,
+        &work.to_string_lossy(),
+        RebaseStrategy::Latest,
+    )
+    .unwrap();
+
+    assert_eq!(
+        plan.original_base_diff()
+            .unwrap()
+            .file_name()
+            .unwrap()
+            .to_string_lossy(),
+        "work.clip.20260101_100000.hdiff.diff"
+    );
+}
+
+#[test]
+fn test_original_base_diff_skipped_when_rotation_diff_exists() {
+    // 世代交代で作られた世代には、世代フォルダと同じ時刻の差分が既にある
+    let dir = tempdir().unwrap();
+    let gen = setup_generation(dir.path());
+    fs::write(gen.join("work.clip.20260101_100000.hdiff.diff"), b"d0").unwrap();
+    let work = dir.path().join("work.clip");
+    let plan = rebase::plan_rebase(
+        dir.path(),
+        2,
+        &work.to_string_lossy(),
+        RebaseStrategy::Latest,
+    )
+    .unwrap();
+
+    assert!(plan.original_base_diff().is_none());
+}
+
+// =====================================================================
+// stage_new_base / commit_rebase
+// =====================================================================
+
+#[test]
+fn test_stage_and_commit_rebase_swaps_generation() {
+    let dir = tempdir().unwrap();
+    let gen = setup_generation(dir.path());
+    let work = dir.path().join("work.clip");
+    let plan = rebase::plan_rebase(
+        dir.path(),
+        2,
+        &work.to_string_lossy(),
+        RebaseStrategy::Latest,
+    )
+    .unwrap();
+
+    // hpatchz / hdiffz の代わりにスナップショットと新しい差分を直接置く
+    for (i, diff) in plan.diffs.iter().enumerate() {
+        fs::write(plan.snapshot_path(diff), format!("state{}", i)).unwrap();
+    }
+    rebase::stage_new_base(&plan).unwrap();
+    for diff in &plan.diffs {
+        fs::write(plan.staged_diff(diff), b"x").unwrap();
+    }
+    fs::write(plan.original_base_diff().unwrap(), b"x").unwrap();
+
+    let report = rebase::commit_rebase(&plan).unwrap();
+
+    assert_eq!(report.generation, 2);
+    assert_eq!(report.diff_count, 3);
+    assert_eq!(report.new_base_from, "work.clip.20260101_101500.hdiff.diff");
+    assert_eq!(
+        report.saved_bytes,
+        report.before_bytes as i64 - report.after_bytes as i64
+    );
+    assert_eq!(fs::read(gen.join("work.clip.base")).unwrap(), b"state2");
+    assert_eq!(
+        fs::read(gen.join("work.clip.20260101_101000.hdiff.diff.note")).unwrap(),
+        b"memo"
+    );
+    assert!(gen.join("work.clip.20260101_100000.hdiff.diff").exists());
+    assert!(!plan.workspace.exists());
+}
+
+#[test]
+fn test_backup_waits_for_running_rebase() {
+    let dir = tempdir().unwrap();
+    let gen = setup_generation(dir.path());
+    let work = dir.path().join("work.clip");
+    let locks = RootLocks::new();
+
+    // リベースの実行中は、同じルートへのバックアップを始められない
+    let rebase_guard = locks.acquire(dir.path(), "リベース").unwrap();
+    let plan = rebase::plan_rebase(
+        dir.path(),
+        2,
+        &work.to_string_lossy(),
+        RebaseStrategy::Latest,
+    )
+    .unwrap();
+    let err = locks
+        .acquire_shared(dir.path(), "差分バックアップ")
+        .unwrap_err();
+    assert!(err.contains("リベース"), "{}", err);
+
+    for (i, diff) in plan.diffs.iter().enumerate() {
+        fs::write(plan.snapshot_path(diff), format!("state{}", i)).unwrap();
+    }
+    rebase::stage_new_base(&plan).unwrap();
+    for diff in &plan.diffs {
+        fs::write(plan.staged_diff(diff), b"x").unwrap();
+    }
+    fs::write(plan.original_base_diff().unwrap(), b"x").unwrap();
+    rebase::commit_rebase(&plan).unwrap();
+    drop(rebase_guard);
+
+    // 終わった後のバックアップは入れ替わった世代フォルダへ書かれ、残る
+    let backup_guard = locks
+        .acquire_shared(dir.path(), "差分バックアップ")
+        .unwrap();
+    let diff = gen.join("work.clip.20260101_102000.hdiff.diff");
+    fs::write(&diff, b"d4").unwrap();
+    assert!(locks.acquire(dir.path(), "リベース").is_err());
+    drop(backup_guard);
+    assert!(diff.exists());
+    assert!(!plan.workspace.exists());
+}
+
+#[test]
+fn test_abort_rebase_keeps_original() {
+    let dir = tempdir().unwrap();
+    let gen = setup_generation(dir.path());
+    let work = dir.path().join("work.clip");
+    let plan = rebase::plan_rebase(
+        dir.path(),
+        2,
+        &work.to_string_lossy(),
+        RebaseStrategy::Median,
+    )
+    .unwrap();
+
+    rebase::abort_rebase(&plan);
+
+    assert!(!plan.workspace.exists());
+    assert_eq!(fs::read(gen.join("work.clip.base")).unwrap(), b"base");
+}
+
+// =====================================================================
+// recover_interrupted_rebase
+// =====================================================================
+
+#[test]
+fn test_recover_interrupted_rebase_restores_old_generation() {
+    // 入れ替え途中（旧フォルダ退避済み・新フォルダ未配置）で中断したケース
+    let dir = tempdir().unwrap();
+    let workspace = dir.path().join("rebase_tmp_base2_20260101_100000");
+    let old = workspace.join("old");
+    fs::create_dir_all(&old).unwrap();
+    fs::write(old.join("work.clip.base"), b"base").unwrap();
+
+    rebase::recover_interrupted_rebase(dir.path()).unwrap();
+
+    let gen = dir.path().join("base2_20260101_100000");
+    assert_eq!(fs::read(gen.join("work.clip.base")).unwrap(), b"base");
+    assert!(!workspace.exists());
+}
+
+#[test]
+fn test_recover_interrupted_rebase_removes_stale_workspace() {
+    let dir = tempdir().unwrap();
+    setup_generation(dir.path());
+    let workspace = dir.path().join("rebase_tmp_base2_20260101_100000");
+    fs::create_dir_all(workspace.join("snapshots")).unwrap();
+
+    rebase::recover_interrupted_rebase(dir.path()).unwrap();
+
+    assert!(!workspace.exists());
+    assert!(dir.path().join("base2_20260101_100000").exists());
+}
+
+// =====================================================================
+// same_content
+// =====================================================================
+
+#[test]
+fn test_same_content_files_and_dirs() {
+    let dir = tempdir().unwrap();
+    let a = dir.path().join("a");
+    let b = dir.path().join("b");
+    for d in [&a, &b] {
+        fs::create_dir_all(d.join("sub")).unwrap();
+        fs::write(d.join("sub/page.clip"), b"page").unwrap();
+    }
+    assert!(rebase::same_content(&a, &b).unwrap());
+
+    fs::write(b.join("sub/page.clip"), b"pagX").unwrap();
+    assert!(!rebase::same_content(&a, &b).unwrap());
+
+    fs::write(b.join("extra.txt"), b"").unwrap();
+    assert!(!rebase::same_content(&a, &b).unwrap());
+
+    assert!(rebase::same_content(&a.join("sub/page.clip"), &a.join("sub/page.clip")).unwrap());
+}
@@ -0,0 +1,233 @@
+use chrono::NaiveDateTime;
+use std::fs;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::archive;
+use work_backup_tool::core::backup::restore::{self, ConflictPolicy, RestoreKind};
+
+fn dt(s: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+}
+
+/// 差分・フルコピー・単体アーカイブ・世代アーカイブを混在させた構成
+fn setup_tree(root: &std::path::Path) {
+    let g1 = root.join("base1_20260101_100000");
+    fs::create_dir_all(&g1).unwrap();
+    fs::write(g1.join("work.clip.base"), b"base").unwrap();
+    fs::write(g1.join("work.clip.20260101_100500.hdiff.diff"), b"d1").unwrap();
+    let zip = root.join("base1_20260101_100000.zip");
+    archive::compress_dir_zip(&g1, &zip, "").unwrap();
+    fs::remove_dir_all(&g1).unwrap();
+
+    let g2 = root.join("base2_20260102_100000");
+    fs::create_dir_all(&g2).unwrap();
+    fs::write(g2.join("work.clip.base"), b"base").unwrap();
+    fs::write(g2.join("work.clip.20260102_120000.hdiff.diff"), b"d2").unwrap();
+
+    fs::write(root.join("work_20260101_180000.clip"), b"full").unwrap();
+    fs::write(root.join("work_20260102_090000.zip"), b"zip").unwrap();
+    // 別ファイルのコピーは候補にしない
+    fs::write(root.join("other_20260101_200000.clip"), b"x").unwrap();
+}
+
+#[test]
+fn test_parse_restore_instant_formats() {
+    let expected = dt("2026-01-01 18:00:00");
+    for s in [
+        "2026-01-01 18:00:00",
+        "2026-01-01 18:00",
+        "2026-01-01T18:00:00",
+        "20260101_180000",
+    ] {
+        assert_eq!(
+            restore::parse_restore_instant(s).unwrap(),
+            expected,
+            "{}",
+            s
+        );
+    }
+    assert!(restore::parse_restore_instant("yesterday").is_err());
+}
+
+#[test]
+fn test_collect_restore_points_kinds() {
+    let dir = tempdir().unwrap();
+    setup_tree(dir.path());
+    let work = dir.path().join("work.clip");
+
+    let points = restore::collect_restore_points(
+        &work.to_string_lossy(),
+        &dir.path().to_string_lossy(),
+        false,
+        true,
+    );
+
+    let kinds: Vec<_> = points.iter().map(|p| (p.time, p.kind)).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (dt("2026-01-01 10:05:00"), RestoreKind::Diff),
+            (dt("2026-01-01 18:00:00"), RestoreKind::Copy),
+            (dt("2026-01-02 09:00:00"), RestoreKind::Archive),
+            (dt("2026-01-02 12:00:00"), RestoreKind::Diff),
+        ]
+    );
+    // 世代アーカイブ内の差分は仮想パスで返る
+    assert!(archive::split_archived_entry_path(&points[0].path).is_some());
+}
+
+#[test]
+fn test_find_restore_point_newest_at_or_before() {
+    let dir = tempdir().unwrap();
+    setup_tree(dir.path());
+    let work = dir.path().join("work.clip");
+    let points = restore::collect_restore_points(
+        &work.to_string_lossy(),
+        &dir.path().to_string_lossy(),
+        false,
+        true,
+    );
+
+    let p = restore::find_restore_point(&points, dt("2026-01-01 18:00:00")).unwrap();
+    assert_eq!(p.kind, RestoreKind::Copy);
+
+    let p = restore::find_restore_point(&points, dt("2026-01-02 11:59:59")).unwrap();
+    assert_eq!(p.kind, RestoreKind::Archive);
+
+    let p = restore::find_restore_point(&points, dt("2030-01-01 00:00:00")).unwrap();
+    assert!(p.path.ends_with("work.clip.20260102_120000.hdiff.diff"));
+
+    assert!(restore::find_restore_point(&points, dt("2025-12-31 23:59:59")).is_none());
+}
+
+#[test]
+fn test_restore_copy_file_and_folder() {
+    let dir = tempdir().unwrap();
+    let src_file = dir.path().join("work_20260101_180000.clip");
+    fs::write(&src_file, b"full").unwrap();
+    let out_file = dir.path().join("out/work_restored.clip");
+    restore::restore_copy(&src_file.to_string_lossy(), &out_file.to_string_lossy()).unwrap();
+    assert_eq!(fs::read(&out_file).unwrap(), b"full");
+
+    let src_dir = dir.path().join("world_20260101_180000");
+    fs::create_dir_all(src_dir.join("sub")).unwrap();
+    fs::write(src_dir.join("sub/page.txt"), b"page").unwrap();
+    let out_dir = dir.path().join("world_restored");
+    restore::restore_copy(&src_dir.to_string_lossy(), &out_dir.to_string_lossy()).unwrap();
+    assert_eq!(fs::read(out_dir.join("sub/page.txt")).unwrap(), b"page");
+}
+
+// =====================================================================
+// 出力先指定 (resolve_output_target / place_restored)
+// =====================================================================
+
+#[test]
+fn test_conflict_policy_parse() {
+    assert_eq!(
+        "".parse::<ConflictPolicy>().unwrap(),
+        ConflictPolicy::Rename
+    );
+    assert_eq!(
+        "overwrite".parse::<ConflictPolicy>().unwrap(),
+        ConflictPolicy::Overwrite
+    );
+    assert_eq!(
+        "skip".parse::<ConflictPolicy>().unwrap(),
+        ConflictPolicy::Skip
+    );
+    assert!("merge".parse::<ConflictPolicy>().is_err());
+}
+
+#[test]
+fn test_resolve_output_target_dir_and_file() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.clip");
+    fs::write(&work, b"w").unwrap();
+    let out_dir = dir.path().join("out");
+    fs::create_dir(&out_dir).unwrap();
+
+    let target =
+        restore::resolve_output_target(&work.to_string_lossy(), &out_dir.to_string_lossy());
+    assert_eq!(target.parent().unwrap(), out_dir);
+    let name = target.file_name().unwrap().to_string_lossy().into_owned();
+    assert!(name.starts_with("work_restored_") && name.ends_with(".clip"));
+
+    let file = dir.path().join("explicit.clip");
+    assert_eq!(
+        restore::resolve_output_target(&work.to_string_lossy(), &file.to_string_lossy()),
+        file
+    );
+}
+
+#[test]
+fn test_place_restored_file_policies() {
+    let dir = tempdir().unwrap();
+    let dest = dir.path().join("work.clip");
+    fs::write(&dest, b"existing").unwrap();
+
+    let staged = dir.path().join("staged1");
+    fs::write(&staged, b"new").unwrap();
+    let placed = restore::place_restored(&staged, &dest, ConflictPolicy::Rename)
+        .unwrap()
+        .unwrap();
+    assert_eq!(placed, dir.path().join("work_1.clip"));
+    assert_eq!(fs::read(&dest).unwrap(), b"existing");
+
+    let staged = dir.path().join("staged2");
+    fs::write(&staged, b"new").unwrap();
+    assert!(
+        restore::place_restored(&staged, &dest, ConflictPolicy::Skip)
+            .unwrap()
+            .is_none()
+    );
+    assert!(!staged.exists());
+    assert_eq!(fs::read(&dest).unwrap(), b"existing");
+
+    let staged = dir.path().join("staged3");
+    fs::write(&staged, b"new").unwrap();
+    restore::place_restored(&staged, &dest, ConflictPolicy::Overwrite).unwrap();
+    assert_eq!(fs::read(&dest).unwrap(), b"new");
+}
+
+fn make_folder(path: &std::path::Path, files: &[(&str, &[u8])]) {
+    for (rel, body) in files {
+        let p = path.join(rel);
+        fs::create_dir_all(p.parent().unwrap()).unwrap();
+        fs::write(p, body).unwrap();
+    }
+}
+
+#[test]
+fn test_place_restored_folder_overwrite_and_skip() {
+    let dir = tempdir().unwrap();
+    let dest = dir.path().join("world");
+    make_folder(&dest, &[("a.txt", b"old-a"), ("keep.txt", b"keep")]);
+
+    let staged = dir.path().join("staged");
+    make_folder(&staged, &[("a.txt", b"new-a"), ("sub/b.txt", b"new-b")]);
+    restore::place_restored(&staged, &dest, ConflictPolicy::Skip).unwrap();
+    assert_eq!(fs::read(dest.join("a.txt")).unwrap(), b"old-a");
+    assert_eq!(fs::read(dest.join("sub/b.txt")).unwrap(), b"new-b");
+    assert!(!staged.exists());
+
+    make_folder(&staged, &[("a.txt", b"new-a")]);
+    restore::place_restored(&staged, &dest, ConflictPolicy::Overwrite).unwrap();
+    assert_eq!(fs::read(dest.join("a.txt")).unwrap(), b"new-a");
+    assert_eq!(fs::read(dest.join("keep.txt")).unwrap(), b"keep");
+}
+
+#[test]
+fn test_place_restored_folder_rename() {
+    let dir = tempdir().unwrap();
+    let dest = dir.path().join("world");
+    make_folder(&dest, &[("a.txt", b"old")]);
+    let staged = dir.path().join("staged");
+    make_folder(&staged, &[("a.txt", b"new")]);
+
+    let placed = restore::place_restored(&staged, &dest, ConflictPolicy::Rename)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(placed, dir.path().join("world_1"));
+    assert_eq!(fs::read(placed.join("a.txt")).unwrap(), b"new");
+    assert_eq!(fs::read(dest.join("a.txt")).unwrap(), b"old");
+}
@@ -0,0 +1,47 @@
+use tempfile::tempdir;
+use work_backup_tool::core::backup::root_lock::RootLocks;
+
+#[test]
+fn test_root_lock_is_exclusive_until_dropped() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("wbt_backup_work");
+    std::fs::create_dir_all(&root).unwrap();
+    let locks = RootLocks::new();
+
+    let guard = locks.acquire(&root, "リベース").unwrap();
+    assert!(locks.is_busy(&root));
+    // 別の表記でも同じルートとして扱う
+    let other = root.join("..").join("wbt_backup_work");
+    let err = locks.acquire(&other, "世代の統合").unwrap_err();
+    assert!(err.contains("リベース"), "{}", err);
+    // 複製した RootLocks も同じ状態を共有する
+    assert!(locks.clone().is_busy(&root));
+    assert!(!locks.is_busy(dir.path()));
+
+    drop(guard);
+    assert!(!locks.is_busy(&root));
+    assert!(locks.acquire(&other, "世代の統合").is_ok());
+}
+
+#[test]
+fn test_root_lock_shared_and_exclusive() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("wbt_backup_work");
+    let locks = RootLocks::new();
+
+    // 共有同士は同時に使え、すべて終わるまで排他の作業は始められない
+    let a = locks.acquire_shared(&root, "重複排除バックアップ").unwrap();
+    let b = locks.acquire_shared(&root, "重複排除バックアップ").unwrap();
+    // まだないルートも、作られた後と同じものとして扱う
+    std::fs::create_dir_all(&root).unwrap();
+    let err = locks.acquire(&root, "チャンクの整理").unwrap_err();
+    assert!(err.contains("重複排除バックアップ"), "{}", err);
+    drop(a);
+    assert!(locks.acquire(&root, "チャンクの整理").is_err());
+    drop(b);
+
+    let gc = locks.acquire(&root, "チャンクの整理").unwrap();
+    assert!(locks.acquire_shared(&root, "重複排除バックアップ").is_err());
+    drop(gc);
+    assert!(locks.acquire_shared(&root, "重複排除バックアップ").is_ok());
+}
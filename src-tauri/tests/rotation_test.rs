@@ -0,0 +1,200 @@
+use chrono::NaiveDateTime;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::rotation::{self, GenerationState, RotationPolicy};
+use work_backup_tool::core::backup::stats;
+use work_backup_tool::core::config::loader;
+use work_backup_tool::core::types::{AppConfig, RotationReason, RotationRecord};
+
+fn at(s: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(s, "%Y%m%d_%H%M%S").unwrap()
+}
+
+fn make_generation(root: &Path, name: &str, base: &[u8], diffs: &[usize]) -> std::path::PathBuf {
+    let gen = root.join(name);
+    fs::create_dir_all(&gen).unwrap();
+    fs::write(gen.join("work.clip.base"), base).unwrap();
+    for (i, size) in diffs.iter().enumerate() {
+        fs::write(
+            gen.join(format!("work.clip.20260101_1{:05}.hdiff.diff", i)),
+            vec![0u8; *size],
+        )
+        .unwrap();
+    }
+    gen
+}
+
+#[test]
+fn test_inspect_generation() {
+    let dir = tempdir().unwrap();
+    let mut base = b"CSFCHUNK".to_vec();
+    base.resize(1000, 0);
+    let gen = make_generation(dir.path(), "base1_20260101_100000", &base, &[10, 20]);
+    // 暗号化した差分も数える
+    fs::write(
+        gen.join("work.clip.20260101_120000.hdiff.diff.enc"),
+        vec![0u8; 5],
+    )
+    .unwrap();
+
+    let state = rotation::inspect_generation(&gen, "work.clip");
+    assert_eq!(state.base_size, 1000);
+    assert_eq!(state.base_format, Some("clip"));
+    assert_eq!((state.diff_count, state.diff_bytes), (3, 35));
+    assert_eq!(state.created, Some(at("20260101_100000")));
+}
+
+#[test]
+fn test_decide_rotation_rules() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.txt");
+    fs::write(&work, vec![b'a'; 1000]).unwrap();
+    let now = at("20260110_100000");
+    let state = GenerationState {
+        base_size: 1000,
+        diff_count: 3,
+        diff_bytes: 300,
+        created: Some(at("20260101_100000")),
+        ..Default::default()
+    };
+
+    // 既定値（差分1つのサイズのみ）では交代しない
+    let policy = RotationPolicy::default();
+    assert_eq!(
+        rotation::decide_rotation(&policy, &state, &work, 1000, 50, now),
+        None
+    );
+
+    let reason = |policy: &RotationPolicy, work_size: u64| {
+        rotation::decide_rotation(policy, &state, &work, work_size, 50, now).map(|(r, _)| r)
+    };
+    let cumulative = RotationPolicy {
+        cumulative_ratio: 0.3,
+        ..Default::default()
+    };
+    assert_eq!(
+        reason(&cumulative, 1000),
+        Some(RotationReason::CumulativeDiff)
+    );
+    let max_diffs = RotationPolicy {
+        max_diffs: 3,
+        ..Default::default()
+    };
+    assert_eq!(reason(&max_diffs, 1000), Some(RotationReason::MaxDiffs));
+    let max_age = RotationPolicy {
+        max_age_days: 9,
+        ..Default::default()
+    };
+    assert_eq!(reason(&max_age, 1000), Some(RotationReason::MaxAge));
+    let shrink = RotationPolicy {
+        shrink_ratio: 0.5,
+        ..Default::default()
+    };
+    assert_eq!(reason(&shrink, 400), Some(RotationReason::WorkShrunk));
+    assert_eq!(reason(&shrink, 600), None);
+}
+
+#[test]
+fn test_decide_rotation_type_changed() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.clip");
+    fs::write(&work, b"\x89PNG\r\n\x1a\nrest").unwrap();
+    let state = GenerationState {
+        base_size: 100,
+        base_format: Some("clip"),
+        ..Default::default()
+    };
+    // 既定では種類が変わっても交代しない
+    assert_eq!(
+        rotation::decide_rotation(
+            &RotationPolicy::default(),
+            &state,
+            &work,
+            12,
+            1,
+            at("20260101_100000"),
+        ),
+        None
+    );
+    let policy = RotationPolicy {
+        type_changed: true,
+        ..Default::default()
+    };
+    let (reason, detail) =
+        rotation::decide_rotation(&policy, &state, &work, 12, 1, at("20260101_100000")).unwrap();
+    assert_eq!(reason, RotationReason::TypeChanged);
+    assert_eq!(detail, "clip -> png");
+
+    // ファイル -> フォルダ
+    let folder = dir.path().join("folder");
+    fs::create_dir_all(&folder).unwrap();
+    let state = GenerationState {
+        base_size: 100,
+        ..Default::default()
+    };
+    assert!(matches!(
+        rotation::decide_rotation(&policy, &state, &folder, 0, 0, at("20260101_100000")),
+        Some((RotationReason::TypeChanged, _))
+    ));
+}
+
+#[test]
+fn test_rotation_log_appears_in_stats() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.clip");
+    fs::write(&work, b"work").unwrap();
+    let root = dir.path().join("wbt_backup_work");
+    make_generation(&root, "base1_20260101_100000", b"base", &[1]);
+    make_generation(&root, "base2_20260102_100000", b"base", &[]);
+
+    rotation::record_rotation(
+        &root,
+        RotationRecord {
+            generation: 2,
+            from_generation: 1,
+            reason: RotationReason::MaxDiffs,
+            detail: "1 diffs".to_string(),
+            time: "20260102_100000".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(rotation::load_rotation_log(&root).len(), 1);
+
+    let s =
+        stats::collect_backup_stats(&work.to_string_lossy(), &root, &dir.path().join("c")).unwrap();
+    assert_eq!(s.generations[0].rotation_reason, None);
+    assert_eq!(
+        s.generations[1].rotation_reason,
+        Some(RotationReason::MaxDiffs)
+    );
+}
+
+#[test]
+fn test_rotation_defaults_match_bundled_config() {
+    // 世代交代の設定がない古い設定ファイルでは、追加の条件はすべて無効（同梱の設定と同じ）
+    let mut value = serde_json::to_value(loader::default_config()).unwrap();
+    let obj = value.as_object_mut().unwrap();
+    for key in [
+        "rotateCumulativeRatio",
+        "rotateMaxDiffs",
+        "rotateMaxAgeDays",
+        "rotateShrinkRatio",
+        "rotateOnTypeChange",
+    ] {
+        assert!(obj.remove(key).is_some(), "{}", key);
+    }
+    let old: AppConfig = serde_json::from_value(value).unwrap();
+    let policy = RotationPolicy::from_config(&old);
+    assert_eq!(
+        policy,
+        RotationPolicy::from_config(&loader::default_config())
+    );
+    assert_eq!(
+        policy,
+        RotationPolicy {
+            threshold: old.auto_base_generation_threshold,
+            ..Default::default()
+        }
+    );
+}
@@ -1,6 +1,6 @@
 use std::fs;
 use tempfile::tempdir;
-use work_backup_tool::core::backup::scanner;
+use work_backup_tool::core::backup::{archive, scanner};
 
 fn setup_backup_tree(root: &std::path::Path) {
     // base1 世代
@@ -135,6 +135,52 @@ fn test_scan_backups_item_fields() {
     assert!(!item.timestamp.is_empty());
 }
 
+#[test]
+fn test_scan_backups_lists_diffs_inside_uncached_archive() {
+    let dir = tempdir().unwrap();
+    setup_backup_tree(dir.path());
+    let g2 = dir.path().join("base2_20260101_110000");
+    archive::compress_dir_zip(&g2, &dir.path().join("base2_20260101_110000.zip"), "").unwrap();
+    fs::remove_dir_all(&g2).unwrap();
+
+    let work_file = dir.path().join("work.clip").to_string_lossy().to_string();
+    let backup_dir = dir.path().to_string_lossy().to_string();
+
+    let items = scanner::scan_backups(&work_file, &backup_dir, false, true);
+    assert_eq!(items.len(), 3);
+
+    let archived: Vec<_> = items.iter().filter(|i| i.is_archived).collect();
+    assert_eq!(archived.len(), 1);
+    assert_eq!(archived[0].generation, 2);
+    assert_eq!(
+        archived[0].file_name,
+        "work.clip.20260101_110500.hdiff.diff"
+    );
+    assert_eq!(archived[0].timestamp, "2026-01-01 11:05:00");
+    assert!(archive::split_archived_entry_path(&archived[0].file_path).is_some());
+}
+
+#[test]
+fn test_scan_backups_archive_with_cache_not_duplicated() {
+    let dir = tempdir().unwrap();
+    setup_backup_tree(dir.path());
+    let g2 = dir.path().join("base2_20260101_110000");
+    let zip = dir.path().join("base2_20260101_110000.zip");
+    archive::compress_dir_zip(&g2, &zip, "").unwrap();
+    fs::remove_dir_all(&g2).unwrap();
+    archive::extract_to_cache(&zip.to_string_lossy(), &dir.path().join(".wbt_cache"), None)
+        .unwrap();
+
+    let work_file = dir.path().join("work.clip").to_string_lossy().to_string();
+    let backup_dir = dir.path().to_string_lossy().to_string();
+
+    let items = scanner::scan_backups(&work_file, &backup_dir, false, true);
+    let archived: Vec<_> = items.iter().filter(|i| i.is_archived).collect();
+    assert_eq!(archived.len(), 1);
+    // キャッシュ側の実ファイルを指す
+    assert!(archive::split_archived_entry_path(&archived[0].file_path).is_none());
+}
+
 // =====================================================================
 // scan_generation_folders
 // =====================================================================
@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::PathBuf;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::restore::ConflictPolicy;
+use work_backup_tool::core::backup::{archive, selective};
+
+fn make_project(root: &std::path::Path) -> std::path::PathBuf {
+    let proj = root.join("comic_20260101_100000");
+    fs::create_dir_all(proj.join("pages")).unwrap();
+    fs::write(proj.join("pages/page_01.clip"), b"p1").unwrap();
+    fs::write(proj.join("pages/page_07.clip"), b"p7").unwrap();
+    fs::write(proj.join("cover.png"), b"cover").unwrap();
+    proj
+}
+
+#[test]
+fn test_normalize_rel_path() {
+    assert_eq!(
+        selective::normalize_rel_path("pages\\page_07.clip").unwrap(),
+        PathBuf::from("pages/page_07.clip")
+    );
+    assert!(selective::normalize_rel_path("../secret").is_err());
+    assert!(selective::normalize_rel_path("/").is_err());
+}
+
+#[test]
+fn test_list_folder_files() {
+    let dir = tempdir().unwrap();
+    let proj = make_project(dir.path());
+
+    let files = selective::list_folder_files(&proj).unwrap();
+    let names: Vec<_> = files.iter().map(|f| f.rel_path.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["cover.png", "pages/page_01.clip", "pages/page_07.clip"]
+    );
+    assert!(selective::list_folder_files(&proj.join("cover.png")).is_err());
+}
+
+#[test]
+fn test_list_folder_archive_files() {
+    let dir = tempdir().unwrap();
+    let proj = make_project(dir.path());
+    let zip = dir.path().join("comic.zip");
+    archive::compress_dir_zip(&proj, &zip, "").unwrap();
+
+    let files = archive::list_folder_archive_files(&zip).unwrap();
+    assert!(files.contains(&("pages/page_07.clip".to_string(), 2)));
+    assert_eq!(files.len(), 3);
+}
+
+#[test]
+fn test_extract_folder_entries_only_selected() {
+    for tar in [false, true] {
+        let dir = tempdir().unwrap();
+        let proj = make_project(dir.path());
+        let arc = dir
+            .path()
+            .join(if tar { "comic.tar.gz" } else { "comic.zip" });
+        if tar {
+            archive::compress_dir_tar(&proj, &arc).unwrap();
+        } else {
+            archive::compress_dir_zip(&proj, &arc, "").unwrap();
+        }
+        let out = dir.path().join("out");
+
+        let n = archive::extract_folder_entries(
+            &arc,
+            &[PathBuf::from("pages/page_07.clip")],
+            &out,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(n, 1);
+        assert_eq!(fs::read(out.join("pages/page_07.clip")).unwrap(), b"p7");
+        assert!(!out.join("cover.png").exists());
+    }
+}
+
+#[test]
+fn test_copy_selected_file_and_folder() {
+    let dir = tempdir().unwrap();
+    let proj = make_project(dir.path());
+    let dest = dir.path().join("restored");
+
+    let written = selective::copy_selected(
+        &proj,
+        &[PathBuf::from("cover.png"), PathBuf::from("pages")],
+        &dest,
+        ConflictPolicy::Rename,
+    )
+    .unwrap();
+
+    assert_eq!(written.len(), 3);
+    assert_eq!(fs::read(dest.join("pages/page_01.clip")).unwrap(), b"p1");
+}
+
+#[test]
+fn test_copy_selected_conflicts() {
+    let dir = tempdir().unwrap();
+    let proj = make_project(dir.path());
+    let dest = dir.path().join("restored");
+    fs::create_dir_all(dest.join("pages")).unwrap();
+    fs::write(dest.join("pages/page_07.clip"), b"current").unwrap();
+    let sel = [PathBuf::from("pages/page_07.clip")];
+
+    let written = selective::copy_selected(&proj, &sel, &dest, ConflictPolicy::Skip).unwrap();
+    assert!(written.is_empty());
+    assert_eq!(
+        fs::read(dest.join("pages/page_07.clip")).unwrap(),
+        b"current"
+    );
+
+    selective::copy_selected(&proj, &sel, &dest, ConflictPolicy::Rename).unwrap();
+    assert_eq!(fs::read(dest.join("pages/page_07_1.clip")).unwrap(), b"p7");
+
+    selective::copy_selected(&proj, &sel, &dest, ConflictPolicy::Overwrite).unwrap();
+    assert_eq!(fs::read(dest.join("pages/page_07.clip")).unwrap(), b"p7");
+}
+
+#[test]
+fn test_copy_selected_missing_path() {
+    let dir = tempdir().unwrap();
+    let proj = make_project(dir.path());
+    let result = selective::copy_selected(
+        &proj,
+        &[PathBuf::from("pages/page_99.clip")],
+        &dir.path().join("restored"),
+        ConflictPolicy::Rename,
+    );
+    assert!(result.is_err());
+}
@@ -0,0 +1,94 @@
+use std::fs;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::{archive, stats};
+
+#[test]
+fn test_collect_backup_stats() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.clip");
+    fs::write(&work, vec![0u8; 100]).unwrap();
+
+    let root = dir.path().join("wbt_backup_work");
+    // 世代1: アーカイブ済み
+    let gen1 = root.join("base1_20260101_100000");
+    fs::create_dir_all(&gen1).unwrap();
+    fs::write(gen1.join("work.clip.base"), vec![1u8; 100]).unwrap();
+    fs::write(
+        gen1.join("work.clip.20260101_100500.hdiff.diff"),
+        vec![2u8; 7],
+    )
+    .unwrap();
+    archive::compress_dir_zip(&gen1, &root.join("base1_20260101_100000.zip"), "").unwrap();
+    fs::remove_dir_all(&gen1).unwrap();
+    // 世代2: 展開済み
+    let gen2 = root.join("base2_20260102_100000");
+    fs::create_dir_all(&gen2).unwrap();
+    fs::write(gen2.join("work.clip.base"), vec![1u8; 100]).unwrap();
+    fs::write(
+        gen2.join("work.clip.20260102_100500.hdiff.diff"),
+        vec![2u8; 10],
+    )
+    .unwrap();
+    fs::write(
+        gen2.join("work.clip.20260102_101000.hdiff.diff"),
+        vec![2u8; 30],
+    )
+    .unwrap();
+    // フルコピーと単体アーカイブ
+    fs::write(root.join("work_20260103_100000.clip"), vec![0u8; 100]).unwrap();
+    fs::write(root.join("work_20260103_110000.zip"), vec![0u8; 40]).unwrap();
+    fs::write(root.join("unrelated.txt"), b"x").unwrap();
+
+    let cache = dir.path().join(".wbt_cache");
+    fs::create_dir_all(cache.join("base1_20260101_100000")).unwrap();
+    fs::write(
+        cache.join("base1_20260101_100000/work.clip.base"),
+        vec![0u8; 25],
+    )
+    .unwrap();
+
+    let s = stats::collect_backup_stats(&work.to_string_lossy(), &root, &cache).unwrap();
+    assert_eq!(s.work_size, 100);
+    assert_eq!(s.generations.len(), 2);
+
+    let g1 = &s.generations[0];
+    assert!(g1.archived);
+    assert_eq!((g1.generation, g1.base_size, g1.diff_count), (1, 100, 1));
+    assert_eq!(g1.diff_bytes, 7);
+
+    let g2 = &s.generations[1];
+    assert!(!g2.archived);
+    assert_eq!((g2.base_size, g2.diff_count, g2.diff_bytes), (100, 2, 40));
+    assert_eq!(g2.largest_diff_bytes, 30);
+    assert_eq!(
+        g2.largest_diff_name.as_deref(),
+        Some("work.clip.20260102_101000.hdiff.diff")
+    );
+    assert_eq!(g2.stored_bytes, 140);
+    assert!((g2.compression_ratio - 140.0 / 300.0).abs() < 1e-9);
+
+    assert_eq!((s.copy_count, s.copy_bytes), (1, 100));
+    assert_eq!((s.archive_count, s.archive_bytes), (1, 40));
+    assert_eq!(s.cache_bytes, 25);
+    assert_eq!(
+        s.total_bytes,
+        s.generation_bytes + s.copy_bytes + s.archive_bytes + s.cache_bytes
+    );
+}
+
+#[test]
+fn test_collect_backup_stats_without_root() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("work.clip");
+    fs::write(&work, b"abc").unwrap();
+
+    let s = stats::collect_backup_stats(
+        &work.to_string_lossy(),
+        &dir.path().join("missing"),
+        &dir.path().join(".wbt_cache"),
+    )
+    .unwrap();
+    assert_eq!(s.work_size, 3);
+    assert!(s.generations.is_empty());
+    assert_eq!(s.total_bytes, 0);
+}
@@ -158,6 +158,67 @@ fn test_copy_file_nonexistent_src() {
     assert!(result.is_err());
 }
 
+// =====================================================================
+// copy_dir_preserving
+// =====================================================================
+
+fn mtime(p: &std::path::Path) -> std::time::SystemTime {
+    fs::symlink_metadata(p).unwrap().modified().unwrap()
+}
+
+#[test]
+fn test_copy_dir_preserving_keeps_times_and_empty_dirs() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("project");
+    fs::create_dir_all(src.join("empty")).unwrap();
+    fs::create_dir_all(src.join("sub")).unwrap();
+    fs::write(src.join("sub").join("a.txt"), b"a").unwrap();
+    let old = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000);
+    utils::set_modified_time(&src.join("sub").join("a.txt"), old).unwrap();
+    utils::set_modified_time(&src.join("sub"), old).unwrap();
+
+    let dst = dir.path().join("copy");
+    utils::copy_dir_preserving(&src, &dst, false).unwrap();
+
+    assert!(dst.join("empty").is_dir());
+    assert_eq!(fs::read(dst.join("sub").join("a.txt")).unwrap(), b"a");
+    assert_eq!(mtime(&dst.join("sub").join("a.txt")), old);
+    assert_eq!(mtime(&dst.join("sub")), old);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_copy_dir_preserving_permissions_and_symlinks() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("project");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(src.join("run.sh"), b"#!/bin/sh").unwrap();
+    fs::set_permissions(src.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    std::os::unix::fs::symlink("run.sh", src.join("link")).unwrap();
+
+    let kept = dir.path().join("kept");
+    utils::copy_dir_preserving(&src, &kept, true).unwrap();
+    let mode = fs::metadata(kept.join("run.sh"))
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o755);
+    assert_eq!(
+        fs::read_link(kept.join("link")).unwrap(),
+        std::path::Path::new("run.sh")
+    );
+
+    // リンクを辿る場合は中身がコピーされる
+    let followed = dir.path().join("followed");
+    utils::copy_dir_preserving(&src, &followed, false).unwrap();
+    assert!(!fs::symlink_metadata(followed.join("link"))
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(fs::read(followed.join("link")).unwrap(), b"#!/bin/sh");
+}
+
 // =====================================================================
 // move_file_safe
 // =====================================================================
@@ -253,7 +314,6 @@ fn test_get_file_size_empty_path() {
     assert!(utils::get_file_size("").is_err());
 }
 
-
 #[test]
 fn test_get_file_size_directory() {
     let dir = tempdir().unwrap();
@@ -0,0 +1,132 @@
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+use work_backup_tool::core::backup::identity;
+use work_backup_tool::core::config::vault::{self, KeyVault};
+
+#[test]
+fn test_vault_persists_passwords_encrypted() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join(vault::VAULT_FILE);
+    let root = dir.path().join("wbt_backup_work");
+
+    let mut v = KeyVault::unlock(&path, "master").unwrap();
+    assert!(path.exists());
+    v.set_password(&root, "archive-secret").unwrap();
+
+    // ファイルに平文のパスワードやパスが残らない
+    let raw = fs::read(&path).unwrap();
+    assert!(!raw.windows(14).any(|w| w == b"archive-secret"));
+
+    let v = KeyVault::unlock(&path, "master").unwrap();
+    assert_eq!(v.password_for(&root), Some("archive-secret"));
+    assert_eq!(v.roots().len(), 1);
+}
+
+#[test]
+fn test_vault_wrong_master_password() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join(vault::VAULT_FILE);
+    KeyVault::unlock(&path, "master").unwrap();
+
+    let err = KeyVault::unlock(&path, "other").err().unwrap();
+    assert!(err.contains("マスターパスワードが違います"));
+    assert!(KeyVault::unlock(&path, "").is_err());
+}
+
+#[test]
+fn test_vault_password_for_nested_backup_paths() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join(vault::VAULT_FILE);
+    let root = dir.path().join("wbt_backup_work");
+    let mut v = KeyVault::unlock(&path, "master").unwrap();
+    // 末尾の区切り文字の有無は区別しない
+    v.set_password(Path::new(&format!("{}/", root.display())), "pw")
+        .unwrap();
+
+    let diff = root
+        .join("base1_20260101_100000")
+        .join("work.clip.20260101_110000.hdiff.diff.enc");
+    assert_eq!(v.password_for(&diff), Some("pw"));
+    let archived = root.join("base2_20260102_100000.tar.gz.enc");
+    assert_eq!(v.password_for(&archived), Some("pw"));
+    assert_eq!(v.password_for(&dir.path().join("other")), None);
+}
+
+#[test]
+fn test_vault_remove_and_change_master() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join(vault::VAULT_FILE);
+    let root_a = dir.path().join("a");
+    let root_b = dir.path().join("b");
+    let mut v = KeyVault::unlock(&path, "master").unwrap();
+    v.set_password(&root_a, "pw-a").unwrap();
+    v.set_password(&root_b, "pw-b").unwrap();
+
+    assert!(v.remove_password(&root_a).unwrap());
+    assert!(!v.remove_password(&root_a).unwrap());
+    v.change_master("new-master").unwrap();
+
+    assert!(KeyVault::unlock(&path, "master").is_err());
+    let v = KeyVault::unlock(&path, "new-master").unwrap();
+    assert_eq!(v.password_for(&root_a), None);
+    assert_eq!(v.password_for(&root_b), Some("pw-b"));
+}
+
+#[test]
+fn test_vault_follows_relinked_root() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join(vault::VAULT_FILE);
+    let old_work = dir.path().join("illust.clip");
+    fs::write(&old_work, b"clip").unwrap();
+    let old_root = dir.path().join("wbt_backup_illust");
+    identity::record_backup(&old_root, &old_work, "20260101_100000").unwrap();
+
+    // 記述ファイルのあるルートは ID で登録する（保管庫がロック中に移しても引ける）
+    let mut v = KeyVault::unlock(&path, "master").unwrap();
+    v.set_password(&old_root, "pw").unwrap();
+    let new_work = dir.path().join("cover.clip");
+    fs::rename(&old_work, &new_work).unwrap();
+    let report = identity::relink(&old_root, &new_work, true, "20260102_100000").unwrap();
+    let new_root = Path::new(&report.backup_dir);
+    assert!(!old_root.exists());
+    assert_eq!(v.password_for(&new_root.join("base1.zip")), Some("pw"));
+
+    // 表示用のパスは新しい場所にする
+    assert!(v.relocate(&old_root, new_root).unwrap());
+    assert_eq!(v.roots(), vec![vault::vault_key(new_root)]);
+
+    // パスで登録していたもの（記述ファイルを作る前に登録した）も引き継ぐ
+    let legacy_root = dir.path().join("legacy");
+    let mut v = KeyVault::unlock(&dir.path().join("other.enc"), "master").unwrap();
+    v.set_password(&legacy_root, "old").unwrap();
+    assert!(v.relocate(&legacy_root, new_root).unwrap());
+    assert_eq!(v.password_for(new_root), Some("old"));
+    assert_eq!(v.password_for(&legacy_root), None);
+    assert!(v.remove_password(new_root).unwrap());
+    assert!(v.roots().is_empty());
+}
+
+#[test]
+fn test_vault_follows_relocated_root() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("illust.clip");
+    fs::write(&work, b"clip").unwrap();
+    let root = dir.path().join("wbt_backup_illust");
+    identity::record_backup(&root, &work, "20260101_100000").unwrap();
+    let mut v = KeyVault::unlock(&dir.path().join(vault::VAULT_FILE), "master").unwrap();
+    v.set_password(&root, "pw").unwrap();
+
+    // 作業ファイルとルートをまとめて別の場所へ移して開く
+    let moved = dir.path().join("drive_d");
+    fs::create_dir_all(&moved).unwrap();
+    fs::rename(&work, moved.join("illust.clip")).unwrap();
+    let new_root = moved.join("wbt_backup_illust");
+    fs::rename(&root, &new_root).unwrap();
+    let opened = identity::open_root(&new_root, true, "20260102_100000").unwrap();
+    assert!(opened.relocated);
+
+    assert_eq!(v.password_for(&new_root.join("base1.zip")), Some("pw"));
+    assert!(v.relocate(&root, &new_root).unwrap());
+    assert_eq!(v.roots(), vec![vault::vault_key(&new_root)]);
+}
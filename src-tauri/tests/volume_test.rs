@@ -0,0 +1,187 @@
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use tempfile::tempdir;
+use work_backup_tool::core::backup::archive::{self, CompressionOptions};
+use work_backup_tool::core::backup::restore::{self, RestoreKind};
+use work_backup_tool::core::backup::volume::{self, VolumeReader};
+use work_backup_tool::core::backup::{generation_ops, scanner};
+
+/// 圧縮が効かないデータ（分割されるだけの大きさのアーカイブを作るため）
+fn noise(len: usize) -> Vec<u8> {
+    let mut x = 0x2545_f491_u32;
+    (0..len)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x as u8
+        })
+        .collect()
+}
+
+fn volume_names(dir: &std::path::Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_volume_name_helpers() {
+    assert_eq!(volume::split_volume_name("a.zip.002"), Some(("a.zip", 2)));
+    assert_eq!(volume::split_volume_name("a.zip"), None);
+    assert_eq!(volume::split_volume_name("a.zip.01"), None);
+    assert!(volume::is_first_volume("a.tar.gz.enc.001"));
+    assert!(!volume::is_first_volume("a.tar.gz.enc.002"));
+    assert_eq!(volume::strip_first_volume_ext("a.zip.001"), "a.zip");
+    assert_eq!(volume::strip_first_volume_ext("a.zip.002"), "a.zip.002");
+}
+
+#[test]
+fn test_split_and_read_volumes_roundtrip() {
+    let dir = tempdir().unwrap();
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+
+    // 割り切れない場合と、ちょうど割り切れる場合（空の最終ボリュームが付く）
+    for (volume_size, count) in [(3000u64, 4usize), (2500, 5)] {
+        let path = dir.path().join(format!("data_{}.bin", volume_size));
+        fs::write(&path, &data).unwrap();
+        let first = volume::split_into_volumes(&path, volume_size).unwrap();
+        assert!(!path.exists());
+        assert!(first.to_string_lossy().ends_with(".bin.001"));
+        assert_eq!(volume::list_volumes(&first).unwrap().len(), count);
+        assert_eq!(volume::volume_set_size(&first), data.len() as u64);
+
+        let mut reader = VolumeReader::open(&first).unwrap();
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, data);
+
+        // ボリュームをまたぐシーク
+        reader.seek(SeekFrom::Start(2990)).unwrap();
+        let mut buf = [0u8; 20];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[2990..3010]);
+
+        let joined = volume::join_volumes(&first, &dir.path().join("joined")).unwrap();
+        assert_eq!(fs::read(&joined).unwrap(), data);
+    }
+
+    // 収まる場合・0 の場合は分割しない
+    let small = dir.path().join("small.bin");
+    fs::write(&small, b"abc").unwrap();
+    assert_eq!(volume::split_into_volumes(&small, 3).unwrap(), small);
+    assert_eq!(volume::split_into_volumes(&small, 0).unwrap(), small);
+}
+
+#[test]
+fn test_missing_volumes_are_reported() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("data.zip");
+    fs::write(&path, vec![1u8; 1000]).unwrap();
+    let first = volume::split_into_volumes(&path, 300).unwrap();
+
+    // 途中のボリュームが欠けている
+    let second = dir.path().join("data.zip.002");
+    let saved = fs::read(&second).unwrap();
+    fs::remove_file(&second).unwrap();
+    let err = VolumeReader::open(&first).err().unwrap();
+    assert!(err.contains("data.zip.002"), "{}", err);
+    fs::write(&second, saved).unwrap();
+
+    // 末尾のボリュームが欠けている
+    fs::remove_file(dir.path().join("data.zip.004")).unwrap();
+    let err = volume::list_volumes(&first).unwrap_err();
+    assert!(err.contains("data.zip.004"), "{}", err);
+}
+
+#[test]
+fn test_split_archive_backup_restore_and_list() {
+    let dir = tempdir().unwrap();
+    let work = dir.path().join("project");
+    fs::create_dir_all(work.join("sub")).unwrap();
+    let noise = noise(50_000);
+    fs::write(work.join("a.bin"), &noise).unwrap();
+    fs::write(work.join("sub").join("b.txt"), b"hello").unwrap();
+    let backup_dir = dir.path().join("backup");
+
+    let opts = CompressionOptions {
+        volume_size: 16 * 1024,
+        ..Default::default()
+    };
+    let dst = archive::execute_archive_backup(
+        &work.to_string_lossy(),
+        Some(backup_dir.clone()),
+        "zip",
+        "",
+        &opts,
+    )
+    .unwrap();
+    assert!(dst.ends_with(".zip.001"), "{}", dst);
+    assert!(volume_names(&backup_dir).len() > 1);
+    assert!(archive::is_archive_name(&dst));
+    assert_eq!(restore::restore_kind_of(&dst), RestoreKind::Archive);
+
+    let files = archive::list_folder_archive_files(std::path::Path::new(&dst)).unwrap();
+    assert_eq!(files.len(), 2);
+
+    let out = dir.path().join("restored");
+    let count = archive::extract_folder_entries(
+        std::path::Path::new(&dst),
+        &[std::path::PathBuf::from("a.bin"), "sub".into()],
+        &out,
+        None,
+    )
+    .unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(fs::read(out.join("a.bin")).unwrap(), noise);
+    assert_eq!(fs::read(out.join("sub").join("b.txt")).unwrap(), b"hello");
+}
+
+#[test]
+fn test_split_generation_archive_cache_scan_and_delete() {
+    let dir = tempdir().unwrap();
+    let noise = noise(40_000);
+    let g1 = dir.path().join("base1_20260101_100000");
+    fs::create_dir(&g1).unwrap();
+    fs::write(g1.join("work.clip.base"), &noise).unwrap();
+    fs::write(g1.join("work.clip.20260101_110000.hdiff.diff"), b"diff").unwrap();
+    let g2 = dir.path().join("base2_20260102_100000");
+    fs::create_dir(&g2).unwrap();
+    fs::write(g2.join("work.clip.base"), b"base2").unwrap();
+
+    let opts = CompressionOptions {
+        volume_size: 10 * 1024,
+        ..Default::default()
+    };
+    let work_file = dir.path().join("work.clip").to_string_lossy().into_owned();
+    let backup_dir = dir.path().to_string_lossy().into_owned();
+    archive::execute_generation_archive(1, "zip", &work_file, &backup_dir, "", &opts).unwrap();
+    let first = dir.path().join("base1_20260101_100000.zip.001");
+    assert!(first.exists());
+    assert!(dir.path().join("base1_20260101_100000.zip.002").exists());
+
+    let cache_root = dir.path().join(".wbt_cache");
+    archive::extract_to_cache(&first.to_string_lossy(), &cache_root, None).unwrap();
+    assert_eq!(
+        fs::read(
+            cache_root
+                .join("base1_20260101_100000")
+                .join("work.clip.base")
+        )
+        .unwrap(),
+        noise
+    );
+
+    // 一覧には1つ目のボリュームの差分だけが出る
+    let items = scanner::scan_backups(&work_file, &backup_dir, false, true);
+    assert_eq!(items.iter().filter(|i| i.generation == 1).count(), 1);
+
+    generation_ops::delete_generation(dir.path(), &cache_root, 1, false).unwrap();
+    assert!(!volume_names(dir.path())
+        .iter()
+        .any(|n| n.starts_with("base1_")));
+}
@@ -32,7 +32,7 @@ import {
 } from "./ui";
 
 import { setupGlobalEvents } from "./events";
-import { switchTab } from "./actions";
+import { switchTab, offerRelink, startMirrorRetry } from "./actions";
 
 // --- 初期化ロジック ---
 async function Initialize() {
@@ -52,6 +52,7 @@ async function Initialize() {
   setupDragAndDrop();
 
   await setupGlobalEvents();
+  startMirrorRetry();
 
   // 先頭タブを必ずアクティブにする
   if (tabs.length > 0 && !tabs.some((t) => t.active)) {
@@ -136,6 +137,10 @@ function setupInitialUI() {
     titles[2].textContent = i18n.diffTitle;
     descs[2].textContent = i18n.diffDesc;
   }
+  if (titles.length >= 4) {
+    titles[3].textContent = i18n.dedupTitle;
+    descs[3].textContent = i18n.dedupDesc;
+  }
 
   setText("execute-backup-btn", i18n.executeBtn);
   const lockBtn = document.getElementById("lock-mode-btn");
@@ -144,6 +149,19 @@ function setupInitialUI() {
   setText("refresh-diff-btn", i18n.refreshBtn);
 
   setText("apply-selected-btn", i18n.applyBtn);
+  setText("encrypt-selected-btn", i18n.encryptBtn);
+  setText("vault-modal-title", i18n.vaultTitle);
+  setText("vault-unlock-btn", i18n.vaultUnlockBtn);
+  setText("vault-save-btn", i18n.vaultSaveBtn);
+  setText("vault-lock-btn", i18n.vaultLockBtn);
+  setTitle("vault-btn", i18n.vaultTitle);
+  setPlaceholder("vault-master-password", i18n.vaultMasterPlaceholder);
+  setText("backup-set-modal-title", i18n.backupSetTitle);
+  setPlaceholder("backup-set-name", i18n.backupSetNamePlaceholder);
+  setText("backup-set-add-file-btn", i18n.backupSetAddFile);
+  setText("backup-set-add-folder-btn", i18n.backupSetAddFolder);
+  setText("backup-set-cancel-btn", i18n.cancel);
+  setText("backup-set-save-btn", i18n.backupSetSave);
 
   setText("select-all-btn", i18n.selectAllBtn);
 
@@ -156,6 +174,10 @@ function setupInitialUI() {
   setText("drop-cancel", i18n.dropCancel);
 
   setTitle("add-tab-btn", i18n.addTabBtn || "Add New Tab");
+  setTitle("open-root-btn", i18n.openRootBtn);
+  setText("label-mirror", i18n.labelMirror);
+  setText("mirrordir-btn", i18n.backupDirBtn);
+  setText("mirrordir-clear-btn", i18n.mirrorClearBtn);
 
   // --- ここに追記 ---
   const typeSelect = document.getElementById("work-target-type-select");
@@ -164,6 +186,8 @@ function setupInitialUI() {
       typeSelect.options[0].textContent = i18n.workTargetTypeFile || "File";
     if (typeSelect.options[1])
       typeSelect.options[1].textContent = i18n.workTargetTypeFolder || "Folder";
+    if (typeSelect.options[2])
+      typeSelect.options[2].textContent = i18n.workTargetTypeSet || "Set";
   }
 
   // Compact用テキスト
@@ -175,6 +199,9 @@ function setupInitialUI() {
     if (typeCompactSelect.options[1])
       typeCompactSelect.options[1].textContent =
         i18n.workTargetTypeFolder || "Folder";
+    if (typeCompactSelect.options[2])
+      typeCompactSelect.options[2].textContent =
+        i18n.workTargetTypeSet || "Set";
   }
 
   setQueryText(".compact-title-text", i18n.compactMode || "Compact");
@@ -242,11 +269,16 @@ function setupDragAndDrop() {
         const tab = getActiveTab();
 
         tab.workFile = droppedPath;
+        tab.backupSet = null;
 
-        tab.workFileSize = await GetFileSize(droppedPath);
+        tab.workFileSize = await GetFileSize(
+          droppedPath,
+          tab.hdiffIgnoreList,
+        );
 
         tab.backupDir = "";
         tab.selectedTargetDir = "";
+        await offerRelink(tab);
 
         addToRecentFiles(droppedPath);
 
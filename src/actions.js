@@ -2,7 +2,16 @@ import {
   CopyBackupFile,
   ArchiveBackupFile,
   BackupOrDiff,
+  DedupBackup,
   RestoreBackup,
+  SyncBackupSet,
+  RestoreBackupSet,
+  FindRelinkCandidates,
+  RelinkBackupHistory,
+  SaveRootSettings,
+  OpenBackupRoot,
+  SyncMirror,
+  GetMirrorStatus,
   GetFileSize,
   DirExists,
   GetShowMemoAfterBackup,
@@ -14,6 +23,7 @@ import {
 import {
   i18n,
   tabs,
+  recentFiles,
   getActiveTab,
   addToRecentFiles,
   saveCurrentSession,
@@ -25,9 +35,11 @@ import {
   UpdateHistory,
   toggleProgress,
   showFloatingMessage,
+  showFloatingError,
   UpdateAllUI,
 } from "./ui";
 import { showMemoDialog, parseNoteContent, serializeNote } from "./memo.js";
+import { ask } from "@tauri-apps/plugin-dialog";
 
 // --- タブ操作ロジック ---
 export async function switchTab(id) {
@@ -53,6 +65,7 @@ export async function addTab() {
     active: true,
     backupMode: "diff",
     compressMode: "zstd",
+    mirrorDir: "",
   });
   await UpdateAllUI();
   saveCurrentSession();
@@ -109,21 +122,36 @@ export async function OnExecute() {
   try {
     let successText = "";
     let newFilePath = null;
+    // セットはメンバーの現在の内容をセットのフォルダへ揃えてから、そのフォルダをバックアップする
+    if (tab.backupSet) {
+      const info = await SyncBackupSet(
+        tab.backupSet,
+        tab.backupDir,
+        hdiffIgnoreList,
+      );
+      tab.workFile = info.workFile;
+      tab.backupDir = info.backupDir;
+    }
     // --- A. 単純コピーモード ---
     if (mode === "copy") {
-      newFilePath = await CopyBackupFile(tab.workFile, tab.backupDir);
+      newFilePath = await CopyBackupFile(
+        tab.workFile,
+        tab.backupDir,
+        hdiffIgnoreList,
+      );
       successText = i18n.copyBackupSuccess;
     }
     // --- B. アーカイブモード ---
     else if (mode === "archive") {
       let fmt = archiveFormat;
-      let pwd = fmt === "zip-pass" ? pwdValue : "";
+      let pwd = fmt === "zip-pass" || fmt.startsWith("tar") ? pwdValue : "";
       if (fmt === "zip-pass") fmt = "zip";
       newFilePath = await ArchiveBackupFile(
         tab.workFile,
         tab.backupDir,
         fmt,
         pwd,
+        hdiffIgnoreList,
       );
       successText = i18n.archiveBackupSuccess.replace(
         "{format}",
@@ -159,10 +187,33 @@ export async function OnExecute() {
       );
       successText = `${i18n.diffBackupSuccess} (${algo.toUpperCase()}${algo === "hdiff" ? ":" + compress : ""})`;
     }
+    // --- D. 重複排除モード ---
+    else if (mode === "dedup") {
+      newFilePath = await DedupBackup(
+        tab.workFile,
+        tab.backupDir,
+        hdiffIgnoreList,
+      );
+      successText = i18n.dedupBackupSuccess;
+    }
 
     toggleProgress(false);
     showFloatingMessage(successText);
 
+    // ルートを別の場所・別の PC で開いたときにタブを組み立て直せるよう、設定を記録する
+    SaveRootSettings(tab.workFile, tab.backupDir, {
+      backupMode: mode,
+      compressMode: compress,
+      diffAlgo: algo,
+      archiveFormat,
+      hdiffIgnoreList,
+      backupSet: tab.backupSet || null,
+      mirrorDir: tab.mirrorDir || "",
+    }).catch((err) => console.error("Failed to save root settings:", err));
+
+    // ミラー先へは待たずに写す（写せなかったものは後で再試行する）
+    syncMirror(tab);
+
     // メモダイアログをオプションで表示
     const showMemo = await GetShowMemoAfterBackup();
     if (showMemo && newFilePath) {
@@ -181,6 +232,139 @@ export async function OnExecute() {
   }
 }
 
+// --- 名前・場所の変わった作業ファイルの履歴の付け替え ---
+// 作業ファイルを選び直したときに呼ぶ。既定のバックアップ先がまだなく、
+// 以前の名前・場所の履歴が見つかった場合は付け替えるか尋ねる
+export async function offerRelink(tab) {
+  if (!tab?.workFile || tab.backupDir || tab.backupSet) return false;
+  const searchDirs = [
+    ...new Set(
+      recentFiles.map((p) => p.replace(/[\\/][^\\/]*$/, "")).filter(Boolean),
+    ),
+  ];
+  let candidates = [];
+  try {
+    candidates = await FindRelinkCandidates(tab.workFile, searchDirs);
+  } catch (err) {
+    console.error("Failed to find relink candidates:", err);
+    return false;
+  }
+  for (const c of candidates) {
+    const message = (c.exact ? i18n.relinkConfirmExact : i18n.relinkConfirm)
+      .replace("{name}", c.workName)
+      .replace("{path}", c.workPath);
+    const ok = await ask(message, { title: "CG File Backup", type: "info" });
+    if (!ok) continue;
+    try {
+      const report = await RelinkBackupHistory(tab.workFile, c.backupDir);
+      tab.backupDir = report.isDefaultDir ? "" : report.backupDir;
+      tab.selectedTargetDir = "";
+      saveCurrentSession();
+      showFloatingMessage(
+        i18n.relinkSuccess.replace("{count}", report.renamed),
+      );
+      // バックアップ先を新しい既定の場所へ移せなかった場合は、元の場所のまま使うことを伝える
+      if (report.warning) {
+        alert(report.warning);
+      }
+      return true;
+    } catch (err) {
+      alert(err);
+      return false;
+    }
+  }
+  return false;
+}
+
+// --- 既存のバックアップルートを開く ---
+// ルートの記述ファイルから作業対象と設定を読み、新しいタブとして開く
+export async function openBackupRoot(dir) {
+  let opened;
+  try {
+    opened = await OpenBackupRoot(dir);
+  } catch (err) {
+    alert(err);
+    return false;
+  }
+  const s = opened.settings || {};
+  const tab = {
+    id: Date.now(),
+    workFile: opened.workFile,
+    workFileSize: 0,
+    backupDir: opened.backupDir,
+    selectedTargetDir: "",
+    active: true,
+    backupMode: s.backupMode || "diff",
+    compressMode: s.compressMode || "zstd",
+    diffAlgo: s.diffAlgo || "hdiff",
+    archiveFormat: s.archiveFormat || "zip",
+    hdiffIgnoreList: s.hdiffIgnoreList || [],
+    backupSet: s.backupSet || null,
+    mirrorDir: s.mirrorDir || "",
+  };
+  if (!opened.workMissing) {
+    tab.workFileSize = await GetFileSize(
+      tab.workFile,
+      tab.hdiffIgnoreList,
+    ).catch(() => 0);
+    if (!tab.backupSet) addToRecentFiles(tab.workFile);
+  }
+  tabs.forEach((t) => (t.active = false));
+  tabs.push(tab);
+  await UpdateAllUI();
+  saveCurrentSession();
+  showFloatingMessage(
+    (opened.workMissing ? i18n.openRootMissing : i18n.openRootSuccess).replace(
+      "{path}",
+      opened.workFile,
+    ),
+  );
+  return true;
+}
+
+// --- ミラー ---
+// 写せなかったもの・ミラー先がなかったときのものを再試行する間隔
+const MIRROR_RETRY_MS = 5 * 60 * 1000;
+const mirrorRunning = new Set();
+
+// タブのバックアップ先をミラー先へ写す。quiet の場合は失敗を通知しない（定期的な再試行用）
+export async function syncMirror(tab, quiet = false) {
+  if (!tab?.workFile || !tab.mirrorDir || mirrorRunning.has(tab.id)) return;
+  mirrorRunning.add(tab.id);
+  try {
+    const status = await SyncMirror(tab.workFile, tab.backupDir, tab.mirrorDir);
+    if (status.failed.length > 0) {
+      if (!quiet) {
+        showFloatingError(
+          i18n.mirrorFailed.replace("{count}", status.failed.length),
+        );
+      }
+    } else if (status.copied > 0) {
+      showFloatingMessage(i18n.mirrorSuccess.replace("{count}", status.copied));
+    }
+  } catch (err) {
+    console.error("Mirror failed:", err);
+    if (!quiet) {
+      showFloatingError(i18n.mirrorUnavailable.replace("{error}", err));
+    }
+  } finally {
+    mirrorRunning.delete(tab.id);
+  }
+  if (getActiveTab()?.id === tab.id) UpdateHistory();
+}
+
+// 未了のものがあるタブを定期的に写し直す
+export function startMirrorRetry() {
+  setInterval(async () => {
+    for (const tab of tabs.filter((t) => t.workFile && t.mirrorDir)) {
+      const status = await GetMirrorStatus(tab.workFile, tab.backupDir).catch(
+        () => null,
+      );
+      if (status && status.pending > 0) await syncMirror(tab, true);
+    }
+  }, MIRROR_RETRY_MS);
+}
+
 // --- 復元・適用ロジック ---
 export async function applySelectedBackups() {
   const tab = getActiveTab();
@@ -190,8 +374,30 @@ export async function applySelectedBackups() {
   if (targets.length > 0 && confirm(i18n.restoreConfirm)) {
     toggleProgress(true, "Restoring...");
     try {
+      // 世代アーカイブ内の差分を直接復元する場合に使う
+      const archivePassword =
+        document.getElementById("archive-password")?.value || null;
+      if (tab.backupSet) {
+        // セットは全体、または選んだメンバーだけを元の場所の隣へ書き出す
+        const member =
+          document.getElementById("set-restore-member")?.value || null;
+        let count = 0;
+        for (const p of targets) {
+          const placed = await RestoreBackupSet(
+            p,
+            tab.workFile,
+            member,
+            archivePassword,
+          );
+          count += placed.length;
+        }
+        toggleProgress(false);
+        showFloatingMessage(i18n.setRestoreSuccess.replace("{count}", count));
+        await UpdateAllUI();
+        return;
+      }
       for (const p of targets) {
-        await RestoreBackup(p, tab.workFile);
+        await RestoreBackup(p, tab.workFile, archivePassword);
       }
       toggleProgress(false);
       showFloatingMessage(i18n.diffApplySuccess);
@@ -8,6 +8,13 @@ import {
   EventsOn,
   OnFileDrop,
   ArchiveGeneration,
+  EncryptBackupFiles,
+  GetVaultStatus,
+  UnlockVault,
+  LockVault,
+  SetVaultPassword,
+  RebuildArchiveCaches,
+  SyncBackupSet,
 } from "./tauri_exports";
 
 import {
@@ -29,9 +36,19 @@ import {
   showArchiveModal,
   showSettingsModal,
   handleSettingChange,
+  backupSetDraft,
+  showBackupSetModal,
+  renderBackupSetMembers,
 } from "./ui";
 
-import { addTab, OnExecute, switchTab } from "./actions";
+import {
+  addTab,
+  OnExecute,
+  switchTab,
+  offerRelink,
+  openBackupRoot,
+  syncMirror,
+} from "./actions";
 import { ask } from "@tauri-apps/plugin-dialog";
 import {
   isPermissionGranted,
@@ -46,6 +63,23 @@ const preventDefault = (e) => {
   e.stopPropagation();
 };
 
+/**
+ * 鍵保管庫の状態を表示する
+ */
+async function updateVaultStatus() {
+  const status = await GetVaultStatus();
+  const el = document.getElementById("vault-status");
+  if (!el) return;
+  if (status.unlocked) {
+    el.textContent = i18n.vaultUnlocked.replace(
+      "{count}",
+      status.roots.length,
+    );
+  } else {
+    el.textContent = status.exists ? i18n.vaultLocked : i18n.vaultNew;
+  }
+}
+
 export async function setupGlobalEvents() {
   window.addEventListener("dragenter", preventDefault, true);
 
@@ -58,6 +92,10 @@ export async function setupGlobalEvents() {
       : "work-target-type-select";
     const sel = document.getElementById(targetId);
     const targetType = sel ? sel.value : "file";
+    if (targetType === "set") {
+      showBackupSetModal();
+      return;
+    }
 
     let res;
     if (targetType === "folder") {
@@ -69,9 +107,13 @@ export async function setupGlobalEvents() {
     }
     if (res) {
       tab.workFile = res;
-      tab.workFileSize = await GetFileSize(res).catch(() => 0);
+      tab.backupSet = null;
+      tab.workFileSize = await GetFileSize(res, tab.hdiffIgnoreList).catch(
+        () => 0,
+      );
       tab.backupDir = "";
       tab.selectedTargetDir = "";
+      await offerRelink(tab);
       addToRecentFiles(res);
       renderTabs();
       await UpdateDisplay();
@@ -100,9 +142,13 @@ export async function setupGlobalEvents() {
     const tab = getActiveTab();
     try {
       tab.workFile = path;
-      tab.workFileSize = await GetFileSize(path).catch(() => 0);
+      tab.backupSet = null;
+      tab.workFileSize = await GetFileSize(path, tab.hdiffIgnoreList).catch(
+        () => 0,
+      );
       tab.backupDir = "";
       tab.selectedTargetDir = "";
+      await offerRelink(tab);
 
       addToRecentFiles(path);
       saveCurrentSession();
@@ -133,6 +179,12 @@ export async function setupGlobalEvents() {
       return;
     }
 
+    if (id === "open-root-btn") {
+      const dir = await SelectAnyFolder(i18n.openRootBtn);
+      if (dir) await openBackupRoot(dir);
+      return;
+    }
+
     // 世代バッジ
     const genBadge = e.target.closest(".gen-selector-badge");
     if (genBadge) {
@@ -200,6 +252,26 @@ export async function setupGlobalEvents() {
     } else if (id === "backupdir-btn") {
       await handleSelectBackupDir();
       return;
+    } else if (id === "mirrordir-btn") {
+      const res = await SelectAnyFolder(i18n.labelMirror);
+      if (res) {
+        tab.mirrorDir = res;
+        await UpdateDisplay();
+        saveCurrentSession();
+        showFloatingMessage(i18n.updatedMirrorDir);
+        // これまでの履歴もまとめて写す
+        syncMirror(tab);
+      }
+      return;
+    } else if (id === "mirrordir-clear-btn") {
+      tab.mirrorDir = "";
+      await UpdateDisplay();
+      UpdateHistory();
+      saveCurrentSession();
+      return;
+    } else if (id === "mirror-retry-btn") {
+      await syncMirror(tab);
+      return;
     } else if (id === "execute-backup-btn" || id === "compact-execute-btn") {
       if (target.disabled) return;
       await OnExecute();
@@ -212,6 +284,32 @@ export async function setupGlobalEvents() {
       const all = Array.from(cbs).every((cb) => cb.checked);
       cbs.forEach((cb) => (cb.checked = !all));
       return;
+    } else if (id === "encrypt-selected-btn") {
+      const targets = Array.from(
+        document.querySelectorAll(".diff-checkbox:checked"),
+      ).map((el) => el.value);
+      if (targets.length === 0) return;
+      const password = document.getElementById("archive-password")?.value;
+      if (!password) {
+        showFloatingError(i18n.encryptPasswordRequired);
+        return;
+      }
+      const isConfirmed = await ask(i18n.encryptConfirm, {
+        title: "CG File Backup",
+        type: "warning",
+      });
+      if (!isConfirmed) return;
+      toggleProgress(true, "Encrypting...");
+      try {
+        await EncryptBackupFiles(targets, password, tab.workFile, tab.backupDir);
+        toggleProgress(false);
+        showFloatingMessage(i18n.encryptSuccess);
+        UpdateHistory();
+      } catch (err) {
+        toggleProgress(false);
+        alert(err);
+      }
+      return;
     } else if (id === "apply-selected-btn") {
       e.preventDefault();
       e.stopPropagation();
@@ -228,8 +326,11 @@ export async function setupGlobalEvents() {
       if (isConfirmed) {
         toggleProgress(true, "Restoring...");
         try {
+          // 世代アーカイブ内の差分を直接復元する場合に使う
+          const archivePassword =
+            document.getElementById("archive-password")?.value || null;
           for (const p of targets) {
-            await RestoreBackup(p, tab.workFile);
+            await RestoreBackup(p, tab.workFile, archivePassword);
           }
           toggleProgress(false);
           showFloatingMessage(i18n.diffApplySuccess);
@@ -268,12 +369,21 @@ export async function setupGlobalEvents() {
       }
 
       const format = document.getElementById("archive-format-select").value;
+      // パスワード欄に入力があれば、世代アーカイブも暗号化する
+      const archivePassword =
+        document.getElementById("archive-password")?.value || null;
       toggleProgress(true, "Archiving...");
 
       try {
         for (const cb of selectedChecks) {
           const genNum = parseInt(cb.getAttribute("data-gen"));
-          await ArchiveGeneration(genNum, format, tab.workFile, tab.backupDir);
+          await ArchiveGeneration(
+            genNum,
+            format,
+            tab.workFile,
+            tab.backupDir,
+            archivePassword,
+          );
         }
         toggleProgress(false);
         showFloatingMessage("Archiving completed.");
@@ -287,6 +397,104 @@ export async function setupGlobalEvents() {
       return;
     }
 
+    // バックアップセットの編集
+    const setRemoveBtn = e.target.closest(".backup-set-remove");
+    if (setRemoveBtn) {
+      backupSetDraft.members.splice(Number(setRemoveBtn.dataset.index), 1);
+      renderBackupSetMembers();
+      return;
+    }
+    if (
+      id === "backup-set-add-file-btn" ||
+      id === "backup-set-add-folder-btn"
+    ) {
+      const res =
+        id === "backup-set-add-file-btn"
+          ? await SelectAnyFile(i18n.backupSetAddFile)
+          : await SelectAnyFolder(i18n.backupSetAddFolder);
+      if (res && !backupSetDraft.members.includes(res)) {
+        backupSetDraft.members.push(res);
+        renderBackupSetMembers();
+      }
+      return;
+    } else if (id === "backup-set-cancel-btn") {
+      document.getElementById("backup-set-modal").classList.add("hidden");
+      return;
+    } else if (id === "backup-set-save-btn") {
+      const name = document.getElementById("backup-set-name").value.trim();
+      if (!name || backupSetDraft.members.length === 0) {
+        showFloatingError(i18n.backupSetNameRequired);
+        return;
+      }
+      const set = { name, members: [...backupSetDraft.members] };
+      // 別のセット名・別の作業対象から切り替えた場合は既定のバックアップ先を使う
+      const keepDir = tab.backupSet?.name === name ? tab.backupDir : "";
+      toggleProgress(true, i18n.processingMsg);
+      try {
+        const info = await SyncBackupSet(set, keepDir, tab.hdiffIgnoreList);
+        tab.backupSet = set;
+        tab.workFile = info.workFile;
+        tab.backupDir = info.backupDir;
+        tab.selectedTargetDir = "";
+        tab.workFileSize = await GetFileSize(
+          info.workFile,
+          tab.hdiffIgnoreList,
+        ).catch(() => 0);
+        toggleProgress(false);
+        document.getElementById("backup-set-modal").classList.add("hidden");
+        renderTabs();
+        await UpdateDisplay();
+        UpdateHistory();
+        saveCurrentSession();
+        showFloatingMessage(i18n.backupSetSaved);
+      } catch (err) {
+        toggleProgress(false);
+        showFloatingError(err);
+      }
+      return;
+    }
+
+    if (id === "vault-btn") {
+      await updateVaultStatus();
+      document.getElementById("vault-modal").classList.remove("hidden");
+      return;
+    } else if (id === "vault-modal") {
+      if (e.target.id === "vault-modal") e.target.classList.add("hidden");
+      return;
+    } else if (id === "vault-unlock-btn") {
+      const masterEl = document.getElementById("vault-master-password");
+      try {
+        await UnlockVault(masterEl.value);
+        masterEl.value = "";
+        await updateVaultStatus();
+        // 暗号化した世代アーカイブもキャッシュできるようになる
+        if (tab?.workFile) {
+          await RebuildArchiveCaches(tab.workFile, tab.backupDir || "");
+        }
+      } catch (err) {
+        showFloatingError(err);
+      }
+      return;
+    } else if (id === "vault-save-btn") {
+      const password = document.getElementById("archive-password")?.value;
+      if (!tab?.workFile || !password) {
+        showFloatingError(i18n.encryptPasswordRequired);
+        return;
+      }
+      try {
+        await SetVaultPassword(tab.workFile, tab.backupDir || "", password);
+        showFloatingMessage(i18n.vaultSaved);
+        await updateVaultStatus();
+      } catch (err) {
+        showFloatingError(err);
+      }
+      return;
+    } else if (id === "vault-lock-btn") {
+      await LockVault();
+      await updateVaultStatus();
+      return;
+    }
+
     if (id == "lock-mode-btn") {
       if (!tab) return;
       tab.isLocked = !tab.isLocked;
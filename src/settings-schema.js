@@ -10,6 +10,23 @@ export const settingsSchema = [
     label: "startupCacheLimit",
     hint: "startupCacheLimitHint",
   },
+  {
+    key: "cacheMaxSizeMb",
+    type: "number",
+    min: 0,
+    max: null,
+    step: 256,
+    category: "cache",
+    label: "cacheMaxSizeMb",
+    hint: "cacheMaxSizeMbHint",
+  },
+  {
+    key: "cacheArchivedGenerations",
+    type: "boolean",
+    category: "cache",
+    label: "cacheArchivedGenerationsLabel",
+    hint: "cacheArchivedGenerationsHint",
+  },
   {
     key: "autoBaseGenerationThreshold",
     type: "number",
@@ -20,6 +37,90 @@ export const settingsSchema = [
     label: "thresholdLabel",
     hint: "thresholdHint",
   },
+  {
+    key: "rotateCumulativeRatio",
+    type: "number",
+    min: 0,
+    max: null,
+    step: 0.5,
+    category: "backup",
+    label: "rotateCumulativeRatio",
+    hint: "rotateCumulativeRatioHint",
+  },
+  {
+    key: "rotateMaxDiffs",
+    type: "number",
+    min: 0,
+    max: null,
+    step: 10,
+    category: "backup",
+    label: "rotateMaxDiffs",
+    hint: "rotateMaxDiffsHint",
+  },
+  {
+    key: "rotateMaxAgeDays",
+    type: "number",
+    min: 0,
+    max: null,
+    step: 1,
+    category: "backup",
+    label: "rotateMaxAgeDays",
+    hint: "rotateMaxAgeDaysHint",
+  },
+  {
+    key: "rotateShrinkRatio",
+    type: "number",
+    min: 0,
+    max: 1.0,
+    step: 0.1,
+    category: "backup",
+    label: "rotateShrinkRatio",
+    hint: "rotateShrinkRatioHint",
+  },
+  {
+    key: "rotateOnTypeChange",
+    type: "boolean",
+    category: "backup",
+    label: "rotateOnTypeChangeLabel",
+    hint: "rotateOnTypeChangeHint",
+  },
+  {
+    key: "archiveCompressionLevel",
+    type: "number",
+    min: 0,
+    max: 22,
+    step: 1,
+    category: "backup",
+    label: "archiveCompressionLevel",
+    hint: "archiveCompressionLevelHint",
+  },
+  {
+    key: "archiveCompressionThreads",
+    type: "number",
+    min: 0,
+    max: 64,
+    step: 1,
+    category: "backup",
+    label: "archiveCompressionThreads",
+    hint: "archiveCompressionThreadsHint",
+  },
+  {
+    key: "archiveVolumeSizeMb",
+    type: "number",
+    min: 0,
+    max: null,
+    step: 100,
+    category: "backup",
+    label: "archiveVolumeSizeMb",
+    hint: "archiveVolumeSizeMbHint",
+  },
+  {
+    key: "keepSymlinks",
+    type: "boolean",
+    category: "backup",
+    label: "keepSymlinksLabel",
+    hint: "keepSymlinksHint",
+  },
   {
     key: "hdiffStrictHashCheck",
     type: "boolean",
@@ -22,6 +22,8 @@ export let tabs = [
     searchQuery: "",
     isLocked: false,
     hdiffIgnoreList: [],
+    backupSet: null,
+    mirrorDir: "",
   },
 ];
 export let recentFiles = JSON.parse(
@@ -97,8 +97,9 @@ export async function ReadTextFile(path) {
 /**
  * バックアップ・ファイル操作系
  */
-export async function GetFileSize(path) {
-  return await invoke("get_file_size", { path });
+// ignoreList を渡すとフォルダはタブの除外パターンと .wbtignore に一致するものを除いて数える
+export async function GetFileSize(path, ignoreList = []) {
+  return await invoke("get_file_size", { path, ignoreList });
 }
 
 export async function DirExists(path) {
@@ -108,20 +109,112 @@ export async function FileExists(path) {
   return await invoke("file_exists", { path });
 }
 
-export async function CopyBackupFile(src, backupDir) {
-  return await invoke("copy_backup_file", { src, backupDir });
+export async function CopyBackupFile(src, backupDir, ignoreList = []) {
+  return await invoke("copy_backup_file", { src, backupDir, ignoreList });
 }
 
-export async function RestoreBackup(path, workFile) {
-  return await invoke("restore_backup", { path, workFile });
+export async function RestoreBackup(
+  path,
+  workFile,
+  password = null,
+  output = null,
+  conflict = null,
+) {
+  return await invoke("restore_backup", {
+    path,
+    workFile,
+    password,
+    output,
+    conflict,
+  });
 }
 
-export async function ArchiveBackupFile(src, backupDir, format, password) {
+export async function ArchiveBackupFile(
+  src,
+  backupDir,
+  format,
+  password,
+  ignoreList = [],
+) {
   return await invoke("archive_backup_file", {
     src,
     backupDir,
     format,
     password,
+    ignoreList,
+  });
+}
+
+export async function DedupBackup(src, backupDir, ignoreList = []) {
+  return await invoke("dedup_backup", { src, backupDir, ignoreList });
+}
+
+/**
+ * バックアップセット（複数のファイル/フォルダをまとめてバックアップする）
+ * set: { name, members: [path, ...] }
+ * 戻り値: { workFile, backupDir, members } （workFile はセットのフォルダ）
+ */
+export async function SyncBackupSet(set, backupDir, ignoreList = []) {
+  return await invoke("sync_backup_set", { set, backupDir, ignoreList });
+}
+
+export async function GetBackupSetMembers(workFile) {
+  return await invoke("get_backup_set_members", { workFile });
+}
+
+// member を省略するとセット全体を、指定するとそのメンバーだけを元の場所の隣へ復元する
+export async function RestoreBackupSet(
+  path,
+  workFile,
+  member = null,
+  password = null,
+  output = null,
+  conflict = null,
+) {
+  return await invoke("restore_backup_set", {
+    path,
+    workFile,
+    member,
+    password,
+    output,
+    conflict,
+  });
+}
+
+export async function GcDedupChunks(workFile, backupDir) {
+  return await invoke("gc_dedup_chunks", { workFile, backupDir });
+}
+
+export async function GetVaultStatus() {
+  return await invoke("get_vault_status");
+}
+
+export async function UnlockVault(master) {
+  return await invoke("unlock_vault", { master });
+}
+
+export async function LockVault() {
+  return await invoke("lock_vault");
+}
+
+export async function SetVaultPassword(workFile, backupDir, password) {
+  return await invoke("set_vault_password", { workFile, backupDir, password });
+}
+
+export async function RemoveVaultPassword(workFile, backupDir) {
+  return await invoke("remove_vault_password", { workFile, backupDir });
+}
+
+export async function ChangeVaultMaster(newMaster) {
+  return await invoke("change_vault_master", { newMaster });
+}
+
+export async function EncryptBackupFiles(paths, password, workFile, backupDir) {
+  return await invoke("encrypt_backup_files", {
+    paths,
+    password,
+    workFile,
+    backupDir,
   });
 }
 
@@ -141,12 +234,95 @@ export async function BackupOrDiff(
   });
 }
 
-export async function GetBackupList(workFile, backupDir) {
-  return await invoke("get_backup_list", { workFile, backupDir });
+/**
+ * 作業ファイルの名前・場所が変わった場合の、元の履歴の候補
+ * 戻り値: [{ backupDir, workName, workPath, exact, updated }, ...]
+ */
+export async function FindRelinkCandidates(workFile, searchDirs = []) {
+  return await invoke("find_relink_candidates", { workFile, searchDirs });
+}
+
+/**
+ * 履歴を作業ファイルへ付け替える
+ * 戻り値: { backupDir, isDefaultDir, renamed, previousPath }
+ */
+export async function RelinkBackupHistory(workFile, backupDir) {
+  return await invoke("relink_backup_history", { workFile, backupDir });
+}
+
+/**
+ * タブの設定をバックアップルートの記述ファイルへ記録する
+ */
+export async function SaveRootSettings(workFile, backupDir, settings) {
+  return await invoke("save_root_settings", { workFile, backupDir, settings });
+}
+
+/**
+ * 既存のバックアップルートを開く
+ * 戻り値: { workFile, backupDir, workMissing, relocated, settings }
+ */
+export async function OpenBackupRoot(backupDir) {
+  return await invoke("open_backup_root", { backupDir });
+}
+
+/**
+ * バックアップ先の新しいもの・変わったものをミラー先へ写す
+ * 戻り値: { mirrorRoot, lastSync, lastError, copied, synced, pending, failed }
+ */
+export async function SyncMirror(workFile, backupDir, mirrorDir) {
+  return await invoke("sync_mirror", { workFile, backupDir, mirrorDir });
+}
+
+/**
+ * ミラーの現在の状態
+ */
+export async function GetMirrorStatus(workFile, backupDir) {
+  return await invoke("get_mirror_status", { workFile, backupDir });
+}
+
+/**
+ * 手動バックアップ（xxx_v3.clip など）を世代構造に取り込む
+ */
+export async function ImportManualBackups(
+  workFile,
+  backupDir,
+  files,
+  folder = null,
+  pattern = null,
+  algo = "hdiff",
+  compress = "zstd",
+  ignoreList = [],
+) {
+  return await invoke("import_manual_backups", {
+    workFile,
+    backupDir,
+    files,
+    folder,
+    pattern,
+    algo,
+    compress,
+    ignoreList,
+  });
+}
+
+export async function GetBackupList(workFile, backupDir, mirrorDir = "") {
+  return await invoke("get_backup_list", { workFile, backupDir, mirrorDir });
 }
 
-export async function ApplyMultiDiff(workFile, diffPaths) {
-  return await invoke("apply_multi_diff", { workFile, diffPaths });
+export async function ApplyMultiDiff(
+  workFile,
+  diffPaths,
+  password = null,
+  output = null,
+  conflict = null,
+) {
+  return await invoke("apply_multi_diff", {
+    workFile,
+    diffPaths,
+    password,
+    output,
+    conflict,
+  });
 }
 
 /**
@@ -175,6 +351,191 @@ export async function ArchiveGeneration(
   });
 }
 
+/**
+ * 世代メンテナンス：新しいスナップショットを .base にして差分を作り直す
+ * @param {string} strategy - "latest" | "median"
+ */
+export async function RebaseGeneration(
+  targetN,
+  workFile,
+  backupDir,
+  strategy = "latest",
+  compress = "zstd",
+) {
+  return await invoke("rebase_generation", {
+    targetN,
+    workFile,
+    backupDir,
+    strategy,
+    compress,
+  });
+}
+
+/**
+ * 世代メンテナンス：世代をまるごと削除（最新世代は force 指定時のみ）
+ */
+export async function DeleteGeneration(
+  targetN,
+  workFile,
+  backupDir,
+  force = false,
+) {
+  return await invoke("delete_generation", {
+    targetN,
+    workFile,
+    backupDir,
+    force,
+  });
+}
+
+/**
+ * 世代メンテナンス：foldN の世代を keepN の世代へ統合
+ */
+export async function MergeGenerations(
+  keepN,
+  foldN,
+  workFile,
+  backupDir,
+  force = false,
+  password = null,
+  compress = "zstd",
+) {
+  return await invoke("merge_generations", {
+    keepN,
+    foldN,
+    workFile,
+    backupDir,
+    force,
+    password,
+    compress,
+  });
+}
+
+/**
+ * 指定日時の時点の状態を復元（datetime 以前で最新のバックアップを使用）
+ */
+export async function RestoreAt(
+  workFile,
+  backupDir,
+  datetime,
+  password = null,
+  output = null,
+  conflict = null,
+) {
+  return await invoke("restore_at", {
+    workFile,
+    backupDir,
+    datetime,
+    password,
+    output,
+    conflict,
+  });
+}
+
+/**
+ * 作業ファイルそのものを上書き復元（上書き前の状態は自動で退避）
+ */
+export async function RestoreInPlace(path, workFile, backupDir, password = null) {
+  return await invoke("restore_in_place", {
+    path,
+    workFile,
+    backupDir,
+    password,
+  });
+}
+
+/**
+ * 直近の上書き復元を取り消す
+ */
+export async function UndoInPlaceRestore(workFile, backupDir) {
+  return await invoke("undo_in_place_restore", { workFile, backupDir });
+}
+
+/**
+ * フォルダのバックアップに含まれるファイル一覧（相対パス）
+ */
+export async function ListBackupFiles(path, workFile, password = null) {
+  return await invoke("list_backup_files", { path, workFile, password });
+}
+
+/**
+ * フォルダのバックアップから選択したファイルだけを復元
+ */
+export async function RestoreSelectedFiles(
+  path,
+  workFile,
+  relPaths,
+  output = null,
+  conflict = null,
+  password = null,
+) {
+  return await invoke("restore_selected_files", {
+    path,
+    workFile,
+    relPaths,
+    output,
+    conflict,
+    password,
+  });
+}
+
+/**
+ * フォルダのバックアップ同士（b が null なら現在の作業フォルダ）を比較
+ * 作業フォルダ側は ignoreList（タブの除外パターン）と .wbtignore に一致するものを除く
+ */
+export async function CompareBackups(
+  a,
+  b,
+  workFile,
+  password = null,
+  ignoreList = [],
+) {
+  return await invoke("compare_backups", {
+    a,
+    b,
+    workFile,
+    password,
+    ignoreList,
+  });
+}
+
+/**
+ * 2つのバックアップを画像として比較（横並び画像と差分ヒートマップ）
+ */
+export async function CompareImages(a, b, workFile, backupDir, password = null) {
+  return await invoke("compare_images", {
+    a,
+    b,
+    workFile,
+    backupDir,
+    password,
+  });
+}
+
+/**
+ * バックアップのサムネイル（PNG のパス）を取得。未生成なら作成する
+ */
+export async function GetBackupThumbnail(
+  path,
+  workFile,
+  backupDir,
+  password = null,
+) {
+  return await invoke("get_backup_thumbnail", {
+    path,
+    workFile,
+    backupDir,
+    password,
+  });
+}
+
+/**
+ * バックアップ先の容量の内訳（世代ごと・フルコピー・アーカイブ・キャッシュ）
+ */
+export async function GetBackupStats(workFile, backupDir) {
+  return await invoke("get_backup_stats", { workFile, backupDir });
+}
+
 /**
  * キャッシュ関連コマンド
  */
@@ -1,5 +1,6 @@
 import {
   GetBackupList,
+  GetMirrorStatus,
   GetFileSize,
   WriteTextFile,
   ReadTextFile,
@@ -321,12 +322,27 @@ export async function UpdateDisplay() {
     tabSelect.value = tab.id;
   }
 
+  // セットのタブでは、復元するメンバーを選べるようにする
+  const memberSel = document.getElementById("set-restore-member");
+  if (memberSel) {
+    const members = tab.backupSet?.members || [];
+    memberSel.classList.toggle("hidden", members.length === 0);
+    memberSel.innerHTML = [
+      `<option value="">${i18n.setRestoreWhole}</option>`,
+      ...members.map(
+        (m) => `<option value="${m}">${m.split(/[\\/]/).pop()}</option>`,
+      ),
+    ].join("");
+  }
+
   const fileEl = document.getElementById("selected-workfile");
   const dirEl = document.getElementById("selected-backupdir");
   if (fileEl) {
-    const baseName = tab.workFile
-      ? tab.workFile.split(/[\\/]/).pop()
-      : i18n.selectedWorkFile;
+    const baseName = tab.backupSet
+      ? `🗂 ${tab.backupSet.name} (${tab.backupSet.members.length})`
+      : tab.workFile
+        ? tab.workFile.split(/[\\/]/).pop()
+        : i18n.selectedWorkFile;
     const sizeText = tab.workFile ? ` [${formatSize(tab.workFileSize)}]` : "";
     if (!workTargetExists && tab.workFile) {
       fileEl.innerHTML = `<span style="color: #ff4d4d; font-weight: bold;">${baseName} (Not Found)</span>`;
@@ -335,6 +351,11 @@ export async function UpdateDisplay() {
       fileEl.style.color = "";
     }
   }
+  const mirrorEl = document.getElementById("selected-mirrordir");
+  if (mirrorEl) {
+    mirrorEl.textContent = tab.mirrorDir || i18n.mirrorNotSet;
+    mirrorEl.title = tab.mirrorDir || "";
+  }
   if (dirEl) {
     const baseName = tab.backupDir
       ? tab.backupDir.split(/[\\/]/).pop()
@@ -383,9 +404,11 @@ export async function UpdateDisplay() {
     lockBtn.textContent = locked ? "🔒" : "🔓";
     lockBtn.title = locked ? i18n.unlockMode : i18n.lockMode;
   }
+  // zip は AES、tar 系はアーカイブ全体を暗号化する（空欄なら暗号化しない）
+  const archiveFormatValue = document.getElementById("archive-format")?.value;
   const isPass =
     mode === "archive" &&
-    document.getElementById("archive-format")?.value === "zip-pass";
+    (archiveFormatValue === "zip-pass" || archiveFormatValue?.startsWith("tar"));
   const pwdArea = document.querySelector(".password-wrapper");
   if (pwdArea) {
     pwdArea.style.opacity = isPass ? "1" : "0.3";
@@ -428,7 +451,11 @@ export async function UpdateHistory() {
   }
 
   try {
-    let data = await GetBackupList(tab.workFile, tab.backupDir);
+    let data = await GetBackupList(
+      tab.workFile,
+      tab.backupDir,
+      tab.mirrorDir || "",
+    );
     if (!data || data.length === 0) {
       list.innerHTML = `<div class="info-msg">${i18n.noHistory}</div>`;
       return;
@@ -468,7 +495,8 @@ export async function UpdateHistory() {
         }
 
         const isDiffFile = item.fileName.toLowerCase().endsWith(".diff");
-        const isArchive = !isDiffFile && item.generation === 0;
+        const isDedup = item.fileName.toLowerCase().endsWith(".dedup");
+        const isArchive = !isDiffFile && !isDedup && item.generation === 0;
 
         const itemDir =
           item.filePath.substring(0, item.filePath.lastIndexOf("/")) ||
@@ -490,7 +518,11 @@ export async function UpdateHistory() {
               })()
             : "";
 
-        if (isArchive) {
+        if (isDedup) {
+          const dedupText = i18n.dedupTitle || "Dedup";
+          statusHtml = `<div style="color:#7a5fb0; font-weight:bold;">${dedupText}</div>`;
+          genBadge = `<span style="font-size:10px; color:#fff; background:#7a5fb0; padding:1px 4px; border-radius:3px; margin-left:5px;">Dedup</span>`;
+        } else if (isArchive) {
           const archiveText = i18n.fullArchive || " Full Archive";
           statusHtml = `<div style="color:#2f8f5b; font-weight:bold;">${archiveText}</div>`;
           genBadge = `<span style="font-size:10px; color:#fff; background:#2f8f5b; padding:1px 4px; border-radius:3px; margin-left:5px;">Archive</span>`;
@@ -531,6 +563,15 @@ export async function UpdateHistory() {
           genBadge = `<span class="gen-selector-badge" data-dir="${itemDir}" style="${badgeStyle}">${genLabel}.${currentGen}${currentLabel}</span>`;
         }
 
+        const mirrorInfo = {
+          synced: ["#2f8f5b", i18n.mirrorSynced],
+          pending: ["#888", i18n.mirrorPending],
+          failed: ["#c0392b", i18n.mirrorFailedBadge],
+        }[item.mirror];
+        const mirrorBadge = mirrorInfo
+          ? `<span style="font-size:10px; color:#fff; background:${mirrorInfo[0]}; padding:1px 4px; border-radius:3px; margin-left:3px;">${mirrorInfo[1]}</span>`
+          : "";
+
         const markLabels = {
           1: i18n?.priorityLow || "Low",
           2: i18n?.priorityMid || "Mid",
@@ -552,7 +593,7 @@ export async function UpdateHistory() {
               <input type="checkbox" class="diff-checkbox" value="${item.filePath}" style="margin-right:10px;">
               <div style="display:flex; flex-direction:column; flex:1; min-width:0;">
                 <span class="diff-name" data-hover-content="${encodeURIComponent(popupContent)}" style="font-weight:bold; overflow:hidden; text-overflow:ellipsis; white-space:nowrap;">
-                  ${displayedFileName} ${genBadge} ${markBadge} <span style="font-size:10px; color:#3B5998;">(${formatSize(item.fileSize)})</span>
+                  ${displayedFileName} ${genBadge} ${markBadge} ${mirrorBadge} <span style="font-size:10px; color:#3B5998;">(${formatSize(item.fileSize)})</span>
                 </span>
                 <span style="font-size:10px; color:#888;">${item.timestamp}</span>
                 ${note ? `<div style="font-size:10px; color:#2f8f5b; font-style:italic; overflow:hidden; text-overflow:ellipsis; white-space:nowrap;"> ${displayedNote}</div>` : ""}
@@ -563,7 +604,9 @@ export async function UpdateHistory() {
         </div>`;
       }),
     );
-    list.innerHTML = itemsHtml.filter((html) => html !== null).join("");
+    list.innerHTML =
+      (await mirrorSummaryHtml(tab)) +
+      itemsHtml.filter((html) => html !== null).join("");
 
     if (executeBtn) {
       if (isTargetArchivedGeneration) {
@@ -586,6 +629,27 @@ export async function UpdateHistory() {
   }
 }
 
+// 履歴の先頭に出すミラーの状態（未了・失敗があれば再試行ボタンを付ける）
+async function mirrorSummaryHtml(tab) {
+  if (!tab.mirrorDir) return "";
+  const status = await GetMirrorStatus(tab.workFile, tab.backupDir).catch(
+    () => null,
+  );
+  if (!status) return "";
+  const text = i18n.mirrorSummary
+    .replace("{synced}", status.synced)
+    .replace("{pending}", status.pending)
+    .replace("{last}", status.lastSync || "-");
+  const error = status.lastError
+    ? `<div style="color:#c0392b; overflow:hidden; text-overflow:ellipsis; white-space:nowrap;" title="${status.lastError}">${status.lastError}</div>`
+    : "";
+  const retry =
+    status.pending > 0
+      ? ` <button id="mirror-retry-btn" class="mini-btn">${i18n.mirrorRetryBtn}</button>`
+      : "";
+  return `<div class="info-msg" style="font-size:11px; text-align:left;">${text}${retry}${error}</div>`;
+}
+
 export async function showArchiveModal() {
   const tab = getActiveTab();
   const modal = document.getElementById("archive-modal");
@@ -650,6 +714,40 @@ export async function showArchiveModal() {
   }
 }
 
+// 編集中のバックアップセット（保存するまでタブには反映しない）
+export const backupSetDraft = { members: [] };
+
+export function showBackupSetModal() {
+  const tab = getActiveTab();
+  const modal = document.getElementById("backup-set-modal");
+  if (!tab || !modal) return;
+  document.getElementById("backup-set-name").value = tab.backupSet?.name || "";
+  backupSetDraft.members = [...(tab.backupSet?.members || [])];
+  renderBackupSetMembers();
+  modal.classList.remove("hidden");
+}
+
+export function renderBackupSetMembers() {
+  const list = document.getElementById("backup-set-members");
+  if (!list) return;
+  if (backupSetDraft.members.length === 0) {
+    list.innerHTML = `
+      <div style="font-size:11px; color:#888; text-align:center; padding:15px;">
+        ${i18n.backupSetEmpty}
+      </div>`;
+    return;
+  }
+  list.innerHTML = backupSetDraft.members
+    .map(
+      (path, i) => `
+      <div class="archive-item" title="${path}">
+        <span style="flex:1; text-align:left;">${path.split(/[\\/]/).pop()}</span>
+        <button class="settings-tag-remove backup-set-remove" data-index="${i}" title="Remove">×</button>
+      </div>`,
+    )
+    .join("");
+}
+
 export async function showSettingsModal() {
   const modal = document.getElementById("settings-modal");
   if (!modal || !i18n) return;